@@ -0,0 +1,17 @@
+//! Links the blob with `blob.ld`.
+
+use std::env;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rerun-if-changed=blob.ld");
+    println!("cargo:rustc-link-arg=-T{}/blob.ld", manifest_dir);
+    // Hosted targets link through the C compiler, which would add its
+    // startup files and libraries.
+    if env::var("CARGO_CFG_TARGET_OS").unwrap() != "none" {
+        println!("cargo:rustc-link-arg=-nostartfiles");
+        println!("cargo:rustc-link-arg=-nostdlib");
+        println!("cargo:rustc-link-arg=-static");
+        println!("cargo:rustc-link-arg=-Wl,--build-id=none");
+    }
+}
@@ -0,0 +1,100 @@
+//! Position-independent relox decompressor blob
+//!
+//! This crate builds the decompressor as a self-contained binary blob, which
+//! can be copied anywhere in RAM and executed, e.g. by a ROM bootloader
+//! relocating an image before jumping to it. The blob contains code and
+//! read-only data only: no GOT, no `.data` and no `.bss`. Its entry point is
+//! at offset 0.
+//!
+//! # ABI
+//!
+//! The entry point follows the C calling convention of the target, e.g. AAPCS
+//! on ARM, and relocates the image in place:
+//!
+//! | Register (ARM) | Argument                                              |
+//! |----------------|-------------------------------------------------------|
+//! | `r0`           | Address of the compressed relocation section.         |
+//! | `r1`           | Size of the compressed relocation section in bytes.   |
+//! | `r2`           | Bias: load address minus link address of the image.   |
+//! | `r3`           | Type of relative relocations, e.g. 23 on ARM.         |
+//!
+//! For every relocation, the word at the relocated address plus bias is
+//! incremented by the bias. The result is returned in `r0`:
+//!
+//! | Value | Meaning                                                  |
+//! |-------|----------------------------------------------------------|
+//! | 0     | Success.                                                 |
+//! | 1     | The section is malformed.                                |
+//! | 2     | The section ends prematurely.                            |
+//! | 3     | A buffer is too small.                                   |
+//! | 4     | The section is valid but not supported.                  |
+//! | 5     | An address accumulated from the section overflows.       |
+//! | 6     | A relocation is not of the type passed in `r3`.          |
+//! | 7     | There are more groups than the layout can hold.          |
+//! | 8     | There is no section with the requested name and type.    |
+//!
+//! The blob uses the stack of the caller only and does not check the
+//! compressed data beyond its framing, as it is built with the
+//! `embedded_minimal` feature group.
+//!
+//! # Building
+//!
+//! ```text
+//! make blob BLOB_TARGET=thumbv7m-none-eabi
+//! ```
+//!
+//! The blob is written to `target/relox-blob.bin`.
+
+#![no_std]
+#![no_main]
+#![deny(missing_docs, unused, unused_imports)]
+
+use core::panic::PanicInfo;
+use core::ptr;
+
+use relox_core::{elf32_relocate, Error, ErrorKind};
+
+/// Relocates an image in place using a compressed relocation section of
+/// relative relocations and returns 0, or the error code on failure.
+///
+/// # Safety
+///
+/// `crel` must point to `crel_len` readable bytes and every relocated word
+/// must be readable and writable.
+#[no_mangle]
+#[link_section = ".text.relox_blob_relocate"]
+pub unsafe extern "C" fn relox_blob_relocate(
+    crel: *const u8,
+    crel_len: usize,
+    bias: u32,
+    relocation_type: u32,
+) -> u32 {
+    let data = core::slice::from_raw_parts(crel, crel_len);
+    let result = elf32_relocate(data, &mut |r_type, address| {
+        if r_type as u32 != relocation_type {
+            return Err(Error::new(ErrorKind::UnsupportedRelocationType));
+        }
+        let word = address.wrapping_add(bias) as usize as *mut u32;
+        ptr::write_unaligned(word, ptr::read_unaligned(word).wrapping_add(bias));
+        Ok(())
+    });
+    match result {
+        Ok(_) => 0,
+        Err(err) => match err.kind() {
+            ErrorKind::InvalidData => 1,
+            ErrorKind::NotEnoughData => 2,
+            ErrorKind::BufferSmall => 3,
+            ErrorKind::Unsupported => 4,
+            ErrorKind::Overflow => 5,
+            ErrorKind::UnsupportedRelocationType => 6,
+            ErrorKind::TooManyGroups => 7,
+            ErrorKind::SectionNotFound => 8,
+        },
+    }
+}
+
+/// Hangs, the decoder does not panic with `no_bounds_check`.
+#[panic_handler]
+fn panic(_: &PanicInfo) -> ! {
+    loop {}
+}
@@ -0,0 +1,63 @@
+/*
+ * Views of compressed ELF32 relocation sections produced by relox.
+ *
+ * The structures match the `#[repr(C)]` views of the `view` module of
+ * relox-core and can be overlaid on a compressed section at any offset. Words
+ * are held as bytes in the byte order of the section, which is the byte order
+ * of the target.
+ */
+
+#ifndef RELOX_H
+#define RELOX_H
+
+#include <stdint.h>
+
+/* Group counts marking the layouts of compressed sections. */
+#define RELOX_SCALED 0xF5u
+#define RELOX_REPEAT 0xF6u
+#define RELOX_ALIGNED 0xF7u
+#define RELOX_SIGNED 0xF8u
+#define RELOX_BITMAP 0xF9u
+#define RELOX_SYMBOLS 0xFAu
+#define RELOX_GROUP_VARINT 0xFBu
+#define RELOX_MACHINE 0xFCu
+#define RELOX_BASED 0xFDu
+#define RELOX_DIRECTORY 0xFEu
+#define RELOX_INTERLEAVED 0xFFu
+
+/* Header of a compressed section. */
+struct relox_header {
+    uint8_t base_address[4];
+    /* Group count, or the marker of the layout. */
+    uint8_t count;
+};
+
+/* Header of a compressed section using the directory or the based layout. */
+struct relox_directory_header {
+    struct relox_header header;
+    uint8_t count;
+};
+
+/* Entry of the group directory, following `struct relox_directory_header`. */
+struct relox_directory_entry {
+    uint8_t relocation_type;
+    uint8_t count[4];
+    /* Offset of the group's offsets relative to the end of the directory. */
+    uint8_t stream[4];
+};
+
+/*
+ * Fixed-size part of a group using the based layout, followed by the number
+ * of relocations and the offsets, both encoded as ULEB128.
+ */
+struct relox_based_group {
+    uint8_t relocation_type;
+    uint8_t base_address[4];
+};
+
+_Static_assert(sizeof(struct relox_header) == 5, "relox_header");
+_Static_assert(sizeof(struct relox_directory_header) == 6, "relox_directory_header");
+_Static_assert(sizeof(struct relox_directory_entry) == 9, "relox_directory_entry");
+_Static_assert(sizeof(struct relox_based_group) == 5, "relox_based_group");
+
+#endif /* RELOX_H */
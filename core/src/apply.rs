@@ -0,0 +1,957 @@
+//! Apply a compressed ELF32 relocation section
+//!
+//! This module can be used to apply the relocations of a compressed ELF32
+//! relocation section directly to memory, without calling a user provided
+//! function for every relocation.
+//!
+//! Self-relocating firmware of 32-bit Arm, RISC-V or x86 targets can use
+//! [apply_arm_relative](fn.apply_arm_relative.html),
+//! [apply_riscv_relative](fn.apply_riscv_relative.html) or
+//! [apply_x86_relative](fn.apply_x86_relative.html) to add the load-time slide
+//! to the words of its relative relocations:
+//!
+//! ```no_run
+//! # fn example(crel: &[u8], load_address: u32, link_address: u32, size: usize)
+//! #     -> Result<(), relox_core::Error> {
+//! let image =
+//!     unsafe { core::slice::from_raw_parts_mut(load_address as usize as *mut u8, size) };
+//! let slide = load_address.wrapping_sub(link_address);
+//! relox_core::apply_arm_relative(crel, image, link_address, slide)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::decompress::{decode_offsets, elf32_relocate, saturating_usize, Header, TARGET_MACHINE};
+use crate::endian::Endianness;
+use crate::error::Error;
+use crate::fdpic::R_ARM_RELATIVE;
+
+/// Relative relocation of RISC-V, `B + A`.
+pub const R_RISCV_RELATIVE: u8 = 3;
+/// Relative relocation of x86, `B + A`.
+pub const R_386_RELATIVE: u8 = 8;
+
+/// Target machine (`e_machine`) of x86.
+const EM_386: u16 = 3;
+/// Target machine (`e_machine`) of 32-bit Arm.
+const EM_ARM: u16 = 40;
+/// Target machine (`e_machine`) of RISC-V.
+const EM_RISCV: u16 = 243;
+
+/// Describes how a relocated word is patched.
+#[derive(Copy, Clone, Debug)]
+pub enum PatchPolicy {
+    /// Adds the bias to the word.
+    AddBias,
+    /// Replaces the word with the given value.
+    Replace(u32),
+    /// Adds `bias >> shift` to the contiguous bit field selected by `mask`,
+    /// leaving the other bits of the word untouched.
+    Masked {
+        /// Bits of the word which are relocated.
+        mask: u32,
+        /// Right shift applied to the bias before it is added to the field.
+        shift: u32,
+    },
+    /// Replaces the word with the result of the function called with the
+    /// original word and the bias, e.g. to patch split immediates of MOVW/MOVT
+    /// instruction pairs.
+    ReadModifyWrite(fn(u32, u32) -> u32),
+}
+
+impl PatchPolicy {
+    /// Returns the patched value of `word`.
+    pub(crate) fn patch(&self, word: u32, bias: u32) -> u32 {
+        match *self {
+            PatchPolicy::AddBias => word.wrapping_add(bias),
+            PatchPolicy::Replace(value) => value,
+            PatchPolicy::Masked { mask, shift } => {
+                let lsb = mask.trailing_zeros() % 32;
+                let field = ((word & mask) >> lsb).wrapping_add(bias.wrapping_shr(shift));
+                (word & !mask) | (field.wrapping_shl(lsb) & mask)
+            }
+            PatchPolicy::ReadModifyWrite(op) => op(word, bias),
+        }
+    }
+}
+
+/// Translates the address of a relocated word to its offset in the image
+/// being patched, e.g. to redirect relocations of execute-in-place regions to
+/// a staging buffer.
+pub trait AddressTranslation {
+    /// Returns the offset of `address` in the image or `None` if `address` is
+    /// not mapped.
+    fn translate(&self, address: u32) -> Option<usize>;
+
+    /// Returns the offset of `address` in the image and the byte order of the
+    /// word stored there or `None` if `address` is not mapped.
+    ///
+    /// Words use native endianness unless overridden, e.g. by regions of ARM
+    /// BE8 images, whose instructions are little-endian but whose data is
+    /// big-endian.
+    fn translate_word(&self, address: u32) -> Option<(usize, Endianness)> {
+        self.translate(address)
+            .map(|offset| (offset, Endianness::native()))
+    }
+}
+
+impl<F: Fn(u32) -> Option<usize>> AddressTranslation for F {
+    fn translate(&self, address: u32) -> Option<usize> {
+        self(address)
+    }
+}
+
+/// A contiguous address range of a translation table.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Region {
+    address: u32,
+    size: u32,
+    offset: usize,
+    endianness: Endianness,
+}
+
+impl Region {
+    /// Creates a new `Region` instance mapping `size` bytes starting at
+    /// `address` to `offset` in the image.
+    pub fn new(address: u32, size: u32, offset: usize) -> Self {
+        Self::with_endianness(address, size, offset, Endianness::native())
+    }
+
+    /// Creates a new `Region` instance like [new](#method.new) whose words use
+    /// the given byte order.
+    pub fn with_endianness(address: u32, size: u32, offset: usize, endianness: Endianness) -> Self {
+        Self {
+            address,
+            size,
+            offset,
+            endianness,
+        }
+    }
+
+    /// Returns the first address of the region.
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the offset in the image the region is mapped to.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the byte order of the words of the region.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+}
+
+#[cfg(feature = "serde")]
+serde_struct!(Region {
+    address: u32,
+    size: u32,
+    offset: usize,
+    endianness: Endianness,
+});
+
+impl AddressTranslation for [Region] {
+    fn translate(&self, address: u32) -> Option<usize> {
+        self.translate_word(address).map(|(offset, _)| offset)
+    }
+
+    fn translate_word(&self, address: u32) -> Option<(usize, Endianness)> {
+        self.iter()
+            .find(|region| address.wrapping_sub(region.address) < region.size)
+            .map(|region| {
+                (
+                    region
+                        .offset
+                        .saturating_add(saturating_usize(address.wrapping_sub(region.address))),
+                    region.endianness,
+                )
+            })
+    }
+}
+
+/// Applies a compressed ELF32 relocation section to `image` using the patch
+/// policy registered for the type of each relocation in `policies`.
+///
+/// `image` is the memory which was linked at `image_address`. Relocated words
+/// may be unaligned and use native endianness.
+///
+/// Returns the number of bytes read from `data`.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if a relocation
+/// targets a word outside of `image`, `UnsupportedRelocationType` if there is
+/// no policy for the type of a relocation, or `Unsupported` if the section
+/// records a target machine other than
+/// [TARGET_MACHINE](constant.TARGET_MACHINE.html).
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn relocate_image(
+    data: &[u8],
+    image: &mut [u8],
+    image_address: u32,
+    bias: u32,
+    policies: &[(u8, PatchPolicy)],
+) -> Result<usize, Error> {
+    relocate_image_translated(
+        data,
+        image,
+        &|address: u32| Some(saturating_usize(address.wrapping_sub(image_address))),
+        bias,
+        policies,
+    )
+}
+
+/// Applies a compressed ELF32 relocation section to `image` like
+/// [relocate_image](fn.relocate_image.html), using `translation` to locate
+/// relocated words in `image` and to select their byte order.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if a relocation
+/// targets an address which is not translated to a word in `image`,
+/// `UnsupportedRelocationType` if there is no policy for the type of a
+/// relocation, or `Unsupported` if the section records a target machine
+/// other than [TARGET_MACHINE](constant.TARGET_MACHINE.html).
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn relocate_image_translated<T: AddressTranslation + ?Sized>(
+    data: &[u8],
+    image: &mut [u8],
+    translation: &T,
+    bias: u32,
+    policies: &[(u8, PatchPolicy)],
+) -> Result<usize, Error> {
+    patch_image(
+        data,
+        image,
+        translation,
+        bias,
+        policies,
+        &mut |_, _, _, _| Ok(()),
+    )
+}
+
+/// Applies a compressed ELF32 relocation section to `image` like
+/// [relocate_image_translated](fn.relocate_image_translated.html) and calls
+/// `op` with the type, the address and the old and new value of every
+/// patched word.
+pub(crate) fn patch_image<T, F>(
+    data: &[u8],
+    image: &mut [u8],
+    translation: &T,
+    bias: u32,
+    policies: &[(u8, PatchPolicy)],
+    op: &mut F,
+) -> Result<usize, Error>
+where
+    T: AddressTranslation + ?Sized,
+    F: FnMut(u8, u32, u32, u32) -> Result<(), Error>,
+{
+    Header::read(data)?.check_machine(TARGET_MACHINE)?;
+    elf32_relocate(data, &mut |relocation_type, address| {
+        let policy = policies
+            .iter()
+            .find(|(policy_type, _)| *policy_type == relocation_type)
+            .map(|(_, policy)| policy)
+            .ok_or_else(Error::unsupported_relocation_type)?;
+        let (offset, endianness) = translate(translation, address)?;
+        let word = word_ptr(image, offset)?;
+        let old = endianness.convert(unsafe { word.read_unaligned() });
+        let value = policy.patch(old, bias);
+        unsafe { word.write_unaligned(endianness.convert(value)) };
+        op(relocation_type, address, old, value)
+    })
+}
+
+/// Applies a compressed ELF32 relocation section like
+/// [relocate_image](fn.relocate_image.html) through caller provided word
+/// accessors and reads every patched word back, e.g. when patching flash via
+/// a driver which can silently fail.
+///
+/// `read` returns the word at the given address and `write` stores a word at
+/// the given address. Applying stops at the first word which does not read
+/// back as written.
+///
+/// Returns the address of the first mismatching word or `None` if every word
+/// was verified.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if `read` or `write`
+/// fails, `UnsupportedRelocationType` if there is no policy for the type of a
+/// relocation, or `Unsupported` if the section records a target machine
+/// other than [TARGET_MACHINE](constant.TARGET_MACHINE.html).
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn relocate_verified<R, W>(
+    data: &[u8],
+    bias: u32,
+    policies: &[(u8, PatchPolicy)],
+    read: &mut R,
+    write: &mut W,
+) -> Result<Option<u32>, Error>
+where
+    R: FnMut(u32) -> Result<u32, Error>,
+    W: FnMut(u32, u32) -> Result<(), Error>,
+{
+    Header::read(data)?.check_machine(TARGET_MACHINE)?;
+    let mut mismatch = None;
+    let result = elf32_relocate(data, &mut |relocation_type, address| {
+        let policy = policies
+            .iter()
+            .find(|(policy_type, _)| *policy_type == relocation_type)
+            .map(|(_, policy)| policy)
+            .ok_or_else(Error::unsupported_relocation_type)?;
+        let value = policy.patch(read(address)?, bias);
+        write(address, value)?;
+        if read(address)? != value {
+            mismatch = Some(address);
+            // Stops decoding, the error is replaced by the address.
+            return Err(Error::invalid_data());
+        }
+        Ok(())
+    });
+    match mismatch {
+        Some(address) => Ok(Some(address)),
+        None => result.map(|_| None),
+    }
+}
+
+/// Applies a compressed ELF32 relocation section which contains relative
+/// relocations only.
+///
+/// `image` is the memory which was linked at `image_address`. Every
+/// relocation must be of type `relocation_type` and must target a
+/// word-aligned word in `image`, which is incremented by `bias` using native
+/// endianness.
+///
+/// Returns the number of bytes read from `data`.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if a relocation does
+/// not target a word-aligned word in `image`, `UnsupportedRelocationType` if
+/// it contains a relocation of another type, or `Unsupported` if the section
+/// records a target machine other than [TARGET_MACHINE](constant.TARGET_MACHINE.html).
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_relative(
+    data: &[u8],
+    relocation_type: u8,
+    image: &mut [u8],
+    image_address: u32,
+    bias: u32,
+) -> Result<usize, Error> {
+    elf32_relocate_relative_translated(
+        data,
+        relocation_type,
+        image,
+        &linked_at(image_address),
+        bias,
+    )
+}
+
+/// Applies a compressed ELF32 relocation section which contains relative
+/// relocations only like
+/// [elf32_relocate_relative](fn.elf32_relocate_relative.html), using
+/// `translation` to locate relocated words in `image` and to select their byte
+/// order.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if a relocation
+/// targets an address which is not translated to a word-aligned word in
+/// `image`, `UnsupportedRelocationType` if it contains a relocation of another
+/// type, or `Unsupported` if the section records a target machine other than
+/// [TARGET_MACHINE](constant.TARGET_MACHINE.html).
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_relative_translated<T: AddressTranslation + ?Sized>(
+    data: &[u8],
+    relocation_type: u8,
+    image: &mut [u8],
+    translation: &T,
+    bias: u32,
+) -> Result<usize, Error> {
+    relocate_relative(
+        data,
+        TARGET_MACHINE,
+        relocation_type,
+        image,
+        translation,
+        bias,
+    )
+}
+
+/// Applies the `R_ARM_RELATIVE` relocations of a compressed ELF32 relocation
+/// section to `image`, which was linked at `image_address`, adding `slide`,
+/// the difference of the load and link addresses, to every relocated word.
+///
+/// Returns the number of bytes read from `data`.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if a relocation does
+/// not target a word-aligned word in `image`, `UnsupportedRelocationType` if
+/// it contains a relocation of another type, or `Unsupported` if the section
+/// records a target machine other than 32-bit Arm.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn apply_arm_relative(
+    data: &[u8],
+    image: &mut [u8],
+    image_address: u32,
+    slide: u32,
+) -> Result<usize, Error> {
+    relocate_relative(
+        data,
+        Some(EM_ARM),
+        R_ARM_RELATIVE,
+        image,
+        &linked_at(image_address),
+        slide,
+    )
+}
+
+/// Applies the `R_RISCV_RELATIVE` relocations of a compressed ELF32
+/// relocation section to `image` like
+/// [apply_arm_relative](fn.apply_arm_relative.html).
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if a relocation does
+/// not target a word-aligned word in `image`, `UnsupportedRelocationType` if
+/// it contains a relocation of another type, or `Unsupported` if the section
+/// records a target machine other than RISC-V.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn apply_riscv_relative(
+    data: &[u8],
+    image: &mut [u8],
+    image_address: u32,
+    slide: u32,
+) -> Result<usize, Error> {
+    relocate_relative(
+        data,
+        Some(EM_RISCV),
+        R_RISCV_RELATIVE,
+        image,
+        &linked_at(image_address),
+        slide,
+    )
+}
+
+/// Applies the `R_386_RELATIVE` relocations of a compressed ELF32 relocation
+/// section to `image` like [apply_arm_relative](fn.apply_arm_relative.html).
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if a relocation does
+/// not target a word-aligned word in `image`, `UnsupportedRelocationType` if
+/// it contains a relocation of another type, or `Unsupported` if the section
+/// records a target machine other than x86.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn apply_x86_relative(
+    data: &[u8],
+    image: &mut [u8],
+    image_address: u32,
+    slide: u32,
+) -> Result<usize, Error> {
+    relocate_relative(
+        data,
+        Some(EM_386),
+        R_386_RELATIVE,
+        image,
+        &linked_at(image_address),
+        slide,
+    )
+}
+
+/// Returns the translation of an image linked at `image_address`.
+#[inline(always)]
+fn linked_at(image_address: u32) -> impl Fn(u32) -> Option<usize> {
+    move |address: u32| Some(saturating_usize(address.wrapping_sub(image_address)))
+}
+
+/// Applies a compressed ELF32 relocation section which contains relocations
+/// of `relocation_type` only and may record `machine` as target machine,
+/// adding `bias` to every relocated word.
+fn relocate_relative<T: AddressTranslation + ?Sized>(
+    data: &[u8],
+    machine: Option<u16>,
+    relocation_type: u8,
+    image: &mut [u8],
+    translation: &T,
+    bias: u32,
+) -> Result<usize, Error> {
+    let header = Header::read(data)?;
+    header.check_machine(machine)?;
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut end = offset;
+    for _ in 0..header.count {
+        let group = header.group(data, offset, address)?;
+        if group.relocation_type != relocation_type {
+            return Err(Error::unsupported_relocation_type());
+        }
+        let (read, last) = decode_offsets(
+            data,
+            group.codec,
+            group.scale,
+            group.offsets,
+            group.count,
+            group.address,
+            &mut |address| {
+                let (offset, endianness) = translate(translation, address)?;
+                patch_word(image, offset, endianness, bias)
+            },
+        )?;
+        end = read;
+        offset = header.next(offset, end);
+        address = last;
+    }
+    Ok(end)
+}
+
+/// Returns the offset of `address` in the image and the byte order of the
+/// word stored there.
+#[inline(always)]
+fn translate<T: AddressTranslation + ?Sized>(
+    translation: &T,
+    address: u32,
+) -> Result<(usize, Endianness), Error> {
+    translation
+        .translate_word(address)
+        .ok_or_else(Error::invalid_data)
+}
+
+/// Returns a pointer to the word at `offset` in `image`.
+#[inline(always)]
+pub(crate) fn word_ptr(image: &mut [u8], offset: usize) -> Result<*mut u32, Error> {
+    if !cfg!(feature = "no_bounds_check") && (offset >= image.len() || image.len() - offset < 4) {
+        return Err(Error::invalid_data());
+    }
+    Ok(unsafe { image.as_mut_ptr().add(offset) } as *mut u32)
+}
+
+/// Adds `bias` to the word of the given byte order at `offset` in `image`
+/// using a word store.
+#[inline(always)]
+fn patch_word(
+    image: &mut [u8],
+    offset: usize,
+    endianness: Endianness,
+    bias: u32,
+) -> Result<(), Error> {
+    let word = word_ptr(image, offset)?;
+    // Checked whatever the features, as the word store needs the alignment.
+    if (word as usize) & 0x3 != 0 {
+        return Err(Error::invalid_data());
+    }
+    unsafe { *word = endianness.convert(endianness.convert(*word).wrapping_add(bias)) };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use core::cell::Cell;
+
+    const CREL: [u8; 10] = [
+        0x04, 0x10, 0x00, 0x00, // base_address
+        0x01, // count
+        0x17, // group[0].relocation_type
+        0x03, // group[0].count
+        0x00, // group[0].offsets[0]
+        0x04, // group[0].offsets[1]
+        0x08, // group[0].offsets[2]
+    ];
+
+    fn as_bytes(words: &mut [u32]) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 4) }
+    }
+
+    #[test]
+    fn test_relocate_relative() {
+        let mut image: [u32; 5] = [1, 2, 3, 4, 5];
+        let read =
+            elf32_relocate_relative(&CREL, 0x17, as_bytes(&mut image), 0x1000, 0x100).unwrap();
+        assert_eq!(read, CREL.len());
+        assert_eq!(image, [1, 0x102, 0x103, 4, 0x105]);
+    }
+
+    /// Returns `CREL` recording `machine` as target machine.
+    fn crel_for_machine(machine: u16) -> [u8; 13] {
+        let mut data = [0; 13];
+        data[..4].copy_from_slice(&CREL[..4]);
+        data[4] = 0xFC;
+        data[5..7].copy_from_slice(&machine.to_ne_bytes());
+        data[7..].copy_from_slice(&CREL[4..]);
+        data
+    }
+
+    #[test]
+    fn test_relocate_relative_machine() {
+        let machine = TARGET_MACHINE.unwrap_or(0);
+        let mut image: [u32; 5] = [1, 2, 3, 4, 5];
+        let crel = crel_for_machine(machine);
+        let read =
+            elf32_relocate_relative(&crel, 0x17, as_bytes(&mut image), 0x1000, 0x100).unwrap();
+        assert_eq!(read, crel.len());
+        assert_eq!(image, [1, 0x102, 0x103, 4, 0x105]);
+        if TARGET_MACHINE.is_some() {
+            let crel = crel_for_machine(machine.wrapping_add(1));
+            let err = elf32_relocate_relative(&crel, 0x17, as_bytes(&mut image), 0x1000, 0x100)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Unsupported);
+            let err = relocate_image(
+                &crel,
+                as_bytes(&mut image),
+                0x1000,
+                0x100,
+                &[(0x17, PatchPolicy::AddBias)],
+            )
+            .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Unsupported);
+            assert_eq!(image, [1, 0x102, 0x103, 4, 0x105]);
+        }
+    }
+
+    #[test]
+    fn test_relocate_relative_bad_type() {
+        let mut image: [u32; 5] = [0; 5];
+        let err =
+            elf32_relocate_relative(&CREL, 0x02, as_bytes(&mut image), 0x1000, 0x100).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedRelocationType);
+        assert_eq!(image, [0; 5]);
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_relocate_relative_outside_image() {
+        let mut image: [u32; 4] = [0; 4];
+        let err =
+            elf32_relocate_relative(&CREL, 0x17, as_bytes(&mut image), 0x1000, 0x100).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut image: [u32; 5] = [0; 5];
+        let err =
+            elf32_relocate_relative(&CREL, 0x17, as_bytes(&mut image), 0x1008, 0x100).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_relocate_relative_unaligned() {
+        let mut image: [u32; 6] = [0; 6];
+        let err = elf32_relocate_relative(&CREL, 0x17, &mut as_bytes(&mut image)[1..], 0x1000, 1)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_apply_relative() {
+        type Apply = fn(&[u8], &mut [u8], u32, u32) -> Result<usize, Error>;
+        let helpers: [(Apply, u16, u8); 3] = [
+            (apply_arm_relative, 40, 23),
+            (apply_riscv_relative, 243, 3),
+            (apply_x86_relative, 3, 8),
+        ];
+        for (apply, machine, relocation_type) in helpers.iter() {
+            let mut crel = CREL;
+            crel[5] = *relocation_type;
+            let mut image: [u32; 5] = [1, 2, 3, 4, 5];
+            let read = apply(&crel, as_bytes(&mut image), 0x1000, 0x100).unwrap();
+            assert_eq!(read, crel.len());
+            assert_eq!(image, [1, 0x102, 0x103, 4, 0x105]);
+
+            let mut tagged = crel_for_machine(*machine);
+            tagged[8] = *relocation_type;
+            apply(&tagged, as_bytes(&mut image), 0x1000, 0x100).unwrap();
+            assert_eq!(image, [1, 0x202, 0x203, 4, 0x205]);
+
+            let other = helpers
+                .iter()
+                .find(|(_, other, _)| other != machine)
+                .unwrap();
+            let mut tagged = crel_for_machine(other.1);
+            tagged[8] = *relocation_type;
+            let err = apply(&tagged, as_bytes(&mut image), 0x1000, 0x100).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+            let err = apply(&CREL_MIXED, as_bytes(&mut image), 0x1000, 0x100).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::UnsupportedRelocationType);
+        }
+    }
+
+    const CREL_MIXED: [u8; 12] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x01, // group[0].count
+        0x04, // group[0].offsets[0]
+        0x17, // group[1].relocation_type
+        0x02, // group[1].count
+        0x00, // group[1].offsets[0]
+        0x08, // group[1].offsets[1]
+    ];
+
+    #[test]
+    fn test_patch_policy() {
+        assert_eq!(PatchPolicy::AddBias.patch(0x10, 0x100), 0x110);
+        assert_eq!(PatchPolicy::Replace(0x1234).patch(0x10, 0x100), 0x1234);
+        let masked = PatchPolicy::Masked {
+            mask: 0x0000_FF00,
+            shift: 8,
+        };
+        assert_eq!(masked.patch(0xAA01_02BB, 0x0300), 0xAA01_05BB);
+        assert_eq!(masked.patch(0xAA01_FFBB, 0x0100), 0xAA01_00BB);
+        let rmw = PatchPolicy::ReadModifyWrite(|word, bias| word ^ bias);
+        assert_eq!(rmw.patch(0xFF, 0x0F), 0xF0);
+    }
+
+    #[test]
+    fn test_relocate_image() {
+        let mut image: [u32; 4] = [0x10, 0x20, 0x30, 0x40];
+        let policies = [
+            (0x02, PatchPolicy::Replace(0xCAFE)),
+            (0x17, PatchPolicy::AddBias),
+        ];
+        let read =
+            relocate_image(&CREL_MIXED, as_bytes(&mut image), 0x1000, 0x100, &policies).unwrap();
+        assert_eq!(read, CREL_MIXED.len());
+        assert_eq!(image, [0x110, 0xCAFE, 0x130, 0x40]);
+    }
+
+    #[test]
+    fn test_relocate_image_unaligned() {
+        let mut image: [u8; 13] = [0; 13];
+        let policies = [(0x02, PatchPolicy::AddBias), (0x17, PatchPolicy::AddBias)];
+        relocate_image(&CREL_MIXED, &mut image[1..], 0x1000, 0x01020304, &policies).unwrap();
+        assert_eq!(
+            u32::from_ne_bytes([image[5], image[6], image[7], image[8]]),
+            0x01020304
+        );
+    }
+
+    #[test]
+    fn test_relocate_image_no_policy() {
+        let mut image: [u32; 4] = [0; 4];
+        let policies = [(0x17, PatchPolicy::AddBias)];
+        let err = relocate_image(&CREL_MIXED, as_bytes(&mut image), 0x1000, 0x100, &policies)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedRelocationType);
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_relocate_image_outside_image() {
+        let mut image: [u32; 2] = [0; 2];
+        let policies = [(0x02, PatchPolicy::AddBias), (0x17, PatchPolicy::AddBias)];
+        let err = relocate_image(&CREL_MIXED, as_bytes(&mut image), 0x1000, 0x100, &policies)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_relocate_verified() {
+        let mut image: [u32; 4] = [0x10, 0x20, 0x30, 0x40];
+        let policies = [
+            (0x02, PatchPolicy::Replace(0xCAFE)),
+            (0x17, PatchPolicy::AddBias),
+        ];
+        let words = Cell::from_mut(&mut image[..]).as_slice_of_cells();
+        let mismatch = relocate_verified(
+            &CREL_MIXED,
+            0x100,
+            &policies,
+            &mut |address| Ok(words[(address - 0x1000) as usize / 4].get()),
+            &mut |address, value| {
+                words[(address - 0x1000) as usize / 4].set(value);
+                Ok(())
+            },
+        );
+        assert_eq!(mismatch.unwrap(), None);
+        assert_eq!(image, [0x110, 0xCAFE, 0x130, 0x40]);
+    }
+
+    #[test]
+    fn test_relocate_verified_mismatch() {
+        let mut image: [u32; 4] = [0x10, 0x20, 0x30, 0x40];
+        let policies = [(0x02, PatchPolicy::AddBias), (0x17, PatchPolicy::AddBias)];
+        let words = Cell::from_mut(&mut image[..]).as_slice_of_cells();
+        // The driver drops writes to 0x1000.
+        let mismatch = relocate_verified(
+            &CREL_MIXED,
+            0x100,
+            &policies,
+            &mut |address| Ok(words[(address - 0x1000) as usize / 4].get()),
+            &mut |address, value| {
+                if address != 0x1000 {
+                    words[(address - 0x1000) as usize / 4].set(value);
+                }
+                Ok(())
+            },
+        );
+        assert_eq!(mismatch.unwrap(), Some(0x1000));
+        assert_eq!(image, [0x10, 0x120, 0x30, 0x40]);
+
+        let err = relocate_verified(
+            &CREL_MIXED,
+            0x100,
+            &policies,
+            &mut |_| Err(Error::not_enough_data()),
+            &mut |_, _| Ok(()),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[test]
+    fn test_region() {
+        let region = Region::new(0x1000, 0x10, 0x20);
+        assert_eq!(region.address(), 0x1000);
+        assert_eq!(region.size(), 0x10);
+        assert_eq!(region.offset(), 0x20);
+        assert_eq!(region.endianness(), Endianness::native());
+        let region = Region::with_endianness(0x1000, 0x10, 0x20, Endianness::Big);
+        assert_eq!(region.endianness(), Endianness::Big);
+    }
+
+    #[test]
+    fn test_translation_table() {
+        let table = [
+            Region::new(0x1000, 0x08, 0x10),
+            Region::new(0x2000, 0x10, 0x00),
+        ];
+        assert_eq!(table[..].translate(0x0FFF), None);
+        assert_eq!(table[..].translate(0x1000), Some(0x10));
+        assert_eq!(table[..].translate(0x1007), Some(0x17));
+        assert_eq!(table[..].translate(0x1008), None);
+        assert_eq!(table[..].translate(0x200C), Some(0x0C));
+        assert_eq!(table[..].translate(0x2010), None);
+
+        // Regions may wrap the top of the address space.
+        let table = [Region::new(0xFFFF_FFF0, 0x20, 0x00)];
+        assert_eq!(table[..].translate(0xFFFF_FFFC), Some(0x0C));
+        assert_eq!(table[..].translate(0x0000_0008), Some(0x18));
+        assert_eq!(table[..].translate(0x0000_0010), None);
+    }
+
+    #[test]
+    fn test_relocate_image_translated() {
+        let mut image: [u32; 4] = [0x10, 0x20, 0x30, 0x40];
+        let table = [
+            Region::new(0x1000, 0x04, 0x0C),
+            Region::new(0x1004, 0x08, 0x00),
+        ];
+        let policies = [(0x02, PatchPolicy::AddBias), (0x17, PatchPolicy::AddBias)];
+        relocate_image_translated(
+            &CREL_MIXED,
+            as_bytes(&mut image),
+            &table[..],
+            0x100,
+            &policies,
+        )
+        .unwrap();
+        assert_eq!(image, [0x110, 0x120, 0x30, 0x140]);
+    }
+
+    #[test]
+    fn test_relocate_image_translated_unmapped() {
+        let mut image: [u32; 4] = [0; 4];
+        let policies = [(0x02, PatchPolicy::AddBias), (0x17, PatchPolicy::AddBias)];
+        let translation = |address: u32| if address < 0x1008 { Some(0) } else { None };
+        let err = relocate_image_translated(
+            &CREL_MIXED,
+            as_bytes(&mut image),
+            &translation,
+            0x100,
+            &policies,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_relocate_relative_translated() {
+        let mut image: [u32; 4] = [1, 2, 3, 4];
+        let table = [
+            Region::new(0x1004, 0x04, 0x08),
+            Region::new(0x1008, 0x04, 0x00),
+            Region::new(0x1010, 0x04, 0x0C),
+        ];
+        elf32_relocate_relative_translated(&CREL, 0x17, as_bytes(&mut image), &table[..], 0x100)
+            .unwrap();
+        assert_eq!(image, [0x101, 2, 0x103, 0x104]);
+    }
+
+    #[test]
+    fn test_relocate_image_translated_be8() {
+        let mut image: [u8; 16] = [
+            0x00, 0x00, 0x00, 0x10, // data, big-endian
+            0x20, 0x00, 0x00, 0x00, // code, little-endian
+            0x00, 0x00, 0x00, 0x30, // data, big-endian
+            0x00, 0x00, 0x00, 0x40, // not relocated
+        ];
+        let table = [
+            Region::with_endianness(0x1000, 0x04, 0x00, Endianness::Big),
+            Region::with_endianness(0x1004, 0x04, 0x04, Endianness::Little),
+            Region::with_endianness(0x1008, 0x08, 0x08, Endianness::Big),
+        ];
+        let policies = [(0x02, PatchPolicy::AddBias), (0x17, PatchPolicy::AddBias)];
+        relocate_image_translated(&CREL_MIXED, &mut image, &table[..], 0x0102, &policies).unwrap();
+        assert_eq!(
+            image,
+            [
+                0x00, 0x00, 0x01, 0x12, 0x22, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x32, 0x00, 0x00,
+                0x00, 0x40,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relocate_relative_translated_be8() {
+        let mut image: [u32; 4] = [0; 4];
+        let table = [Region::with_endianness(0x1004, 0x10, 0x00, Endianness::Big)];
+        elf32_relocate_relative_translated(&CREL, 0x17, as_bytes(&mut image), &table[..], 0x0102)
+            .unwrap();
+        let bytes = as_bytes(&mut image);
+        assert_eq!(bytes[0..4], [0x00, 0x00, 0x01, 0x02]);
+        assert_eq!(bytes[4..8], [0x00, 0x00, 0x01, 0x02]);
+        assert_eq!(bytes[8..12], [0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(bytes[12..16], [0x00, 0x00, 0x01, 0x02]);
+    }
+}
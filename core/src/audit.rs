@@ -0,0 +1,291 @@
+//! Audit log of applied relocations
+//!
+//! This module can be used to record every word patched while applying a
+//! compressed ELF32 relocation section, e.g. so that a secure boot flow can
+//! hash the record and report it.
+//!
+//! Every record is serialized as 13 bytes, independent of the target:
+//!
+//! ```text
+//! struct AuditRecord {
+//!     relocation_type: u8,
+//!     address: u32, // little-endian
+//!     old: u32,     // little-endian
+//!     new: u32,     // little-endian
+//! }
+//! ```
+
+use crate::apply::{patch_image, AddressTranslation, PatchPolicy};
+use crate::decompress::saturating_usize;
+use crate::error::Error;
+
+/// Size of a serialized audit record.
+pub const AUDIT_RECORD_SIZE: usize = 13;
+
+/// A relocation applied to a word.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    relocation_type: u8,
+    address: u32,
+    old: u32,
+    new: u32,
+}
+
+impl AuditRecord {
+    /// Creates a new `AuditRecord` instance.
+    pub fn new(relocation_type: u8, address: u32, old: u32, new: u32) -> Self {
+        Self {
+            relocation_type,
+            address,
+            old,
+            new,
+        }
+    }
+
+    /// Returns the type of the relocation.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the address of the patched word.
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Returns the value of the word before it was patched.
+    pub fn old(&self) -> u32 {
+        self.old
+    }
+
+    /// Returns the value of the word after it was patched.
+    pub fn new_value(&self) -> u32 {
+        self.new
+    }
+
+    /// Returns the serialized record.
+    pub fn to_bytes(&self) -> [u8; AUDIT_RECORD_SIZE] {
+        let mut bytes = [0; AUDIT_RECORD_SIZE];
+        bytes[0] = self.relocation_type;
+        bytes[1..5].copy_from_slice(&self.address.to_le_bytes());
+        bytes[5..9].copy_from_slice(&self.old.to_le_bytes());
+        bytes[9..13].copy_from_slice(&self.new.to_le_bytes());
+        bytes
+    }
+}
+
+#[cfg(feature = "serde")]
+serde_struct!(AuditRecord {
+    relocation_type: u8,
+    address: u32,
+    old: u32,
+    new: u32,
+});
+
+/// Destination of audit records.
+pub trait AuditLog {
+    /// Records an applied relocation.
+    ///
+    /// # Errors
+    ///
+    /// If the record cannot be stored, which stops applying relocations.
+    fn record(&mut self, record: &AuditRecord) -> Result<(), Error>;
+}
+
+impl<F: FnMut(&AuditRecord) -> Result<(), Error>> AuditLog for F {
+    fn record(&mut self, record: &AuditRecord) -> Result<(), Error> {
+        self(record)
+    }
+}
+
+/// Audit log serializing records into a caller provided buffer.
+pub struct AuditBuffer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> AuditBuffer<'a> {
+    /// Creates a new `AuditBuffer` instance writing to `buffer`.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Returns the number of bytes written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no record was written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the serialized records.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+impl AuditLog for AuditBuffer<'_> {
+    fn record(&mut self, record: &AuditRecord) -> Result<(), Error> {
+        let end = self.len + AUDIT_RECORD_SIZE;
+        if end > self.buffer.len() {
+            return Err(Error::buffer_small());
+        }
+        self.buffer[self.len..end].copy_from_slice(&record.to_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Applies a compressed ELF32 relocation section to `image` like
+/// [relocate_image](fn.relocate_image.html) and records every patched word
+/// in `log`.
+///
+/// # Errors
+///
+/// If applying the relocations fails, or if `log` fails to store a record,
+/// e.g. `BufferSmall` if an [AuditBuffer](struct.AuditBuffer.html) is full.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn relocate_image_audited<L: AuditLog + ?Sized>(
+    data: &[u8],
+    image: &mut [u8],
+    image_address: u32,
+    bias: u32,
+    policies: &[(u8, PatchPolicy)],
+    log: &mut L,
+) -> Result<usize, Error> {
+    relocate_image_translated_audited(
+        data,
+        image,
+        &|address: u32| Some(saturating_usize(address.wrapping_sub(image_address))),
+        bias,
+        policies,
+        log,
+    )
+}
+
+/// Applies a compressed ELF32 relocation section to `image` like
+/// [relocate_image_translated](fn.relocate_image_translated.html) and
+/// records every patched word in `log`.
+///
+/// # Errors
+///
+/// If applying the relocations fails, or if `log` fails to store a record.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn relocate_image_translated_audited<T, L>(
+    data: &[u8],
+    image: &mut [u8],
+    translation: &T,
+    bias: u32,
+    policies: &[(u8, PatchPolicy)],
+    log: &mut L,
+) -> Result<usize, Error>
+where
+    T: AddressTranslation + ?Sized,
+    L: AuditLog + ?Sized,
+{
+    patch_image(
+        data,
+        image,
+        translation,
+        bias,
+        policies,
+        &mut |relocation_type, address, old, new| {
+            log.record(&AuditRecord::new(relocation_type, address, old, new))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const CREL_MIXED: [u8; 12] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x01, // group[0].count
+        0x04, // group[0].offsets[0]
+        0x17, // group[1].relocation_type
+        0x02, // group[1].count
+        0x00, // group[1].offsets[0]
+        0x08, // group[1].offsets[1]
+    ];
+
+    #[test]
+    fn test_audit_record() {
+        let record = AuditRecord::new(0x17, 0x1000, 0x10, 0x110);
+        assert_eq!(record.relocation_type(), 0x17);
+        assert_eq!(record.address(), 0x1000);
+        assert_eq!(record.old(), 0x10);
+        assert_eq!(record.new_value(), 0x110);
+        assert_eq!(
+            record.to_bytes(),
+            [0x17, 0x00, 0x10, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x10, 0x01, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_relocate_image_audited() {
+        let mut image = [0u8; 16];
+        for (index, word) in image.chunks_mut(4).enumerate() {
+            word.copy_from_slice(&(0x10 * (index as u32 + 1)).to_ne_bytes());
+        }
+        let policies = [
+            (0x02, PatchPolicy::Replace(0xCAFE)),
+            (0x17, PatchPolicy::AddBias),
+        ];
+        let mut buffer = [0; 3 * AUDIT_RECORD_SIZE];
+        let mut log = AuditBuffer::new(&mut buffer);
+        assert!(log.is_empty());
+        relocate_image_audited(&CREL_MIXED, &mut image, 0x1000, 0x100, &policies, &mut log)
+            .unwrap();
+        assert_eq!(log.len(), 3 * AUDIT_RECORD_SIZE);
+        let bytes = log.as_bytes();
+        assert_eq!(
+            bytes[..AUDIT_RECORD_SIZE],
+            AuditRecord::new(0x02, 0x1004, 0x20, 0xCAFE).to_bytes()
+        );
+        assert_eq!(
+            bytes[2 * AUDIT_RECORD_SIZE..],
+            AuditRecord::new(0x17, 0x1008, 0x30, 0x130).to_bytes()
+        );
+
+        let mut records = 0;
+        relocate_image_audited(
+            &CREL_MIXED,
+            &mut image,
+            0x1000,
+            0x100,
+            &policies,
+            &mut |_: &AuditRecord| {
+                records += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(records, 3);
+    }
+
+    #[test]
+    fn test_relocate_image_audited_full() {
+        let mut image = [0u8; 16];
+        let policies = [(0x02, PatchPolicy::AddBias), (0x17, PatchPolicy::AddBias)];
+        let mut buffer = [0; 2 * AUDIT_RECORD_SIZE];
+        let mut log = AuditBuffer::new(&mut buffer);
+        let err =
+            relocate_image_audited(&CREL_MIXED, &mut image, 0x1000, 0x100, &policies, &mut log)
+                .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        assert_eq!(log.len(), 2 * AUDIT_RECORD_SIZE);
+    }
+}
@@ -0,0 +1,97 @@
+//! Apply compressed bFLT relocation tables
+//!
+//! uClinux FLAT (bFLT) binaries relocate every word listed in their
+//! relocation table by the address the text or data segment is loaded at.
+//! Their relocation table can be compressed like an ELF32 relocation section
+//! whose relocations are all of type
+//! [BFLT_RELOCATION_TYPE](constant.BFLT_RELOCATION_TYPE.html) and whose
+//! offsets are the offsets of the table, relative to the start of the text
+//! segment.
+
+use crate::apply::word_ptr;
+use crate::decompress::{elf32_relocate, saturating_usize};
+use crate::error::Error;
+
+/// Relocation type of the relocations of compressed bFLT relocation tables.
+pub const BFLT_RELOCATION_TYPE: u8 = 0;
+
+/// Applies a compressed bFLT relocation table to the text and data segments
+/// of a bFLT binary, as the loader would with the original table.
+///
+/// `text` holds the text segment (the file up to `data_start`) loaded at
+/// `text_address`, `data` holds the data and bss segments loaded at
+/// `data_address`. Offsets and relocated words below the size of `text`
+/// refer to the text segment, others to the data segment. Relocated words
+/// may be unaligned and use native endianness.
+///
+/// Returns the number of bytes read from `compressed`.
+///
+/// # Errors
+///
+/// If the compressed relocation table is malformed, if it contains a
+/// relocation of another type or if a relocation targets a word outside of
+/// the segments.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn bflt_relocate(
+    compressed: &[u8],
+    text: &mut [u8],
+    data: &mut [u8],
+    text_address: u32,
+    data_address: u32,
+) -> Result<usize, Error> {
+    let text_size = text.len() as u32;
+    let relocate = |value: u32| match value.checked_sub(text_size) {
+        Some(offset) => data_address.wrapping_add(offset),
+        None => text_address.wrapping_add(value),
+    };
+    elf32_relocate(compressed, &mut |relocation_type, offset| {
+        if relocation_type != BFLT_RELOCATION_TYPE {
+            return Err(Error::invalid_data());
+        }
+        let word = match offset.checked_sub(text_size) {
+            Some(offset) => word_ptr(data, saturating_usize(offset))?,
+            None => word_ptr(text, saturating_usize(offset))?,
+        };
+        unsafe { word.write_unaligned(relocate(word.read_unaligned())) };
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREL_BFLT: [u8; 10] = [
+        0x00, 0x00, 0x00, 0x00, // base_address
+        0x01, // count
+        0x00, // group[0].relocation_type
+        0x03, // group[0].count
+        0x04, 0x04, 0x04, // group[0].offsets
+    ];
+
+    #[test]
+    fn test_bflt_relocate() {
+        let mut text = [0; 8];
+        text[4..8].copy_from_slice(&6u32.to_ne_bytes());
+        let mut data = [0; 8];
+        data[0..4].copy_from_slice(&12u32.to_ne_bytes());
+        let read = bflt_relocate(&CREL_BFLT, &mut text, &mut data, 0x1000, 0x8000).unwrap();
+        assert_eq!(read, CREL_BFLT.len());
+        assert_eq!(text[4..8], 0x1006u32.to_ne_bytes());
+        assert_eq!(data[0..4], 0x8004u32.to_ne_bytes());
+        assert_eq!(data[4..8], 0x1000u32.to_ne_bytes());
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_bflt_relocate_outside() {
+        let mut text = [0; 8];
+        let mut data = [0; 6];
+        let err = bflt_relocate(&CREL_BFLT, &mut text, &mut data, 0x1000, 0x8000).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,464 @@
+//! Parsed compressed ELF32 relocation sections
+//!
+//! [Elf32CRel](struct.Elf32CRel.html) validates the header of a compressed
+//! section once and iterates over its relocations lazily, so it composes
+//! with `?` and `for` loops:
+//!
+//! ```
+//! use core::convert::TryFrom;
+//! use relox_core::Elf32CRel;
+//!
+//! # fn main() -> Result<(), relox_core::Error> {
+//! let data = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04];
+//! for relocation in Elf32CRel::try_from(&data[..])? {
+//!     let relocation = relocation?;
+//!     assert_eq!(relocation.relocation_type(), 0x17);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use core::convert::TryFrom;
+
+use crate::decompress::{read_symbol_at, Codec, Header};
+use crate::error::Error;
+use crate::reader::{
+    add_delta, add_offset, read_sleb128_at, read_u8, read_uleb128_at, scale_delta, scale_offset,
+    Checked,
+};
+use crate::rel::Elf32Rel;
+
+/// A compressed ELF32 relocation section with a validated header.
+#[derive(Copy, Clone, Debug)]
+pub struct Elf32CRel<'a> {
+    data: &'a [u8],
+    header: Header,
+}
+
+impl<'a> Elf32CRel<'a> {
+    /// Returns the base address of the relocations.
+    pub fn base_address(&self) -> u32 {
+        self.header.base_address
+    }
+
+    /// Returns the number of groups, or runs if the section is interleaved.
+    pub fn group_count(&self) -> u32 {
+        self.header.count
+    }
+
+    /// Returns the target machine (`e_machine`) recorded in the section, if
+    /// any.
+    pub fn machine(&self) -> Option<u16> {
+        self.header.machine
+    }
+
+    /// Returns the compressed section.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns an iterator over the relocations of the section.
+    pub fn iter(&self) -> Elf32CRelEntries<'a> {
+        Elf32CRelEntries {
+            data: self.data,
+            header: self.header,
+            groups: 0,
+            group: self.header.offset,
+            relocation_type: 0,
+            remaining: 0,
+            index: 0,
+            codec: self.header.codec,
+            address: self.header.base_address,
+            scale: 1,
+            tag: 0,
+            lanes: 0,
+            delta: 0,
+            symbols: None,
+            symbol: 0,
+            failed: false,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Elf32CRel<'a> {
+    type Error = Error;
+
+    /// Parses and validates the header of a compressed section.
+    ///
+    /// # Panics
+    ///
+    /// If the provided data is too small for any reason and `no_bounds_check`
+    /// feature is not requested.
+    fn try_from(data: &'a [u8]) -> Result<Self, Error> {
+        let header = Header::read(data)?;
+        Ok(Self { data, header })
+    }
+}
+
+impl<'a> IntoIterator for Elf32CRel<'a> {
+    type Item = Result<Elf32Rel, Error>;
+    type IntoIter = Elf32CRelEntries<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &Elf32CRel<'a> {
+    type Item = Result<Elf32Rel, Error>;
+    type IntoIter = Elf32CRelEntries<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Serializes the compressed section as bytes.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Elf32CRel<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(self.data)
+    }
+}
+
+/// Deserializes a compressed section borrowed from the input and validates
+/// its header.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Elf32CRel<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = <&[u8]>::deserialize(deserializer)?;
+        Self::try_from(data).map_err(|_| {
+            let unexpected = serde::de::Unexpected::Bytes(data);
+            serde::de::Error::invalid_value(unexpected, &"a compressed ELF32 relocation section")
+        })
+    }
+}
+
+/// Iterator over the relocations of a compressed section.
+///
+/// Yields the relocations in the order they are encoded. If the section is
+/// malformed, it yields the error once and stops.
+#[derive(Clone, Debug)]
+pub struct Elf32CRelEntries<'a> {
+    data: &'a [u8],
+    header: Header,
+    /// Number of groups started.
+    groups: u32,
+    /// Offset of the current group, or the next one if it is finished.
+    group: usize,
+    relocation_type: u8,
+    /// Number of relocations left in the current group.
+    remaining: u32,
+    /// Offset of the next encoded offset.
+    index: usize,
+    /// Codec of the offsets of the current group.
+    codec: Codec,
+    /// Last decoded address.
+    address: u32,
+    /// Scale the offsets of the current group are multiplied by.
+    scale: u32,
+    /// Remaining lengths of the current group-varint block, or the current
+    /// bitmap byte.
+    tag: u8,
+    /// Number of offsets left in the current group-varint block, number
+    /// of bits left in the current bitmap byte, or number of repeats left.
+    lanes: u32,
+    /// Offset of the current repeat.
+    delta: u32,
+    /// Offset of the next encoded symbol index, if the group carries them.
+    symbols: Option<usize>,
+    /// Last decoded symbol index.
+    symbol: u32,
+    failed: bool,
+}
+
+impl<'a> Elf32CRelEntries<'a> {
+    /// Moves to the next group holding relocations, if any.
+    fn next_group(&mut self) -> Result<bool, Error> {
+        while self.remaining == 0 {
+            if self.groups == self.header.count {
+                return Ok(false);
+            }
+            let group = self.header.group(self.data, self.group, self.address)?;
+            self.groups += 1;
+            self.relocation_type = group.relocation_type;
+            self.remaining = group.count;
+            self.index = group.offsets;
+            self.codec = group.codec;
+            self.address = group.address;
+            self.scale = group.scale;
+            self.tag = 0;
+            self.lanes = 0;
+            self.symbols = group.symbols;
+            self.symbol = 0;
+            if self.remaining == 0 {
+                self.group = self.header.next(self.group, self.index);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Decodes the next offset of the current group.
+    fn next_offset(&mut self) -> Result<u32, Error> {
+        match self.codec {
+            Codec::Uleb128 => return read_uleb128_at(self.data, &mut self.index),
+            Codec::Sleb128 => return read_sleb128_at(self.data, &mut self.index),
+            Codec::Bitmap => return self.next_bitmap_offset(),
+            Codec::Repeat => return self.next_repeat_offset(),
+            Codec::GroupVarint => {}
+        }
+        if self.lanes == 0 {
+            self.tag = read_u8(self.data, self.index)?;
+            self.index += 1;
+            self.lanes = self.remaining.min(4);
+        }
+        let length = (self.tag & 0x3) as usize + 1;
+        self.tag >>= 2;
+        self.lanes -= 1;
+        let mut offset = 0;
+        for byte in 0..length {
+            offset |= (read_u8(self.data, self.index + byte)? as u32) << (8 * byte);
+        }
+        self.index += length;
+        Ok(offset)
+    }
+
+    /// Decodes the next bitmap encoded offset of the current group.
+    fn next_bitmap_offset(&mut self) -> Result<u32, Error> {
+        let mut words = 0;
+        loop {
+            while self.lanes > 0 {
+                let bit = 7 - self.lanes;
+                self.lanes -= 1;
+                words += 1;
+                if self.tag & (1 << bit) != 0 {
+                    return Ok(4 * words);
+                }
+            }
+            // The highest bit continues the bitmap with another byte.
+            if self.tag & 0x80 == 0 {
+                break;
+            }
+            self.tag = read_u8(self.data, self.index)?;
+            self.index += 1;
+            self.lanes = 7;
+        }
+        let offset = read_uleb128_at(self.data, &mut self.index)?;
+        // A bitmap byte follows, unless this is the last relocation.
+        self.tag = 0x80;
+        Ok(offset)
+    }
+
+    /// Decodes the next offset of the current group holding repeats.
+    fn next_repeat_offset(&mut self) -> Result<u32, Error> {
+        if self.lanes == 0 {
+            let opcode = read_uleb128_at(self.data, &mut self.index)?;
+            if opcode & 1 == 0 {
+                return Ok(opcode >> 1);
+            }
+            self.lanes = opcode >> 1;
+            self.delta = read_uleb128_at(self.data, &mut self.index)?;
+            if self.lanes == 0 {
+                return Err(Error::invalid_data().at(self.index));
+            }
+        }
+        self.lanes -= 1;
+        Ok(self.delta)
+    }
+
+    fn next_entry(&mut self) -> Result<Option<Elf32Rel>, Error> {
+        if !self.next_group()? {
+            return Ok(None);
+        }
+        let offset = self.next_offset()?;
+        self.address = match self.codec {
+            Codec::Sleb128 => {
+                let delta = scale_delta::<Checked>(offset, self.scale, self.index)?;
+                add_delta::<Checked>(self.address, delta, self.index)?
+            }
+            _ => {
+                let offset = scale_offset::<Checked>(offset, self.scale, self.index)?;
+                add_offset::<Checked>(self.address, offset, self.index)?
+            }
+        };
+        if let Some(index) = self.symbols.as_mut() {
+            self.symbol = read_symbol_at(self.data, index, self.symbol)?;
+        }
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.group = self.header.next(self.group, self.index);
+        }
+        Ok(Some(Elf32Rel::with_symbol(
+            self.address,
+            self.relocation_type,
+            self.symbol,
+        )))
+    }
+}
+
+impl<'a> Iterator for Elf32CRelEntries<'a> {
+    type Item = Result<Elf32Rel, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let entry = self.next_entry();
+        self.failed = entry.is_err();
+        entry.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const CREL_TWO_GROUPS: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    fn decode(data: &[u8]) -> Result<([(u32, u8); 8], usize), Error> {
+        let mut relocations = [(0, 0); 8];
+        let mut count = 0;
+        for relocation in Elf32CRel::try_from(data)? {
+            let relocation = relocation?;
+            relocations[count] = (relocation.offset(), relocation.relocation_type());
+            count += 1;
+        }
+        Ok((relocations, count))
+    }
+
+    #[test]
+    fn test_elf32_crel() {
+        let crel = Elf32CRel::try_from(&CREL_TWO_GROUPS[..]).unwrap();
+        assert_eq!(crel.base_address(), 0x1000);
+        assert_eq!(crel.group_count(), 2);
+        assert_eq!(crel.machine(), None);
+        assert_eq!(crel.as_bytes(), CREL_TWO_GROUPS);
+        assert_eq!((&crel).into_iter().count(), 5);
+
+        let (relocations, count) = decode(&CREL_TWO_GROUPS).unwrap();
+        assert_eq!(
+            relocations[..count],
+            [
+                (0x1004, 0x02),
+                (0x1084, 0x02),
+                (0x1000, 0x16),
+                (0x1004, 0x16),
+                (0x1008, 0x16),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf32_crel_layouts() {
+        let interleaved = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xFF, 0x03, // interleaved, 3 runs
+            0x16, 0x01, 0x00, // run[0]
+            0x02, 0x01, 0x04, // run[1]
+            0x16, 0x00, // run[2], empty
+        ];
+        let (relocations, count) = decode(&interleaved).unwrap();
+        assert_eq!(relocations[..count], [(0x1000, 0x16), (0x1004, 0x02)]);
+
+        let group_varint = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xFB, 0x01, // group-varint, 1 group
+            0x17, 0x02, // group[0]
+            0x04, 0x04, 0x00, 0x01, // tag and offsets
+        ];
+        let (relocations, count) = decode(&group_varint).unwrap();
+        assert_eq!(relocations[..count], [(0x1004, 0x17), (0x1104, 0x17)]);
+
+        let bitmap = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xF9, 0x02, // bitmap, 2 groups
+            0x17, 0x05, // group[0]
+            0x04, 0x85, 0x01, 0x40, // offset and bitmap, last offset
+            0x02, 0x01, // group[1]
+            0x08, // last offset
+        ];
+        let (relocations, count) = decode(&bitmap).unwrap();
+        assert_eq!(
+            relocations[..count],
+            [
+                (0x1004, 0x17),
+                (0x1008, 0x17),
+                (0x1010, 0x17),
+                (0x1024, 0x17),
+                (0x1064, 0x17),
+                (0x1008, 0x02)
+            ]
+        );
+
+        let repeat = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xF6, 0x01, // repeated offsets, 1 group
+            0x17, 0x07, // group[0], repeated
+            0x05, 0x04, 0x08, // 2 x +0x4, +0x4
+        ];
+        let (relocations, count) = decode(&repeat).unwrap();
+        assert_eq!(
+            relocations[..count],
+            [(0x1004, 0x17), (0x1008, 0x17), (0x100C, 0x17)]
+        );
+
+        let scaled = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xF8, 0xF5, 0x01, // signed, scaled offsets, 1 group
+            0x17, 0x05, 0x04, // group[0], signed, scale
+            0x01, 0x7F, // +0x4, -0x4
+        ];
+        let (relocations, count) = decode(&scaled).unwrap();
+        assert_eq!(relocations[..count], [(0x1004, 0x17), (0x1000, 0x17)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_elf32_crel_deserialize() {
+        use serde::de::value::{BorrowedBytesDeserializer, Error as ValueError};
+        use serde::Deserialize;
+
+        let bytes = BorrowedBytesDeserializer::<ValueError>::new(&CREL_TWO_GROUPS);
+        let crel = Elf32CRel::deserialize(bytes).unwrap();
+        assert_eq!(crel.as_bytes(), CREL_TWO_GROUPS);
+        let bytes =
+            BorrowedBytesDeserializer::<ValueError>::new(&[0x00, 0x10, 0x00, 0x00, 0xFB, 0xFB]);
+        assert!(Elf32CRel::deserialize(bytes).is_err());
+    }
+
+    #[test]
+    fn test_elf32_crel_errors() {
+        let mut data = CREL_TWO_GROUPS;
+        data[4] = 0xFB;
+        data[5] = 0xFB;
+        let err = Elf32CRel::try_from(&data[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        #[cfg(not(feature = "no_bounds_check"))]
+        {
+            let err = Elf32CRel::try_from(&CREL_TWO_GROUPS[..3]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+            let crel = Elf32CRel::try_from(&CREL_TWO_GROUPS[..13]).unwrap();
+            let mut entries = crel.into_iter();
+            assert_eq!(entries.by_ref().filter(Result::is_ok).count(), 3);
+            assert!(entries.next().is_none());
+            let err = decode(&CREL_TWO_GROUPS[..13]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Bundles of compressed relocation sections
+//!
+//! Firmware with overlays holds one compressed relocation section per
+//! overlay and applies the one of the overlay being loaded. A bundle stores
+//! the sections behind a table of contents, so
+//! [bundle_relocate](fn.bundle_relocate.html) finds a section without
+//! decoding the others:
+//!
+//! ```text
+//! struct Elf32CRelBundleEntry {
+//!     id: u32,     // Identifier of the section, e.g. an ordinal or the
+//!                  // hash of its name
+//!     offset: u32, // Offset of the section from the start of the bundle
+//!     size: u32,   // Size of the section
+//! }
+//!
+//! struct Elf32CRelBundle {
+//!     magic: [u8; 4],                      // Always "RXBN"
+//!     count: u32,                          // Number of sections
+//!     entries: [Elf32CRelBundleEntry; count],
+//!     sections: [u8],                      // Until the end of the data
+//! }
+//! ```
+//!
+//! Words are in the byte order of the target, like the base address of the
+//! compressed sections. Identifiers are unique within a bundle.
+
+use crate::decompress::{elf32_relocate, saturating_usize};
+use crate::error::Error;
+use crate::reader::read_u32_at;
+
+/// Magic of a bundle.
+pub const BUNDLE_MAGIC: [u8; 4] = *b"RXBN";
+
+/// Size of the header of a bundle.
+const BUNDLE_HEADER_SIZE: usize = 8;
+
+/// Size of an entry of the table of contents of a bundle.
+const BUNDLE_ENTRY_SIZE: usize = 12;
+
+/// Returns the identifier of the section `name` in a bundle, the 32-bit
+/// FNV-1a hash of the name.
+///
+/// ```
+/// use relox_core::bundle_section_id;
+///
+/// const OVERLAY: u32 = bundle_section_id(".rel.overlay1");
+/// assert_eq!(bundle_section_id(""), 0x811C_9DC5);
+/// ```
+pub const fn bundle_section_id(name: &str) -> u32 {
+    let name = name.as_bytes();
+    let mut hash: u32 = 0x811C_9DC5;
+    let mut index = 0;
+    while index < name.len() {
+        hash = (hash ^ name[index] as u32).wrapping_mul(0x0100_0193);
+        index += 1;
+    }
+    hash
+}
+
+/// Table of contents of a bundle of compressed ELF32 relocation sections.
+#[derive(Copy, Clone, Debug)]
+pub struct Elf32CRelBundle<'a> {
+    data: &'a [u8],
+    count: usize,
+}
+
+impl<'a> Elf32CRelBundle<'a> {
+    /// Reads the table of contents of a bundle.
+    ///
+    /// # Errors
+    ///
+    /// `InvalidData` if `data` does not start with
+    /// [BUNDLE_MAGIC](constant.BUNDLE_MAGIC.html), or `NotEnoughData` if the
+    /// table of contents is truncated.
+    ///
+    /// # Panics
+    ///
+    /// If the provided data is too small for any reason and `no_bounds_check`
+    /// feature is not requested.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if !data.starts_with(&BUNDLE_MAGIC) {
+            return Err(Error::invalid_data());
+        }
+        let count = saturating_usize(read_u32_at(data, BUNDLE_MAGIC.len())?);
+        let size = count
+            .checked_mul(BUNDLE_ENTRY_SIZE)
+            .and_then(|size| size.checked_add(BUNDLE_HEADER_SIZE))
+            .ok_or_else(Error::not_enough_data)?;
+        if data.len() < size {
+            return Err(Error::not_enough_data().at(data.len()));
+        }
+        Ok(Self { data, count })
+    }
+
+    /// Returns the number of sections in the bundle.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if the bundle holds no section.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the identifier of the `index`th section of the table of
+    /// contents, if any.
+    pub fn id(&self, index: usize) -> Option<u32> {
+        match index < self.count {
+            true => read_u32_at(self.data, entry_offset(index)).ok(),
+            false => None,
+        }
+    }
+
+    /// Returns the compressed section identified by `id`.
+    ///
+    /// # Errors
+    ///
+    /// `SectionNotFound` if the bundle holds no section `id`, or
+    /// `InvalidData` if the section is outside of the bundle.
+    pub fn section(&self, id: u32) -> Result<&'a [u8], Error> {
+        let index = (0..self.count)
+            .find(|index| self.id(*index) == Some(id))
+            .ok_or_else(Error::section_not_found)?;
+        let entry = entry_offset(index);
+        let offset = saturating_usize(read_u32_at(self.data, entry + 4)?);
+        let size = saturating_usize(read_u32_at(self.data, entry + 8)?);
+        // The table of contents is checked whatever the features, as a wrong
+        // entry would make the decoder read any memory.
+        self.data
+            .get(offset..offset.saturating_add(size))
+            .ok_or_else(|| Error::invalid_data().at(entry))
+    }
+}
+
+/// Returns the offset of the `index`th entry of the table of contents.
+fn entry_offset(index: usize) -> usize {
+    BUNDLE_HEADER_SIZE + index * BUNDLE_ENTRY_SIZE
+}
+
+/// Processes the compressed ELF32 relocation section `section_id` of a bundle
+/// and calls `op` for every relocation, e.g. for the overlay being loaded.
+///
+/// Returns the number of bytes of the section processed.
+///
+/// # Errors
+///
+/// If the bundle or the section is malformed, `SectionNotFound` if the bundle
+/// holds no section `section_id`, or if `op` fails.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn bundle_relocate<F>(bundle: &[u8], section_id: u32, op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+{
+    let section = Elf32CRelBundle::parse(bundle)?.section(section_id)?;
+    elf32_relocate(section, op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const OVERLAY1: u32 = bundle_section_id(".rel.overlay1");
+
+    fn bundle() -> [u8; 49] {
+        let mut bundle = [0; 49];
+        bundle[..4].copy_from_slice(&BUNDLE_MAGIC);
+        bundle[4..8].copy_from_slice(&2u32.to_ne_bytes());
+        for (entry, (id, offset, size)) in [(0, 32, 9), (OVERLAY1, 41, 8)].iter().enumerate() {
+            let entry = &mut bundle[entry_offset(entry)..];
+            entry[..4].copy_from_slice(&id.to_ne_bytes());
+            entry[4..8].copy_from_slice(&u32::to_ne_bytes(*offset));
+            entry[8..12].copy_from_slice(&u32::to_ne_bytes(*size));
+        }
+        bundle[32..36].copy_from_slice(&0x1000u32.to_ne_bytes());
+        bundle[36..41].copy_from_slice(&[0x01, 0x17, 0x02, 0x00, 0x04]);
+        bundle[41..45].copy_from_slice(&0x2000u32.to_ne_bytes());
+        bundle[45..49].copy_from_slice(&[0x01, 0x02, 0x01, 0x00]);
+        bundle
+    }
+
+    #[test]
+    fn test_bundle_section_id() {
+        assert_eq!(bundle_section_id("a"), 0xE40C_292C);
+        assert_ne!(OVERLAY1, bundle_section_id(".rel.overlay2"));
+    }
+
+    #[test]
+    fn test_bundle_relocate() {
+        let bundle = bundle();
+        let parsed = Elf32CRelBundle::parse(&bundle).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(
+            (parsed.id(0), parsed.id(1), parsed.id(2)),
+            (Some(0), Some(OVERLAY1), None)
+        );
+        assert_eq!(parsed.section(0).unwrap(), &bundle[32..41]);
+
+        let mut relocations = [(0, 0); 3];
+        let mut count = 0;
+        let mut op = |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        };
+        assert_eq!(bundle_relocate(&bundle, OVERLAY1, &mut op).unwrap(), 8);
+        assert_eq!(bundle_relocate(&bundle, 0, &mut op).unwrap(), 9);
+        assert_eq!(
+            relocations,
+            [(0x02, 0x2000), (0x17, 0x1000), (0x17, 0x1004)]
+        );
+    }
+
+    #[test]
+    fn test_bundle_relocate_errors() {
+        let mut bundle = bundle();
+        let mut op = |_, _| Ok(());
+        let err = bundle_relocate(&bundle, 1, &mut op).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SectionNotFound);
+        let err = bundle_relocate(&bundle[1..], 0, &mut op).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = bundle_relocate(&bundle[..31], 0, &mut op).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+
+        bundle[28..32].copy_from_slice(&9u32.to_ne_bytes());
+        let err = bundle_relocate(&bundle, OVERLAY1, &mut op).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        bundle[4..8].copy_from_slice(&u32::MAX.to_ne_bytes());
+        let err = Elf32CRelBundle::parse(&bundle).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+}
@@ -0,0 +1,269 @@
+//! Compress ELF32 relocation sections without allocating
+//!
+//! Self-updating firmware can recompress relocations on the device, e.g.
+//! after rebasing an image. This module compresses them into a caller-provided
+//! buffer using a caller-provided scratch area instead of the heap, producing
+//! the same output as the compressor of `relox` with the grouped encoding,
+//! offsets sorted and repeated offsets disabled by
+//! `Elf32Relocs::set_repeat_offsets(false)`, as this module does not emit
+//! repeat opcodes.
+
+use crate::error::Error;
+use crate::layout::{SCALED, SYMBOLS};
+use crate::rel::Elf32Rel;
+use crate::uleb128;
+
+/// Compresses regular ELF32 relocations into `output` using the grouped
+/// layout and returns the number of bytes written.
+///
+/// The relocations are copied to `scratch`, which must have room for all of
+/// them, and sorted there by type and offset, so the lowest offset is the base
+/// address and offsets of a group are ascending. Symbol indices are recorded
+/// if any relocation has one. The base address is written in the byte order of
+/// the target.
+///
+/// ```
+/// use relox_core::{elf32_compress, Elf32Rel};
+///
+/// # fn main() -> Result<(), relox_core::Error> {
+/// let relocations = [Elf32Rel::new(0x1008, 0x17), Elf32Rel::new(0x1004, 0x17)];
+/// let mut scratch = [Elf32Rel::new(0, 0); 2];
+/// let mut output = [0; 16];
+/// let written = elf32_compress(&relocations, &mut scratch, &mut output)?;
+/// assert_eq!(output[4..written], [0x01, 0x17, 0x02, 0x00, 0x04]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// `BufferSmall` if `scratch` or `output` is too small, or `TooManyGroups`
+/// if there are too many relocation types for the grouped layout.
+pub fn elf32_compress(
+    relocations: &[Elf32Rel],
+    scratch: &mut [Elf32Rel],
+    output: &mut [u8],
+) -> Result<usize, Error> {
+    let entries = scratch
+        .get_mut(..relocations.len())
+        .ok_or_else(Error::buffer_small)?;
+    entries.copy_from_slice(relocations);
+    compress_entries(entries, output)
+}
+
+/// Compresses a regular ELF32 relocation section in the byte order of the
+/// target into `output` like [elf32_compress](fn.elf32_compress.html) and
+/// returns the number of bytes written.
+///
+/// `scratch` must have room for the relocations of the section, which is
+/// `section.len() / 8` entries. Bytes following the last complete entry are
+/// ignored.
+///
+/// # Errors
+///
+/// `BufferSmall` if `scratch` or `output` is too small, or `TooManyGroups`
+/// if there are too many relocation types for the grouped layout.
+pub fn elf32_compress_section(
+    section: &[u8],
+    scratch: &mut [Elf32Rel],
+    output: &mut [u8],
+) -> Result<usize, Error> {
+    let entries = scratch
+        .get_mut(..section.len() / 8)
+        .ok_or_else(Error::buffer_small)?;
+    for (entry, record) in entries.iter_mut().zip(section.chunks_exact(8)) {
+        let offset = u32::from_ne_bytes([record[0], record[1], record[2], record[3]]);
+        let info = u32::from_ne_bytes([record[4], record[5], record[6], record[7]]);
+        *entry = Elf32Rel::with_symbol(offset, info as u8, info >> 8);
+    }
+    compress_entries(entries, output)
+}
+
+/// Sorts `entries` by type and offset and compresses them into `output`.
+fn compress_entries(entries: &mut [Elf32Rel], output: &mut [u8]) -> Result<usize, Error> {
+    entries.sort_unstable_by_key(|entry| (entry.relocation_type(), entry.offset(), entry.symbol()));
+    let base_address = entries
+        .iter()
+        .map(Elf32Rel::offset)
+        .min()
+        .unwrap_or(u32::MAX);
+    let symbols = entries.iter().any(|entry| entry.symbol() != 0);
+    let mut groups = 0;
+    let mut previous = None;
+    for entry in entries.iter() {
+        if previous != Some(entry.relocation_type()) {
+            groups += 1;
+            previous = Some(entry.relocation_type());
+        }
+    }
+    // `SCALED` and the markers above it are reserved.
+    if groups >= SCALED as usize {
+        return Err(Error::too_many_groups());
+    }
+
+    let mut writer = Writer { output, len: 0 };
+    writer.write(&base_address.to_ne_bytes())?;
+    if symbols {
+        writer.write(&[SYMBOLS])?;
+    }
+    writer.write(&[groups as u8])?;
+    let mut rest = &entries[..];
+    while let Some(first) = rest.first() {
+        let count = rest
+            .iter()
+            .take_while(|entry| entry.relocation_type() == first.relocation_type())
+            .count();
+        let (group, next) = rest.split_at(count);
+        writer.write(&[first.relocation_type()])?;
+        let indices = group.iter().any(|entry| entry.symbol() != 0);
+        match (symbols, indices) {
+            (false, _) => writer.write_uleb128(count as u32)?,
+            (true, false) => writer.write_uleb128((count as u32) << 1)?,
+            (true, true) => {
+                writer.write_uleb128(((count as u32) << 1) | 1)?;
+                let mut symbol = 0u32;
+                for entry in group.iter() {
+                    // Zigzag encoded difference to the previous index.
+                    let delta = entry.symbol().wrapping_sub(symbol) as i32;
+                    writer.write_uleb128(((delta << 1) ^ (delta >> 31)) as u32)?;
+                    symbol = entry.symbol();
+                }
+            }
+        }
+        let mut address = base_address;
+        for entry in group.iter() {
+            writer.write_uleb128(entry.offset() - address)?;
+            address = entry.offset();
+        }
+        rest = next;
+    }
+    Ok(writer.len)
+}
+
+/// Writes to a byte slice.
+struct Writer<'a> {
+    output: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    /// Writes `bytes`.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.output
+            .get_mut(self.len..self.len + bytes.len())
+            .ok_or_else(Error::buffer_small)?
+            .copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    /// Writes a value encoded as ULEB128.
+    fn write_uleb128(&mut self, value: u32) -> Result<(), Error> {
+        let mut encoded = [0; uleb128::MAX_LEN_U32];
+        let written = uleb128::write_u32(value, &mut encoded)?;
+        self.write(&encoded[..written])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn relocations() -> [Elf32Rel; 4] {
+        [
+            Elf32Rel::with_symbol(0x1008, 0x02, 5),
+            Elf32Rel::with_symbol(0x1000, 0x17, 0),
+            Elf32Rel::with_symbol(0x1004, 0x02, 3),
+            Elf32Rel::with_symbol(0x1010, 0x17, 0),
+        ]
+    }
+
+    #[test]
+    fn test_compress() {
+        let relocations = relocations();
+        let mut scratch = [Elf32Rel::new(0, 0); 4];
+        let mut output = [0; 32];
+        let written = elf32_compress(&relocations, &mut scratch, &mut output).unwrap();
+        let mut expected = [0; 16];
+        expected[..4].copy_from_slice(&0x1000u32.to_ne_bytes());
+        expected[4..].copy_from_slice(&[
+            0xFA, 0x02, // symbols, 2 groups
+            0x02, 0x05, 0x06, 0x04, 0x04, 0x04, // symbols 3, 5
+            0x17, 0x04, 0x00, 0x10, // 0x1000, 0x1010
+        ]);
+        assert_eq!(output[..written], expected);
+
+        let mut section = [0; 32];
+        for (record, entry) in section.chunks_exact_mut(8).zip(relocations.iter()) {
+            let info = (entry.symbol() << 8) | entry.relocation_type() as u32;
+            record[..4].copy_from_slice(&entry.offset().to_ne_bytes());
+            record[4..].copy_from_slice(&info.to_ne_bytes());
+        }
+        let written = elf32_compress_section(&section, &mut scratch, &mut output).unwrap();
+        assert_eq!(output[..written], expected);
+    }
+
+    #[test]
+    fn test_compress_small() {
+        let relocations = relocations();
+        let mut scratch = [Elf32Rel::new(0, 0); 3];
+        let err = elf32_compress(&relocations, &mut scratch, &mut [0; 32]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        let mut scratch = [Elf32Rel::new(0, 0); 4];
+        for len in 0..16 {
+            let mut output = [0; 16];
+            let err = elf32_compress(&relocations, &mut scratch, &mut output[..len]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        }
+    }
+
+    #[test]
+    fn test_compress_too_many_groups() {
+        let mut relocations = [Elf32Rel::new(0, 0); 245];
+        for (relocation_type, entry) in relocations.iter_mut().enumerate() {
+            *entry = Elf32Rel::new(0x1000, relocation_type as u8);
+        }
+        let mut scratch = relocations;
+        let mut output = [0; 1024];
+        let err = elf32_compress(&relocations, &mut scratch, &mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooManyGroups);
+        let written = elf32_compress(&relocations[1..], &mut scratch, &mut output).unwrap();
+        assert_eq!((written, output[4]), (5 + 244 * 3, 244));
+    }
+
+    #[cfg(feature = "decompress")]
+    #[test]
+    fn test_compress_decompress() {
+        use crate::decompress::elf32_relocate_symbols;
+        use rand::prelude::*;
+
+        let mut rng = rand::thread_rng();
+        let mut relocations = [Elf32Rel::new(0, 0); 256];
+        for entry in relocations.iter_mut() {
+            *entry = Elf32Rel::with_symbol(
+                0x2000_0000 + 4 * rng.gen_range(0, 0x10000),
+                rng.gen_range(0, 4),
+                rng.gen_range(0, 3),
+            );
+        }
+        let mut scratch = [Elf32Rel::new(0, 0); 256];
+        let mut output = [0; 2048];
+        let written = elf32_compress(&relocations, &mut scratch, &mut output).unwrap();
+        let mut decompressed = [Elf32Rel::new(0, 0); 256];
+        let mut count = 0;
+        let read = elf32_relocate_symbols(
+            &output[..written],
+            &mut |relocation_type, address, symbol| {
+                decompressed[count] = Elf32Rel::with_symbol(address, relocation_type, symbol);
+                count += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!((read, count), (written, relocations.len()));
+        relocations.sort_unstable();
+        decompressed.sort_unstable();
+        assert_eq!(decompressed, relocations);
+    }
+}
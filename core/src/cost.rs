@@ -0,0 +1,224 @@
+//! Decode cost estimation
+//!
+//! This module can be used to estimate the work of decoding a compressed
+//! ELF32 relocation section on the target, e.g. to budget boot time before
+//! the firmware runs on hardware.
+
+use core::mem::size_of;
+
+use crate::decompress::{decode_offsets, saturating_usize, Codec, Group, Header, Layout};
+use crate::error::Error;
+
+/// Estimated work of decoding a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Elf32CRelDecodeCost {
+    relocations: u32,
+    groups: u32,
+    uleb_bytes: usize,
+    tag_bytes: usize,
+    size: usize,
+}
+
+impl Elf32CRelDecodeCost {
+    /// Returns the number of relocations, the number of times the decoder
+    /// calls back.
+    pub fn relocations(&self) -> u32 {
+        self.relocations
+    }
+
+    /// Returns the number of groups, runs or directory entries.
+    pub fn groups(&self) -> u32 {
+        self.groups
+    }
+
+    /// Returns the number of ULEB128 encoded bytes, each of which costs a
+    /// load, a shift and a continuation test.
+    pub fn uleb_bytes(&self) -> usize {
+        self.uleb_bytes
+    }
+
+    /// Returns the number of tag bytes of group-varint encoded offsets, each
+    /// of which costs a load and a mask for every offset it describes, or
+    /// the number of bytes of bitmap encoded offsets.
+    pub fn tag_bytes(&self) -> usize {
+        self.tag_bytes
+    }
+
+    /// Returns the number of bytes read from the compressed section.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the expected number of conditional branches: a continuation
+    /// test for every ULEB128 byte, a loop test for every group-varint tag
+    /// byte and every relocation and a loop test and a layout dispatch for
+    /// every group.
+    pub fn branches(&self) -> usize {
+        self.uleb_bytes
+            .saturating_add(self.tag_bytes)
+            .saturating_add(saturating_usize(self.relocations))
+            .saturating_add(saturating_usize(self.groups).saturating_mul(2))
+    }
+
+    /// Returns an estimate of the peak transient RAM of the decoder in bytes:
+    /// its header and group state and the cursors of the decoding loops. The
+    /// decoder does not allocate, so this does not depend on the section.
+    pub fn transient_ram(&self) -> usize {
+        size_of::<Header>() + size_of::<Group>() + 4 * size_of::<usize>()
+    }
+}
+
+#[cfg(feature = "serde")]
+serde_struct!(Elf32CRelDecodeCost {
+    relocations: u32,
+    groups: u32,
+    uleb_bytes: usize,
+    tag_bytes: usize,
+    size: usize,
+});
+
+/// Walks a compressed ELF32 relocation section without applying it and
+/// returns an estimate of the work of decoding it.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_decode_cost(data: &[u8]) -> Result<Elf32CRelDecodeCost, Error> {
+    let header = Header::read(data)?;
+    // Offset of the group count, which follows the machine, codec, symbol,
+    // signed, aligned, repeat and scaled prefixes.
+    let mut start = if header.machine.is_some() { 8 } else { 5 };
+    if header.codec != Codec::Uleb128 {
+        start += 1;
+    }
+    if header.symbols {
+        start += 1;
+    }
+    if header.signed {
+        start += 1;
+    }
+    if header.alignment > 1 {
+        start += 2;
+    }
+    if header.repeat {
+        start += 1;
+    }
+    if header.scaled {
+        start += 1;
+    }
+    let mut cost = Elf32CRelDecodeCost {
+        groups: header.count,
+        ..Elf32CRelDecodeCost::default()
+    };
+    if let Layout::Interleaved = header.layout {
+        cost.uleb_bytes += header.offset - start;
+    }
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut end = offset;
+    for _ in 0..header.count {
+        let group = header.group(data, offset, address)?;
+        // Padding preceding the group is skipped, not read.
+        let start = header.align(offset);
+        cost.uleb_bytes += match header.layout {
+            Layout::Directory => 0,
+            Layout::Based => group.offsets - start - 5,
+            _ => group.offsets - start - 1,
+        };
+        let (read, last) = decode_offsets(
+            data,
+            group.codec,
+            group.scale,
+            group.offsets,
+            group.count,
+            group.address,
+            &mut |_| Ok(()),
+        )?;
+        cost.relocations += group.count;
+        match group.codec {
+            Codec::Uleb128 | Codec::Sleb128 | Codec::Repeat => {
+                cost.uleb_bytes += read - group.offsets
+            }
+            Codec::GroupVarint => cost.tag_bytes += saturating_usize(group.count.div_ceil(4)),
+            // Offsets starting a run and bitmap bytes are not told apart.
+            Codec::Bitmap => cost.tag_bytes += read - group.offsets,
+        }
+        end = read;
+        offset = header.next(offset, end);
+        address = last;
+    }
+    cost.size = end;
+    Ok(cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREL_TWO_GROUPS: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    const CREL_INTERLEAVED: [u8; 14] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFF, // count, interleaved
+        0x02, // runs
+        0x02, // run[0].relocation_type
+        0x02, // run[0].count
+        0x00, 0x80, 0x01, // run[0].offsets
+        0x17, // run[1].relocation_type
+        0x01, // run[1].count
+        0x04, // run[1].offsets
+    ];
+
+    #[test]
+    fn test_decode_cost() {
+        let cost = elf32_decode_cost(&CREL_TWO_GROUPS).unwrap();
+        assert_eq!(cost.relocations(), 5);
+        assert_eq!(cost.groups(), 2);
+        assert_eq!(cost.uleb_bytes(), 8);
+        assert_eq!(cost.size(), CREL_TWO_GROUPS.len());
+        assert_eq!(cost.branches(), 8 + 5 + 4);
+        assert!(cost.transient_ram() > 0);
+    }
+
+    #[test]
+    fn test_decode_cost_interleaved() {
+        let cost = elf32_decode_cost(&CREL_INTERLEAVED).unwrap();
+        assert_eq!(cost.relocations(), 3);
+        assert_eq!(cost.groups(), 2);
+        assert_eq!(cost.uleb_bytes(), 7);
+        assert_eq!(cost.size(), CREL_INTERLEAVED.len());
+    }
+
+    #[test]
+    fn test_decode_cost_group_varint() {
+        let data = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xFB, // group-varint codec
+            0x01, // count
+            0x02, // group[0].relocation_type
+            0x05, // group[0].count
+            0x04, 0x00, 0x80, 0x00, 0x04, 0x04, // group[0].offsets[0..4]
+            0x00, 0x04, // group[0].offsets[4]
+        ];
+        let cost = elf32_decode_cost(&data).unwrap();
+        assert_eq!(cost.relocations(), 5);
+        assert_eq!(cost.uleb_bytes(), 1);
+        assert_eq!(cost.tag_bytes(), 2);
+        assert_eq!(cost.size(), data.len());
+        assert_eq!(cost.branches(), 1 + 2 + 5 + 2);
+    }
+}
@@ -0,0 +1,2624 @@
+//! Decompress a compressed ELF32 relocation section
+//!
+//! This module can be used to decompress a compressed ELF32 relocation section.
+
+use core::convert::TryFrom;
+use core::iter::FusedIterator;
+
+use crate::blob::{Elf32CRel, Elf32CRelEntries};
+use crate::error::{Error, ErrorKind};
+use crate::layout::{
+    ALIGNED, BASED, BITMAP, DIRECTORY, GROUP_VARINT, INTERLEAVED, MACHINE, REPEAT, SCALED, SIGNED,
+    SYMBOLS,
+};
+use crate::reader::{
+    add_delta, add_offset, read_sleb128_with, read_u16_with, read_u32_with, read_u8_with,
+    read_uleb128_at, read_uleb128_with, scale_delta, scale_offset, Access, Checked, Strict,
+    Unchecked,
+};
+#[cfg(feature = "heapless")]
+use crate::rel::Elf32Rel;
+
+/// Size of an entry of the group directory.
+const DIRECTORY_ENTRY_SIZE: usize = 9;
+
+/// Target machine (`e_machine`) of the running code, if it is known.
+pub const TARGET_MACHINE: Option<u16> = if cfg!(target_arch = "x86") {
+    Some(3)
+} else if cfg!(target_arch = "powerpc") {
+    Some(20)
+} else if cfg!(target_arch = "arm") {
+    Some(40)
+} else if cfg!(target_arch = "x86_64") {
+    Some(62)
+} else if cfg!(target_arch = "xtensa") {
+    Some(94)
+} else if cfg!(any(target_arch = "riscv32", target_arch = "riscv64")) {
+    Some(243)
+} else {
+    None
+};
+
+/// Processes a compressed ELF32 relocation section and calls `op` for every
+/// relocation for further processing.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+{
+    let header = Header::read(data)?;
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut end = offset;
+    for number in 0..header.count {
+        let group = header
+            .group(data, offset, address)
+            .map_err(|err| err.in_group(number, 0))?;
+        #[cfg(feature = "detailed_errors")]
+        let mut entry = 0;
+        let result = decode_offsets(
+            data,
+            group.codec,
+            group.scale,
+            group.offsets,
+            group.count,
+            group.address,
+            &mut |address| {
+                let result = op(group.relocation_type, address);
+                // Entries are only counted for the context of errors.
+                #[cfg(feature = "detailed_errors")]
+                if result.is_ok() {
+                    entry += 1;
+                }
+                result
+            },
+        );
+        #[cfg(feature = "detailed_errors")]
+        let result = result.map_err(|err| err.in_group(number, entry));
+        let (read, last) = result?;
+        end = read;
+        offset = header.next(offset, end);
+        address = last;
+    }
+    Ok(end)
+}
+
+/// Processes a compressed ELF32 relocation section like
+/// [elf32_relocate](fn.elf32_relocate.html) without bounds and sanity checks,
+/// whatever the `no_bounds_check` and `no_sanity_check` features, e.g. for the
+/// section embedded in the running image while sections loaded from external
+/// media are decoded with the checks.
+///
+/// # Errors
+///
+/// If `op` fails, or if the header of the section is malformed.
+///
+/// # Safety
+///
+/// `data` must hold a well-formed compressed relocation section, e.g. as
+/// written by the compressor. Malformed data makes this function read out of
+/// bounds.
+pub unsafe fn elf32_relocate_unchecked<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+{
+    let header = Header::read_with::<Unchecked>(data)?;
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut end = offset;
+    for _ in 0..header.count {
+        let group = header.group_with::<Unchecked>(data, offset, address)?;
+        let (read, last) = decode_offsets_with::<Unchecked, _>(
+            data,
+            group.codec,
+            group.scale,
+            group.offsets,
+            group.count,
+            group.address,
+            &mut |address| op(group.relocation_type, address),
+        )?;
+        end = read;
+        offset = header.next(offset, end);
+        address = last;
+    }
+    Ok(end)
+}
+
+/// Processes a compressed ELF32 relocation section like
+/// [elf32_relocate](fn.elf32_relocate.html), treating it as untrusted input,
+/// e.g. in secure boot loaders or fuzz targets.
+///
+/// All bounds and sanity checks are performed whatever the `no_bounds_check`
+/// and `no_sanity_check` features. Additionally, the section must end with
+/// its last group, LEB128 encodings must be minimal, groups must be sorted by
+/// type unless the section uses the interleaved encoding and no address may
+/// overflow. Sections with several groups of a type out of order, e.g. built
+/// by appending to a compressed section, are rejected.
+///
+/// Relocations are processed while the section is validated, so `op` may be
+/// called for some relocations before an error is returned.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or violates any of the
+/// above, or if `op` fails.
+pub fn elf32_relocate_strict<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+{
+    let header = Header::read_with::<Strict>(data)?;
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut end = offset;
+    let mut previous = None;
+    for number in 0..header.count {
+        let group = header
+            .group_with::<Strict>(data, offset, address)
+            .map_err(|err| err.in_group(number, 0))?;
+        // Runs of the interleaved encoding are in address order instead.
+        if header.layout != Layout::Interleaved && previous > Some(group.relocation_type) {
+            return Err(Error::invalid_data()
+                .at(header.align(offset))
+                .in_group(number, 0));
+        }
+        previous = Some(group.relocation_type);
+        #[cfg(feature = "detailed_errors")]
+        let mut entry = 0;
+        let result = decode_offsets_with::<Strict, _>(
+            data,
+            group.codec,
+            group.scale,
+            group.offsets,
+            group.count,
+            group.address,
+            &mut |address| {
+                let result = op(group.relocation_type, address);
+                #[cfg(feature = "detailed_errors")]
+                if result.is_ok() {
+                    entry += 1;
+                }
+                result
+            },
+        );
+        #[cfg(feature = "detailed_errors")]
+        let result = result.map_err(|err| err.in_group(number, entry));
+        let (read, last) = result?;
+        // Offset streams of the directory layout may be in any order.
+        end = end.max(read);
+        offset = header.next(offset, read);
+        address = last;
+    }
+    if end != data.len() {
+        return Err(Error::invalid_data().at(end));
+    }
+    Ok(end)
+}
+
+/// Processes a compressed ELF32 relocation section and calls `op` for every
+/// relocation with its type, address and symbol index.
+///
+/// The symbol index is 0 for relocations of groups which do not carry symbol
+/// indices, e.g. relative relocations. [elf32_relocate](fn.elf32_relocate.html)
+/// skips symbol indices.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_symbols<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(u8, u32, u32) -> Result<(), Error>,
+{
+    let header = Header::read(data)?;
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut end = offset;
+    for _ in 0..header.count {
+        let group = header.group(data, offset, address)?;
+        let mut symbols = group.symbols;
+        let mut symbol = 0;
+        let (read, last) = decode_offsets(
+            data,
+            group.codec,
+            group.scale,
+            group.offsets,
+            group.count,
+            group.address,
+            &mut |address| {
+                if let Some(index) = symbols.as_mut() {
+                    symbol = read_symbol_at(data, index, symbol)?;
+                }
+                op(group.relocation_type, address, symbol)
+            },
+        )?;
+        end = read;
+        offset = header.next(offset, end);
+        address = last;
+    }
+    Ok(end)
+}
+
+/// Iterator over the relocation types and addresses of a compressed ELF32
+/// relocation section, yielding what
+/// [elf32_relocate](fn.elf32_relocate.html) passes to its callback, so
+/// relocations can be consumed with `for` loops and iterator adapters:
+///
+/// ```
+/// use relox_core::Elf32CRelIter;
+///
+/// # fn main() -> Result<(), relox_core::Error> {
+/// let data = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04];
+/// for relocation in Elf32CRelIter::new(&data).take(1) {
+///     let (relocation_type, address) = relocation?;
+///     assert_eq!((relocation_type, address), (0x17, 0x1004));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// If the section is malformed, it yields the error once and stops.
+#[derive(Clone, Debug)]
+pub struct Elf32CRelIter<'a> {
+    /// Entries of the section, or the kind of the error of its header until
+    /// it is yielded.
+    entries: Result<Elf32CRelEntries<'a>, Option<ErrorKind>>,
+}
+
+impl<'a> Elf32CRelIter<'a> {
+    /// Creates an iterator over the relocations of a compressed section. The
+    /// header is validated by the first call to `next`.
+    ///
+    /// # Panics
+    ///
+    /// If the provided data is too small for any reason and `no_bounds_check`
+    /// feature is not requested.
+    pub fn new(data: &'a [u8]) -> Self {
+        let entries = Elf32CRel::try_from(data)
+            .map(|crel| crel.iter())
+            .map_err(|err| Some(err.kind()));
+        Self { entries }
+    }
+}
+
+impl Iterator for Elf32CRelIter<'_> {
+    type Item = Result<(u8, u32), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.entries.as_mut() {
+            Ok(entries) => entries
+                .next()
+                .map(|entry| entry.map(|entry| (entry.relocation_type(), entry.offset()))),
+            Err(kind) => kind.take().map(|kind| Err(Error::new(kind))),
+        }
+    }
+}
+
+impl FusedIterator for Elf32CRelIter<'_> {}
+
+/// Processes a compressed ELF32 relocation section like
+/// [elf32_relocate](fn.elf32_relocate.html) through a trait object, so the
+/// decoder is instantiated once however many callbacks a loader uses.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+#[inline(never)]
+pub fn elf32_relocate_dyn(
+    data: &[u8],
+    op: &mut dyn FnMut(u8, u32) -> Result<(), Error>,
+) -> Result<usize, Error> {
+    elf32_relocate(data, &mut |relocation_type, address| {
+        op(relocation_type, address)
+    })
+}
+
+/// Processes a compressed ELF32 relocation section and calls `op` for every
+/// relocation whose address is in `address_low..address_high`, e.g. to
+/// relocate a RAM bank only when it is powered up.
+///
+/// Returns the number of relocations `op` is called for.
+///
+/// Offsets within a group must be ascending, which is the case for
+/// relocation sections sorted by offset: decoding a group stops at the first
+/// address beyond the range and the rest of its offsets are skipped without
+/// decoding them, or not read at all if the section uses the directory
+/// layout. Sections using the interleaved encoding are not processed beyond
+/// the range. Group-varint, bitmap and signed offsets are decoded in full.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if `op` fails.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_range<F>(
+    data: &[u8],
+    address_low: u32,
+    address_high: u32,
+    op: &mut F,
+) -> Result<usize, Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+{
+    let header = Header::read(data)?;
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut relocations = 0;
+    for _ in 0..header.count {
+        let group = header.group(data, offset, address)?;
+        if group.codec != Codec::Uleb128 {
+            let (end, last) = decode_offsets(
+                data,
+                group.codec,
+                group.scale,
+                group.offsets,
+                group.count,
+                group.address,
+                &mut |address| {
+                    if address >= address_low && address < address_high {
+                        op(group.relocation_type, address)?;
+                        relocations += 1;
+                    }
+                    Ok(())
+                },
+            )?;
+            offset = header.next(offset, end);
+            address = last;
+            continue;
+        }
+        let mut index = group.offsets;
+        address = group.address;
+        let mut remaining = group.count;
+        let mut beyond = false;
+        while remaining > 0 && !beyond {
+            let delta = read_uleb128_at(data, &mut index)?;
+            let delta = scale_offset::<Checked>(delta, group.scale, index)?;
+            address = add_offset::<Checked>(address, delta, index)?;
+            remaining -= 1;
+            beyond = address >= address_high;
+            if !beyond && address >= address_low {
+                op(group.relocation_type, address)?;
+                relocations += 1;
+            }
+        }
+        if beyond {
+            match header.layout {
+                Layout::Grouped | Layout::Based => {
+                    index = skip_offsets::<Checked>(data, group.codec, index, remaining)?
+                }
+                Layout::Interleaved => break,
+                Layout::Directory => {}
+            }
+        }
+        offset = header.next(offset, index);
+    }
+    Ok(relocations)
+}
+
+/// Returns the number of relocation groups of a compressed ELF32 relocation
+/// section, or the number of runs if it uses the interleaved encoding.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_group_count(data: &[u8]) -> Result<u32, Error> {
+    Ok(Header::read(data)?.count)
+}
+
+/// Returns the target machine (`e_machine`) recorded in a compressed ELF32
+/// relocation section, if any.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed.
+pub fn elf32_machine(data: &[u8]) -> Result<Option<u16>, Error> {
+    Ok(Header::read(data)?.machine)
+}
+
+/// Checks that a compressed ELF32 relocation section targets `machine`, e.g.
+/// [TARGET_MACHINE](constant.TARGET_MACHINE.html), if it records its target
+/// machine.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed, or `Unsupported` if it
+/// records another target machine.
+pub fn elf32_check_machine(data: &[u8], machine: u16) -> Result<(), Error> {
+    Header::read(data)?.check_machine(Some(machine))
+}
+
+/// Decodes the group with the given number of a compressed ELF32 relocation
+/// section using the directory layout and calls `op` for every relocation of
+/// the group.
+///
+/// The group is located through the directory without decoding any other
+/// group, so groups can be processed independently, e.g. in parallel on the
+/// host.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed, if it does not use the
+/// directory layout or if there is no group with the given number.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_directory_group<F>(data: &[u8], number: u32, op: &mut F) -> Result<(), Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+{
+    let header = Header::read(data)?;
+    if header.layout != Layout::Directory {
+        return Err(Error::unsupported());
+    }
+    if number >= header.count {
+        return Err(Error::invalid_data());
+    }
+    let offset = header.offset + number as usize * DIRECTORY_ENTRY_SIZE;
+    let group = header.group(data, offset, header.base_address)?;
+    decode_offsets(
+        data,
+        group.codec,
+        group.scale,
+        group.offsets,
+        group.count,
+        group.address,
+        &mut |address| op(group.relocation_type, address),
+    )?;
+    Ok(())
+}
+
+/// Processes a compressed ELF32 relocation section like
+/// [elf32_relocate](fn.elf32_relocate.html) and additionally calls `hook`
+/// after every `interval` relocations, e.g. to kick a watchdog or to yield to
+/// a cooperative scheduler. `hook` is never called if `interval` is zero.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if `op` or `hook`
+/// fails.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_with_hook<F, H>(
+    data: &[u8],
+    op: &mut F,
+    interval: u32,
+    hook: &mut H,
+) -> Result<usize, Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+    H: FnMut() -> Result<(), Error>,
+{
+    let mut remaining = interval;
+    elf32_relocate(data, &mut |relocation_type, address| {
+        op(relocation_type, address)?;
+        if remaining > 0 {
+            remaining -= 1;
+            if remaining == 0 {
+                remaining = interval;
+                hook()?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// A relocation group of a compressed ELF32 relocation section with its
+/// offsets still encoded.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32CRelRawGroup<'a> {
+    relocation_type: u8,
+    count: u32,
+    base_address: u32,
+    offsets: &'a [u8],
+}
+
+impl<'a> Elf32CRelRawGroup<'a> {
+    /// Returns the type of the relocations in the group.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the number of relocations in the group.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the address the first offset of the group is relative to.
+    pub fn base_address(&self) -> u32 {
+        self.base_address
+    }
+
+    /// Returns the ULEB128 encoded offsets of the group: the first one is
+    /// relative to the base address, otherwise `offset[i + 1]` is relative to
+    /// `offset[i]`.
+    pub fn offsets(&self) -> &'a [u8] {
+        self.offsets
+    }
+}
+
+/// Processes a compressed ELF32 relocation section and calls `op` for every
+/// relocation group without decoding its offsets, e.g. for runtimes decoding
+/// offsets with a hand-written loop while relox handles framing and
+/// validation.
+///
+/// Offsets are validated to be `count` complete ULEB128 values, which are not
+/// longer than 5 bytes unless `no_sanity_check` feature is requested.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if `op` fails, or
+/// `Unsupported` if it uses the interleaved encoding, whose groups depend on
+/// the previous ones, the group-varint or bitmap codec, or signed, repeated
+/// or scaled offsets.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_raw<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(&Elf32CRelRawGroup) -> Result<(), Error>,
+{
+    let header = Header::read(data)?;
+    if header.layout == Layout::Interleaved
+        || header.codec != Codec::Uleb128
+        || header.signed
+        || header.repeat
+        || header.scaled
+    {
+        return Err(Error::unsupported());
+    }
+    let mut offset = header.offset;
+    let mut end = offset;
+    for _ in 0..header.count {
+        let group = header.group(data, offset, header.base_address)?;
+        end = skip_offsets::<Checked>(data, group.codec, group.offsets, group.count)?;
+        op(&Elf32CRelRawGroup {
+            relocation_type: group.relocation_type,
+            count: group.count,
+            base_address: group.address,
+            offsets: data
+                .get(group.offsets..end)
+                .ok_or_else(Error::not_enough_data)?,
+        })?;
+        offset = header.next(offset, end);
+    }
+    Ok(end)
+}
+
+/// Location of a relocation group in a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Elf32CRelGroupIndex {
+    offset: usize,
+    address: u32,
+    first: u32,
+    count: u32,
+}
+
+impl Elf32CRelGroupIndex {
+    /// Returns the offset of the group in the compressed section, or of its
+    /// entry in the group directory if the section uses the directory layout.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the address the first offset of the group is relative to: the
+    /// base address of the compressed section, or the last address of the
+    /// previous run if the section uses the interleaved encoding.
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Returns the index of the first relocation of the group.
+    pub fn first(&self) -> u32 {
+        self.first
+    }
+
+    /// Returns the number of relocations in the group.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+#[cfg(feature = "serde")]
+serde_struct!(Elf32CRelGroupIndex {
+    offset: usize,
+    address: u32,
+    first: u32,
+    count: u32,
+});
+
+/// Builds the group index table of a compressed ELF32 relocation section in
+/// `index` and returns the number of groups.
+///
+/// Relocations are numbered in the order they are decoded. Within a group
+/// this is the order of the regular relocation section, so for sections with
+/// a single relocation type, e.g. `.rel.plt`, the numbering is unchanged.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if `index` is too
+/// small.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_index_groups(data: &[u8], index: &mut [Elf32CRelGroupIndex]) -> Result<usize, Error> {
+    let header = Header::read(data)?;
+    let count = saturating_usize(header.count);
+    if index.len() < count {
+        return Err(Error::buffer_small());
+    }
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    let mut first = 0;
+    for entry in index.iter_mut().take(count) {
+        let group = header.group(data, offset, address)?;
+        *entry = Elf32CRelGroupIndex {
+            offset: header.align(offset),
+            address: group.address,
+            first,
+            count: group.count,
+        };
+        offset = match header.layout {
+            Layout::Directory => offset + DIRECTORY_ENTRY_SIZE,
+            _ => {
+                let (end, last) = decode_offsets(
+                    data,
+                    group.codec,
+                    group.scale,
+                    group.offsets,
+                    group.count,
+                    group.address,
+                    &mut |_| Ok(()),
+                )?;
+                address = last;
+                end
+            }
+        };
+        first += group.count;
+    }
+    Ok(count)
+}
+
+/// Decodes the relocation with the given number using the group index table
+/// built by [elf32_index_groups](fn.elf32_index_groups.html) and calls `op`
+/// for it, e.g. to bind a single PLT slot lazily.
+///
+/// Only the group of the relocation is decoded, up to the relocation itself.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if there is no
+/// relocation with the given number.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_one<F>(
+    data: &[u8],
+    index: &[Elf32CRelGroupIndex],
+    number: u32,
+    op: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(u8, u32) -> Result<(), Error>,
+{
+    let group = index
+        .iter()
+        .find(|group| number >= group.first && number - group.first < group.count)
+        .ok_or_else(Error::invalid_data)?;
+    let header = Header::read(data)?;
+    let encoded = header.group(data, group.offset, group.address)?;
+    let (_, address) = decode_offsets(
+        data,
+        encoded.codec,
+        encoded.scale,
+        encoded.offsets,
+        number - group.first + 1,
+        group.address,
+        &mut |_| Ok(()),
+    )?;
+    op(encoded.relocation_type, address)
+}
+
+/// Expands the relocation group `group` of the group index table built by
+/// [elf32_index_groups](fn.elf32_index_groups.html) into the absolute
+/// addresses of its relocations and writes them to `addresses`.
+///
+/// Returns the number of addresses written, e.g. for a DMA-driven patcher
+/// which sorts or coalesces writes before applying them. Groups are decoded
+/// whatever the layout, codec and prefixes of the section.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed, or `BufferSmall` if
+/// `addresses` is too small, in which case it is left untouched.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn decompress_group_into(
+    data: &[u8],
+    group: &Elf32CRelGroupIndex,
+    addresses: &mut [u32],
+) -> Result<usize, Error> {
+    let header = Header::read(data)?;
+    let encoded = header.group(data, group.offset, group.address)?;
+    if addresses.len() < saturating_usize(encoded.count) {
+        return Err(Error::buffer_small());
+    }
+    let mut written = 0;
+    decode_offsets(
+        data,
+        encoded.codec,
+        encoded.scale,
+        encoded.offsets,
+        encoded.count,
+        encoded.address,
+        &mut |address| {
+            addresses[written] = address;
+            written += 1;
+            Ok(())
+        },
+    )?;
+    Ok(written)
+}
+
+/// Decodes all relocations of a compressed ELF32 relocation section into a
+/// `heapless::Vec` with capacity `N`, e.g. for `no_std` runtimes which keep
+/// the full relocation table in RAM.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed, or `BufferSmall` if it
+/// contains more than `N` relocations.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+#[cfg(feature = "heapless")]
+pub fn decompress_into_heapless<const N: usize>(
+    data: &[u8],
+) -> Result<heapless::Vec<Elf32Rel, N>, Error> {
+    let mut relocations = heapless::Vec::new();
+    elf32_relocate(data, &mut |relocation_type, address| {
+        relocations
+            .push(Elf32Rel::new(address, relocation_type))
+            .map_err(|_| Error::buffer_small())
+    })?;
+    Ok(relocations)
+}
+
+/// Layout of a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Layout {
+    /// Relocations grouped by type.
+    Grouped,
+    /// Runs of relocations of the same type in address order.
+    Interleaved,
+    /// Relocations grouped by type with a group directory.
+    Directory,
+    /// Relocations grouped by type with a base address per group.
+    Based,
+}
+
+/// Codec of the offsets of a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Codec {
+    /// Every offset is encoded as ULEB128.
+    Uleb128,
+    /// Every four offsets share a tag byte holding their lengths less one in
+    /// two bits each, least significant bits first, followed by the offsets
+    /// as little-endian values of those lengths.
+    GroupVarint,
+    /// Every offset starting a run is encoded as ULEB128 and followed by
+    /// bitmap bytes, each marking which of the next seven words hold
+    /// relocations in its lower bits and continued by its highest bit.
+    Bitmap,
+    /// Every offset is encoded as SLEB128, for groups whose offsets are not
+    /// ascending.
+    Sleb128,
+    /// Offsets are encoded as ULEB128 opcodes, each holding an offset or a
+    /// repeat count followed by the repeated offset.
+    Repeat,
+}
+
+/// A group, run or directory entry of a compressed section.
+pub(crate) struct Group {
+    /// Type of the relocations.
+    pub(crate) relocation_type: u8,
+    /// Number of relocations.
+    pub(crate) count: u32,
+    /// Offset of the first encoded offset.
+    pub(crate) offsets: usize,
+    /// Codec of the offsets.
+    pub(crate) codec: Codec,
+    /// Address the first offset is relative to.
+    pub(crate) address: u32,
+    /// Scale the offsets are multiplied by.
+    pub(crate) scale: u32,
+    /// Offset of the first encoded symbol index, if the group carries them.
+    pub(crate) symbols: Option<usize>,
+}
+
+/// Header of a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Header {
+    /// Base address of all the relocations.
+    pub(crate) base_address: u32,
+    /// Number of groups or runs.
+    pub(crate) count: u32,
+    /// Offset of the first group, run or directory entry.
+    pub(crate) offset: usize,
+    /// Layout of the section.
+    pub(crate) layout: Layout,
+    /// Codec of the offsets.
+    pub(crate) codec: Codec,
+    /// Target machine (`e_machine`) recorded in the section, if any.
+    pub(crate) machine: Option<u16>,
+    /// Whether groups can carry symbol indices.
+    pub(crate) symbols: bool,
+    /// Whether groups can encode their offsets as signed deltas.
+    pub(crate) signed: bool,
+    /// Alignment of the groups or runs relative to the start of the section.
+    pub(crate) alignment: usize,
+    /// Whether groups can encode their offsets as opcodes.
+    pub(crate) repeat: bool,
+    /// Whether groups carry a scale their offsets are multiplied by.
+    pub(crate) scaled: bool,
+}
+
+impl Header {
+    /// Reads the header of a compressed section.
+    #[inline(always)]
+    pub(crate) fn read(data: &[u8]) -> Result<Self, Error> {
+        Self::read_with::<Checked>(data)
+    }
+
+    /// Reads the header of a compressed section using the checks of `A`.
+    pub(crate) fn read_with<A: Access>(data: &[u8]) -> Result<Self, Error> {
+        let read_u8 = read_u8_with::<A>;
+        let base_address = read_u32_with::<A>(data, 0)?;
+        let (machine, start) = match read_u8(data, 4)? {
+            MACHINE => (Some(read_u16_with::<A>(data, 5)?), 7),
+            _ => (None, 4),
+        };
+        let (codec, start) = match read_u8(data, start)? {
+            GROUP_VARINT => (Codec::GroupVarint, start + 1),
+            BITMAP => (Codec::Bitmap, start + 1),
+            _ => (Codec::Uleb128, start),
+        };
+        let (symbols, start) = match read_u8(data, start)? {
+            SYMBOLS => (true, start + 1),
+            _ => (false, start),
+        };
+        let (signed, start) = match read_u8(data, start)? {
+            SIGNED => (true, start + 1),
+            _ => (false, start),
+        };
+        let (alignment, start) = match read_u8(data, start)? {
+            ALIGNED => match read_u8(data, start + 1)? {
+                alignment if alignment.is_power_of_two() => (alignment as usize, start + 2),
+                _ => return Err(Error::invalid_data().at(start + 1)),
+            },
+            _ => (1, start),
+        };
+        let (repeat, start) = match read_u8(data, start)? {
+            REPEAT => (true, start + 1),
+            _ => (false, start),
+        };
+        // Bitmaps mark words, which are not scaled.
+        let (scaled, start) = match read_u8(data, start)? {
+            SCALED if codec == Codec::Bitmap => return Err(Error::invalid_data().at(start)),
+            SCALED => (true, start + 1),
+            _ => (false, start),
+        };
+        let count = read_u8(data, start)?;
+        let (count, offset, layout) = match count {
+            INTERLEAVED => {
+                let mut index = start + 1;
+                let runs = read_uleb128_with::<A>(data, &mut index)?;
+                (runs, index, Layout::Interleaved)
+            }
+            // Directory entries have no room for symbol indices or flags and
+            // are not padded.
+            DIRECTORY if symbols || signed || alignment > 1 || repeat || scaled => {
+                return Err(Error::invalid_data().at(start))
+            }
+            DIRECTORY => (
+                read_u8(data, start + 1)? as u32,
+                start + 2,
+                Layout::Directory,
+            ),
+            BASED => (read_u8(data, start + 1)? as u32, start + 2, Layout::Based),
+            MACHINE | GROUP_VARINT | SYMBOLS | BITMAP | SIGNED | ALIGNED | REPEAT | SCALED => {
+                return Err(Error::invalid_data().at(start))
+            }
+            _ => (count as u32, start + 1, Layout::Grouped),
+        };
+        Ok(Self {
+            base_address,
+            count,
+            offset,
+            layout,
+            codec,
+            machine,
+            symbols,
+            signed,
+            alignment,
+            repeat,
+            scaled,
+        })
+    }
+
+    /// Checks that the section targets `machine` if both are known.
+    pub(crate) fn check_machine(&self, machine: Option<u16>) -> Result<(), Error> {
+        match (self.machine, machine) {
+            (Some(recorded), Some(machine)) if recorded != machine => Err(Error::unsupported()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the group, run or directory entry at `offset`, given the last
+    /// address of the previous group.
+    #[inline(always)]
+    pub(crate) fn group(&self, data: &[u8], offset: usize, last: u32) -> Result<Group, Error> {
+        self.group_with::<Checked>(data, offset, last)
+    }
+
+    /// Reads the group, run or directory entry at `offset` like
+    /// [group](#method.group) using the checks of `A`.
+    pub(crate) fn group_with<A: Access>(
+        &self,
+        data: &[u8],
+        offset: usize,
+        last: u32,
+    ) -> Result<Group, Error> {
+        let offset = self.align(offset);
+        let relocation_type = read_u8_with::<A>(data, offset)?;
+        let (address, mut index) = match self.layout {
+            Layout::Directory => {
+                let count = read_u32_with::<A>(data, offset + 1)?;
+                let stream = read_u32_with::<A>(data, offset + 5)?;
+                let streams = self.offset + self.count as usize * DIRECTORY_ENTRY_SIZE;
+                return Ok(Group {
+                    relocation_type,
+                    count,
+                    offsets: streams.saturating_add(saturating_usize(stream)),
+                    codec: self.codec,
+                    address: self.base_address,
+                    scale: 1,
+                    symbols: None,
+                });
+            }
+            Layout::Based => (read_u32_with::<A>(data, offset + 1)?, offset + 5),
+            Layout::Interleaved => (last, offset + 1),
+            Layout::Grouped => (self.base_address, offset + 1),
+        };
+        let mut count = read_uleb128_with::<A>(data, &mut index)?;
+        // The lowest bit of the count flags offsets encoded as opcodes, the
+        // next one offsets encoded as signed deltas.
+        let repeat = self.repeat && count & 1 != 0;
+        if self.repeat {
+            count >>= 1;
+        }
+        let codec = match (repeat, self.signed && count & 1 != 0) {
+            (true, true) => return Err(Error::invalid_data().at(offset)),
+            (true, false) => Codec::Repeat,
+            (false, true) => Codec::Sleb128,
+            (false, false) => self.codec,
+        };
+        if self.signed {
+            count >>= 1;
+        }
+        let scale = match self.scaled {
+            true => read_uleb128_with::<A>(data, &mut index)?,
+            false => 1,
+        };
+        if A::SANITY && scale == 0 {
+            return Err(Error::invalid_data().at(index - 1));
+        }
+        if !self.symbols {
+            return Ok(Group {
+                relocation_type,
+                count,
+                offsets: index,
+                codec,
+                address,
+                scale,
+                symbols: None,
+            });
+        }
+        // The lowest bit of the count flags symbol indices preceding the
+        // offsets.
+        let symbols = match count & 1 {
+            0 => None,
+            _ => Some(index),
+        };
+        if symbols.is_some() {
+            index = skip_offsets::<A>(data, Codec::Uleb128, index, count >> 1)?;
+        }
+        Ok(Group {
+            relocation_type,
+            count: count >> 1,
+            offsets: index,
+            codec,
+            address,
+            scale,
+            symbols,
+        })
+    }
+
+    /// Returns the offset of the group starting at `offset` or after the
+    /// padding following it, which precedes the groups of aligned sections.
+    pub(crate) fn align(&self, offset: usize) -> usize {
+        (offset + self.alignment - 1) & !(self.alignment - 1)
+    }
+
+    /// Returns the offset of the group following the one at `offset`, given
+    /// the end of its encoded offsets.
+    pub(crate) fn next(&self, offset: usize, end: usize) -> usize {
+        match self.layout {
+            Layout::Directory => offset + DIRECTORY_ENTRY_SIZE,
+            _ => end,
+        }
+    }
+}
+
+/// Reads the symbol index following `previous` at `index`, encoded as the
+/// zigzag encoded difference of the two as ULEB128, and advances `index`.
+pub(crate) fn read_symbol_at(data: &[u8], index: &mut usize, previous: u32) -> Result<u32, Error> {
+    let delta = read_uleb128_at(data, index)?;
+    Ok(previous.wrapping_add((delta >> 1) ^ 0u32.wrapping_sub(delta & 1)))
+}
+
+/// Decodes `count` offsets encoded with `codec` and multiplied by `scale`
+/// starting at `index`, the first one relative to `address`, and calls `op`
+/// for every address.
+///
+/// Returns the end of the encoded offsets and the last address.
+#[inline(always)]
+pub(crate) fn decode_offsets<F>(
+    data: &[u8],
+    codec: Codec,
+    scale: u32,
+    index: usize,
+    count: u32,
+    address: u32,
+    op: &mut F,
+) -> Result<(usize, u32), Error>
+where
+    F: FnMut(u32) -> Result<(), Error>,
+{
+    decode_offsets_with::<Checked, F>(data, codec, scale, index, count, address, op)
+}
+
+/// Decodes offsets like [decode_offsets](fn.decode_offsets.html) using the
+/// checks of `A`.
+fn decode_offsets_with<A: Access, F>(
+    data: &[u8],
+    codec: Codec,
+    scale: u32,
+    mut index: usize,
+    count: u32,
+    mut address: u32,
+    op: &mut F,
+) -> Result<(usize, u32), Error>
+where
+    F: FnMut(u32) -> Result<(), Error>,
+{
+    match codec {
+        Codec::GroupVarint => {
+            return decode_group_varint::<A, F>(data, index, count, address, scale, op)
+        }
+        Codec::Bitmap => return decode_bitmap::<A, F>(data, index, count, address, op),
+        Codec::Repeat => return decode_repeat::<A, F>(data, index, count, address, scale, op),
+        Codec::Sleb128 => {
+            for _ in 0..count {
+                let delta = read_sleb128_with::<A>(data, &mut index)?;
+                let delta = scale_delta::<A>(delta, scale, index)?;
+                address = add_delta::<A>(address, delta, index)?;
+                op(address)?;
+            }
+            return Ok((index, address));
+        }
+        Codec::Uleb128 => {}
+    }
+    for _ in 0..count {
+        let offset = read_uleb128_with::<A>(data, &mut index)?;
+        let offset = scale_offset::<A>(offset, scale, index)?;
+        address = add_offset::<A>(address, offset, index)?;
+        op(address)?;
+    }
+    Ok((index, address))
+}
+
+/// Decodes `count` group-varint encoded offsets multiplied by `scale`
+/// starting at `index`, the first one relative to `address`, and calls `op`
+/// for every address.
+///
+/// Returns the end of the encoded offsets and the last address.
+fn decode_group_varint<A: Access, F>(
+    data: &[u8],
+    mut index: usize,
+    count: u32,
+    mut address: u32,
+    scale: u32,
+    op: &mut F,
+) -> Result<(usize, u32), Error>
+where
+    F: FnMut(u32) -> Result<(), Error>,
+{
+    let mut remaining = count;
+    while remaining > 0 {
+        let mut tag = read_u8_with::<A>(data, index)?;
+        index += 1;
+        let lanes = remaining.min(4);
+        for _ in 0..lanes {
+            let length = (tag & 0x3) as usize + 1;
+            tag >>= 2;
+            let offset = if data.len() >= index + 4 {
+                // A single load and a mask instead of a branch per byte.
+                let word = u32::from_le(read_u32_with::<A>(data, index)?);
+                word & (u32::MAX >> (32 - 8 * length))
+            } else {
+                let mut offset = 0;
+                for byte in 0..length {
+                    offset |= (read_u8_with::<A>(data, index + byte)? as u32) << (8 * byte);
+                }
+                offset
+            };
+            index += length;
+            let offset = scale_offset::<A>(offset, scale, index)?;
+            address = add_offset::<A>(address, offset, index)?;
+            op(address)?;
+        }
+        remaining -= lanes;
+    }
+    Ok((index, address))
+}
+
+/// Decodes `count` bitmap encoded offsets starting at `index`, the first one
+/// relative to `address`, and calls `op` for every address.
+///
+/// The offsets end with the bitmap byte holding the last relocation.
+///
+/// Returns the end of the encoded offsets and the last address.
+fn decode_bitmap<A: Access, F>(
+    data: &[u8],
+    mut index: usize,
+    count: u32,
+    mut address: u32,
+    op: &mut F,
+) -> Result<(usize, u32), Error>
+where
+    F: FnMut(u32) -> Result<(), Error>,
+{
+    let mut remaining = count;
+    while remaining > 0 {
+        let offset = read_uleb128_with::<A>(data, &mut index)?;
+        address = add_offset::<A>(address, offset, index)?;
+        op(address)?;
+        remaining -= 1;
+        let mut window = address;
+        let mut bitmap = 0x80;
+        while remaining > 0 && bitmap & 0x80 != 0 {
+            bitmap = read_u8_with::<A>(data, index)?;
+            index += 1;
+            for bit in 0..7 {
+                if remaining > 0 && bitmap & (1 << bit) != 0 {
+                    address = add_offset::<A>(window, 4 * (bit + 1), index)?;
+                    op(address)?;
+                    remaining -= 1;
+                }
+            }
+            // Advanced only if another bitmap byte follows, so the window of
+            // the last one may end at the top of the address space.
+            if bitmap & 0x80 != 0 {
+                window = add_offset::<A>(window, 28, index)?;
+            }
+        }
+    }
+    Ok((index, address))
+}
+
+/// Decodes `count` offsets encoded as opcodes and multiplied by `scale`
+/// starting at `index`, the first one relative to `address`, and calls `op`
+/// for every address.
+///
+/// Repeats beyond `count` are ignored, unless checks are strict.
+///
+/// Returns the end of the encoded offsets and the last address.
+fn decode_repeat<A: Access, F>(
+    data: &[u8],
+    mut index: usize,
+    count: u32,
+    mut address: u32,
+    scale: u32,
+    op: &mut F,
+) -> Result<(usize, u32), Error>
+where
+    F: FnMut(u32) -> Result<(), Error>,
+{
+    let mut remaining = count;
+    while remaining > 0 {
+        let opcode = read_uleb128_with::<A>(data, &mut index)?;
+        if opcode & 1 == 0 {
+            let offset = scale_offset::<A>(opcode >> 1, scale, index)?;
+            address = add_offset::<A>(address, offset, index)?;
+            op(address)?;
+            remaining -= 1;
+            continue;
+        }
+        let repeats = opcode >> 1;
+        if A::STRICT && (repeats == 0 || repeats > remaining) {
+            return Err(Error::invalid_data().at(index - 1));
+        }
+        let offset = read_uleb128_with::<A>(data, &mut index)?;
+        let offset = scale_offset::<A>(offset, scale, index)?;
+        for _ in 0..repeats.min(remaining) {
+            address = add_offset::<A>(address, offset, index)?;
+            op(address)?;
+        }
+        remaining -= repeats.min(remaining);
+    }
+    Ok((index, address))
+}
+
+/// Returns the end of `count` offsets encoded with `codec` starting at
+/// `index` without decoding them.
+pub(crate) fn skip_offsets<A: Access>(
+    data: &[u8],
+    codec: Codec,
+    mut index: usize,
+    count: u32,
+) -> Result<usize, Error> {
+    let mut remaining = count;
+    if codec == Codec::Bitmap {
+        return decode_bitmap::<A, _>(data, index, count, 0, &mut |_| Ok(())).map(|(end, _)| end);
+    }
+    if codec == Codec::Repeat {
+        return decode_repeat::<A, _>(data, index, count, 0, 1, &mut |_| Ok(()))
+            .map(|(end, _)| end);
+    }
+    if codec == Codec::GroupVarint {
+        while remaining > 0 {
+            let tag = read_u8_with::<A>(data, index)?;
+            let lanes = remaining.min(4);
+            index += 1
+                + (0..lanes)
+                    .map(|lane| ((tag >> (2 * lane)) & 0x3) as usize + 1)
+                    .sum::<usize>();
+            remaining -= lanes;
+        }
+        return match !A::BOUNDS || index <= data.len() {
+            true => Ok(index),
+            false => Err(Error::not_enough_data().at(data.len())),
+        };
+    }
+    let mut length = 0;
+    while remaining > 0 {
+        let byte = read_u8_with::<A>(data, index)?;
+        index += 1;
+        if byte & 0x80 == 0 {
+            if A::STRICT && codec == Codec::Uleb128 && byte == 0 && length > 0 {
+                return Err(Error::invalid_data().at(index - 1));
+            }
+            remaining -= 1;
+            length = 0;
+        } else {
+            length += 1;
+            if A::SANITY && length >= 5 {
+                return Err(Error::invalid_data().at(index - 1));
+            }
+        }
+    }
+    Ok(index)
+}
+
+/// Converts a 32-bit value to `usize`, saturating on targets whose `usize` is
+/// 16 bits wide, e.g. AVR and MSP430, so that length checks fail instead of
+/// passing with a wrapped value.
+#[inline(always)]
+pub(crate) fn saturating_usize(value: u32) -> usize {
+    usize::try_from(value).unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused)]
+    use super::*;
+    #[allow(unused)]
+    use crate::error::ErrorKind;
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_no_data() {
+        elf32_relocate(&[0; 0], &mut |_, _| unreachable!()).unwrap_err();
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_base_address_only() {
+        elf32_relocate(&[0; 4], &mut |_, _| unreachable!()).unwrap_err();
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_count_only() {
+        elf32_relocate(&[1; 5], &mut |_, _| unreachable!()).unwrap_err();
+    }
+
+    #[test]
+    fn test_saturating_usize() {
+        assert_eq!(saturating_usize(0x1234), 0x1234);
+        let expected = match core::mem::size_of::<usize>() {
+            2 => usize::MAX,
+            _ => 0xFFFF_FFFF,
+        };
+        assert_eq!(saturating_usize(u32::MAX), expected);
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_count_is_zero() {
+        elf32_relocate(&[0; 5], &mut |_, _| unreachable!()).unwrap();
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_group_reloc_type_no_data() {
+        elf32_relocate(&[1; 6], &mut |_, _| unreachable!()).unwrap_err();
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_group_count_no_data() {
+        elf32_relocate(&[1; 6], &mut |_, _| unreachable!()).unwrap_err();
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_group_offset_no_data() {
+        elf32_relocate(&[1; 7], &mut |_, _| unreachable!()).unwrap_err();
+    }
+
+    #[cfg(all(feature = "detailed_errors", not(feature = "no_bounds_check")))]
+    #[test]
+    fn test_decompress_detailed_errors() {
+        let data = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0x02, // count
+            0x17, 0x02, 0x04, 0x04, // group[0]
+            0x02, 0x02, 0x08, // group[1], truncated
+        ];
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        assert_eq!(
+            (err.offset(), err.group(), err.entry()),
+            (Some(12), Some(1), Some(1))
+        );
+
+        let mut calls = 0;
+        let err = elf32_relocate(&data, &mut |_, _| {
+            calls += 1;
+            match calls {
+                2 => Err(Error::new(ErrorKind::Unsupported)),
+                _ => Ok(()),
+            }
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert_eq!(
+            (err.offset(), err.group(), err.entry()),
+            (None, Some(0), Some(1))
+        );
+
+        let err = elf32_relocate(&data[..5], &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(
+            (err.offset(), err.group(), err.entry()),
+            (Some(5), Some(0), Some(0))
+        );
+        let err = elf32_relocate(&data[..4], &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!((err.offset(), err.group()), (Some(4), None));
+    }
+
+    #[test]
+    fn test_decompress_relocate_one() {
+        let memory = [
+            0x04, 0x03, 0x02, 0x01, // base_address
+            0x01, // count
+            0x01, // group[0].relocation_type
+            0x01, // group[0].count
+            0x00, // group[0].offsets[0]
+        ];
+        let read = elf32_relocate(&memory, &mut |relocation_type, address| {
+            assert_eq!(relocation_type, 0x01);
+            assert_eq!(address, 0x01020304);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, 8);
+    }
+
+    #[test]
+    fn test_decompress_with_hook() {
+        let memory = [
+            0x00, 0x00, 0x00, 0x00, // base_address
+            0x01, // count
+            0x01, // group[0].relocation_type
+            0x05, // group[0].count
+            0x00, 0x01, 0x01, 0x01, 0x01, // group[0].offsets
+        ];
+        for (interval, expected) in &[(0, 0), (1, 5), (2, 2), (5, 1), (6, 0)] {
+            let mut relocations = 0;
+            let mut hooks = 0;
+            elf32_relocate_with_hook(
+                &memory,
+                &mut |_, _| {
+                    relocations += 1;
+                    Ok(())
+                },
+                *interval,
+                &mut || {
+                    hooks += 1;
+                    Ok(())
+                },
+            )
+            .unwrap();
+            assert_eq!(relocations, 5);
+            assert_eq!(hooks, *expected);
+        }
+    }
+
+    #[test]
+    fn test_decompress_with_hook_error() {
+        let memory = [
+            0x00, 0x00, 0x00, 0x00, // base_address
+            0x01, // count
+            0x01, // group[0].relocation_type
+            0x02, // group[0].count
+            0x00, 0x01, // group[0].offsets
+        ];
+        let mut relocations = 0;
+        let err = elf32_relocate_with_hook(
+            &memory,
+            &mut |_, _| {
+                relocations += 1;
+                Ok(())
+            },
+            1,
+            &mut || Err(Error::new(ErrorKind::InvalidData)),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(relocations, 1);
+    }
+
+    const CREL_TWO_GROUPS: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    #[test]
+    fn test_decompress_machine() {
+        let mut data = [0; 18];
+        data[..4].copy_from_slice(&CREL_TWO_GROUPS[..4]);
+        data[4] = 0xFC;
+        data[5..7].copy_from_slice(&40u16.to_ne_bytes());
+        data[7..].copy_from_slice(&CREL_TWO_GROUPS[4..]);
+        let mut relocations = [(0, 0); 5];
+        let mut count = 0;
+        let read = elf32_relocate(&data, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, data.len());
+        assert_eq!(relocations, TWO_GROUPS_RELOCATIONS);
+        assert_eq!(elf32_machine(&data).unwrap(), Some(40));
+        assert_eq!(elf32_machine(&CREL_TWO_GROUPS).unwrap(), None);
+        elf32_check_machine(&data, 40).unwrap();
+        elf32_check_machine(&CREL_TWO_GROUPS, 40).unwrap();
+        let err = elf32_check_machine(&data, 243).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        data[7] = 0xFC;
+        let err = elf32_machine(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        if !cfg!(feature = "no_bounds_check") {
+            let err = elf32_machine(&data[..6]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        }
+    }
+
+    const CREL_GROUP_VARINT: [u8; 18] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFB, // group-varint codec
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x04, 0x80, 0x00, // group[0].offsets, lengths 1 and 2
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x00, 0x04, 0x04, // group[1].offsets, lengths 1, 1 and 1
+    ];
+
+    #[test]
+    fn test_decompress_group_varint() {
+        let mut relocations = [(0, 0); 5];
+        let mut count = 0;
+        let read = elf32_relocate(&CREL_GROUP_VARINT, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_GROUP_VARINT.len());
+        assert_eq!(relocations, TWO_GROUPS_RELOCATIONS);
+
+        let mut data = [0; 21];
+        data[..4].copy_from_slice(&CREL_GROUP_VARINT[..4]);
+        data[4] = 0xFC;
+        data[5..7].copy_from_slice(&40u16.to_ne_bytes());
+        data[7..].copy_from_slice(&CREL_GROUP_VARINT[4..]);
+        count = 0;
+        elf32_relocate(&data, &mut |_, _| {
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 5);
+        assert_eq!(elf32_machine(&data).unwrap(), Some(40));
+        data[8] = 0xFB;
+        let err = elf32_machine(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = elf32_relocate_raw(&CREL_GROUP_VARINT, &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        if !cfg!(feature = "no_bounds_check") {
+            let err = elf32_relocate(&CREL_GROUP_VARINT[..17], &mut |_, _| Ok(())).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        }
+    }
+
+    #[test]
+    fn test_relocate_range_group_varint() {
+        let mut relocations = [(0, 0); 2];
+        let mut count = 0;
+        let found = elf32_relocate_range(
+            &CREL_GROUP_VARINT,
+            0x1004,
+            0x1008,
+            &mut |relocation_type, address| {
+                relocations[count] = (relocation_type, address);
+                count += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(found, 2);
+        assert_eq!(relocations, [(0x02, 0x1004), (0x16, 0x1004)]);
+    }
+
+    const CREL_BITMAP: [u8; 17] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xF9, // bitmap codec
+        0x02, // count
+        0x17, // group[0].relocation_type
+        0x06, // group[0].count
+        0x04, 0x8B, 0x08, // group[0].offsets[0..5], offset and bitmap
+        0xD0, 0x1F, // group[0].offsets[5], last offset without bitmap
+        0x02, // group[1].relocation_type
+        0x02, // group[1].count
+        0x08, 0x01, // group[1].offsets, offset and bitmap
+    ];
+
+    const BITMAP_RELOCATIONS: [(u8, u32); 8] = [
+        (0x17, 0x1004),
+        (0x17, 0x1008),
+        (0x17, 0x100C),
+        (0x17, 0x1014),
+        (0x17, 0x1030),
+        (0x17, 0x2000),
+        (0x02, 0x1008),
+        (0x02, 0x100C),
+    ];
+
+    #[test]
+    fn test_decompress_bitmap() {
+        let mut relocations = [(0, 0); 8];
+        let mut count = 0;
+        let read = elf32_relocate(&CREL_BITMAP, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_BITMAP.len());
+        assert_eq!(relocations, BITMAP_RELOCATIONS);
+
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_BITMAP, &mut index).unwrap();
+        for (number, relocation) in BITMAP_RELOCATIONS.iter().enumerate() {
+            let mut decoded = None;
+            elf32_relocate_one(&CREL_BITMAP, &index, number as u32, &mut |t, a| {
+                decoded = Some((t, a));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(decoded, Some(*relocation));
+        }
+
+        count = 0;
+        let found = elf32_relocate_range(&CREL_BITMAP, 0x100C, 0x1020, &mut |_, _| {
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!((found, count), (3, 3));
+
+        let err = elf32_relocate_raw(&CREL_BITMAP, &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let mut data = CREL_BITMAP;
+        data[5] = 0xF9;
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        if !cfg!(feature = "no_bounds_check") {
+            let err = elf32_relocate(&CREL_BITMAP[..10], &mut |_, _| Ok(())).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        }
+    }
+
+    #[test]
+    fn test_decompress_dyn() {
+        let mut relocations = 0;
+        let mut highest = 0;
+        let read = elf32_relocate_dyn(&CREL_GROUP_VARINT, &mut |_, _| {
+            relocations += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_GROUP_VARINT.len());
+        elf32_relocate_dyn(&CREL_GROUP_VARINT, &mut |_, address| {
+            highest = highest.max(address);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(relocations, 5);
+        assert_eq!(highest, 0x1084);
+    }
+
+    #[test]
+    fn test_index_groups() {
+        let mut index = [Elf32CRelGroupIndex::default(); 3];
+        let count = elf32_index_groups(&CREL_TWO_GROUPS, &mut index).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(index[0].offset(), 5);
+        assert_eq!(index[0].first(), 0);
+        assert_eq!(index[0].count(), 2);
+        assert_eq!(index[1].offset(), 10);
+        assert_eq!(index[1].first(), 2);
+        assert_eq!(index[1].count(), 3);
+    }
+
+    #[test]
+    fn test_index_groups_small() {
+        let mut index = [Elf32CRelGroupIndex::default(); 1];
+        let err = elf32_index_groups(&CREL_TWO_GROUPS, &mut index).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+    }
+
+    #[test]
+    fn test_relocate_one() {
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_TWO_GROUPS, &mut index).unwrap();
+        let expected = [
+            (0x02, 0x1004),
+            (0x02, 0x1084),
+            (0x16, 0x1000),
+            (0x16, 0x1004),
+            (0x16, 0x1008),
+        ];
+        for (number, relocation) in expected.iter().enumerate() {
+            let mut decoded = None;
+            elf32_relocate_one(&CREL_TWO_GROUPS, &index, number as u32, &mut |t, a| {
+                decoded = Some((t, a));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(decoded, Some(*relocation));
+        }
+        let err = elf32_relocate_one(&CREL_TWO_GROUPS, &index, 5, &mut |_, _| unreachable!())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    const CREL_INTERLEAVED: [u8; 24] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFF, // count, interleaved
+        0x03, // runs
+        0x02, // run[0].relocation_type
+        0x02, // run[0].count
+        0x00, 0x80, 0x80, 0x40, // run[0].offsets
+        0x17, // run[1].relocation_type
+        0x02, // run[1].count
+        0x04, 0x80, 0x80, 0x40, // run[1].offsets
+        0x02, // run[2].relocation_type
+        0x02, // run[2].count
+        0x04, 0x80, 0x80, 0x40, // run[2].offsets
+    ];
+
+    const INTERLEAVED_RELOCATIONS: [(u8, u32); 6] = [
+        (0x02, 0x001000),
+        (0x02, 0x101000),
+        (0x17, 0x101004),
+        (0x17, 0x201004),
+        (0x02, 0x201008),
+        (0x02, 0x301008),
+    ];
+
+    #[test]
+    fn test_decompress_interleaved() {
+        let mut relocations = [(0, 0); 6];
+        let mut count = 0;
+        let read = elf32_relocate(&CREL_INTERLEAVED, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_INTERLEAVED.len());
+        assert_eq!(relocations, INTERLEAVED_RELOCATIONS);
+    }
+
+    #[test]
+    fn test_relocate_one_interleaved() {
+        let mut index = [Elf32CRelGroupIndex::default(); 3];
+        let count = elf32_index_groups(&CREL_INTERLEAVED, &mut index).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(index[1].offset(), 12);
+        assert_eq!(index[1].address(), 0x101000);
+        assert_eq!(index[2].first(), 4);
+        for (number, relocation) in INTERLEAVED_RELOCATIONS.iter().enumerate() {
+            let mut decoded = None;
+            elf32_relocate_one(&CREL_INTERLEAVED, &index, number as u32, &mut |t, a| {
+                decoded = Some((t, a));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(decoded, Some(*relocation));
+        }
+    }
+
+    const CREL_DIRECTORY: [u8; 30] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFE, // count, directory
+        0x02, // groups
+        0x02, // directory[0].relocation_type
+        0x02, 0x00, 0x00, 0x00, // directory[0].count
+        0x00, 0x00, 0x00, 0x00, // directory[0].stream
+        0x16, // directory[1].relocation_type
+        0x03, 0x00, 0x00, 0x00, // directory[1].count
+        0x03, 0x00, 0x00, 0x00, // directory[1].stream
+        0x04, 0x80, 0x01, // streams[0]
+        0x00, 0x04, 0x04, // streams[1]
+    ];
+
+    const TWO_GROUPS_RELOCATIONS: [(u8, u32); 5] = [
+        (0x02, 0x1004),
+        (0x02, 0x1084),
+        (0x16, 0x1000),
+        (0x16, 0x1004),
+        (0x16, 0x1008),
+    ];
+
+    #[test]
+    fn test_decompress_directory() {
+        let mut relocations = [(0, 0); 5];
+        let mut count = 0;
+        let read = elf32_relocate(&CREL_DIRECTORY, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_DIRECTORY.len());
+        assert_eq!(relocations, TWO_GROUPS_RELOCATIONS);
+        assert_eq!(elf32_group_count(&CREL_DIRECTORY).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_relocate_directory_group() {
+        let mut relocations = [(0, 0); 3];
+        let mut count = 0;
+        elf32_relocate_directory_group(&CREL_DIRECTORY, 1, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(relocations, TWO_GROUPS_RELOCATIONS[2..]);
+        let err = elf32_relocate_directory_group(&CREL_DIRECTORY, 2, &mut |_, _| unreachable!())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = elf32_relocate_directory_group(&CREL_TWO_GROUPS, 0, &mut |_, _| unreachable!())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_relocate_one_directory() {
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_DIRECTORY, &mut index).unwrap();
+        assert_eq!(index[1].offset(), 15);
+        assert_eq!(index[1].first(), 2);
+        for (number, relocation) in TWO_GROUPS_RELOCATIONS.iter().enumerate() {
+            let mut decoded = None;
+            elf32_relocate_one(&CREL_DIRECTORY, &index, number as u32, &mut |t, a| {
+                decoded = Some((t, a));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(decoded, Some(*relocation));
+        }
+    }
+
+    const CREL_BASED: [u8; 24] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFD, // count, based
+        0x02, // groups
+        0x02, // group[0].relocation_type
+        0x00, 0x10, 0x00, 0x00, // group[0].base_address
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x00, 0x20, 0x00, 0x00, // group[1].base_address
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    const BASED_RELOCATIONS: [(u8, u32); 5] = [
+        (0x02, 0x1004),
+        (0x02, 0x1084),
+        (0x16, 0x2000),
+        (0x16, 0x2004),
+        (0x16, 0x2008),
+    ];
+
+    #[test]
+    fn test_decompress_based() {
+        let mut relocations = [(0, 0); 5];
+        let mut count = 0;
+        let read = elf32_relocate(&CREL_BASED, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_BASED.len());
+        assert_eq!(relocations, BASED_RELOCATIONS);
+        assert_eq!(elf32_group_count(&CREL_BASED).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_relocate_one_based() {
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_BASED, &mut index).unwrap();
+        assert_eq!(index[1].offset(), 15);
+        assert_eq!(index[1].address(), 0x2000);
+        for (number, relocation) in BASED_RELOCATIONS.iter().enumerate() {
+            let mut decoded = None;
+            elf32_relocate_one(&CREL_BASED, &index, number as u32, &mut |t, a| {
+                decoded = Some((t, a));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(decoded, Some(*relocation));
+        }
+    }
+
+    #[test]
+    fn test_relocate_raw_based() {
+        let mut base_addresses = [0; 2];
+        let mut groups = 0;
+        elf32_relocate_raw(&CREL_BASED, &mut |group| {
+            base_addresses[groups] = group.base_address();
+            groups += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(base_addresses, [0x1000, 0x2000]);
+    }
+
+    const CREL_SYMBOLS: [u8; 18] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFA, // symbols
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x07, // group[0].count, with symbols
+        0x0A, 0x03, 0x7F, // group[0].symbols: 5, 3, 0xFFFF_FFC3
+        0x04, 0x80, 0x01, 0x04, // group[0].offsets
+        0x17, // group[1].relocation_type
+        0x02, // group[1].count, without symbols
+        0x00, // group[1].offsets[0]
+    ];
+
+    const SYMBOLS_RELOCATIONS: [(u8, u32, u32); 4] = [
+        (0x02, 0x1004, 5),
+        (0x02, 0x1084, 3),
+        (0x02, 0x1088, 0xFFFF_FFC3),
+        (0x17, 0x1000, 0),
+    ];
+
+    #[test]
+    fn test_decompress_symbols() {
+        let mut relocations = [(0, 0, 0); 4];
+        let mut count = 0;
+        let read =
+            elf32_relocate_symbols(&CREL_SYMBOLS, &mut |relocation_type, address, symbol| {
+                relocations[count] = (relocation_type, address, symbol);
+                count += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(read, CREL_SYMBOLS.len());
+        assert_eq!(relocations, SYMBOLS_RELOCATIONS);
+
+        count = 0;
+        let read = elf32_relocate(&CREL_SYMBOLS, &mut |relocation_type, address| {
+            let (expected_type, expected_address, _) = SYMBOLS_RELOCATIONS[count];
+            assert_eq!(
+                (relocation_type, address),
+                (expected_type, expected_address)
+            );
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_SYMBOLS.len());
+        assert_eq!(count, 4);
+
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_SYMBOLS, &mut index).unwrap();
+        assert_eq!(index[1].offset(), 15);
+        assert_eq!(index[1].first(), 3);
+    }
+
+    const CREL_SIGNED: [u8; 18] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFA, // symbols
+        0xF8, // signed
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x0B, // group[0].count, with symbols, signed
+        0x0A, 0x03, // group[0].symbols: 5, 3
+        0x80, 0x7E, 0x84, 0x02, // group[0].offsets: -0x100, +0x104
+        0x17, // group[1].relocation_type
+        0x04, // group[1].count, without symbols, unsigned
+        0x08, // group[1].offsets[0]
+    ];
+
+    const SIGNED_RELOCATIONS: [(u8, u32, u32); 3] =
+        [(0x02, 0x0F00, 5), (0x02, 0x1004, 3), (0x17, 0x1008, 0)];
+
+    #[test]
+    fn test_decompress_signed() {
+        let mut relocations = [(0, 0, 0); 3];
+        let mut count = 0;
+        let read = elf32_relocate_symbols(&CREL_SIGNED, &mut |relocation_type, address, symbol| {
+            relocations[count] = (relocation_type, address, symbol);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_SIGNED.len());
+        assert_eq!(relocations, SIGNED_RELOCATIONS);
+
+        for (relocation, expected) in Elf32CRelIter::new(&CREL_SIGNED).zip(SIGNED_RELOCATIONS) {
+            assert_eq!(relocation.unwrap(), (expected.0, expected.1));
+        }
+        count = 0;
+        let found = elf32_relocate_range(&CREL_SIGNED, 0x1000, 0x1008, &mut |_, address| {
+            assert_eq!(address, 0x1004);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!((found, count), (1, 1));
+        let err = elf32_relocate_raw(&CREL_SIGNED, &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+        let mut data = [0; 8];
+        data[4..].copy_from_slice(&[0xF8, 0xFE, 0x00, 0x00]);
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        if !cfg!(feature = "no_sanity_check") {
+            let mut index = 0;
+            let err = crate::reader::read_sleb128_at(&[0x80, 0x80, 0x80, 0x80, 0x10], &mut index)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    const CREL_ALIGNED: [u8; 17] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xF7, 0x04, // aligned to 4 bytes
+        0x02, // count
+        0x00, // padding
+        0x02, // group[0].relocation_type
+        0x01, // group[0].count
+        0x04, // group[0].offsets[0]
+        0x00, // padding
+        0x17, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x08, 0x04, // group[1].offsets
+    ];
+
+    #[test]
+    fn test_decompress_aligned() {
+        let mut relocations = [(0, 0); 4];
+        let mut count = 0;
+        let read = elf32_relocate(&CREL_ALIGNED, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_ALIGNED.len());
+        assert_eq!(
+            relocations,
+            [
+                (0x02, 0x1004),
+                (0x17, 0x1000),
+                (0x17, 0x1008),
+                (0x17, 0x100C)
+            ]
+        );
+
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_ALIGNED, &mut index).unwrap();
+        assert_eq!((index[0].offset(), index[1].offset()), (8, 12));
+
+        let mut data = CREL_ALIGNED;
+        data[5] = 0x03;
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        data[5..7].copy_from_slice(&[0x04, 0xFE]);
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    const CREL_REPEAT: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xF6, // repeated offsets
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x04, // group[0].count
+        0x08, 0x04, // group[0].offsets
+        0x17, // group[1].relocation_type
+        0x09, // group[1].count, repeated
+        0x00, 0x07, 0x04, // group[1].offsets: +0x0, 3 x +0x4
+    ];
+
+    const REPEAT_RELOCATIONS: [(u8, u32); 6] = [
+        (0x02, 0x1008),
+        (0x02, 0x100C),
+        (0x17, 0x1000),
+        (0x17, 0x1004),
+        (0x17, 0x1008),
+        (0x17, 0x100C),
+    ];
+
+    #[test]
+    fn test_decompress_repeat() {
+        let mut relocations = [(0, 0); 6];
+        let mut count = 0;
+        let read = elf32_relocate_strict(&CREL_REPEAT, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_REPEAT.len());
+        assert_eq!(relocations, REPEAT_RELOCATIONS);
+        for (relocation, expected) in Elf32CRelIter::new(&CREL_REPEAT).zip(REPEAT_RELOCATIONS) {
+            assert_eq!(relocation.unwrap(), expected);
+        }
+
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_REPEAT, &mut index).unwrap();
+        assert_eq!((index[0].offset(), index[1].offset()), (6, 10));
+        let mut decoded = None;
+        elf32_relocate_one(&CREL_REPEAT, &index, 4, &mut |t, a| {
+            decoded = Some((t, a));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(decoded, Some(REPEAT_RELOCATIONS[4]));
+        let err = elf32_relocate_raw(&CREL_REPEAT, &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+        // Repeats beyond the count are ignored, unless decoding strictly.
+        let mut data = CREL_REPEAT;
+        data[13] = 0x09;
+        let read = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap();
+        assert_eq!(read, data.len());
+        let err = elf32_relocate_strict(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        data[13] = 0x01;
+        let err = elf32_relocate_strict(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut data = CREL_REPEAT;
+        data[5] = 0xFE;
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        data[4..8].copy_from_slice(&[0xF8, 0xF6, 0x01, 0x17]);
+        data[8] = 0x07;
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    const CREL_SCALED: [u8; 17] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xF8, // signed
+        0xF5, // scaled offsets
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x04, // group[0].count
+        0x04, // group[0].scale
+        0x02, 0x01, // group[0].offsets: +0x8, +0x4
+        0x17, // group[1].relocation_type
+        0x05, // group[1].count, signed
+        0x08, // group[1].scale
+        0x02, 0x7F, // group[1].offsets: +0x10, -0x8
+    ];
+
+    const SCALED_RELOCATIONS: [(u8, u32); 4] = [
+        (0x02, 0x1008),
+        (0x02, 0x100C),
+        (0x17, 0x1010),
+        (0x17, 0x1008),
+    ];
+
+    #[test]
+    fn test_decompress_scaled() {
+        let mut relocations = [(0, 0); 4];
+        let mut count = 0;
+        let read = elf32_relocate(&CREL_SCALED, &mut |relocation_type, address| {
+            relocations[count] = (relocation_type, address);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, CREL_SCALED.len());
+        assert_eq!(relocations, SCALED_RELOCATIONS);
+        for (relocation, expected) in Elf32CRelIter::new(&CREL_SCALED).zip(SCALED_RELOCATIONS) {
+            assert_eq!(relocation.unwrap(), expected);
+        }
+
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_SCALED, &mut index).unwrap();
+        let mut decoded = None;
+        elf32_relocate_one(&CREL_SCALED, &index, 1, &mut |t, a| {
+            decoded = Some((t, a));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(decoded, Some(SCALED_RELOCATIONS[1]));
+        let found = elf32_relocate_range(&CREL_SCALED, 0x1008, 0x100C, &mut |_, address| {
+            assert_eq!(address, 0x1008);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(found, 2);
+        let err = elf32_relocate_raw(&CREL_SCALED, &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+        // Scales of zero, and offsets overflowing once scaled.
+        if !cfg!(feature = "no_sanity_check") {
+            let mut data = CREL_SCALED;
+            data[9] = 0x00;
+            let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            let mut data = [0; 15];
+            data[..6].copy_from_slice(&CREL_SCALED[..6]);
+            data[6..].copy_from_slice(&[0x01, 0x02, 0x02, 0x80, 0x80, 0x04, 0x80, 0x80, 0x04]);
+            let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Overflow);
+        }
+
+        let mut data = CREL_SCALED;
+        data[4] = 0xF9;
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        data[4..6].copy_from_slice(&[0xF5, 0xFE]);
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_decompress_symbols_invalid() {
+        let mut data = [0; 8];
+        data[4..].copy_from_slice(&[0xFA, 0xFE, 0x00, 0x00]);
+        let err = elf32_relocate_symbols(&data, &mut |_, _, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        data[4..6].copy_from_slice(&[0xFA, 0xFA]);
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        #[cfg(not(feature = "no_bounds_check"))]
+        {
+            let err = elf32_relocate(&CREL_SYMBOLS[..10], &mut |_, _| Ok(())).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        }
+    }
+
+    #[test]
+    fn test_decompress_iter() {
+        for (data, expected) in [
+            (&CREL_TWO_GROUPS[..], &TWO_GROUPS_RELOCATIONS[..]),
+            (&CREL_INTERLEAVED[..], &INTERLEAVED_RELOCATIONS[..]),
+            (&CREL_DIRECTORY[..], &TWO_GROUPS_RELOCATIONS[..]),
+            (&CREL_BASED[..], &BASED_RELOCATIONS[..]),
+        ]
+        .iter()
+        {
+            let mut relocations = [(0, 0); 8];
+            let mut count = 0;
+            for relocation in Elf32CRelIter::new(data) {
+                relocations[count] = relocation.unwrap();
+                count += 1;
+            }
+            assert_eq!(relocations[..count], **expected);
+        }
+
+        let mut iter = Elf32CRelIter::new(&CREL_TWO_GROUPS).filter(|relocation| {
+            relocation
+                .as_ref()
+                .map_or(true, |(relocation_type, _)| *relocation_type == 0x16)
+        });
+        assert_eq!(iter.nth(1).unwrap().unwrap(), (0x16, 0x1004));
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_decompress_iter_invalid() {
+        let mut iter = Elf32CRelIter::new(&CREL_TWO_GROUPS[..3]);
+        assert_eq!(
+            iter.next().unwrap().unwrap_err().kind(),
+            ErrorKind::NotEnoughData
+        );
+        assert!(iter.next().is_none());
+
+        let mut iter = Elf32CRelIter::new(&CREL_TWO_GROUPS[..14]);
+        assert_eq!(iter.by_ref().take(4).filter(Result::is_ok).count(), 4);
+        assert_eq!(
+            iter.next().unwrap().unwrap_err().kind(),
+            ErrorKind::NotEnoughData
+        );
+        assert!(iter.next().is_none());
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_decompress_into_heapless() {
+        let relocations = decompress_into_heapless::<5>(&CREL_TWO_GROUPS).unwrap();
+        assert_eq!(relocations.len(), 5);
+        for (relocation, (relocation_type, offset)) in
+            relocations.iter().zip(TWO_GROUPS_RELOCATIONS.iter())
+        {
+            assert_eq!(*relocation, Elf32Rel::new(*offset, *relocation_type));
+        }
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_decompress_into_heapless_overflow() {
+        let err = decompress_into_heapless::<4>(&CREL_TWO_GROUPS).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+    }
+
+    #[test]
+    fn test_relocate_raw() {
+        for data in &[&CREL_TWO_GROUPS[..], &CREL_DIRECTORY[..]] {
+            let mut groups = 0;
+            let read = elf32_relocate_raw(data, &mut |group| {
+                let (relocation_type, count, offsets): (u8, u32, &[u8]) = match groups {
+                    0 => (0x02, 2, &[0x04, 0x80, 0x01]),
+                    _ => (0x16, 3, &[0x00, 0x04, 0x04]),
+                };
+                assert_eq!(group.relocation_type(), relocation_type);
+                assert_eq!(group.count(), count);
+                assert_eq!(group.base_address(), 0x1000);
+                assert_eq!(group.offsets(), offsets);
+                groups += 1;
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(read, data.len());
+            assert_eq!(groups, 2);
+        }
+    }
+
+    #[test]
+    fn test_relocate_raw_interleaved() {
+        let err = elf32_relocate_raw(&CREL_INTERLEAVED, &mut |_| unreachable!()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_relocate_raw_truncated() {
+        let err = elf32_relocate_raw(&CREL_TWO_GROUPS[..14], &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[cfg(not(feature = "no_sanity_check"))]
+    #[test]
+    fn test_relocate_raw_overlong() {
+        let data = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0x01, // count
+            0x02, // group[0].relocation_type
+            0x01, // group[0].count
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x00, // group[0].offsets
+        ];
+        let err = elf32_relocate_raw(&data, &mut |_| unreachable!()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_relocate_range() {
+        for data in &[&CREL_TWO_GROUPS[..], &CREL_DIRECTORY[..]] {
+            let mut relocations = [(0, 0); 2];
+            let mut count = 0;
+            let applied = elf32_relocate_range(data, 0x1004, 0x1008, &mut |t, a| {
+                relocations[count] = (t, a);
+                count += 1;
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(applied, 2);
+            assert_eq!(relocations, [(0x02, 0x1004), (0x16, 0x1004)]);
+        }
+    }
+
+    #[test]
+    fn test_relocate_range_interleaved() {
+        let mut relocations = [(0, 0); 2];
+        let mut count = 0;
+        let applied = elf32_relocate_range(&CREL_INTERLEAVED, 0x101004, 0x201008, &mut |t, a| {
+            relocations[count] = (t, a);
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(applied, 2);
+        assert_eq!(relocations, INTERLEAVED_RELOCATIONS[2..4]);
+        // The range ends in the first run, later runs are not read.
+        let applied =
+            elf32_relocate_range(&CREL_INTERLEAVED[..12], 0, 0x1001, &mut |_, _| Ok(())).unwrap();
+        assert_eq!(applied, 1);
+    }
+
+    #[test]
+    fn test_relocate_range_empty() {
+        let applied =
+            elf32_relocate_range(&CREL_TWO_GROUPS, 0x1100, 0x1000, &mut |_, _| unreachable!())
+                .unwrap();
+        assert_eq!(applied, 0);
+    }
+
+    #[test]
+    fn test_decompress_group_into() {
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_TWO_GROUPS, &mut index).unwrap();
+        let mut addresses = [0; 4];
+        let written = decompress_group_into(&CREL_TWO_GROUPS, &index[0], &mut addresses).unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(addresses, [0x1004, 0x1084, 0, 0]);
+        let written = decompress_group_into(&CREL_TWO_GROUPS, &index[1], &mut addresses).unwrap();
+        assert_eq!(written, 3);
+        assert_eq!(addresses, [0x1000, 0x1004, 0x1008, 0]);
+    }
+
+    #[test]
+    fn test_decompress_group_into_forms() {
+        let sections: [(&[u8], usize, [u32; 4]); 6] = [
+            (&CREL_BASED, 1, [0x2000, 0x2004, 0x2008, 0]),
+            (&CREL_DIRECTORY, 0, [0x1004, 0x1084, 0, 0]),
+            (&CREL_SIGNED, 0, [0x0F00, 0x1004, 0, 0]),
+            (&CREL_REPEAT, 1, [0x1000, 0x1004, 0x1008, 0x100C]),
+            (&CREL_SCALED, 1, [0x1010, 0x1008, 0, 0]),
+            (&CREL_GROUP_VARINT, 0, [0x1004, 0x1084, 0, 0]),
+        ];
+        for (data, group, expected) in sections.iter() {
+            let mut index = [Elf32CRelGroupIndex::default(); 2];
+            elf32_index_groups(data, &mut index).unwrap();
+            let mut addresses = [0; 4];
+            let written = decompress_group_into(data, &index[*group], &mut addresses).unwrap();
+            assert_eq!(written, index[*group].count() as usize);
+            assert_eq!(addresses, *expected);
+        }
+    }
+
+    #[test]
+    fn test_decompress_group_into_small() {
+        let mut index = [Elf32CRelGroupIndex::default(); 2];
+        elf32_index_groups(&CREL_TWO_GROUPS, &mut index).unwrap();
+        let mut addresses = [0; 2];
+        let err = decompress_group_into(&CREL_TWO_GROUPS, &index[1], &mut addresses).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        assert_eq!(addresses, [0; 2]);
+    }
+
+    #[test]
+    fn test_decompress_unchecked() {
+        let sections: [&[u8]; 9] = [
+            &CREL_TWO_GROUPS,
+            &CREL_GROUP_VARINT,
+            &CREL_BITMAP,
+            &CREL_INTERLEAVED,
+            &CREL_DIRECTORY,
+            &CREL_BASED,
+            &CREL_SYMBOLS,
+            &CREL_SIGNED,
+            &CREL_ALIGNED,
+        ];
+        for data in sections.iter() {
+            let mut expected = [(0, 0); 8];
+            let mut count = 0;
+            let read = elf32_relocate(data, &mut |relocation_type, address| {
+                expected[count] = (relocation_type, address);
+                count += 1;
+                Ok(())
+            })
+            .unwrap();
+            let mut relocations = [(0, 0); 8];
+            let mut unchecked = 0;
+            let unchecked_read = unsafe {
+                elf32_relocate_unchecked(data, &mut |relocation_type, address| {
+                    relocations[unchecked] = (relocation_type, address);
+                    unchecked += 1;
+                    Ok(())
+                })
+            }
+            .unwrap();
+            assert_eq!((unchecked_read, unchecked), (read, count));
+            assert_eq!(relocations, expected);
+        }
+        let err = unsafe {
+            elf32_relocate_unchecked(&CREL_TWO_GROUPS, &mut |_, _| {
+                Err(Error::new(ErrorKind::BufferSmall))
+            })
+        }
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+    }
+
+    #[cfg(not(feature = "no_sanity_check"))]
+    #[test]
+    fn test_decompress_overflow() {
+        use crate::stream::Elf32CRelDecoder;
+
+        let mut data = CREL_TWO_GROUPS;
+        data[..4].copy_from_slice(&0xFFFF_FFF0u32.to_ne_bytes());
+        let mut count = 0;
+        let err = elf32_relocate(&data, &mut |_, _| {
+            count += 1;
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!((err.kind(), count), (ErrorKind::Overflow, 1));
+        let err = Elf32CRelIter::new(&data).nth(1).unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Overflow);
+        let err = Elf32CRelDecoder::new()
+            .feed(&data, &mut |_, _| Ok(()))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Overflow);
+
+        let mut data = CREL_SIGNED;
+        data[..4].copy_from_slice(&0x80u32.to_ne_bytes());
+        let err = elf32_relocate(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Overflow);
+    }
+
+    #[test]
+    fn test_decompress_strict() {
+        let sections: [&[u8]; 8] = [
+            &CREL_TWO_GROUPS,
+            &CREL_GROUP_VARINT,
+            &CREL_INTERLEAVED,
+            &CREL_DIRECTORY,
+            &CREL_BASED,
+            &CREL_SYMBOLS,
+            &CREL_SIGNED,
+            &CREL_ALIGNED,
+        ];
+        for data in sections.iter() {
+            let mut expected = [(0, 0); 8];
+            let mut count = 0;
+            let read = elf32_relocate(data, &mut |relocation_type, address| {
+                expected[count] = (relocation_type, address);
+                count += 1;
+                Ok(())
+            })
+            .unwrap();
+            let mut relocations = [(0, 0); 8];
+            let mut strict = 0;
+            let strict_read = elf32_relocate_strict(data, &mut |relocation_type, address| {
+                relocations[strict] = (relocation_type, address);
+                strict += 1;
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!((strict_read, strict), (read, count));
+            assert_eq!(relocations, expected);
+        }
+
+        // Trailing bytes.
+        let mut data = [0; 16];
+        data[..15].copy_from_slice(&CREL_TWO_GROUPS);
+        elf32_relocate(&data, &mut |_, _| Ok(())).unwrap();
+        let err = elf32_relocate_strict(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // The last offset encoded in two bytes instead of one.
+        data[14..].copy_from_slice(&[0x84, 0x00]);
+        elf32_relocate(&data, &mut |_, _| Ok(())).unwrap();
+        let err = elf32_relocate_strict(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // Groups not sorted by type.
+        elf32_relocate(&CREL_BITMAP, &mut |_, _| Ok(())).unwrap();
+        let err = elf32_relocate_strict(&CREL_BITMAP, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // Addresses past the end of the address space.
+        let mut data = CREL_TWO_GROUPS;
+        data[..4].copy_from_slice(&0xFFFF_FFF0u32.to_ne_bytes());
+        let err = elf32_relocate_strict(&data, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Overflow);
+    }
+}
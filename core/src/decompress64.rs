@@ -0,0 +1,108 @@
+//! Decompress a compressed ELF64 relocation section
+//!
+//! The ELF64 layout mirrors the grouped ELF32 layout, but encodes every
+//! value, including the base address, the group count and the relocation
+//! types, as ULEB128 to cover 64-bit offsets and 32-bit relocation types.
+
+use crate::error::Error;
+use crate::uleb128;
+
+/// Reads a ULEB128 encoded 32-bit value at `index`, advancing `index`.
+fn read_u32(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    let mut value = 0;
+    *index += uleb128::read_u32(
+        data.get(*index..).ok_or_else(Error::not_enough_data)?,
+        &mut value,
+    )?;
+    Ok(value)
+}
+
+/// Reads a ULEB128 encoded 64-bit value at `index`, advancing `index`.
+fn read_u64(data: &[u8], index: &mut usize) -> Result<u64, Error> {
+    let mut value = 0;
+    *index += uleb128::read_u64(
+        data.get(*index..).ok_or_else(Error::not_enough_data)?,
+        &mut value,
+    )?;
+    Ok(value)
+}
+
+/// Processes a compressed ELF64 relocation section and calls `op` for every
+/// relocation with its type and address for further processing.
+///
+/// Returns the number of bytes of the section.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed, e.g. if an address is
+/// beyond the 64-bit address space, or if `op` fails.
+pub fn elf64_relocate<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(u32, u64) -> Result<(), Error>,
+{
+    let mut index = 0;
+    let base_address = read_u64(data, &mut index)?;
+    let groups = read_u32(data, &mut index)?;
+    for _ in 0..groups {
+        let relocation_type = read_u32(data, &mut index)?;
+        let count = read_u32(data, &mut index)?;
+        let mut address = base_address;
+        for _ in 0..count {
+            let delta = read_u64(data, &mut index)?;
+            address = address.checked_add(delta).ok_or_else(Error::invalid_data)?;
+            op(relocation_type, address)?;
+        }
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const CREL: [u8; 16] = [
+        0x80, 0x80, 0x80, 0x80, 0x10, // base_address
+        0x02, // count
+        0x83, 0x08, // group[0].relocation_type
+        0x01, // group[0].count
+        0x08, // group[0].offsets[0]
+        0x01, // group[1].relocation_type
+        0x02, // group[1].count
+        0x00, // group[1].offsets[0]
+        0x80, 0x80, 0x04, // group[1].offsets[1]
+    ];
+
+    #[test]
+    fn test_elf64_relocate() {
+        let mut relocations = Vec::new();
+        let end = elf64_relocate(&CREL, &mut |relocation_type, address| {
+            relocations.push((relocation_type, address));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(end, CREL.len());
+        assert_eq!(
+            relocations,
+            [
+                (0x403, 0x1_0000_0008),
+                (0x01, 0x1_0000_0000),
+                (0x01, 0x1_0001_0000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf64_relocate_errors() {
+        let err = elf64_relocate(&CREL[..15], &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = elf64_relocate(&CREL, &mut |_, _| Err(Error::buffer_small())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+
+        let mut overflow = [0xFF; 14];
+        overflow[9] = 0x01;
+        overflow[10..].copy_from_slice(&[0x01, 0x00, 0x01, 0x01]);
+        let err = elf64_relocate(&overflow, &mut |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,209 @@
+//! Deltas between compressed relocation sections
+//!
+//! Firmware updates can ship the difference between the compressed
+//! relocation section of the running image and that of the new one instead
+//! of the whole new section. The `relox` crate creates deltas, and
+//! [apply_delta](fn.apply_delta.html) rebuilds the new section on the target.
+//!
+//! ```text
+//! struct Elf32CRelDelta {
+//!     magic: [u8; 4],     // Always "RXDL"
+//!     old_size: u32,      // ULEB128
+//!     old_checksum: u32,  // Little-endian
+//!     new_size: u32,      // ULEB128
+//!     new_checksum: u32,  // Little-endian
+//!     operations: [u8],   // Until the end of the data
+//! }
+//! ```
+//!
+//! Every operation starts with a ULEB128 encoded value holding its length
+//! shifted left by one. If the least significant bit is clear, the operation
+//! copies `length` bytes of the old section from the ULEB128 encoded offset
+//! following it. Otherwise the `length` bytes following it are inserted.
+//!
+//! Checksums are 32-bit FNV-1a hashes, see
+//! [delta_checksum](fn.delta_checksum.html). They detect deltas applied to
+//! the wrong section, they do not authenticate the delta.
+
+use crate::decompress::saturating_usize;
+use crate::error::Error;
+
+/// Magic of a delta.
+pub const DELTA_MAGIC: [u8; 4] = *b"RXDL";
+
+/// Returns the 32-bit FNV-1a hash of `data`.
+pub fn delta_checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811C_9DC5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Reads a ULEB128 encoded value at `*index` and advances `*index` past it.
+///
+/// Unlike the decoder, this is bounds-checked even if the `no_bounds_check`
+/// feature is requested, as deltas are received from the outside.
+fn read_uleb128(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *data.get(*index).ok_or_else(Error::not_enough_data)?;
+        *index += 1;
+        if shift == 28 && byte > 0x0F {
+            return Err(Error::invalid_data());
+        }
+        value |= ((byte & 0x7F) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::invalid_data())
+}
+
+/// Reads a little-endian checksum at `*index` and advances `*index` past it.
+fn read_checksum(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    let bytes = data
+        .get(*index..*index + 4)
+        .ok_or_else(Error::not_enough_data)?;
+    *index += 4;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Returns the size of the section a delta produces.
+///
+/// # Errors
+///
+/// `InvalidData` if `delta` does not start with
+/// [DELTA_MAGIC](constant.DELTA_MAGIC.html), or `NotEnoughData` if its header
+/// is truncated.
+pub fn delta_new_size(delta: &[u8]) -> Result<usize, Error> {
+    let mut index = check_magic(delta)?;
+    read_uleb128(delta, &mut index)?;
+    read_checksum(delta, &mut index)?;
+    read_uleb128(delta, &mut index).map(saturating_usize)
+}
+
+/// Checks the magic of a delta and returns the offset following it.
+fn check_magic(delta: &[u8]) -> Result<usize, Error> {
+    match delta.starts_with(&DELTA_MAGIC) {
+        true => Ok(DELTA_MAGIC.len()),
+        false => Err(Error::invalid_data()),
+    }
+}
+
+/// Applies `delta` to `old`, the compressed section it was created from, and
+/// writes the new section to `output`.
+///
+/// Returns the size of the new section. The delta is bounds-checked even if
+/// the `no_bounds_check` feature is requested, as deltas are received from
+/// the outside.
+///
+/// # Errors
+///
+/// `InvalidData` if the delta is malformed, if `old` is not the section it
+/// was created from or if the result does not match the recorded checksum,
+/// `NotEnoughData` if the delta is truncated, or `BufferSmall` if `output` is
+/// smaller than the new section.
+pub fn apply_delta(old: &[u8], delta: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut index = check_magic(delta)?;
+    let old_size = saturating_usize(read_uleb128(delta, &mut index)?);
+    let old_checksum = read_checksum(delta, &mut index)?;
+    if old.len() != old_size || delta_checksum(old) != old_checksum {
+        return Err(Error::invalid_data());
+    }
+    let new_size = saturating_usize(read_uleb128(delta, &mut index)?);
+    let new_checksum = read_checksum(delta, &mut index)?;
+    let output = output.get_mut(..new_size).ok_or_else(Error::buffer_small)?;
+    let mut written = 0;
+    while index < delta.len() {
+        let operation = read_uleb128(delta, &mut index)?;
+        let length = saturating_usize(operation >> 1);
+        let source = match operation & 1 {
+            0 => {
+                let offset = saturating_usize(read_uleb128(delta, &mut index)?);
+                old.get(offset..offset.saturating_add(length))
+                    .ok_or_else(Error::invalid_data)?
+            }
+            _ => {
+                let source = delta
+                    .get(index..index.saturating_add(length))
+                    .ok_or_else(Error::not_enough_data)?;
+                index += length;
+                source
+            }
+        };
+        output
+            .get_mut(written..written + length)
+            .ok_or_else(Error::invalid_data)?
+            .copy_from_slice(source);
+        written += length;
+    }
+    if written != new_size || delta_checksum(output) != new_checksum {
+        return Err(Error::invalid_data());
+    }
+    Ok(new_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const OLD: [u8; 9] = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04];
+    const NEW: [u8; 10] = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x03, 0x04, 0x04, 0x04];
+
+    fn delta() -> [u8; 24] {
+        let mut delta = [0; 24];
+        delta[..4].copy_from_slice(&DELTA_MAGIC);
+        delta[4] = OLD.len() as u8;
+        delta[5..9].copy_from_slice(&delta_checksum(&OLD).to_le_bytes());
+        delta[9] = NEW.len() as u8;
+        delta[10..14].copy_from_slice(&delta_checksum(&NEW).to_le_bytes());
+        delta[14..24].copy_from_slice(&[
+            0x0C, 0x00, // copy 6 bytes from 0
+            0x03, 0x03, // insert 1 byte
+            0x04, 0x07, // copy 2 bytes from 7
+            0x03, 0x04, // insert 1 byte
+            0x00, 0x00, // copy nothing
+        ]);
+        delta
+    }
+
+    #[test]
+    fn test_delta_checksum() {
+        assert_eq!(delta_checksum(&[]), 0x811C_9DC5);
+        assert_eq!(delta_checksum(b"a"), 0xE40C_292C);
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let delta = delta();
+        assert_eq!(delta_new_size(&delta).unwrap(), NEW.len());
+        let mut output = [0; 16];
+        let written = apply_delta(&OLD, &delta, &mut output).unwrap();
+        assert_eq!(output[..written], NEW);
+    }
+
+    #[test]
+    fn test_apply_delta_errors() {
+        let delta = delta();
+        let mut output = [0; 16];
+        let err = apply_delta(&NEW, &delta, &mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = apply_delta(&OLD, &delta[1..], &mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = apply_delta(&OLD, &delta, &mut output[..9]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        let err = apply_delta(&OLD, &delta[..17], &mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = apply_delta(&OLD, &delta[..20], &mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut corrupted = delta;
+        corrupted[19] = 0x08;
+        let err = apply_delta(&OLD, &corrupted, &mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        corrupted[19] = 0x04;
+        corrupted[18] = 0x08;
+        let err = apply_delta(&OLD, &corrupted, &mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
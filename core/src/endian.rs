@@ -0,0 +1,43 @@
+//! Byte order of data
+
+/// Byte order of data.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Endianness {
+    /// Least significant byte first.
+    Little,
+    /// Most significant byte first.
+    Big,
+}
+
+impl Endianness {
+    /// Returns the byte order of the target.
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        }
+    }
+
+    /// Converts a word between this byte order and the native one.
+    pub fn convert(self, word: u32) -> u32 {
+        if self == Self::native() {
+            word
+        } else {
+            word.swap_bytes()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let word = u32::from_ne_bytes([0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(Endianness::Little.convert(word), 0x04030201);
+        assert_eq!(Endianness::Big.convert(word), 0x01020304);
+        assert_eq!(Endianness::native().convert(word), word);
+    }
+}
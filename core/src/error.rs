@@ -0,0 +1,428 @@
+use core::fmt;
+
+/// Possible reasons of an [Error](#Error).
+///
+/// `InvalidData`, `NotEnoughData` and `Overflow` report corrupt input, while
+/// [is_unsupported](#method.is_unsupported) tells apart valid input which
+/// cannot be handled, e.g. to fall back to another encoding or decoder.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// The data provided is invalid.
+    InvalidData,
+    /// There is not enough data to perform the requested operation.
+    NotEnoughData,
+    /// Buffer is too small.
+    BufferSmall,
+    /// The data provided is valid but not supported.
+    Unsupported,
+    /// An address accumulated from the data provided overflows.
+    Overflow,
+    /// The data provided holds a relocation of a type which is not handled.
+    UnsupportedRelocationType,
+    /// There are more relocation groups than the layout can hold.
+    TooManyGroups,
+    /// There is no section with the requested name and type.
+    SectionNotFound,
+}
+
+impl ErrorKind {
+    /// Returns a short description of this kind of error.
+    pub(crate) fn message(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidData => "invalid data",
+            ErrorKind::NotEnoughData => "not enough data",
+            ErrorKind::BufferSmall => "buffer is too small",
+            ErrorKind::Unsupported => "unsupported data",
+            ErrorKind::Overflow => "address overflow",
+            ErrorKind::UnsupportedRelocationType => "unsupported relocation type",
+            ErrorKind::TooManyGroups => "too many relocation groups",
+            ErrorKind::SectionNotFound => "section not found",
+        }
+    }
+
+    /// Returns whether the input is valid but not supported: `Unsupported`,
+    /// `UnsupportedRelocationType` or `TooManyGroups`.
+    pub fn is_unsupported(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Unsupported
+                | ErrorKind::UnsupportedRelocationType
+                | ErrorKind::TooManyGroups
+        )
+    }
+}
+
+/// Representation of an error.
+#[derive(Debug)]
+pub struct Error {
+    /// Kind of the error.
+    reason: ErrorKind,
+    /// Offset into the compressed section where decoding failed, if known.
+    #[cfg(feature = "detailed_errors")]
+    offset: Option<usize>,
+    /// Index of the group being decoded, if known.
+    #[cfg(feature = "detailed_errors")]
+    group: Option<u32>,
+    /// Index of the entry of the group being decoded, if known.
+    #[cfg(feature = "detailed_errors")]
+    entry: Option<u32>,
+}
+
+impl Error {
+    /// Creates a new `Error` instance.
+    pub fn new(reason: ErrorKind) -> Self {
+        Self {
+            reason,
+            #[cfg(feature = "detailed_errors")]
+            offset: None,
+            #[cfg(feature = "detailed_errors")]
+            group: None,
+            #[cfg(feature = "detailed_errors")]
+            entry: None,
+        }
+    }
+
+    /// Returns the reason of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.reason
+    }
+}
+
+#[cfg(feature = "detailed_errors")]
+impl Error {
+    /// Returns the offset into the compressed section where decoding failed,
+    /// if known.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Returns the index of the group, run or directory entry being decoded,
+    /// if known.
+    pub fn group(&self) -> Option<u32> {
+        self.group
+    }
+
+    /// Returns the index of the entry of the group being decoded, if known.
+    pub fn entry(&self) -> Option<u32> {
+        self.entry
+    }
+}
+
+// Context is only recorded with the `detailed_errors` feature, otherwise
+// these are no-ops, so the decoder can attach it unconditionally.
+#[allow(unused)]
+impl Error {
+    /// Records `offset` as the offset where decoding failed, unless an inner
+    /// call already recorded one.
+    #[inline(always)]
+    pub(crate) fn at(mut self, offset: usize) -> Self {
+        #[cfg(feature = "detailed_errors")]
+        if self.offset.is_none() {
+            self.offset = Some(offset);
+        }
+        self
+    }
+
+    /// Records the group and the entry of the group being decoded.
+    #[inline(always)]
+    pub(crate) fn in_group(mut self, group: u32, entry: u32) -> Self {
+        #[cfg(feature = "detailed_errors")]
+        {
+            self.group = Some(group);
+            self.entry = Some(entry);
+        }
+        self
+    }
+}
+
+// Errors are the rare path of the decoder. Constructing them in cold functions
+// marks every failing check as unlikely, so error exits are moved out of the
+// common path and merged instead of being laid out inline at every check.
+// They are not forced out of line: an `Error` is a single byte, so a call
+// would take more code than constructing it.
+#[allow(unused)]
+impl Error {
+    /// Creates a new `Error` of kind `InvalidData`.
+    #[cold]
+    pub(crate) fn invalid_data() -> Self {
+        Self::new(ErrorKind::InvalidData)
+    }
+
+    /// Creates a new `Error` of kind `NotEnoughData`.
+    #[cold]
+    pub(crate) fn not_enough_data() -> Self {
+        Self::new(ErrorKind::NotEnoughData)
+    }
+
+    /// Creates a new `Error` of kind `BufferSmall`.
+    #[cold]
+    pub(crate) fn buffer_small() -> Self {
+        Self::new(ErrorKind::BufferSmall)
+    }
+
+    /// Creates a new `Error` of kind `Unsupported`.
+    #[cold]
+    pub(crate) fn unsupported() -> Self {
+        Self::new(ErrorKind::Unsupported)
+    }
+
+    /// Creates a new `Error` of kind `Overflow`.
+    #[cold]
+    pub(crate) fn overflow() -> Self {
+        Self::new(ErrorKind::Overflow)
+    }
+
+    /// Creates a new `Error` of kind `UnsupportedRelocationType`.
+    #[cold]
+    pub(crate) fn unsupported_relocation_type() -> Self {
+        Self::new(ErrorKind::UnsupportedRelocationType)
+    }
+
+    /// Creates a new `Error` of kind `TooManyGroups`.
+    #[cold]
+    pub(crate) fn too_many_groups() -> Self {
+        Self::new(ErrorKind::TooManyGroups)
+    }
+
+    /// Creates a new `Error` of kind `SectionNotFound`.
+    #[cold]
+    pub(crate) fn section_not_found() -> Self {
+        Self::new(ErrorKind::SectionNotFound)
+    }
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind()
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.reason, f)?;
+        #[cfg(feature = "detailed_errors")]
+        {
+            if let Some(offset) = self.offset {
+                write!(f, " at offset {}", offset)?;
+            }
+            if let (Some(group), Some(entry)) = (self.group, self.entry) {
+                write!(f, " in group {}, entry {}", group, entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        let kind = match err.kind() {
+            ErrorKind::InvalidData => std::io::ErrorKind::InvalidData,
+            ErrorKind::NotEnoughData => std::io::ErrorKind::UnexpectedEof,
+            ErrorKind::BufferSmall => std::io::ErrorKind::WriteZero,
+            ErrorKind::Unsupported => std::io::ErrorKind::Unsupported,
+            ErrorKind::Overflow => std::io::ErrorKind::InvalidData,
+            ErrorKind::UnsupportedRelocationType | ErrorKind::TooManyGroups => {
+                std::io::ErrorKind::Unsupported
+            }
+            ErrorKind::SectionNotFound => std::io::ErrorKind::NotFound,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for ErrorKind {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            ErrorKind::InvalidData => "InvalidData",
+            ErrorKind::NotEnoughData => "NotEnoughData",
+            ErrorKind::BufferSmall => "BufferSmall",
+            ErrorKind::Unsupported => "Unsupported",
+            ErrorKind::Overflow => "Overflow",
+            ErrorKind::UnsupportedRelocationType => "UnsupportedRelocationType",
+            ErrorKind::TooManyGroups => "TooManyGroups",
+            ErrorKind::SectionNotFound => "SectionNotFound",
+        })
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDisplay for ErrorKind {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.message())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.debug_struct("Error")?
+            .field("reason", &self.reason)?
+            .finish()
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        ufmt::uDisplay::fmt(&self.reason, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        Error::new(ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_kind() {
+        let err = Error::new(ErrorKind::InvalidData);
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_ne!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[test]
+    fn test_is_unsupported() {
+        assert!(ErrorKind::TooManyGroups.is_unsupported());
+        assert!(ErrorKind::UnsupportedRelocationType.is_unsupported());
+        assert!(!ErrorKind::Overflow.is_unsupported());
+        assert!(!ErrorKind::SectionNotFound.is_unsupported());
+    }
+
+    #[test]
+    fn test_partialeq() {
+        let err1 = Error::new(ErrorKind::InvalidData);
+        let err2 = Error::new(ErrorKind::NotEnoughData);
+        let err3 = Error::new(ErrorKind::NotEnoughData);
+        assert_ne!(err1, err2);
+        assert_eq!(err2, err3);
+    }
+
+    #[test]
+    fn test_std_fmt_debug() {
+        println!("{:?}", Error::new(ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_std_fmt_display() {
+        assert_eq!(
+            format!("{}", Error::new(ErrorKind::NotEnoughData)),
+            "not enough data"
+        );
+        assert_eq!(format!("{}", ErrorKind::Unsupported), "unsupported data");
+    }
+
+    #[cfg(feature = "detailed_errors")]
+    #[test]
+    fn test_detailed() {
+        let err = Error::new(ErrorKind::InvalidData);
+        assert_eq!((err.offset(), err.group(), err.entry()), (None, None, None));
+        let err = err.at(12).at(10).in_group(1, 3);
+        assert_eq!(
+            (err.offset(), err.group(), err.entry()),
+            (Some(12), Some(1), Some(3))
+        );
+        assert_eq!(
+            format!("{}", err),
+            "invalid data at offset 12 in group 1, entry 3"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_std_error() {
+        fn fail() -> Result<(), Box<dyn std::error::Error>> {
+            Err(Error::new(ErrorKind::InvalidData))?
+        }
+        assert_eq!(fail().unwrap_err().to_string(), "invalid data");
+
+        let err = std::io::Error::from(Error::new(ErrorKind::BufferSmall));
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        assert_eq!(err.to_string(), "buffer is too small");
+        let err = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+    }
+
+    #[allow(clippy::clone_on_copy)]
+    #[test]
+    fn test_std_clone_clone() {
+        Error::new(ErrorKind::InvalidData.clone());
+    }
+
+    #[cfg(feature = "ufmt")]
+    struct Buffer {
+        data: [u8; 64],
+        len: usize,
+    }
+
+    #[cfg(feature = "ufmt")]
+    impl Buffer {
+        fn new() -> Self {
+            Self {
+                data: [0; 64],
+                len: 0,
+            }
+        }
+
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.data[..self.len]).unwrap()
+        }
+    }
+
+    #[cfg(feature = "ufmt")]
+    impl ufmt::uWrite for Buffer {
+        type Error = ();
+
+        fn write_str(&mut self, s: &str) -> Result<(), ()> {
+            let end = self.len + s.len();
+            self.data
+                .get_mut(self.len..end)
+                .ok_or(())?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "ufmt")]
+    #[test]
+    fn test_ufmt_udebug() {
+        let mut buffer = Buffer::new();
+        ufmt::uwrite!(buffer, "{:?}", Error::new(ErrorKind::NotEnoughData)).unwrap();
+        assert_eq!(buffer.as_str(), "Error { reason: NotEnoughData }");
+    }
+
+    #[cfg(feature = "ufmt")]
+    #[test]
+    fn test_ufmt_udisplay() {
+        let mut buffer = Buffer::new();
+        ufmt::uwrite!(buffer, "{}", Error::new(ErrorKind::BufferSmall)).unwrap();
+        assert_eq!(buffer.as_str(), "buffer is too small");
+    }
+}
@@ -0,0 +1,243 @@
+//! ARM FDPIC relocation tables
+//!
+//! FDPIC executables run on MMU-less targets with their segments loaded at
+//! independent addresses. Their relocations may reference dynamic symbols,
+//! e.g. the section symbols of section-relative function descriptors, so a
+//! packed FDPIC relocation table holds one compressed relocation section per
+//! symbol:
+//!
+//! ```text
+//! struct Elf32FdpicTable {
+//!     symbol: u32,           // Dynamic symbol index of the relocations
+//!     relocations: Elf32CRel // Link-time addresses of the relocated words
+//! }
+//!
+//! struct Elf32Fdpic {
+//!     tables: [Elf32FdpicTable] // Until the end of the data
+//! }
+//! ```
+//!
+//! [fdpic_relocate](fn.fdpic_relocate.html) applies the table given the load
+//! map of the segments. `R_ARM_GOTFUNCDESC` and `R_ARM_GOTOFFFUNCDESC` are
+//! resolved by the linker, which emits `R_ARM_FUNCDESC_VALUE` relocations for
+//! the descriptors they reference.
+
+use crate::apply::word_ptr;
+use crate::decompress::{elf32_relocate, saturating_usize, Header, TARGET_MACHINE};
+use crate::error::Error;
+use crate::reader::{read_u32_at, tail};
+
+/// Direct 32-bit relocation, `S + A`.
+pub const R_ARM_ABS32: u8 = 2;
+/// Relative relocation, `B(S) + A`.
+pub const R_ARM_RELATIVE: u8 = 23;
+/// Address of the canonical function descriptor of a function.
+pub const R_ARM_FUNCDESC: u8 = 163;
+/// Function descriptor: the address of a function and the GOT address of its
+/// module.
+pub const R_ARM_FUNCDESC_VALUE: u8 = 164;
+
+/// Size of the header of a packed FDPIC relocation table.
+const TABLE_HEADER_SIZE: usize = 4;
+
+/// A segment of an FDPIC load map.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FdpicSegment {
+    address: u32,
+    size: u32,
+    load_address: u32,
+}
+
+impl FdpicSegment {
+    /// Creates a new `FdpicSegment` instance mapping `size` bytes linked at
+    /// `address` to `load_address`.
+    pub fn new(address: u32, size: u32, load_address: u32) -> Self {
+        Self {
+            address,
+            size,
+            load_address,
+        }
+    }
+
+    /// Returns the link-time address of the segment.
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Returns the size of the segment in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the address the segment is loaded at.
+    pub fn load_address(&self) -> u32 {
+        self.load_address
+    }
+}
+
+/// Returns the load address of the link-time `address`.
+fn load_address(segments: &[FdpicSegment], address: u32) -> Result<u32, Error> {
+    segments
+        .iter()
+        .find(|segment| address.wrapping_sub(segment.address) < segment.size)
+        .map(|segment| segment.load_address + (address - segment.address))
+        .ok_or_else(Error::invalid_data)
+}
+
+/// Applies a packed FDPIC relocation table to the loaded segments.
+///
+/// `image` is the memory loaded at `image_address`, which holds the relocated
+/// words at the load addresses of `segments`. Relocated words may be
+/// unaligned and use native endianness.
+///
+/// Words relocated against symbol 0 hold link-time addresses, which are
+/// translated to load addresses. Words relocated against other symbols hold
+/// addends to the symbol's address returned by `symbol`, e.g. the load
+/// address of the section of a section symbol. `R_ARM_FUNCDESC_VALUE`
+/// descriptors get the function address and `got`, the GOT address of the
+/// module.
+///
+/// Returns the number of bytes read from `data`.
+///
+/// # Errors
+///
+/// If the packed table is malformed, if a relocation targets an address
+/// outside of `segments` or a word outside of `image`, or if `symbol` returns
+/// `None`, `UnsupportedRelocationType` if it contains a relocation of another
+/// type, or `Unsupported` if a table records a target machine other than
+/// [TARGET_MACHINE](constant.TARGET_MACHINE.html).
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn fdpic_relocate(
+    data: &[u8],
+    image: &mut [u8],
+    image_address: u32,
+    segments: &[FdpicSegment],
+    got: u32,
+    symbol: &mut dyn FnMut(u32) -> Option<u32>,
+) -> Result<usize, Error> {
+    let mut offset = 0;
+    while offset < data.len() {
+        let table = tail(data, offset)?;
+        let base = match read_u32_at(table, 0)? {
+            0 => None,
+            index => Some(symbol(index).ok_or_else(Error::invalid_data)?),
+        };
+        let value = |word: u32| match base {
+            Some(base) => Ok(base.wrapping_add(word)),
+            None => load_address(segments, word),
+        };
+        let relocations = tail(table, TABLE_HEADER_SIZE)?;
+        Header::read(relocations)?.check_machine(TARGET_MACHINE)?;
+        offset += TABLE_HEADER_SIZE
+            + elf32_relocate(relocations, &mut |relocation_type, address| {
+                let location = load_address(segments, address)?.wrapping_sub(image_address);
+                let word = word_ptr(image, saturating_usize(location))?;
+                match relocation_type {
+                    R_ARM_ABS32 | R_ARM_RELATIVE | R_ARM_FUNCDESC => unsafe {
+                        word.write_unaligned(value(word.read_unaligned())?);
+                    },
+                    R_ARM_FUNCDESC_VALUE => {
+                        let got_word = word_ptr(image, saturating_usize(location.wrapping_add(4)))?;
+                        unsafe {
+                            word.write_unaligned(value(word.read_unaligned())?);
+                            got_word.write_unaligned(got);
+                        }
+                    }
+                    _ => return Err(Error::unsupported_relocation_type()),
+                }
+                Ok(())
+            })?;
+    }
+    Ok(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    // A function descriptor at 0x1000 for the function at 0x0100 of the text
+    // segment, a word at 0x1008 holding its address and a descriptor at
+    // 0x1010 for the function 0x20 bytes into the section of symbol 2.
+    const FDPIC: [u8; 27] = [
+        0x00, 0x00, 0x00, 0x00, // tables[0].symbol
+        0x00, 0x10, 0x00, 0x00, // tables[0].relocations.base_address
+        0x02, // tables[0].relocations.count
+        0xA3, // tables[0].relocations.group[0].relocation_type
+        0x01, // tables[0].relocations.group[0].count
+        0x08, // tables[0].relocations.group[0].offsets
+        0xA4, // tables[0].relocations.group[1].relocation_type
+        0x01, // tables[0].relocations.group[1].count
+        0x00, // tables[0].relocations.group[1].offsets
+        0x02, 0x00, 0x00, 0x00, // tables[1].symbol
+        0x10, 0x10, 0x00, 0x00, // tables[1].relocations.base_address
+        0x01, // tables[1].relocations.count
+        0xA4, // tables[1].relocations.group[0].relocation_type
+        0x01, // tables[1].relocations.group[0].count
+        0x00, // tables[1].relocations.group[0].offsets
+    ];
+
+    #[test]
+    fn test_fdpic_segment() {
+        let segment = FdpicSegment::new(0x1000, 0x20, 0x8000);
+        assert_eq!(segment.address(), 0x1000);
+        assert_eq!(segment.size(), 0x20);
+        assert_eq!(segment.load_address(), 0x8000);
+    }
+
+    #[test]
+    fn test_fdpic_relocate() {
+        let segments = [
+            FdpicSegment::new(0x0000, 0x200, 0x4_0000),
+            FdpicSegment::new(0x1000, 0x20, 0x8_0000),
+        ];
+        let mut image = [0u8; 0x20];
+        image[0..4].copy_from_slice(&0x0100u32.to_ne_bytes());
+        image[8..12].copy_from_slice(&0x1000u32.to_ne_bytes());
+        image[0x10..0x14].copy_from_slice(&0x20u32.to_ne_bytes());
+        let read = fdpic_relocate(
+            &FDPIC,
+            &mut image,
+            0x8_0000,
+            &segments,
+            0x8_0800,
+            &mut |index| match index {
+                2 => Some(0x4_0100),
+                _ => None,
+            },
+        )
+        .unwrap();
+        assert_eq!(read, FDPIC.len());
+        assert_eq!(image[0..4], 0x4_0100u32.to_ne_bytes());
+        assert_eq!(image[4..8], 0x8_0800u32.to_ne_bytes());
+        assert_eq!(image[8..12], 0x8_0000u32.to_ne_bytes());
+        assert_eq!(image[0x10..0x14], 0x4_0120u32.to_ne_bytes());
+        assert_eq!(image[0x14..0x18], 0x8_0800u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_fdpic_relocate_errors() {
+        let segments = [FdpicSegment::new(0x1000, 0x20, 0x8_0000)];
+        let mut image = [0u8; 0x20];
+        // The word at 0x1008 does not hold an address of a segment.
+        let err =
+            fdpic_relocate(&FDPIC, &mut image, 0x8_0000, &segments, 0, &mut |_| None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        image[0..4].copy_from_slice(&0x1000u32.to_ne_bytes());
+        image[8..12].copy_from_slice(&0x1000u32.to_ne_bytes());
+        let err =
+            fdpic_relocate(&FDPIC, &mut image, 0x8_0000, &segments, 0, &mut |_| None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut data = FDPIC;
+        data[9] = R_ARM_RELATIVE + 1;
+        let err =
+            fdpic_relocate(&data, &mut image, 0x8_0000, &segments, 0, &mut |_| None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedRelocationType);
+    }
+}
@@ -0,0 +1,306 @@
+//! Dry-run inspection of compressed sections
+//!
+//! This module can be used to walk a compressed ELF32 relocation section and
+//! summarize it without processing any relocation, e.g. for debuggers showing
+//! the relocations of an image before it is started.
+
+use core::iter::FusedIterator;
+
+use crate::decompress::{skip_offsets, Header};
+use crate::error::Error;
+use crate::reader::Checked;
+
+/// Summary of a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug)]
+pub struct Elf32CRelInfo<'a> {
+    data: &'a [u8],
+    header: Header,
+    relocations: u32,
+    size: usize,
+}
+
+impl<'a> Elf32CRelInfo<'a> {
+    /// Walks a compressed ELF32 relocation section and summarizes it, without
+    /// calling back for any relocation.
+    ///
+    /// ```
+    /// use relox_core::Elf32CRelInfo;
+    ///
+    /// # fn main() -> Result<(), relox_core::Error> {
+    /// let mut data = [0; 12];
+    /// data[..4].copy_from_slice(&0x1000u32.to_ne_bytes());
+    /// data[4..].copy_from_slice(&[0x01, 0x17, 0x03, 0x00, 0x04, 0x04, 0x00, 0x00]);
+    /// let info = Elf32CRelInfo::parse(&data)?;
+    /// assert_eq!((info.group_count(), info.relocation_count()), (1, 3));
+    /// assert_eq!(info.size(), 10);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the compressed relocation section is malformed.
+    ///
+    /// # Panics
+    ///
+    /// If the provided data is too small for any reason and `no_bounds_check`
+    /// feature is not requested.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let header = Header::read(data)?;
+        let mut relocations = 0u32;
+        let mut end = header.offset;
+        for group in GroupWalk::new(data, header) {
+            let (group, read) = group?;
+            relocations = relocations.saturating_add(group.count);
+            end = read;
+        }
+        Ok(Self {
+            data,
+            header,
+            relocations,
+            size: end,
+        })
+    }
+
+    /// Returns the base address of the relocations.
+    pub fn base_address(&self) -> u32 {
+        self.header.base_address
+    }
+
+    /// Returns the target machine (`e_machine`) recorded in the section, if
+    /// any.
+    pub fn machine(&self) -> Option<u16> {
+        self.header.machine
+    }
+
+    /// Returns the number of relocation groups, or the number of runs if the
+    /// section uses the interleaved encoding.
+    pub fn group_count(&self) -> u32 {
+        self.header.count
+    }
+
+    /// Returns the number of relocations, saturating at `u32::MAX`.
+    pub fn relocation_count(&self) -> u32 {
+        self.relocations
+    }
+
+    /// Returns the number of bytes of the compressed section, the value
+    /// returned by [elf32_relocate](fn.elf32_relocate.html). Bytes following
+    /// it, e.g. padding of the section, are not included.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns an iterator over the groups, runs or directory entries of the
+    /// section in the order they are decoded.
+    pub fn groups(&self) -> Elf32CRelGroupInfoIter<'a> {
+        Elf32CRelGroupInfoIter {
+            walk: GroupWalk::new(self.data, self.header),
+        }
+    }
+}
+
+/// Summary of a group, run or directory entry of a compressed section.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32CRelGroupInfo {
+    offset: usize,
+    relocation_type: u8,
+    count: u32,
+}
+
+impl Elf32CRelGroupInfo {
+    /// Returns the offset of the group in the compressed section after any
+    /// padding, or of its entry in the group directory if the section uses
+    /// the directory layout.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the type of the relocations in the group.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the number of relocations in the group.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+#[cfg(feature = "serde")]
+serde_struct!(Elf32CRelGroupInfo {
+    offset: usize,
+    relocation_type: u8,
+    count: u32,
+});
+
+/// Iterator over the groups of a compressed section returned by
+/// [Elf32CRelInfo::groups](struct.Elf32CRelInfo.html#method.groups).
+#[derive(Clone, Debug)]
+pub struct Elf32CRelGroupInfoIter<'a> {
+    walk: GroupWalk<'a>,
+}
+
+impl Iterator for Elf32CRelGroupInfoIter<'_> {
+    type Item = Elf32CRelGroupInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The section was walked by `parse`, so reading it again succeeds.
+        self.walk.next()?.ok().map(|(group, _)| group)
+    }
+}
+
+impl FusedIterator for Elf32CRelGroupInfoIter<'_> {}
+
+/// Walks the groups of a compressed section, skipping their offsets, and
+/// yields every group with the end of its encoded offsets.
+#[derive(Clone, Debug)]
+struct GroupWalk<'a> {
+    data: &'a [u8],
+    header: Header,
+    offset: usize,
+    remaining: u32,
+}
+
+impl<'a> GroupWalk<'a> {
+    fn new(data: &'a [u8], header: Header) -> Self {
+        Self {
+            data,
+            header,
+            offset: header.offset,
+            remaining: header.count,
+        }
+    }
+}
+
+impl Iterator for GroupWalk<'_> {
+    type Item = Result<(Elf32CRelGroupInfo, usize), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // Addresses are not decoded, so the group of an interleaved section
+        // does not need the last address of the previous one.
+        let result = self
+            .header
+            .group(self.data, self.offset, self.header.base_address)
+            .and_then(|group| {
+                let end =
+                    skip_offsets::<Checked>(self.data, group.codec, group.offsets, group.count)?;
+                let info = Elf32CRelGroupInfo {
+                    offset: self.header.align(self.offset),
+                    relocation_type: group.relocation_type,
+                    count: group.count,
+                };
+                Ok((info, end))
+            });
+        match result {
+            Ok((_, end)) => self.offset = self.header.next(self.offset, end),
+            Err(_) => self.remaining = 0,
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompress::elf32_relocate;
+    #[allow(unused)]
+    use crate::error::ErrorKind;
+    use crate::layout::{DIRECTORY, INTERLEAVED};
+
+    const CREL_TWO_GROUPS: [u8; 17] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+        0x00, 0x00, // padding
+    ];
+
+    #[test]
+    fn test_info() {
+        let info = Elf32CRelInfo::parse(&CREL_TWO_GROUPS).unwrap();
+        assert_eq!(info.base_address(), 0x1000);
+        assert_eq!(info.machine(), None);
+        assert_eq!(info.group_count(), 2);
+        assert_eq!(info.relocation_count(), 5);
+        assert_eq!(
+            info.size(),
+            elf32_relocate(&CREL_TWO_GROUPS, &mut |_, _| Ok(())).unwrap()
+        );
+        let mut groups = info.groups();
+        assert_eq!(
+            groups.next(),
+            Some(Elf32CRelGroupInfo {
+                offset: 5,
+                relocation_type: 0x02,
+                count: 2
+            })
+        );
+        assert_eq!(
+            groups.next(),
+            Some(Elf32CRelGroupInfo {
+                offset: 10,
+                relocation_type: 0x16,
+                count: 3
+            })
+        );
+        assert_eq!(groups.next(), None);
+    }
+
+    #[test]
+    fn test_info_layouts() {
+        let data = [
+            0x00,
+            0x10,
+            0x00,
+            0x00, // base_address
+            INTERLEAVED,
+            0x02, // runs
+            0x02,
+            0x02,
+            0x00,
+            0x80,
+            0x01, // run[0]
+            0x17,
+            0x01,
+            0x04, // run[1]
+        ];
+        let info = Elf32CRelInfo::parse(&data).unwrap();
+        assert_eq!((info.group_count(), info.relocation_count()), (2, 3));
+        assert_eq!(info.size(), data.len());
+        let types: [u8; 2] = [0x02, 0x17];
+        assert!(info.groups().map(|group| group.relocation_type()).eq(types));
+
+        let mut data = [0; 27];
+        data[..4].copy_from_slice(&0x1000u32.to_ne_bytes());
+        data[4..6].copy_from_slice(&[DIRECTORY, 0x02]);
+        data[6] = 0x02;
+        data[7..11].copy_from_slice(&1u32.to_ne_bytes());
+        data[15] = 0x17;
+        data[16..20].copy_from_slice(&2u32.to_ne_bytes());
+        data[20..24].copy_from_slice(&1u32.to_ne_bytes());
+        data[24..].copy_from_slice(&[0x00, 0x04, 0x04]);
+        let info = Elf32CRelInfo::parse(&data).unwrap();
+        assert_eq!((info.group_count(), info.relocation_count()), (2, 3));
+        assert_eq!(info.size(), data.len());
+        let offsets: [usize; 2] = [6, 15];
+        assert!(info.groups().map(|group| group.offset()).eq(offsets));
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_info_truncated() {
+        for len in 0..15 {
+            let err = Elf32CRelInfo::parse(&CREL_TWO_GROUPS[..len]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        }
+    }
+}
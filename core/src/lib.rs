@@ -0,0 +1,214 @@
+//! Compressed ELF32 and ELF64 relocation formats and decoder
+//!
+//! This crate holds the wire format, the LEB128 codecs and the decoder of
+//! [relox](https://docs.rs/relox). It is `no_std` and has no dependencies by
+//! default, so it can be used by boot code and other embedded runtimes. The
+//! compressor, ELF integration and command line tool are provided by the
+//! `relox` crate, which re-exports this one.
+//!
+//! See the `relox` crate for the layout of compressed sections.
+//!
+//! ## List of optional features
+//!
+//! * `decompress`: include methods and structures related to decompressing
+//!   (default).
+//! * `compress`: include compressing relocations into caller-provided buffers
+//!   without allocating, e.g. on the device.
+//! * `detailed_errors`: record the offset into the compressed section, the
+//!   group and the entry of the group being decoded in decompression errors.
+//! * `no_bounds_check`: use `unsafe` code instead of bounds-checking variants.
+//! * `no_sanity_check`: do not perform extra sanity checks when processing LEB128
+//!   encodings and accumulating addresses.
+//! * `tiny`: include a reduced decoder for minimal code size, which supports
+//!   the grouped layout only and reports failures as `false`.
+//! * `heapless`: include decoding relocations into a `heapless::Vec`.
+//! * `ufmt`: implement `ufmt` formatting traits for error types.
+//! * `serde`: implement `serde` serialization for relocations, parsed
+//!   sections, group summaries, audit records, decode costs and translation
+//!   regions.
+//! * `std`: use the standard library, e.g. to read relocations from a `Cursor`.
+
+#![crate_name = "relox_core"]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![deny(missing_docs, unused, unused_imports)]
+
+// Declared first, so its macros can be used by the other modules.
+#[cfg(feature = "serde")]
+#[macro_use]
+#[doc(hidden)]
+pub mod serialize;
+
+mod endian;
+mod error;
+mod rel;
+
+// The codecs are not generic, so they are compiled even if unused. Leave them
+// out unless needed to keep the footprint of the reduced decoder.
+#[cfg(any(feature = "decompress", feature = "compress", feature = "std"))]
+pub mod uleb128;
+
+pub use endian::Endianness;
+pub use error::{Error, ErrorKind};
+pub use rel::{Elf32Rel, Elf64Rel};
+
+/// Group counts marking the layouts of compressed sections
+pub mod layout {
+    /// Group count marking a compressed section using the interleaved encoding.
+    pub const INTERLEAVED: u8 = 0xFF;
+
+    /// Group count marking a compressed section using the directory layout.
+    pub const DIRECTORY: u8 = 0xFE;
+
+    /// Group count marking a compressed section with a base address per group.
+    pub const BASED: u8 = 0xFD;
+
+    /// Group count marking a compressed section recording its target machine,
+    /// followed by the machine and the group count of the actual layout.
+    pub const MACHINE: u8 = 0xFC;
+
+    /// Group count marking a compressed section whose offsets use the
+    /// group-varint codec, followed by the group count of the actual layout.
+    /// It follows the machine if both are recorded.
+    pub const GROUP_VARINT: u8 = 0xFB;
+
+    /// Group count marking a compressed section whose groups can carry the
+    /// symbol indices of their relocations, followed by the group count of
+    /// the actual layout. It follows the codec if both are recorded.
+    pub const SYMBOLS: u8 = 0xFA;
+
+    /// Group count marking a compressed section whose offsets use the bitmap
+    /// codec, followed by the group count of the actual layout. It takes the
+    /// place of `GROUP_VARINT`.
+    pub const BITMAP: u8 = 0xF9;
+
+    /// Group count marking a compressed section whose groups can encode their
+    /// offsets as SLEB128 signed deltas, e.g. for relocations not in address
+    /// order, followed by the group count of the actual layout. It follows
+    /// `SYMBOLS` if both are recorded.
+    pub const SIGNED: u8 = 0xF8;
+
+    /// Group count marking a compressed section whose groups or runs start at
+    /// multiples of an alignment relative to the start of the section,
+    /// followed by the alignment, a power of two, and the group count of the
+    /// actual layout. Groups are preceded by zero padding as needed. It
+    /// follows `SIGNED` if both are recorded.
+    pub const ALIGNED: u8 = 0xF7;
+
+    /// Group count marking a compressed section whose groups can encode their
+    /// offsets as opcodes repeating offsets, e.g. for runs of word-aligned
+    /// relocations, followed by the group count of the actual layout. It
+    /// follows the alignment if both are recorded.
+    pub const REPEAT: u8 = 0xF6;
+
+    /// Group count marking a compressed section whose groups or runs carry a
+    /// scale their offsets are multiplied by, e.g. 4 for word-aligned
+    /// relocations, followed by the group count of the actual layout. It
+    /// follows `REPEAT` if both are recorded.
+    pub const SCALED: u8 = 0xF5;
+}
+
+#[cfg(feature = "decompress")]
+mod reader;
+
+#[cfg(feature = "decompress")]
+mod decompress;
+#[cfg(feature = "decompress")]
+pub use decompress::*;
+
+#[cfg(feature = "decompress")]
+mod decompress64;
+#[cfg(feature = "decompress")]
+pub use decompress64::*;
+
+#[cfg(feature = "decompress")]
+mod apply;
+#[cfg(feature = "decompress")]
+pub use apply::*;
+
+#[cfg(feature = "decompress")]
+mod audit;
+#[cfg(feature = "decompress")]
+pub use audit::*;
+
+#[cfg(feature = "decompress")]
+mod resume;
+#[cfg(feature = "decompress")]
+pub use resume::*;
+
+#[cfg(feature = "decompress")]
+mod selftest;
+#[cfg(feature = "decompress")]
+pub use selftest::*;
+
+#[cfg(feature = "decompress")]
+mod sink;
+#[cfg(feature = "decompress")]
+pub use sink::*;
+
+#[cfg(feature = "decompress")]
+mod cost;
+#[cfg(feature = "decompress")]
+pub use cost::*;
+
+#[cfg(feature = "decompress")]
+mod info;
+#[cfg(feature = "decompress")]
+pub use info::*;
+
+#[cfg(feature = "decompress")]
+pub mod view;
+
+#[cfg(feature = "decompress")]
+mod blob;
+#[cfg(feature = "decompress")]
+pub use blob::*;
+
+#[cfg(feature = "decompress")]
+mod stream;
+#[cfg(feature = "decompress")]
+pub use stream::*;
+
+#[cfg(feature = "decompress")]
+mod bflt;
+#[cfg(feature = "decompress")]
+pub use bflt::*;
+
+#[cfg(feature = "decompress")]
+mod llext;
+#[cfg(feature = "decompress")]
+pub use llext::*;
+
+#[cfg(feature = "decompress")]
+mod fdpic;
+#[cfg(feature = "decompress")]
+pub use fdpic::*;
+
+#[cfg(feature = "decompress")]
+mod delta;
+#[cfg(feature = "decompress")]
+pub use delta::*;
+
+#[cfg(feature = "decompress")]
+mod bundle;
+#[cfg(feature = "decompress")]
+pub use bundle::*;
+
+#[cfg(feature = "decompress")]
+mod pointers;
+#[cfg(feature = "decompress")]
+pub use pointers::*;
+
+#[cfg(feature = "decompress")]
+mod sorted;
+#[cfg(feature = "decompress")]
+pub use sorted::*;
+
+#[cfg(feature = "compress")]
+mod compress;
+#[cfg(feature = "compress")]
+pub use compress::*;
+
+#[cfg(feature = "tiny")]
+mod tiny;
+#[cfg(feature = "tiny")]
+pub use tiny::*;
@@ -0,0 +1,153 @@
+//! Zephyr LLEXT relocation tables
+//!
+//! Zephyr's loadable extensions (LLEXT) are relocatable ELF objects whose
+//! `.rel.*` sections reference symbols. A packed LLEXT relocation table holds
+//! one compressed relocation section per target section and symbol, so the
+//! symbol indices are kept while the offsets are compressed:
+//!
+//! ```text
+//! struct Elf32LlextTable {
+//!     symbol: u32,           // Symbol index of the relocations
+//!     section: u16,          // Index of the section to relocate
+//!     relocations: Elf32CRel // Offsets within the section
+//! }
+//!
+//! struct Elf32Llext {
+//!     tables: [Elf32LlextTable] // Until the end of the data
+//! }
+//! ```
+//!
+//! [llext_relocate](fn.llext_relocate.html) reconstructs the `r_offset` and
+//! `r_info` fields of every relocation, so LLEXT's `arch_elf_relocate` hook
+//! can be called unchanged.
+
+use crate::decompress::elf32_relocate;
+use crate::error::Error;
+use crate::reader::{read_u16_at, read_u32_at, tail};
+
+/// Size of the header of a packed LLEXT relocation table.
+const TABLE_HEADER_SIZE: usize = 6;
+
+/// Relocation decoded from a packed LLEXT relocation table.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LlextRelocation {
+    section: u16,
+    symbol: u32,
+    relocation_type: u8,
+    offset: u32,
+}
+
+impl LlextRelocation {
+    /// Returns the index of the section to relocate, the `sh_info` field of
+    /// the original relocation section.
+    pub fn section(&self) -> u16 {
+        self.section
+    }
+
+    /// Returns the index of the symbol the relocation references.
+    pub fn symbol(&self) -> u32 {
+        self.symbol
+    }
+
+    /// Returns the type of the relocation.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the offset of the relocation within its section, `r_offset`.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the `r_info` field of the relocation.
+    pub fn info(&self) -> u32 {
+        self.symbol << 8 | u32::from(self.relocation_type)
+    }
+}
+
+/// Processes a packed LLEXT relocation table and calls `op` for every
+/// relocation, e.g. to rebuild an `elf_rel_t` and call `arch_elf_relocate`
+/// with the location and symbol address LLEXT computes for it.
+///
+/// # Errors
+///
+/// If the packed table is malformed, or if `op` fails.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn llext_relocate<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(LlextRelocation) -> Result<(), Error>,
+{
+    let mut offset = 0;
+    while offset < data.len() {
+        let table = tail(data, offset)?;
+        let symbol = read_u32_at(table, 0)?;
+        let section = read_u16_at(table, 4)?;
+        let relocations = tail(table, TABLE_HEADER_SIZE)?;
+        offset += TABLE_HEADER_SIZE
+            + elf32_relocate(relocations, &mut |relocation_type, address| {
+                op(LlextRelocation {
+                    section,
+                    symbol,
+                    relocation_type,
+                    offset: address,
+                })
+            })?;
+    }
+    Ok(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two R_ARM_ABS32 relocations in section 1 against symbol 5, and one
+    // R_ARM_THM_CALL in section 1 against symbol 7.
+    const LLEXT: [u8; 29] = [
+        0x05, 0x00, 0x00, 0x00, // tables[0].symbol
+        0x01, 0x00, // tables[0].section
+        0x00, 0x00, 0x00, 0x00, // tables[0].relocations.base_address
+        0x01, // tables[0].relocations.count
+        0x02, // tables[0].relocations.group[0].relocation_type
+        0x02, // tables[0].relocations.group[0].count
+        0x04, 0x04, // tables[0].relocations.group[0].offsets
+        0x07, 0x00, 0x00, 0x00, // tables[1].symbol
+        0x01, 0x00, // tables[1].section
+        0x10, 0x00, 0x00, 0x00, // tables[1].relocations.base_address
+        0x01, // tables[1].relocations.count
+        0x0A, // tables[1].relocations.group[0].relocation_type
+        0x01, // tables[1].relocations.group[0].count
+        0x00, // tables[1].relocations.group[0].offsets
+    ];
+
+    #[test]
+    fn test_llext_relocate() {
+        let mut relocations = Vec::new();
+        let read = llext_relocate(&LLEXT, &mut |relocation| {
+            relocations.push(relocation);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, LLEXT.len());
+        assert_eq!(relocations.len(), 3);
+        assert_eq!(relocations[0].section(), 1);
+        assert_eq!(relocations[0].symbol(), 5);
+        assert_eq!(relocations[0].relocation_type(), 2);
+        assert_eq!(relocations[0].offset(), 4);
+        assert_eq!(relocations[1].offset(), 8);
+        assert_eq!(relocations[1].info(), 0x502);
+        assert_eq!(relocations[2].symbol(), 7);
+        assert_eq!(relocations[2].relocation_type(), 10);
+        assert_eq!(relocations[2].offset(), 0x10);
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_llext_relocate_truncated() {
+        let err = llext_relocate(&LLEXT[..20], &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::NotEnoughData);
+    }
+}
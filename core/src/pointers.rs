@@ -0,0 +1,112 @@
+//! Compressed pointer tables
+//!
+//! Pointer tables such as `.init_array` and `.preinit_array` hold link-time
+//! addresses which need the same fix-up as relatively relocated words. They
+//! can be stored compressed and rebuilt at boot instead:
+//!
+//! ```text
+//! struct Elf32CPtrTable {
+//!     first: u32,         // First pointer, native endianness
+//!     count: u32,         // Number of pointers, ULEB128
+//!     deltas: [u32],      // ULEB128, count - 1 zigzag encoded differences
+//! }
+//! ```
+//!
+//! Every delta is the difference of a pointer and the previous one, mapped to
+//! unsigned values by zigzag encoding, `(delta << 1) ^ (delta >> 31)`, as
+//! table entries are not sorted.
+
+use crate::decompress::saturating_usize;
+use crate::error::Error;
+use crate::reader::{read_u32_at, read_uleb128_at};
+
+/// Returns the number of pointers of a compressed pointer table.
+///
+/// # Errors
+///
+/// If the compressed pointer table is malformed.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn pointer_table_len(data: &[u8]) -> Result<usize, Error> {
+    read_u32_at(data, 0)?;
+    let mut index = 4;
+    read_uleb128_at(data, &mut index).map(saturating_usize)
+}
+
+/// Rebuilds a compressed pointer table into `table`, adding `bias` to every
+/// pointer, and writes them in native endianness.
+///
+/// Returns the number of bytes read from `data`.
+///
+/// # Errors
+///
+/// If the compressed pointer table is malformed, or `BufferSmall` if `table`
+/// cannot hold the pointers.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn decompress_pointer_table(data: &[u8], bias: u32, table: &mut [u8]) -> Result<usize, Error> {
+    let mut pointer = read_u32_at(data, 0)?;
+    let mut index = 4;
+    let count = saturating_usize(read_uleb128_at(data, &mut index)?);
+    if table.len() / 4 < count {
+        return Err(Error::buffer_small());
+    }
+    for (number, word) in table.chunks_exact_mut(4).take(count).enumerate() {
+        if number > 0 {
+            let delta = read_uleb128_at(data, &mut index)?;
+            pointer = pointer.wrapping_add((delta >> 1) ^ (delta & 1).wrapping_neg());
+        }
+        word.copy_from_slice(&pointer.wrapping_add(bias).to_ne_bytes());
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn table() -> [u8; 9] {
+        let mut data = [0; 9];
+        data[..4].copy_from_slice(&0x1000u32.to_ne_bytes());
+        data[4..].copy_from_slice(&[
+            0x04, // count
+            0x08, // +4
+            0x07, // -4
+            0x80, 0x02, // +0x80
+        ]);
+        data
+    }
+
+    #[test]
+    fn test_decompress_pointer_table() {
+        let data = table();
+        assert_eq!(pointer_table_len(&data).unwrap(), 4);
+        let mut table = [0u8; 16];
+        let read = decompress_pointer_table(&data, 0x2000_0000, &mut table).unwrap();
+        assert_eq!(read, data.len());
+        let pointers: [u32; 4] = [0x2000_1000, 0x2000_1004, 0x2000_1000, 0x2000_1080];
+        for (word, pointer) in table.chunks(4).zip(pointers.iter()) {
+            assert_eq!(word, pointer.to_ne_bytes());
+        }
+    }
+
+    #[test]
+    fn test_decompress_pointer_table_errors() {
+        let data = table();
+        let mut table = [0u8; 16];
+        let err = decompress_pointer_table(&data, 0, &mut table[..12]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        #[cfg(not(feature = "no_bounds_check"))]
+        {
+            let err = decompress_pointer_table(&data[..8], 0, &mut table).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        }
+    }
+}
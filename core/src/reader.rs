@@ -0,0 +1,375 @@
+//! Bounds-checked reads from compressed sections
+//!
+//! Every decoder reads compressed data through these functions. Multi-byte
+//! values are stored in the byte order of the target and may be unaligned,
+//! so they are assembled from bytes with `from_ne_bytes` instead of read
+//! through a cast pointer.
+//!
+//! With the `no_bounds_check` feature, the same functions skip the length
+//! checks and read through unchecked `unsafe` accesses instead, trusting the
+//! data to be well-formed. The `_with` variants select the checks through an
+//! [Access](trait.Access.html) type instead, so a decoder can skip them at
+//! run time whatever the features, or add the checks of strict validation.
+
+use crate::error::Error;
+
+/// Selects the checks performed when reading a compressed section.
+pub(crate) trait Access {
+    /// Whether reads are bounds-checked.
+    const BOUNDS: bool;
+    /// Whether LEB128 encodings and accumulated addresses are
+    /// sanity-checked.
+    const SANITY: bool;
+    /// Whether LEB128 encodings must be minimal.
+    const STRICT: bool = false;
+}
+
+/// Checks selected by the `no_bounds_check` and `no_sanity_check` features.
+pub(crate) struct Checked;
+
+impl Access for Checked {
+    const BOUNDS: bool = !cfg!(feature = "no_bounds_check");
+    const SANITY: bool = !cfg!(feature = "no_sanity_check");
+}
+
+/// No checks whatever the features, for data known to be well-formed.
+pub(crate) struct Unchecked;
+
+impl Access for Unchecked {
+    const BOUNDS: bool = false;
+    const SANITY: bool = false;
+}
+
+/// All checks whatever the features, additionally rejecting overlong LEB128
+/// encodings, for untrusted data.
+pub(crate) struct Strict;
+
+impl Access for Strict {
+    const BOUNDS: bool = true;
+    const SANITY: bool = true;
+    const STRICT: bool = true;
+}
+
+/// Reads a byte at `index` of a byte slice without panicing.
+#[inline(always)]
+pub(crate) fn read_u8(data: &[u8], index: usize) -> Result<u8, Error> {
+    read_u8_with::<Checked>(data, index)
+}
+
+/// Reads a byte at `index` of a byte slice using the checks of `A`.
+#[inline(always)]
+pub(crate) fn read_u8_with<A: Access>(data: &[u8], index: usize) -> Result<u8, Error> {
+    if !A::BOUNDS {
+        return Ok(unsafe { *data.get_unchecked(index) });
+    }
+    match data.get(index) {
+        Some(byte) => Ok(*byte),
+        None => Err(Error::not_enough_data().at(index)),
+    }
+}
+
+/// Reads an unsigned u16 value at `index` of a byte slice without panicing.
+#[inline(always)]
+pub(crate) fn read_u16_at(data: &[u8], index: usize) -> Result<u16, Error> {
+    read_u16_with::<Checked>(data, index)
+}
+
+/// Reads an unsigned u16 value at `index` of a byte slice using the checks
+/// of `A`.
+#[inline(always)]
+pub(crate) fn read_u16_with<A: Access>(data: &[u8], index: usize) -> Result<u16, Error> {
+    if !A::BOUNDS {
+        return Ok(unsafe { core::ptr::read_unaligned(data.as_ptr().add(index) as *const u16) });
+    }
+    match data.get(index..).and_then(|data| data.get(..2)) {
+        Some(bytes) => Ok(u16::from_ne_bytes([bytes[0], bytes[1]])),
+        None => Err(Error::not_enough_data().at(index)),
+    }
+}
+
+/// Reads an unsigned u32 value at `index` of a byte slice without panicing.
+#[inline(always)]
+pub(crate) fn read_u32_at(data: &[u8], index: usize) -> Result<u32, Error> {
+    read_u32_with::<Checked>(data, index)
+}
+
+/// Reads an unsigned u32 value at `index` of a byte slice using the checks
+/// of `A`.
+#[inline(always)]
+pub(crate) fn read_u32_with<A: Access>(data: &[u8], index: usize) -> Result<u32, Error> {
+    if !A::BOUNDS {
+        return Ok(unsafe { core::ptr::read_unaligned(data.as_ptr().add(index) as *const u32) });
+    }
+    match data.get(index..).and_then(|data| data.get(..4)) {
+        Some(bytes) => Ok(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(Error::not_enough_data().at(index)),
+    }
+}
+
+/// Returns the nonempty rest of a byte slice from `offset` without panicing.
+#[inline(always)]
+pub(crate) fn tail(data: &[u8], offset: usize) -> Result<&[u8], Error> {
+    if !Checked::BOUNDS {
+        return Ok(unsafe {
+            core::slice::from_raw_parts(data.as_ptr().add(offset), data.len() - offset)
+        });
+    }
+    match data.get(offset..) {
+        Some(rest) if !rest.is_empty() => Ok(rest),
+        _ => Err(Error::not_enough_data().at(offset)),
+    }
+}
+
+/// Reads a ULEB128 encoded u32 value at `*index` of a byte slice without
+/// panicing and advances `*index` past it.
+#[inline(always)]
+pub(crate) fn read_uleb128_at(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    read_uleb128_with::<Checked>(data, index)
+}
+
+/// Reads a ULEB128 encoded u32 value at `*index` of a byte slice using the
+/// checks of `A` and advances `*index` past it.
+#[inline(always)]
+pub(crate) fn read_uleb128_with<A: Access>(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = read_u8_with::<A>(data, *index)?;
+        *index += 1;
+        let split = (byte & 0x7F) as u32;
+        if A::SANITY && shift == 28 && split > 0x0F {
+            return Err(Error::invalid_data().at(*index - 1));
+        }
+        value |= split.wrapping_shl(shift);
+        if byte & 0x80 == 0 {
+            // A zero byte ending an encoding adds nothing.
+            if A::STRICT && byte == 0 && shift > 0 {
+                return Err(Error::invalid_data().at(*index - 1));
+            }
+            return Ok(value);
+        }
+        shift += 7;
+        if A::SANITY && shift > 28 {
+            return Err(Error::invalid_data().at(*index - 1));
+        }
+    }
+}
+
+/// Reads an SLEB128 encoded i32 value at `*index` of a byte slice without
+/// panicing, advances `*index` past it and returns it as a u32 to be added
+/// with wrapping.
+pub(crate) fn read_sleb128_at(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    read_sleb128_with::<Checked>(data, index)
+}
+
+/// Reads an SLEB128 encoded i32 value at `*index` of a byte slice using the
+/// checks of `A` like [read_sleb128_at](fn.read_sleb128_at.html).
+pub(crate) fn read_sleb128_with<A: Access>(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    let mut value: u32 = 0;
+    let mut shift = 0;
+    let mut previous = 0;
+    loop {
+        let byte = read_u8_with::<A>(data, *index)?;
+        *index += 1;
+        let split = (byte & 0x7F) as u32;
+        // The fifth byte holds the top 4 bits, the others must extend its
+        // sign.
+        if A::SANITY && shift == 28 && (byte & 0x80 != 0 || (split > 0x07 && split < 0x78)) {
+            return Err(Error::invalid_data().at(*index - 1));
+        }
+        value |= split.wrapping_shl(shift);
+        shift += 7;
+        if byte & 0x80 == 0 {
+            // A byte ending an encoding only extending the sign of the
+            // previous one adds nothing.
+            let redundant = match byte {
+                0x00 => previous & 0x40 == 0,
+                0x7F => previous & 0x40 != 0,
+                _ => false,
+            };
+            if A::STRICT && shift > 7 && redundant {
+                return Err(Error::invalid_data().at(*index - 1));
+            }
+            if shift < 32 && split & 0x40 != 0 {
+                value |= u32::MAX << shift;
+            }
+            return Ok(value);
+        }
+        if A::SANITY && shift > 28 {
+            return Err(Error::invalid_data().at(*index - 1));
+        }
+        previous = byte;
+    }
+}
+
+/// Adds `offset` to `address` read before `index`, rejecting overflows if
+/// `A` performs sanity checks and wrapping otherwise.
+#[inline(always)]
+pub(crate) fn add_offset<A: Access>(address: u32, offset: u32, index: usize) -> Result<u32, Error> {
+    if !A::SANITY {
+        return Ok(address.wrapping_add(offset));
+    }
+    match address.checked_add(offset) {
+        Some(address) => Ok(address),
+        None => Err(Error::overflow().at(index)),
+    }
+}
+
+/// Adds `delta`, a signed delta read by
+/// [read_sleb128_at](fn.read_sleb128_at.html) before `index`, to `address`
+/// like [add_offset](fn.add_offset.html).
+#[inline(always)]
+pub(crate) fn add_delta<A: Access>(address: u32, delta: u32, index: usize) -> Result<u32, Error> {
+    if !A::SANITY {
+        return Ok(address.wrapping_add(delta));
+    }
+    match address.checked_add_signed(delta as i32) {
+        Some(address) => Ok(address),
+        None => Err(Error::overflow().at(index)),
+    }
+}
+
+/// Multiplies `offset` read before `index` by the scale of its group like
+/// [add_offset](fn.add_offset.html).
+#[inline(always)]
+pub(crate) fn scale_offset<A: Access>(offset: u32, scale: u32, index: usize) -> Result<u32, Error> {
+    if !A::SANITY {
+        return Ok(offset.wrapping_mul(scale));
+    }
+    match offset.checked_mul(scale) {
+        Some(offset) => Ok(offset),
+        None => Err(Error::overflow().at(index)),
+    }
+}
+
+/// Multiplies `delta`, a signed delta read before `index`, by the scale of
+/// its group like [add_delta](fn.add_delta.html).
+#[inline(always)]
+pub(crate) fn scale_delta<A: Access>(delta: u32, scale: u32, index: usize) -> Result<u32, Error> {
+    if !A::SANITY {
+        return Ok(delta.wrapping_mul(scale));
+    }
+    match (delta as i32).checked_mul(scale as i32) {
+        Some(delta) if scale <= i32::MAX as u32 => Ok(delta as u32),
+        _ => Err(Error::overflow().at(index)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_read_unaligned() {
+        let data = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05];
+        assert_eq!(read_u8(&data, 5).unwrap(), 0x05);
+        assert_eq!(
+            read_u16_at(&data, 1).unwrap(),
+            u16::from_ne_bytes([0x01, 0x02])
+        );
+        assert_eq!(
+            read_u32_at(&data, 1).unwrap(),
+            u32::from_ne_bytes([0x01, 0x02, 0x03, 0x04])
+        );
+        assert_eq!(tail(&data, 4).unwrap(), [0x04, 0x05]);
+    }
+
+    #[test]
+    fn test_read_unchecked() {
+        let data = [0x00, 0x01, 0x02, 0x03, 0x04, 0xFF, 0xFF, 0xFF, 0xFF, 0x1F];
+        assert_eq!(read_u8_with::<Unchecked>(&data, 4).unwrap(), 0x04);
+        assert_eq!(
+            read_u32_with::<Unchecked>(&data, 1).unwrap(),
+            u32::from_ne_bytes([0x01, 0x02, 0x03, 0x04])
+        );
+        // The overlong encoding is not rejected.
+        let mut index = 5;
+        read_uleb128_with::<Unchecked>(&data, &mut index).unwrap();
+        assert_eq!(index, data.len());
+    }
+
+    #[test]
+    fn test_read_strict() {
+        let mut index = 0;
+        let err = read_uleb128_with::<Strict>(&[0x84, 0x00], &mut index).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut index = 0;
+        assert_eq!(read_uleb128_with::<Strict>(&[0x00], &mut index).unwrap(), 0);
+        // -1 and -64 encoded in two bytes instead of one, and -65 and 64,
+        // which need two bytes.
+        for data in [[0xFF, 0x7F], [0xC0, 0x7F]].iter() {
+            let mut index = 0;
+            let err = read_sleb128_with::<Strict>(data, &mut index).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+        let mut index = 0;
+        let value = read_sleb128_with::<Strict>(&[0xBF, 0x7F], &mut index).unwrap();
+        assert_eq!(value, -65i32 as u32);
+        let mut index = 0;
+        let value = read_sleb128_with::<Strict>(&[0xC0, 0x00], &mut index).unwrap();
+        assert_eq!(value, 64);
+    }
+
+    #[test]
+    fn test_add_offset() {
+        assert_eq!(add_offset::<Unchecked>(u32::MAX, 2, 0).unwrap(), 1);
+        assert_eq!(
+            add_delta::<Unchecked>(1, -2i32 as u32, 0).unwrap(),
+            u32::MAX
+        );
+        assert_eq!(add_delta::<Strict>(4, -2i32 as u32, 0).unwrap(), 2);
+        let err = add_offset::<Strict>(u32::MAX, 2, 0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Overflow);
+        let err = add_delta::<Strict>(1, -2i32 as u32, 0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Overflow);
+        if !cfg!(feature = "no_sanity_check") {
+            let err = add_offset::<Checked>(0xFFFF_FFF0, 0x10, 0).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Overflow);
+        }
+    }
+
+    #[test]
+    fn test_read_uleb128_at() {
+        let data = [0x04, 0x80, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+        let mut index = 0;
+        assert_eq!(read_uleb128_at(&data, &mut index).unwrap(), 0x04);
+        assert_eq!(index, 1);
+        assert_eq!(read_uleb128_at(&data, &mut index).unwrap(), 0x80);
+        assert_eq!(index, 3);
+        assert_eq!(read_uleb128_at(&data, &mut index).unwrap(), u32::MAX);
+        assert_eq!(index, data.len());
+    }
+
+    #[cfg(not(feature = "no_sanity_check"))]
+    #[test]
+    fn test_read_uleb128_at_overflow() {
+        let mut index = 0;
+        let err = read_uleb128_at(&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F], &mut index).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut index = 0;
+        let err = read_uleb128_at(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00], &mut index).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[cfg(not(feature = "no_bounds_check"))]
+    #[test]
+    fn test_read_at_truncated() {
+        let mut index = 1;
+        let err = read_uleb128_at(&[0x00, 0x80], &mut index).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = read_u8(&[0; 2], 2).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = read_u16_at(&[0; 3], 2).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = read_u32_at(&[0; 5], 2).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = read_u32_at(&[0; 3], 0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = read_u32_at(&[0; 8], usize::MAX).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = tail(&[0; 2], 2).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+}
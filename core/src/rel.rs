@@ -0,0 +1,395 @@
+//! Regular ELF32 and ELF64 relocations
+
+#[cfg(feature = "std")]
+use std::io::{Cursor, Read, Write};
+
+use crate::endian::Endianness;
+#[cfg(feature = "std")]
+use crate::error::{Error, ErrorKind};
+
+// Type of a relocation.
+pub(crate) type Elf32RelType = u8;
+
+/// Representation of a regular ELF32 relocation.
+///
+/// Relocations are ordered by offset, then by type, then by symbol index.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Elf32Rel {
+    // The order of the fields defines the derived ordering.
+    offset: u32,
+    relocation_type: Elf32RelType,
+    symbol: u32,
+}
+
+impl Elf32Rel {
+    /// Constructs an `Elf32Rel` instance from its offset and type, against
+    /// symbol 0.
+    pub fn new(offset: u32, relocation_type: Elf32RelType) -> Self {
+        Self::with_symbol(offset, relocation_type, 0)
+    }
+
+    /// Constructs an `Elf32Rel` instance from its offset, type and symbol
+    /// index, which is truncated to 24 bits.
+    pub fn with_symbol(offset: u32, relocation_type: Elf32RelType, symbol: u32) -> Self {
+        Self {
+            offset,
+            relocation_type,
+            symbol: symbol & 0x00FF_FFFF,
+        }
+    }
+
+    /// Returns the offset of the relocation.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the type of the relocation.
+    pub fn relocation_type(&self) -> Elf32RelType {
+        self.relocation_type
+    }
+
+    /// Returns the index of the symbol the relocation refers to.
+    pub fn symbol(&self) -> u32 {
+        self.symbol
+    }
+
+    /// Returns the 8-byte little-endian record of the relocation.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.to_bytes_endian(Endianness::Little)
+    }
+
+    /// Returns the 8-byte record of the relocation in the given byte order.
+    pub fn to_bytes_endian(&self, endianness: Endianness) -> [u8; 8] {
+        let info = (self.symbol << 8) | self.relocation_type as u32;
+        let (offset, info) = match endianness {
+            Endianness::Little => (self.offset.to_le_bytes(), info.to_le_bytes()),
+            Endianness::Big => (self.offset.to_be_bytes(), info.to_be_bytes()),
+        };
+        let mut bytes = [0; 8];
+        bytes[..4].copy_from_slice(&offset);
+        bytes[4..].copy_from_slice(&info);
+        bytes
+    }
+}
+
+#[cfg(feature = "serde")]
+serde_struct!(Elf32Rel {
+    offset: u32,
+    relocation_type: u8,
+    symbol: u32,
+});
+
+#[cfg(feature = "std")]
+impl Elf32Rel {
+    /// Constructs an `Elf32Rel` instace from an in-memory buffer.
+    pub fn from_memory(data: &mut Cursor<&[u8]>) -> Result<Self, Error> {
+        Self::from_memory_endian(data, Endianness::Little)
+    }
+
+    /// Constructs an `Elf32Rel` instance from an in-memory buffer holding
+    /// entries in the given byte order.
+    pub fn from_memory_endian(
+        data: &mut Cursor<&[u8]>,
+        endianness: Endianness,
+    ) -> Result<Self, Error> {
+        let mut read_word = || -> Result<u32, Error> {
+            let mut word = [0; 4];
+            data.read_exact(&mut word)
+                .map_err(|_| Error::new(ErrorKind::NotEnoughData))?;
+            Ok(match endianness {
+                Endianness::Little => u32::from_le_bytes(word),
+                Endianness::Big => u32::from_be_bytes(word),
+            })
+        };
+        let offset = read_word()?;
+        let info = read_word()?;
+        Ok(Self::with_symbol(offset, info as u8, info >> 8))
+    }
+
+    /// Writes the 8-byte little-endian record of the relocation, the
+    /// counterpart of [from_memory](#method.from_memory).
+    ///
+    /// # Errors
+    ///
+    /// `BufferSmall` if the record cannot be written.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.write_to_endian(writer, Endianness::Little)
+    }
+
+    /// Writes the 8-byte record of the relocation in the given byte order.
+    ///
+    /// # Errors
+    ///
+    /// `BufferSmall` if the record cannot be written.
+    pub fn write_to_endian<W: Write>(
+        &self,
+        writer: &mut W,
+        endianness: Endianness,
+    ) -> Result<(), Error> {
+        writer
+            .write_all(&self.to_bytes_endian(endianness))
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))
+    }
+}
+
+/// Representation of a regular ELF64 relocation.
+///
+/// Relocations are ordered by offset, then by type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Elf64Rel {
+    // The order of the fields defines the derived ordering.
+    offset: u64,
+    relocation_type: u32,
+}
+
+impl Elf64Rel {
+    /// Constructs an `Elf64Rel` instance from its offset and type.
+    pub fn new(offset: u64, relocation_type: u32) -> Self {
+        Self {
+            offset,
+            relocation_type,
+        }
+    }
+
+    /// Returns the offset of the relocation.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the type of the relocation.
+    pub fn relocation_type(&self) -> u32 {
+        self.relocation_type
+    }
+
+    /// Returns the 16-byte little-endian record of the relocation, with a
+    /// symbol index of 0.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.to_bytes_endian(Endianness::Little)
+    }
+
+    /// Returns the 16-byte record of the relocation in the given byte order,
+    /// with a symbol index of 0.
+    pub fn to_bytes_endian(&self, endianness: Endianness) -> [u8; 16] {
+        let info = self.relocation_type as u64;
+        let (offset, info) = match endianness {
+            Endianness::Little => (self.offset.to_le_bytes(), info.to_le_bytes()),
+            Endianness::Big => (self.offset.to_be_bytes(), info.to_be_bytes()),
+        };
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&offset);
+        bytes[8..].copy_from_slice(&info);
+        bytes
+    }
+}
+
+#[cfg(feature = "serde")]
+serde_struct!(Elf64Rel {
+    offset: u64,
+    relocation_type: u32,
+});
+
+#[cfg(feature = "std")]
+impl Elf64Rel {
+    /// Constructs an `Elf64Rel` instace from an in-memory buffer.
+    pub fn from_memory(data: &mut Cursor<&[u8]>) -> Result<Self, Error> {
+        Self::from_memory_endian(data, Endianness::Little)
+    }
+
+    /// Constructs an `Elf64Rel` instance from an in-memory buffer holding
+    /// entries in the given byte order. The symbol index in the upper half
+    /// of `r_info` is ignored.
+    pub fn from_memory_endian(
+        data: &mut Cursor<&[u8]>,
+        endianness: Endianness,
+    ) -> Result<Self, Error> {
+        let mut read_word = || -> Result<u64, Error> {
+            let mut word = [0; 8];
+            data.read_exact(&mut word)
+                .map_err(|_| Error::new(ErrorKind::NotEnoughData))?;
+            Ok(match endianness {
+                Endianness::Little => u64::from_le_bytes(word),
+                Endianness::Big => u64::from_be_bytes(word),
+            })
+        };
+        let offset = read_word()?;
+        let info = read_word()?;
+        Ok(Self::new(offset, info as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_elf32rel_new() {
+        let rel = Elf32Rel::new(0x1000, 0x17);
+        assert_eq!(rel.offset(), 0x1000);
+        assert_eq!(rel.relocation_type(), 0x17);
+        assert_eq!(rel.symbol(), 0);
+        let rel = Elf32Rel::with_symbol(0x1000, 0x02, 0x0100_0005);
+        assert_eq!(rel.symbol(), 0x05);
+        assert_eq!(rel.to_bytes()[4..], [0x02, 0x05, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_elf32rel_ord() {
+        let mut relocations = [
+            Elf32Rel::new(0x1004, 0x02),
+            Elf32Rel::new(0x1000, 0x17),
+            Elf32Rel::new(0x1004, 0x02),
+            Elf32Rel::new(0x1000, 0x02),
+        ];
+        relocations.sort_unstable();
+        assert_eq!(
+            relocations,
+            [
+                Elf32Rel::new(0x1000, 0x02),
+                Elf32Rel::new(0x1000, 0x17),
+                Elf32Rel::new(0x1004, 0x02),
+                Elf32Rel::new(0x1004, 0x02),
+            ]
+        );
+        assert!(Elf32Rel::new(0x0FFF, 0xFF) < Elf32Rel::new(0x1000, 0x00));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf32rel_hash() {
+        use std::collections::HashSet;
+
+        let old: HashSet<_> = [Elf32Rel::new(0x1000, 0x17), Elf32Rel::new(0x1004, 0x17)]
+            .iter()
+            .copied()
+            .collect();
+        let new: HashSet<_> = [Elf32Rel::new(0x1004, 0x17), Elf32Rel::new(0x1004, 0x02)]
+            .iter()
+            .copied()
+            .collect();
+        let removed: Vec<_> = old.difference(&new).collect();
+        assert_eq!(removed, [&Elf32Rel::new(0x1000, 0x17)]);
+    }
+
+    #[test]
+    fn test_elf32rel_to_bytes() {
+        let rel = Elf32Rel::new(0x04030201, 0x05);
+        assert_eq!(
+            rel.to_bytes(),
+            [0x01, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(
+            rel.to_bytes_endian(Endianness::Big),
+            [0x04, 0x03, 0x02, 0x01, 0x00, 0x00, 0x00, 0x05]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf32rel_std_fmt_debug() {
+        let memory: [u8; 8] = [0; 8];
+        let mut cursor = Cursor::new(&memory[..]);
+        let elf32rel = Elf32Rel::from_memory(&mut cursor).unwrap();
+        println!("{:?}", elf32rel);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf32rel_from_memory_offset_bad() {
+        let memory: [u8; 3] = [0; 3];
+        let mut cursor = Cursor::new(&memory[..]);
+        let err = Elf32Rel::from_memory(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf32rel_from_memory_info_bad() {
+        let memory: [u8; 7] = [0; 7];
+        let mut cursor = Cursor::new(&memory[..]);
+        let err = Elf32Rel::from_memory(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf32rel_from_memory() {
+        let memory: [u8; 8] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let mut cursor = Cursor::new(&memory[..]);
+        let rel = Elf32Rel::from_memory(&mut cursor).unwrap();
+        let offset = rel.offset();
+        let relocation_type = rel.relocation_type();
+        assert_eq!(offset, 0x04030201);
+        assert_eq!(relocation_type, 0x05);
+        assert_eq!(rel.symbol(), 0x080706);
+        assert_eq!(rel.to_bytes(), memory);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf32rel_from_memory_endian_big() {
+        let memory: [u8; 8] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let mut cursor = Cursor::new(&memory[..]);
+        let rel = Elf32Rel::from_memory_endian(&mut cursor, Endianness::Big).unwrap();
+        assert_eq!(rel.offset(), 0x01020304);
+        assert_eq!(rel.relocation_type(), 0x08);
+        assert_eq!(rel.symbol(), 0x050607);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf32rel_write_to() {
+        let rel = Elf32Rel::new(0x01020304, 0x08);
+        let mut memory = Vec::new();
+        rel.write_to(&mut memory).unwrap();
+        rel.write_to_endian(&mut memory, Endianness::Big).unwrap();
+        let mut cursor = Cursor::new(&memory[..]);
+        assert_eq!(Elf32Rel::from_memory(&mut cursor).unwrap(), rel);
+        let big = Elf32Rel::from_memory_endian(&mut cursor, Endianness::Big).unwrap();
+        assert_eq!(big, rel);
+
+        let mut buffer = [0u8; 7];
+        let err = rel.write_to(&mut &mut buffer[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+    }
+
+    #[test]
+    fn test_elf64rel_to_bytes() {
+        let rel = Elf64Rel::new(0x0807060504030201, 0x0403);
+        assert_eq!(rel.offset(), 0x0807060504030201);
+        assert_eq!(rel.relocation_type(), 0x0403);
+        assert_eq!(
+            rel.to_bytes(),
+            [
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x03, 0x04, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00
+            ]
+        );
+        assert_eq!(
+            rel.to_bytes_endian(Endianness::Big)[8..],
+            [0, 0, 0, 0, 0, 0, 0x04, 0x03]
+        );
+        assert!(Elf64Rel::new(0x0FFF, 0xFF) < Elf64Rel::new(0x1000, 0x00));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_elf64rel_from_memory() {
+        let mut memory = Elf64Rel::new(0x1_0000_1000, 0x403).to_bytes();
+        // Symbol index
+        memory[12] = 0x05;
+        let mut cursor = Cursor::new(&memory[..]);
+        let rel = Elf64Rel::from_memory(&mut cursor).unwrap();
+        assert_eq!(rel, Elf64Rel::new(0x1_0000_1000, 0x403));
+        let memory = rel.to_bytes_endian(Endianness::Big);
+        let mut cursor = Cursor::new(&memory[..]);
+        assert_eq!(
+            Elf64Rel::from_memory_endian(&mut cursor, Endianness::Big).unwrap(),
+            rel
+        );
+        let mut cursor = Cursor::new(&memory[..15]);
+        let err = Elf64Rel::from_memory(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+}
@@ -0,0 +1,267 @@
+//! Resumable application of compressed relocation sections
+//!
+//! Installers which patch an image in flash can lose power while applying
+//! relocations. This module applies a compressed ELF32 relocation section
+//! while periodically persisting a progress marker, and resumes from the last
+//! persisted marker after a reset, skipping the relocations applied before it.
+//!
+//! Relocations applied after the last persisted marker are applied again
+//! when resuming, so patching must be repeatable: the target reads the words
+//! of an unpatched copy of the image, e.g. the update in a staging slot, and
+//! writes the patched words to their destination.
+
+use crate::apply::PatchPolicy;
+use crate::decompress::{decode_offsets, Header, TARGET_MACHINE};
+use crate::error::Error;
+
+/// Position of a resumable apply: the next relocation to apply is entry
+/// `entry` of group `group`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ApplyProgress {
+    group: u32,
+    entry: u32,
+}
+
+impl ApplyProgress {
+    /// Creates a new `ApplyProgress` instance from a persisted marker. The
+    /// default instance is the start of a section.
+    pub fn new(group: u32, entry: u32) -> Self {
+        Self { group, entry }
+    }
+
+    /// Returns the index of the group, run or directory entry.
+    pub fn group(&self) -> u32 {
+        self.group
+    }
+
+    /// Returns the number of relocations of the group already applied.
+    pub fn entry(&self) -> u32 {
+        self.entry
+    }
+}
+
+/// Storage of an image patched by
+/// [relocate_resumable](fn.relocate_resumable.html), e.g. a flash driver.
+pub trait ResumableTarget {
+    /// Returns the unpatched word at `address`.
+    ///
+    /// This must not return a word written by [write](#tymethod.write), or
+    /// relocations applied again after a reset are patched twice.
+    fn read(&mut self, address: u32) -> Result<u32, Error>;
+
+    /// Stores the patched word at `address`.
+    fn write(&mut self, address: u32, value: u32) -> Result<(), Error>;
+
+    /// Persists `progress`, e.g. in a journal sector, after the words written
+    /// before it are durable.
+    fn persist(&mut self, progress: ApplyProgress) -> Result<(), Error>;
+}
+
+impl<T: ResumableTarget + ?Sized> ResumableTarget for &mut T {
+    fn read(&mut self, address: u32) -> Result<u32, Error> {
+        (**self).read(address)
+    }
+
+    fn write(&mut self, address: u32, value: u32) -> Result<(), Error> {
+        (**self).write(address, value)
+    }
+
+    fn persist(&mut self, progress: ApplyProgress) -> Result<(), Error> {
+        (**self).persist(progress)
+    }
+}
+
+/// Applies a compressed ELF32 relocation section through `target` using the
+/// patch policy registered for the type of each relocation in `policies`,
+/// starting at `resume`.
+///
+/// The progress is persisted after every `interval` relocations, or after
+/// every relocation if `interval` is 0, and once every relocation is applied.
+/// Pass the default [ApplyProgress](struct.ApplyProgress.html) to start, or
+/// the last persisted one to resume after a reset.
+///
+/// Returns the final progress, which is also persisted.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed, if `resume` is beyond
+/// its end or if `target` fails, `UnsupportedRelocationType` if there is no
+/// policy for the type of a relocation, or `Unsupported` if the section
+/// records a target machine other than
+/// [TARGET_MACHINE](constant.TARGET_MACHINE.html).
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn relocate_resumable<T: ResumableTarget + ?Sized>(
+    data: &[u8],
+    bias: u32,
+    policies: &[(u8, PatchPolicy)],
+    resume: ApplyProgress,
+    interval: u32,
+    target: &mut T,
+) -> Result<ApplyProgress, Error> {
+    let header = Header::read(data)?;
+    header.check_machine(TARGET_MACHINE)?;
+    if resume.group > header.count || (resume.group == header.count && resume.entry > 0) {
+        return Err(Error::invalid_data());
+    }
+    let interval = interval.max(1);
+    let mut pending = 0;
+    let mut offset = header.offset;
+    let mut address = header.base_address;
+    for number in 0..header.count {
+        let group = header.group(data, offset, address)?;
+        let skip = match number {
+            number if number < resume.group => group.count,
+            number if number == resume.group => resume.entry,
+            _ => 0,
+        };
+        if skip > group.count {
+            return Err(Error::invalid_data());
+        }
+        let policy = policies
+            .iter()
+            .find(|(policy_type, _)| *policy_type == group.relocation_type)
+            .map(|(_, policy)| *policy);
+        let mut entry = 0;
+        let (end, last) = decode_offsets(
+            data,
+            group.codec,
+            group.scale,
+            group.offsets,
+            group.count,
+            group.address,
+            &mut |address| {
+                entry += 1;
+                if entry <= skip {
+                    return Ok(());
+                }
+                let policy = policy.ok_or_else(Error::unsupported_relocation_type)?;
+                let word = target.read(address)?;
+                target.write(address, policy.patch(word, bias))?;
+                pending += 1;
+                if pending == interval {
+                    pending = 0;
+                    target.persist(ApplyProgress::new(number, entry))?;
+                }
+                Ok(())
+            },
+        )?;
+        offset = header.next(offset, end);
+        address = last;
+    }
+    let done = ApplyProgress::new(header.count, 0);
+    target.persist(done)?;
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const CREL: [u8; 13] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x01, // group[0].count
+        0x04, // group[0].offsets[0]
+        0x17, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, // group[1].offsets[0]
+        0x08, // group[1].offsets[1]
+        0x04, // group[1].offsets[2]
+    ];
+
+    const POLICIES: [(u8, PatchPolicy); 2] = [
+        (0x02, PatchPolicy::Replace(0xFFFF_FFFF)),
+        (0x17, PatchPolicy::AddBias),
+    ];
+
+    /// Patches a copy of the image, failing like a reset after `budget`
+    /// writes.
+    struct Flash {
+        source: [u32; 4],
+        destination: [u32; 4],
+        budget: u32,
+        persisted: ApplyProgress,
+        persists: u32,
+    }
+
+    impl Flash {
+        fn new(budget: u32) -> Self {
+            Self {
+                source: [0x10, 0x20, 0x30, 0x40],
+                destination: [0; 4],
+                budget,
+                persisted: ApplyProgress::default(),
+                persists: 0,
+            }
+        }
+    }
+
+    impl ResumableTarget for Flash {
+        fn read(&mut self, address: u32) -> Result<u32, Error> {
+            Ok(self.source[(address - 0x1000) as usize / 4])
+        }
+
+        fn write(&mut self, address: u32, value: u32) -> Result<(), Error> {
+            if self.budget == 0 {
+                return Err(Error::new(ErrorKind::BufferSmall));
+            }
+            self.budget -= 1;
+            self.destination[(address - 0x1000) as usize / 4] = value;
+            Ok(())
+        }
+
+        fn persist(&mut self, progress: ApplyProgress) -> Result<(), Error> {
+            self.persisted = progress;
+            self.persists += 1;
+            Ok(())
+        }
+    }
+
+    const PATCHED: [u32; 4] = [0x1010, 0xFFFF_FFFF, 0x1030, 0x1040];
+
+    #[test]
+    fn test_relocate_resumable() {
+        let mut flash = Flash::new(u32::MAX);
+        let start = ApplyProgress::default();
+        let done = relocate_resumable(&CREL, 0x1000, &POLICIES, start, 2, &mut flash).unwrap();
+        assert_eq!(done, ApplyProgress::new(2, 0));
+        assert_eq!(flash.persisted, done);
+        assert_eq!(flash.persists, 3);
+        assert_eq!(flash.destination, PATCHED);
+    }
+
+    #[test]
+    fn test_relocate_resumable_after_reset() {
+        let mut flash = Flash::new(3);
+        let start = ApplyProgress::default();
+        let err = relocate_resumable(&CREL, 0x1000, &POLICIES, start, 2, &mut flash).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        assert_eq!(flash.persisted, ApplyProgress::new(1, 1));
+
+        flash.budget = u32::MAX;
+        flash.persists = 0;
+        let resume = flash.persisted;
+        relocate_resumable(&CREL, 0x1000, &POLICIES, resume, 0, &mut flash).unwrap();
+        assert_eq!(flash.persists, 3);
+        assert_eq!(flash.destination, PATCHED);
+    }
+
+    #[test]
+    fn test_relocate_resumable_errors() {
+        let mut flash = Flash::new(u32::MAX);
+        for resume in [ApplyProgress::new(1, 4), ApplyProgress::new(2, 1)].iter() {
+            let err = relocate_resumable(&CREL, 0, &POLICIES, *resume, 1, &mut flash).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+        let resume = ApplyProgress::new(1, 0);
+        let err = relocate_resumable(&CREL, 0, &POLICIES[..1], resume, 1, &mut flash).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedRelocationType);
+        assert_eq!(flash.destination, [0; 4]);
+    }
+}
@@ -0,0 +1,132 @@
+//! Known-answer self-test of the decoder
+//!
+//! Functional safety processes can require boot code to check the integrity
+//! of the decoder before relying on it. [self_test](fn.self_test.html)
+//! decodes small built-in sections covering every layout and codec and
+//! compares the results with their known answers.
+
+use crate::decompress::elf32_relocate;
+use crate::error::Error;
+
+/// Grouped layout with a multi-byte ULEB128 offset.
+const GROUPED: [u8; 15] = [
+    0x00, 0x10, 0x00, 0x00, // base_address
+    0x02, // count
+    0x02, 0x02, 0x04, 0x80, 0x01, // group[0]
+    0x16, 0x03, 0x00, 0x04, 0x04, // group[1]
+];
+
+const GROUPED_ANSWER: [(u8, u32); 5] = [
+    (0x02, 0x1004),
+    (0x02, 0x1084),
+    (0x16, 0x1000),
+    (0x16, 0x1004),
+    (0x16, 0x1008),
+];
+
+/// Interleaved layout with an empty run.
+const INTERLEAVED: [u8; 14] = [
+    0x00, 0x10, 0x00, 0x00, // base_address
+    0xFF, 0x03, // interleaved, 3 runs
+    0x16, 0x01, 0x00, // run[0]
+    0x02, 0x01, 0x04, // run[1]
+    0x16, 0x00, // run[2]
+];
+
+const INTERLEAVED_ANSWER: [(u8, u32); 2] = [(0x16, 0x1000), (0x02, 0x1004)];
+
+/// Group-varint codec with offsets of different lengths.
+const GROUP_VARINT: [u8; 12] = [
+    0x00, 0x10, 0x00, 0x00, // base_address
+    0xFB, 0x01, // group-varint, 1 group
+    0x17, 0x02, // group[0]
+    0x04, 0x04, 0x00, 0x01, // tag and offsets
+];
+
+const GROUP_VARINT_ANSWER: [(u8, u32); 2] = [(0x17, 0x1004), (0x17, 0x1104)];
+
+/// Bitmap codec with a bitmap continued over two bytes.
+const BITMAP: [u8; 11] = [
+    0x00, 0x10, 0x00, 0x00, // base_address
+    0xF9, 0x01, // bitmap, 1 group
+    0x17, 0x03, // group[0]
+    0x04, 0x81, 0x02, // offset and bitmap
+];
+
+const BITMAP_ANSWER: [(u8, u32); 3] = [(0x17, 0x1004), (0x17, 0x1008), (0x17, 0x1028)];
+
+const DIRECTORY_ANSWER: [(u8, u32); 3] = [(0x02, 0x1004), (0x17, 0x1000), (0x17, 0x1008)];
+
+/// Returns a section using the directory layout, whose words use native
+/// endianness.
+fn directory() -> [u8; 27] {
+    let mut data = [0; 27];
+    data[..4].copy_from_slice(&0x1000u32.to_ne_bytes());
+    data[4..6].copy_from_slice(&[0xFE, 0x02]);
+    for (entry, (relocation_type, count, stream)) in
+        [(0x02, 1u32, 0u32), (0x17, 2, 1)].iter().enumerate()
+    {
+        let start = 6 + entry * 9;
+        data[start] = *relocation_type;
+        data[start + 1..start + 5].copy_from_slice(&count.to_ne_bytes());
+        data[start + 5..start + 9].copy_from_slice(&stream.to_ne_bytes());
+    }
+    data[24..].copy_from_slice(&[0x04, 0x00, 0x08]);
+    data
+}
+
+/// Decodes `data` and checks that it yields `answer` and ends with the data.
+fn check(data: &[u8], answer: &[(u8, u32)]) -> Result<(), Error> {
+    let mut index = 0;
+    let end = elf32_relocate(data, &mut |relocation_type, address| {
+        if answer.get(index) != Some(&(relocation_type, address)) {
+            return Err(Error::invalid_data());
+        }
+        index += 1;
+        Ok(())
+    })
+    .map_err(|_| Error::invalid_data())?;
+    if end != data.len() || index != answer.len() {
+        return Err(Error::invalid_data());
+    }
+    Ok(())
+}
+
+/// Decodes built-in sections covering every layout and codec and checks the
+/// decoded relocations against their known answers, e.g. as an integrity
+/// check of the decoder at boot.
+///
+/// # Errors
+///
+/// `InvalidData` if the decoder does not produce a known answer.
+pub fn self_test() -> Result<(), Error> {
+    check(&GROUPED, &GROUPED_ANSWER)?;
+    check(&INTERLEAVED, &INTERLEAVED_ANSWER)?;
+    check(&GROUP_VARINT, &GROUP_VARINT_ANSWER)?;
+    check(&BITMAP, &BITMAP_ANSWER)?;
+    check(&directory(), &DIRECTORY_ANSWER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_self_test() {
+        assert!(self_test().is_ok());
+    }
+
+    #[test]
+    fn test_check_mismatch() {
+        let err = check(&GROUPED, &GROUPED_ANSWER[..4]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = check(&INTERLEAVED, &GROUPED_ANSWER).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        #[cfg(not(feature = "no_bounds_check"))]
+        {
+            let err = check(&GROUPED[..14], &GROUPED_ANSWER).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Serialization of data types with `serde`
+//!
+//! The implementations are written out instead of derived, so the feature
+//! does not pull in procedural macros. Structures use the representation
+//! `#[derive(Serialize, Deserialize)]` would produce, so they can be read
+//! from configuration files, e.g. TOML or JSON, and snapshot-tested.
+
+use core::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, VariantAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::endian::Endianness;
+
+/// Implements `Serialize` and `Deserialize` for a structure, given its fields
+/// and their types.
+///
+/// Exported for the data types of `relox`, not part of the public API.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! serde_struct {
+    ($type:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+        impl serde::Serialize for $type {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                use serde::ser::SerializeStruct;
+                const FIELDS: &[&str] = &[$(stringify!($field)),*];
+                let mut state = serializer.serialize_struct(stringify!($type), FIELDS.len())?;
+                $(state.serialize_field(stringify!($field), &self.$field)?;)*
+                state.end()
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                const FIELDS: &[&str] = &[$(stringify!($field)),*];
+                struct StructVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for StructVisitor {
+                    type Value = $type;
+
+                    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                        f.write_str(concat!("struct ", stringify!($type)))
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> Result<$type, A::Error>
+                    where
+                        A: serde::de::SeqAccess<'de>,
+                    {
+                        let mut index = 0;
+                        $(
+                            let $field: $ty = seq
+                                .next_element()?
+                                .ok_or_else(|| serde::de::Error::invalid_length(index, &self))?;
+                            index += 1;
+                        )*
+                        let _ = index;
+                        Ok($type { $($field),* })
+                    }
+
+                    fn visit_map<A>(self, mut map: A) -> Result<$type, A::Error>
+                    where
+                        A: serde::de::MapAccess<'de>,
+                    {
+                        $(let mut $field: Option<$ty> = None;)*
+                        let fields = $crate::serialize::Identifier(FIELDS);
+                        while let Some(index) = map.next_key_seed(fields)? {
+                            let name = FIELDS.get(index).copied();
+                            $(
+                                if name == Some(stringify!($field)) {
+                                    $field = Some(map.next_value()?);
+                                    continue;
+                                }
+                            )*
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                        Ok($type {
+                            $($field: $field.ok_or_else(|| {
+                                serde::de::Error::missing_field(stringify!($field))
+                            })?),*
+                        })
+                    }
+                }
+
+                deserializer.deserialize_struct(stringify!($type), FIELDS, StructVisitor)
+            }
+        }
+    };
+}
+
+/// Deserializes a field or variant name into its index in a list of names,
+/// or the length of the list if the name is unknown.
+#[doc(hidden)]
+#[derive(Copy, Clone)]
+pub struct Identifier(pub &'static [&'static str]);
+
+impl<'de> DeserializeSeed<'de> for Identifier {
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<usize, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Identifier {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an identifier")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<usize, E> {
+        Ok((value as usize).min(self.0.len()))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<usize, E> {
+        let names = self.0;
+        Ok(names
+            .iter()
+            .position(|name| *name == value)
+            .unwrap_or(names.len()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<usize, E> {
+        let names = self.0;
+        let index = names.iter().position(|name| name.as_bytes() == value);
+        Ok(index.unwrap_or(names.len()))
+    }
+}
+
+const ENDIANNESS: &[&str] = &["Little", "Big"];
+
+impl Serialize for Endianness {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let index = *self as u32;
+        serializer.serialize_unit_variant("Endianness", index, ENDIANNESS[index as usize])
+    }
+}
+
+impl<'de> Deserialize<'de> for Endianness {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EndiannessVisitor;
+
+        impl EndiannessVisitor {
+            fn variant<E: de::Error>(index: usize) -> Result<Endianness, E> {
+                match index {
+                    0 => Ok(Endianness::Little),
+                    1 => Ok(Endianness::Big),
+                    _ => Err(E::custom("unknown byte order")),
+                }
+            }
+        }
+
+        impl<'de> Visitor<'de> for EndiannessVisitor {
+            type Value = Endianness;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte order")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Endianness, E> {
+                Self::variant(Identifier(ENDIANNESS).visit_str(value)?)
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<Endianness, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                let (index, variant) = data.variant_seed(Identifier(ENDIANNESS))?;
+                variant.unit_variant()?;
+                Self::variant(index)
+            }
+        }
+
+        deserializer.deserialize_enum("Endianness", ENDIANNESS, EndiannessVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::value::{Error as ValueError, MapDeserializer, StrDeserializer};
+    use serde::de::IntoDeserializer;
+
+    use super::*;
+    use crate::rel::Elf32Rel;
+
+    #[test]
+    fn test_deserialize_struct() {
+        let fields = [
+            ("relocation_type", 0x17u32),
+            ("unknown", 0),
+            ("symbol", 0x05),
+            ("offset", 0x1004),
+        ];
+        let map = MapDeserializer::<_, ValueError>::new(fields.iter().copied());
+        let relocation = Elf32Rel::deserialize(map).unwrap();
+        assert_eq!(relocation, Elf32Rel::with_symbol(0x1004, 0x17, 0x05));
+
+        let map = MapDeserializer::<_, ValueError>::new(fields[..3].iter().copied());
+        assert!(Elf32Rel::deserialize(map).is_err());
+        let fields = [
+            ("offset", 0x1004u32),
+            ("relocation_type", 0x100),
+            ("symbol", 0),
+        ];
+        let map = MapDeserializer::<_, ValueError>::new(fields.iter().copied());
+        assert!(Elf32Rel::deserialize(map).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_endianness() {
+        let big: StrDeserializer<ValueError> = "Big".into_deserializer();
+        assert_eq!(Endianness::deserialize(big).unwrap(), Endianness::Big);
+        let middle: StrDeserializer<ValueError> = "Middle".into_deserializer();
+        assert!(Endianness::deserialize(middle).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Relocation sinks
+//!
+//! This module decouples decoding a compressed ELF32 relocation section from
+//! processing the decoded relocations.
+
+use crate::decompress::elf32_relocate_symbols;
+use crate::error::Error;
+
+/// Receives the relocations decoded from a compressed ELF32 relocation
+/// section.
+///
+/// Sinks can be composed: `&mut S` is a sink if `S` is one, and a pair of
+/// sinks forwards every relocation to the first sink and then to the second.
+pub trait RelocationSink {
+    /// Called for a relative relocation at `address`.
+    fn relative(&mut self, address: u32) -> Result<(), Error>;
+
+    /// Called for a relocation at `address` against symbol `symbol`, which
+    /// is not 0.
+    fn absolute(&mut self, address: u32, symbol: u32) -> Result<(), Error> {
+        let _ = (address, symbol);
+        Err(Error::invalid_data())
+    }
+
+    /// Called for a relocation of any other type at `address`.
+    fn unknown(&mut self, relocation_type: u8, address: u32) -> Result<(), Error> {
+        let _ = (relocation_type, address);
+        Err(Error::invalid_data())
+    }
+}
+
+impl<S: RelocationSink + ?Sized> RelocationSink for &mut S {
+    fn relative(&mut self, address: u32) -> Result<(), Error> {
+        (**self).relative(address)
+    }
+
+    fn absolute(&mut self, address: u32, symbol: u32) -> Result<(), Error> {
+        (**self).absolute(address, symbol)
+    }
+
+    fn unknown(&mut self, relocation_type: u8, address: u32) -> Result<(), Error> {
+        (**self).unknown(relocation_type, address)
+    }
+}
+
+impl<A: RelocationSink, B: RelocationSink> RelocationSink for (A, B) {
+    fn relative(&mut self, address: u32) -> Result<(), Error> {
+        self.0.relative(address)?;
+        self.1.relative(address)
+    }
+
+    fn absolute(&mut self, address: u32, symbol: u32) -> Result<(), Error> {
+        self.0.absolute(address, symbol)?;
+        self.1.absolute(address, symbol)
+    }
+
+    fn unknown(&mut self, relocation_type: u8, address: u32) -> Result<(), Error> {
+        self.0.unknown(relocation_type, address)?;
+        self.1.unknown(relocation_type, address)
+    }
+}
+
+/// Processes a compressed ELF32 relocation section and drives `sink` with
+/// every relocation: relocations of type `relative_type` are reported as
+/// relative ones, other relocations against a symbol as absolute ones and any
+/// other as unknown.
+///
+/// Returns the number of bytes read from `data`.
+///
+/// # Errors
+///
+/// If the compressed relocation section is malformed or if `sink` fails.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn elf32_relocate_sink<S: RelocationSink + ?Sized>(
+    data: &[u8],
+    relative_type: u8,
+    sink: &mut S,
+) -> Result<usize, Error> {
+    elf32_relocate_symbols(data, &mut |relocation_type, address, symbol| {
+        if relocation_type == relative_type {
+            sink.relative(address)
+        } else if symbol != 0 {
+            sink.absolute(address, symbol)
+        } else {
+            sink.unknown(relocation_type, address)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const CREL: [u8; 12] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x01, // group[0].count
+        0x04, // group[0].offsets[0]
+        0x17, // group[1].relocation_type
+        0x02, // group[1].count
+        0x00, // group[1].offsets[0]
+        0x08, // group[1].offsets[1]
+    ];
+
+    #[derive(Default)]
+    struct Counter {
+        relative: u32,
+        unknown: u32,
+        last: u32,
+    }
+
+    impl RelocationSink for Counter {
+        fn relative(&mut self, address: u32) -> Result<(), Error> {
+            self.relative += 1;
+            self.last = address;
+            Ok(())
+        }
+
+        fn unknown(&mut self, _relocation_type: u8, address: u32) -> Result<(), Error> {
+            self.unknown += 1;
+            self.last = address;
+            Ok(())
+        }
+    }
+
+    struct RelativeOnly;
+
+    impl RelocationSink for RelativeOnly {
+        fn relative(&mut self, _address: u32) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_relocate_sink() {
+        let mut counter = Counter::default();
+        let read = elf32_relocate_sink(&CREL, 0x17, &mut counter).unwrap();
+        assert_eq!(read, CREL.len());
+        assert_eq!(counter.relative, 2);
+        assert_eq!(counter.unknown, 1);
+        assert_eq!(counter.last, 0x1008);
+    }
+
+    #[test]
+    fn test_relocate_sink_compose() {
+        let mut first = Counter::default();
+        let mut second = Counter::default();
+        elf32_relocate_sink(&CREL, 0x02, &mut (&mut first, &mut second)).unwrap();
+        assert_eq!(first.relative, 1);
+        assert_eq!(first.unknown, 2);
+        assert_eq!(second.relative, 1);
+        assert_eq!(second.unknown, 2);
+    }
+
+    #[test]
+    fn test_relocate_sink_dyn() {
+        let mut counter = Counter::default();
+        let sink: &mut dyn RelocationSink = &mut counter;
+        elf32_relocate_sink(&CREL, 0x17, sink).unwrap();
+        assert_eq!(counter.relative, 2);
+    }
+
+    #[test]
+    fn test_relocate_sink_absolute() {
+        const CREL_SYMBOLS: [u8; 12] = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xFA, // symbols
+            0x01, // count
+            0x02, // group[0].relocation_type
+            0x05, // group[0].count, with symbols
+            0x06, 0x01, // group[0].symbols: 3, 2
+            0x04, 0x04, // group[0].offsets
+        ];
+
+        #[derive(Default)]
+        struct Absolute(u32, u32);
+
+        impl RelocationSink for Absolute {
+            fn relative(&mut self, _address: u32) -> Result<(), Error> {
+                Err(Error::invalid_data())
+            }
+
+            fn absolute(&mut self, address: u32, symbol: u32) -> Result<(), Error> {
+                *self = Absolute(address, symbol);
+                Ok(())
+            }
+        }
+
+        let mut absolute = Absolute::default();
+        elf32_relocate_sink(&CREL_SYMBOLS, 0x17, &mut absolute).unwrap();
+        assert_eq!((absolute.0, absolute.1), (0x1008, 2));
+        let err = elf32_relocate_sink(&CREL_SYMBOLS, 0x02, &mut absolute).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_relocate_sink_unknown_default() {
+        let err = elf32_relocate_sink(&CREL, 0x17, &mut RelativeOnly).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,79 @@
+//! Sorted `u32` tables
+//!
+//! The delta and ULEB128 coding of relocation offsets also suits other
+//! monotonically increasing tables, e.g. jump tables or address allow lists.
+//! Tables packed by `pack_sorted_u32s` of the `relox` crate are laid out as:
+//!
+//! ```text
+//! struct SortedU32s {
+//!     count: u32,         // ULEB128
+//!     values: [u32],      // ULEB128, the first one relative to 0, otherwise
+//!                         // values[i+1] is relative to values[i]
+//! }
+//! ```
+
+use crate::error::Error;
+use crate::reader::read_uleb128_at;
+
+/// Unpacks a sorted `u32` table and calls `op` for every value in order.
+///
+/// Returns the number of bytes read from `data`.
+///
+/// # Errors
+///
+/// If the table is malformed, e.g. if a value overflows, or if `op` fails.
+///
+/// # Panics
+///
+/// If the provided data is too small for any reason and `no_bounds_check`
+/// feature is not requested.
+pub fn unpack_sorted_u32s<F>(data: &[u8], op: &mut F) -> Result<usize, Error>
+where
+    F: FnMut(u32) -> Result<(), Error>,
+{
+    let mut index = 0;
+    let count = read_uleb128_at(data, &mut index)?;
+    let mut value = 0u32;
+    for _ in 0..count {
+        value = value
+            .checked_add(read_uleb128_at(data, &mut index)?)
+            .ok_or_else(Error::invalid_data)?;
+        op(value)?;
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const TABLE: [u8; 7] = [
+        0x04, // count
+        0x80, 0x20, // 0x1000
+        0x04, // 0x1004
+        0x00, // 0x1004
+        0xFC, 0x1F, // 0x2000
+    ];
+
+    #[test]
+    fn test_unpack_sorted_u32s() {
+        let mut values = [0; 4];
+        let mut count = 0;
+        let read = unpack_sorted_u32s(&TABLE, &mut |value| {
+            values[count] = value;
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, TABLE.len());
+        assert_eq!(values, [0x1000, 0x1004, 0x1004, 0x2000]);
+    }
+
+    #[test]
+    fn test_unpack_sorted_u32s_overflow() {
+        let table = [0x02, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x01];
+        let err = unpack_sorted_u32s(&table, &mut |_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
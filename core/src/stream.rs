@@ -0,0 +1,755 @@
+//! Decoding compressed sections from chunked input
+//!
+//! Devices reading the compressed section from external flash, e.g. QSPI
+//! flash in small DMA chunks, cannot map the whole section into memory. The
+//! decoder of this module is fed one chunk at a time and keeps the state of a
+//! partially decoded field between chunks, so chunks can be released as soon
+//! as they are fed.
+
+use crate::decompress::{Codec, Layout};
+use crate::error::{Error, ErrorKind};
+use crate::layout::{
+    ALIGNED, BASED, BITMAP, DIRECTORY, GROUP_VARINT, INTERLEAVED, MACHINE, REPEAT, SCALED, SIGNED,
+    SYMBOLS,
+};
+use crate::reader::{add_delta, add_offset, scale_delta, scale_offset, Checked};
+
+/// Progress of an [Elf32CRelDecoder](struct.Elf32CRelDecoder.html) after a
+/// chunk is fed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DecodeProgress {
+    /// The whole chunk is consumed and the section continues in the next one.
+    NeedMore,
+    /// The section ends after the given number of bytes of the chunk. The
+    /// rest of the chunk is not part of the section.
+    Done(usize),
+}
+
+/// Field of the compressed section the next byte belongs to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    BaseAddress,
+    Prefix,
+    Machine,
+    Alignment,
+    Runs,
+    Groups,
+    Padding,
+    GroupType,
+    GroupBase,
+    GroupCount,
+    GroupScale,
+    Symbols,
+    Offsets,
+    Done,
+    Failed(ErrorKind),
+}
+
+/// Decoder of a compressed ELF32 relocation section fed in chunks of any
+/// size, calling `op` for every relocation like
+/// [elf32_relocate](fn.elf32_relocate.html):
+///
+/// ```
+/// use relox_core::{DecodeProgress, Elf32CRelDecoder};
+///
+/// # fn main() -> Result<(), relox_core::Error> {
+/// let data = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04];
+/// let mut decoder = Elf32CRelDecoder::new();
+/// let mut count = 0;
+/// for chunk in data.chunks(4) {
+///     if let DecodeProgress::Done(_) = decoder.feed(chunk, &mut |_, _| {
+///         count += 1;
+///         Ok(())
+///     })? {
+///         break;
+///     }
+/// }
+/// assert_eq!(decoder.finish()?, data.len());
+/// assert_eq!(count, 2);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Sections using the directory layout are not supported, as their offset
+/// streams are located through the directory.
+#[derive(Clone, Debug)]
+pub struct Elf32CRelDecoder {
+    state: State,
+    /// Number of bytes of the section fed so far.
+    position: usize,
+    /// Bytes of the current fixed-size field.
+    buffer: [u8; 4],
+    /// Number of bytes in `buffer`, or of the current group-varint lane.
+    bytes: usize,
+    /// Value and shift of the current LEB128 encoded field.
+    leb: u32,
+    shift: u32,
+    /// Number of prefixes read, to enforce their order.
+    prefixes: u8,
+    layout: Layout,
+    codec: Codec,
+    symbols: bool,
+    signed: bool,
+    repeat: bool,
+    scaled: bool,
+    /// Alignment of the groups or runs.
+    alignment: usize,
+    base_address: u32,
+    /// Number of groups or runs left.
+    groups: u32,
+    relocation_type: u8,
+    /// Codec of the offsets of the current group.
+    group_codec: Codec,
+    /// Scale the offsets of the current group are multiplied by.
+    scale: u32,
+    /// Number of relocations of the current group left.
+    remaining: u32,
+    /// Number of symbol indices of the current group left.
+    indices: u32,
+    /// Last decoded address.
+    address: u32,
+    /// Remaining lengths of the current group-varint block, or the window
+    /// start of the current bitmap.
+    tag: u32,
+    /// Number of offsets left in the current group-varint block, or number
+    /// of repeats of the offset being decoded.
+    lanes: u32,
+    /// Whether a bitmap byte follows, or the offset of a repeat.
+    bitmap: bool,
+}
+
+impl Default for Elf32CRelDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Elf32CRelDecoder {
+    /// Creates a new `Elf32CRelDecoder` instance expecting the start of a
+    /// compressed section.
+    pub fn new() -> Self {
+        Self {
+            state: State::BaseAddress,
+            position: 0,
+            buffer: [0; 4],
+            bytes: 0,
+            leb: 0,
+            shift: 0,
+            prefixes: 0,
+            layout: Layout::Grouped,
+            codec: Codec::Uleb128,
+            symbols: false,
+            signed: false,
+            repeat: false,
+            scaled: false,
+            alignment: 1,
+            base_address: 0,
+            groups: 0,
+            relocation_type: 0,
+            group_codec: Codec::Uleb128,
+            scale: 1,
+            remaining: 0,
+            indices: 0,
+            address: 0,
+            tag: 0,
+            lanes: 0,
+            bitmap: false,
+        }
+    }
+
+    /// Decodes the next chunk of the section and calls `op` for every
+    /// relocation completed by it.
+    ///
+    /// Once the section is complete, further chunks are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the compressed relocation section is malformed or if `op` fails,
+    /// or `Unsupported` if it uses the directory layout. The decoder then
+    /// fails with the same kind of error for every further chunk.
+    pub fn feed<F>(&mut self, chunk: &[u8], op: &mut F) -> Result<DecodeProgress, Error>
+    where
+        F: FnMut(u8, u32) -> Result<(), Error>,
+    {
+        for (index, byte) in chunk.iter().enumerate() {
+            match self.state {
+                State::Done => return Ok(DecodeProgress::Done(index)),
+                State::Failed(kind) => return Err(Error::new(kind)),
+                _ => {}
+            }
+            if let Err(err) = self.push(*byte, op) {
+                self.state = State::Failed(err.kind());
+                return Err(err);
+            }
+            self.position += 1;
+        }
+        match self.state {
+            State::Done => Ok(DecodeProgress::Done(chunk.len())),
+            State::Failed(kind) => Err(Error::new(kind)),
+            _ => Ok(DecodeProgress::NeedMore),
+        }
+    }
+
+    /// Returns the size of the section once it is completely decoded.
+    ///
+    /// # Errors
+    ///
+    /// `NotEnoughData` if the section is incomplete, or the kind of the error
+    /// the decoder failed with.
+    pub fn finish(&self) -> Result<usize, Error> {
+        match self.state {
+            State::Done => Ok(self.position),
+            State::Failed(kind) => Err(Error::new(kind)),
+            _ => Err(Error::not_enough_data().at(self.position)),
+        }
+    }
+
+    /// Returns `true` once the section is completely decoded.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Decodes a byte.
+    fn push<F>(&mut self, byte: u8, op: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(u8, u32) -> Result<(), Error>,
+    {
+        match self.state {
+            State::BaseAddress => {
+                if let Some(word) = self.push_word(byte, 4) {
+                    self.base_address = word;
+                    self.address = word;
+                    self.state = State::Prefix;
+                }
+            }
+            State::Prefix => self.push_prefix(byte)?,
+            State::Machine => {
+                // The machine is not checked, like elf32_relocate does.
+                if self.push_word(byte, 2).is_some() {
+                    self.state = State::Prefix;
+                }
+            }
+            State::Alignment => {
+                if !byte.is_power_of_two() {
+                    return Err(Error::invalid_data().at(self.position));
+                }
+                self.alignment = byte as usize;
+                self.state = State::Prefix;
+            }
+            State::Runs => {
+                if let Some(runs) = self.push_uleb128(byte)? {
+                    self.start_groups(runs);
+                }
+            }
+            State::Groups => self.start_groups(byte as u32),
+            State::Padding => self.state = self.group_state(),
+            State::GroupType => {
+                self.relocation_type = byte;
+                self.state = match self.layout {
+                    Layout::Based => State::GroupBase,
+                    _ => State::GroupCount,
+                };
+            }
+            State::GroupBase => {
+                if let Some(word) = self.push_word(byte, 4) {
+                    self.address = word;
+                    self.state = State::GroupCount;
+                }
+            }
+            State::GroupCount => {
+                if let Some(count) = self.push_uleb128(byte)? {
+                    self.start_group(count)?;
+                }
+            }
+            State::GroupScale => {
+                if let Some(scale) = self.push_uleb128(byte)? {
+                    if !cfg!(feature = "no_sanity_check") && scale == 0 {
+                        return Err(Error::invalid_data().at(self.position));
+                    }
+                    self.scale = scale;
+                    self.start_symbols();
+                }
+            }
+            State::Symbols => {
+                if self.push_uleb128(byte)?.is_some() {
+                    self.indices -= 1;
+                    if self.indices == 0 {
+                        self.start_offsets();
+                    }
+                }
+            }
+            State::Offsets => {
+                self.push_offset(byte, op)?;
+                if self.remaining == 0 {
+                    self.end_group();
+                }
+            }
+            State::Done | State::Failed(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Decodes a byte of the prefixes or the group count of the header.
+    fn push_prefix(&mut self, byte: u8) -> Result<(), Error> {
+        match byte {
+            MACHINE if self.prefixes < 1 => {
+                self.prefixes = 1;
+                self.state = State::Machine;
+            }
+            GROUP_VARINT | BITMAP if self.prefixes < 2 => {
+                self.prefixes = 2;
+                self.codec = match byte {
+                    BITMAP => Codec::Bitmap,
+                    _ => Codec::GroupVarint,
+                };
+            }
+            SYMBOLS if self.prefixes < 3 => {
+                self.prefixes = 3;
+                self.symbols = true;
+            }
+            SIGNED if self.prefixes < 4 => {
+                self.prefixes = 4;
+                self.signed = true;
+            }
+            ALIGNED if self.prefixes < 5 => {
+                self.prefixes = 5;
+                self.state = State::Alignment;
+            }
+            REPEAT if self.prefixes < 6 => {
+                self.prefixes = 6;
+                self.repeat = true;
+            }
+            // Bitmaps mark words, which are not scaled.
+            SCALED if self.prefixes < 7 && self.codec != Codec::Bitmap => {
+                self.prefixes = 7;
+                self.scaled = true;
+            }
+            INTERLEAVED => {
+                self.layout = Layout::Interleaved;
+                self.state = State::Runs;
+            }
+            DIRECTORY
+                if self.symbols
+                    || self.signed
+                    || self.alignment > 1
+                    || self.repeat
+                    || self.scaled =>
+            {
+                return Err(Error::invalid_data().at(self.position))
+            }
+            DIRECTORY => return Err(Error::unsupported().at(self.position)),
+            BASED => {
+                self.layout = Layout::Based;
+                self.state = State::Groups;
+            }
+            MACHINE | GROUP_VARINT | SYMBOLS | BITMAP | SIGNED | ALIGNED | REPEAT | SCALED => {
+                return Err(Error::invalid_data().at(self.position))
+            }
+            count => self.start_groups(count as u32),
+        }
+        Ok(())
+    }
+
+    /// Starts decoding `count` groups or runs.
+    fn start_groups(&mut self, count: u32) {
+        self.groups = count;
+        self.state = match count {
+            0 => State::Done,
+            _ => self.group_state(),
+        };
+    }
+
+    /// Returns the state of the byte following the current one if a group or
+    /// run follows: padding until it is aligned.
+    fn group_state(&self) -> State {
+        match (self.position + 1) % self.alignment {
+            0 => State::GroupType,
+            _ => State::Padding,
+        }
+    }
+
+    /// Starts decoding a group, given its encoded count.
+    fn start_group(&mut self, count: u32) -> Result<(), Error> {
+        let mut count = count;
+        // The lowest bit of the count flags offsets encoded as opcodes, the
+        // next one offsets encoded as signed deltas, the next one symbol
+        // indices.
+        let repeat = self.repeat && count & 1 != 0;
+        if self.repeat {
+            count >>= 1;
+        }
+        self.group_codec = match (repeat, self.signed && count & 1 != 0) {
+            (true, true) => return Err(Error::invalid_data().at(self.position)),
+            (true, false) => Codec::Repeat,
+            (false, true) => Codec::Sleb128,
+            (false, false) => self.codec,
+        };
+        if self.signed {
+            count >>= 1;
+        }
+        let indices = self.symbols && count & 1 != 0;
+        if self.symbols {
+            count >>= 1;
+        }
+        if self.layout == Layout::Grouped {
+            self.address = self.base_address;
+        }
+        self.remaining = count;
+        self.indices = if indices { count } else { 0 };
+        match self.scaled {
+            true => self.state = State::GroupScale,
+            false => self.start_symbols(),
+        }
+        Ok(())
+    }
+
+    /// Starts decoding the symbol indices of the current group, if any.
+    fn start_symbols(&mut self) {
+        match self.indices {
+            0 => self.start_offsets(),
+            _ => self.state = State::Symbols,
+        }
+    }
+
+    /// Starts decoding the offsets of the current group.
+    fn start_offsets(&mut self) {
+        self.lanes = 0;
+        self.bitmap = false;
+        self.state = State::Offsets;
+        if self.remaining == 0 {
+            self.end_group();
+        }
+    }
+
+    /// Ends the current group.
+    fn end_group(&mut self) {
+        self.groups -= 1;
+        self.state = match self.groups {
+            0 => State::Done,
+            _ => self.group_state(),
+        };
+    }
+
+    /// Decodes a byte of the offsets of the current group.
+    fn push_offset<F>(&mut self, byte: u8, op: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(u8, u32) -> Result<(), Error>,
+    {
+        match self.group_codec {
+            Codec::Uleb128 => {
+                if let Some(offset) = self.push_uleb128(byte)? {
+                    let offset = scale_offset::<Checked>(offset, self.scale, self.position)?;
+                    let address = add_offset::<Checked>(self.address, offset, self.position)?;
+                    self.emit(address, op)?;
+                }
+            }
+            Codec::Sleb128 => {
+                if let Some(delta) = self.push_sleb128(byte)? {
+                    let delta = scale_delta::<Checked>(delta, self.scale, self.position)?;
+                    let address = add_delta::<Checked>(self.address, delta, self.position)?;
+                    self.emit(address, op)?;
+                }
+            }
+            Codec::GroupVarint => {
+                if self.lanes == 0 {
+                    self.tag = byte as u32;
+                    self.lanes = self.remaining.min(4);
+                    self.bytes = 0;
+                    self.leb = 0;
+                    return Ok(());
+                }
+                self.leb |= (byte as u32) << (8 * self.bytes);
+                self.bytes += 1;
+                if self.bytes == (self.tag & 0x3) as usize + 1 {
+                    let offset = self.leb;
+                    self.tag >>= 2;
+                    self.lanes -= 1;
+                    self.bytes = 0;
+                    self.leb = 0;
+                    let offset = scale_offset::<Checked>(offset, self.scale, self.position)?;
+                    let address = add_offset::<Checked>(self.address, offset, self.position)?;
+                    self.emit(address, op)?;
+                }
+            }
+            Codec::Bitmap => {
+                if !self.bitmap {
+                    if let Some(offset) = self.push_uleb128(byte)? {
+                        let address = add_offset::<Checked>(self.address, offset, self.position)?;
+                        self.emit(address, op)?;
+                        self.tag = self.address;
+                        self.bitmap = self.remaining > 0;
+                    }
+                    return Ok(());
+                }
+                for bit in 0..7 {
+                    if self.remaining > 0 && byte & (1 << bit) != 0 {
+                        let address =
+                            add_offset::<Checked>(self.tag, 4 * (bit + 1), self.position)?;
+                        self.emit(address, op)?;
+                    }
+                }
+                // The highest bit continues the bitmap with another byte.
+                self.bitmap = self.remaining > 0 && byte & 0x80 != 0;
+                if self.bitmap {
+                    self.tag = add_offset::<Checked>(self.tag, 28, self.position)?;
+                }
+            }
+            Codec::Repeat => {
+                let value = match self.push_uleb128(byte)? {
+                    Some(value) => value,
+                    None => return Ok(()),
+                };
+                if !self.bitmap && value & 1 == 0 {
+                    let offset = scale_offset::<Checked>(value >> 1, self.scale, self.position)?;
+                    let address = add_offset::<Checked>(self.address, offset, self.position)?;
+                    return self.emit(address, op);
+                }
+                if !self.bitmap {
+                    self.lanes = value >> 1;
+                    self.bitmap = true;
+                    return Ok(());
+                }
+                self.bitmap = false;
+                let offset = scale_offset::<Checked>(value, self.scale, self.position)?;
+                for _ in 0..self.lanes.min(self.remaining) {
+                    let address = add_offset::<Checked>(self.address, offset, self.position)?;
+                    self.emit(address, op)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Passes the relocation at `address` to `op`.
+    fn emit<F>(&mut self, address: u32, op: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(u8, u32) -> Result<(), Error>,
+    {
+        self.address = address;
+        self.remaining -= 1;
+        op(self.relocation_type, address)
+    }
+
+    /// Adds a byte to a fixed-size field of `length` bytes in native byte
+    /// order and returns the field once complete.
+    fn push_word(&mut self, byte: u8, length: usize) -> Option<u32> {
+        self.buffer[self.bytes] = byte;
+        self.bytes += 1;
+        if self.bytes < length {
+            return None;
+        }
+        self.bytes = 0;
+        Some(match length {
+            2 => u16::from_ne_bytes([self.buffer[0], self.buffer[1]]) as u32,
+            _ => u32::from_ne_bytes(self.buffer),
+        })
+    }
+
+    /// Adds a byte to a ULEB128 encoded field and returns the field once
+    /// complete.
+    fn push_uleb128(&mut self, byte: u8) -> Result<Option<u32>, Error> {
+        let split = (byte & 0x7F) as u32;
+        if !cfg!(feature = "no_sanity_check") && self.shift == 28 && split > 0x0F {
+            return Err(Error::invalid_data().at(self.position));
+        }
+        self.leb |= split.wrapping_shl(self.shift);
+        if byte & 0x80 == 0 {
+            return Ok(Some(self.take_leb()));
+        }
+        self.shift = self.shift.wrapping_add(7);
+        if !cfg!(feature = "no_sanity_check") && self.shift > 28 {
+            return Err(Error::invalid_data().at(self.position));
+        }
+        Ok(None)
+    }
+
+    /// Adds a byte to an SLEB128 encoded field and returns the field as a
+    /// u32 to be added with wrapping once complete.
+    fn push_sleb128(&mut self, byte: u8) -> Result<Option<u32>, Error> {
+        let split = (byte & 0x7F) as u32;
+        // The fifth byte holds the top 4 bits, the others must extend its
+        // sign.
+        if !cfg!(feature = "no_sanity_check")
+            && self.shift == 28
+            && (byte & 0x80 != 0 || (split > 0x07 && split < 0x78))
+        {
+            return Err(Error::invalid_data().at(self.position));
+        }
+        self.leb |= split.wrapping_shl(self.shift);
+        self.shift = self.shift.wrapping_add(7);
+        if byte & 0x80 == 0 {
+            if self.shift < 32 && split & 0x40 != 0 {
+                self.leb |= u32::MAX << self.shift;
+            }
+            return Ok(Some(self.take_leb()));
+        }
+        if !cfg!(feature = "no_sanity_check") && self.shift > 28 {
+            return Err(Error::invalid_data().at(self.position));
+        }
+        Ok(None)
+    }
+
+    /// Returns the current LEB128 encoded field and resets it.
+    fn take_leb(&mut self) -> u32 {
+        let value = self.leb;
+        self.leb = 0;
+        self.shift = 0;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompress::elf32_relocate;
+
+    const SECTIONS: [&[u8]; 9] = [
+        // Grouped layout.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0x02, 0x02, 0x02, 0x04, 0x80, 0x01, 0x16, 0x03, 0x00, 0x04,
+            0x04,
+        ],
+        // Interleaved layout with the machine recorded.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xFC, 0x28, 0x00, 0xFF, 0x03, 0x17, 0x02, 0x00, 0x04, 0x02,
+            0x01, 0x04, 0x17, 0x01, 0x08,
+        ],
+        // Based layout.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xFD, 0x02, 0x02, 0x00, 0x10, 0x00, 0x00, 0x01, 0x04, 0x16,
+            0x00, 0x20, 0x00, 0x00, 0x02, 0x00, 0x04,
+        ],
+        // Group-varint codec.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xFB, 0x01, 0x17, 0x05, 0x04, 0x04, 0x00, 0x01, 0x04, 0x00,
+            0x00, 0x08,
+        ],
+        // Bitmap codec.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xF9, 0x01, 0x17, 0x04, 0x04, 0x81, 0x02, 0x40,
+        ],
+        // Symbol indices and signed offsets.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xFA, 0xF8, 0x02, 0x02, 0x0B, 0x0A, 0x03, 0x80, 0x7E, 0x84,
+            0x02, 0x17, 0x04, 0x08,
+        ],
+        // Interleaved layout with runs aligned to 4 bytes.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xF7, 0x04, 0xFF, 0x03, 0x17, 0x02, 0x00, 0x04, 0x02, 0x01,
+            0x04, 0x00, 0x17, 0x01, 0x08,
+        ],
+        // Repeated offsets.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xF6, 0x02, 0x02, 0x04, 0x08, 0x04, 0x17, 0x09, 0x00, 0x07,
+            0x04,
+        ],
+        // Repeated and scaled offsets.
+        &[
+            0x00, 0x10, 0x00, 0x00, 0xF6, 0xF5, 0x02, 0x02, 0x02, 0x04, 0x02, 0x17, 0x07, 0x04,
+            0x05, 0x01, 0x04,
+        ],
+    ];
+
+    fn decode(data: &[u8], chunk_size: usize) -> ([(u8, u32); 8], usize) {
+        let mut relocations = [(0, 0); 8];
+        let mut count = 0;
+        let mut decoder = Elf32CRelDecoder::new();
+        for chunk in data.chunks(chunk_size) {
+            let progress = decoder
+                .feed(chunk, &mut |relocation_type, address| {
+                    relocations[count] = (relocation_type, address);
+                    count += 1;
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(progress == DecodeProgress::NeedMore, !decoder.is_done());
+        }
+        assert_eq!(decoder.finish().unwrap(), data.len());
+        (relocations, count)
+    }
+
+    #[test]
+    fn test_decoder_chunks() {
+        for data in SECTIONS.iter() {
+            let mut expected = [(0, 0); 8];
+            let mut count = 0;
+            elf32_relocate(data, &mut |relocation_type, address| {
+                expected[count] = (relocation_type, address);
+                count += 1;
+                Ok(())
+            })
+            .unwrap();
+            for chunk_size in 1..=data.len() {
+                assert_eq!(decode(data, chunk_size), (expected, count));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoder_trailing_data() {
+        let mut data = [0xAA; 12];
+        data[..9].copy_from_slice(&[0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04]);
+        let mut decoder = Elf32CRelDecoder::new();
+        let mut op = |_, _| Ok(());
+        assert_eq!(
+            decoder.feed(&data[..8], &mut op).unwrap(),
+            DecodeProgress::NeedMore
+        );
+        assert_eq!(
+            decoder.feed(&data[8..], &mut op).unwrap(),
+            DecodeProgress::Done(1)
+        );
+        assert_eq!(
+            decoder.feed(&data[8..], &mut op).unwrap(),
+            DecodeProgress::Done(0)
+        );
+        assert_eq!(decoder.finish().unwrap(), 9);
+    }
+
+    #[test]
+    fn test_decoder_invalid() {
+        let mut decoder = Elf32CRelDecoder::new();
+        let mut op = |_, _| Ok(());
+        decoder.feed(&[0x00, 0x10, 0x00], &mut op).unwrap();
+        let err = decoder.finish().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = decoder.feed(&[0x00, 0xFE, 0x01], &mut op).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = decoder.feed(&[0x00], &mut op).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert_eq!(decoder.finish().unwrap_err().kind(), ErrorKind::Unsupported);
+
+        let mut decoder = Elf32CRelDecoder::new();
+        let err = decoder
+            .feed(&[0x00, 0x10, 0x00, 0x00, 0xFA, 0xFB], &mut op)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut decoder = Elf32CRelDecoder::new();
+        let err = decoder
+            .feed(&[0x00, 0x10, 0x00, 0x00, 0xF7, 0x03], &mut op)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut decoder = Elf32CRelDecoder::new();
+        let err = decoder
+            .feed(&[0x00, 0x10, 0x00, 0x00, 0xF9, 0xF5], &mut op)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut decoder = Elf32CRelDecoder::new();
+        let err = decoder
+            .feed(
+                &[0x00, 0x10, 0x00, 0x00, 0xF8, 0xF6, 0x01, 0x17, 0x03],
+                &mut op,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut decoder = Elf32CRelDecoder::new();
+        let err = decoder
+            .feed(
+                &[0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x01, 0x04],
+                &mut |_, _| Err(Error::buffer_small()),
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+    }
+}
@@ -0,0 +1,122 @@
+//! Reduced decoder for minimal code size
+//!
+//! This module provides a decoder for boot ROM patches and other targets with
+//! a code size budget well below 1 KiB. Compared to
+//! [elf32_relocate](fn.elf32_relocate.html) it supports the grouped layout
+//! only, reports failures as `false` instead of an `Error` and performs no
+//! sanity checks: overlong ULEB128 encodings and address overflows wrap
+//! around silently. Reads stay within the provided data.
+//!
+//! The decoder is not generic, so it is compiled exactly once and its
+//! footprint can be measured, and checked against a budget, e.g. with
+//! `make footprint FOOTPRINT_TARGET=thumbv6m-none-eabi SIZE_TOOL=llvm-size`.
+//! It takes 308 bytes of text on x86_64 at `opt-level=z`.
+
+/// Processes a compressed ELF32 relocation section using the grouped layout
+/// and calls `op` for every relocation.
+///
+/// Returns `false` if the data ends prematurely or if the section uses another
+/// layout. Relocations decoded before the failure have been passed to `op`.
+pub fn elf32_relocate_tiny(data: &[u8], op: &mut dyn FnMut(u8, u32)) -> bool {
+    relocate(data, op).is_some()
+}
+
+/// Decodes all groups, returning `None` on failure.
+fn relocate(data: &[u8], op: &mut dyn FnMut(u8, u32)) -> Option<()> {
+    let base_address =
+        u32::from_ne_bytes([*data.first()?, *data.get(1)?, *data.get(2)?, *data.get(3)?]);
+    let groups = *data.get(4)?;
+    // Other layouts are marked by the top group counts.
+    if groups >= 0xF5 {
+        return None;
+    }
+    let mut index = 5;
+    for _ in 0..groups {
+        let relocation_type = *data.get(index)?;
+        index += 1;
+        let count = read_uleb128(data, &mut index)?;
+        let mut address = base_address;
+        for _ in 0..count {
+            address = address.wrapping_add(read_uleb128(data, &mut index)?);
+            op(relocation_type, address);
+        }
+    }
+    Some(())
+}
+
+/// Reads a ULEB128 encoded value at `index` and advances `index` past it.
+fn read_uleb128(data: &[u8], index: &mut usize) -> Option<u32> {
+    let mut value: u32 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*index)?;
+        *index += 1;
+        value |= ((byte & 0x7F) as u32).wrapping_shl(shift);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREL_TWO_GROUPS: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    #[test]
+    fn test_tiny_relocate() {
+        let mut relocations = [(0, 0); 5];
+        let mut count = 0;
+        assert!(elf32_relocate_tiny(
+            &CREL_TWO_GROUPS,
+            &mut |relocation_type, address| {
+                relocations[count] = (relocation_type, address);
+                count += 1;
+            }
+        ));
+        assert_eq!(
+            relocations,
+            [
+                (0x02, 0x1004),
+                (0x02, 0x1084),
+                (0x16, 0x1000),
+                (0x16, 0x1004),
+                (0x16, 0x1008),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tiny_relocate_truncated() {
+        for len in 0..CREL_TWO_GROUPS.len() {
+            let mut count = 0;
+            assert!(!elf32_relocate_tiny(
+                &CREL_TWO_GROUPS[..len],
+                &mut |_, _| { count += 1 }
+            ));
+            assert!(count < 5);
+        }
+    }
+
+    #[test]
+    fn test_tiny_relocate_other_layout() {
+        let mut data = CREL_TWO_GROUPS;
+        for marker in &[
+            0xF5, 0xF6, 0xF7, 0xF8, 0xF9, 0xFA, 0xFB, 0xFC, 0xFD, 0xFE, 0xFF,
+        ] {
+            data[4] = *marker;
+            assert!(!elf32_relocate_tiny(&data, &mut |_, _| unreachable!()));
+        }
+    }
+}
@@ -1,10 +1,33 @@
-///! Unsigned LEB128 encoding
-///!
-///! https://en.wikipedia.org/wiki/LEB128
-use crate::error::{Error, ErrorKind};
+//! Unsigned and signed LEB128 encoding
+//!
+//! <https://en.wikipedia.org/wiki/LEB128>
+//!
+//! These are the codecs used by the compressed format, exposed for loaders
+//! which encode their own metadata as ULEB128. Values are written into and
+//! read from byte slices, without allocating:
+//!
+//! ```
+//! use relox_core::uleb128::{decode_u32, encode_u32, MAX_LEN_U32};
+//!
+//! # fn main() -> Result<(), relox_core::Error> {
+//! let mut buffer = [0; MAX_LEN_U32];
+//! let written = encode_u32(624485, &mut buffer)?;
+//! assert_eq!(buffer[..written], [0xE5, 0x8E, 0x26]);
+//! assert_eq!(decode_u32(&buffer[..written])?, (624485, 3));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
 
 const CONTINUE_BIT: u8 = 0x80;
 
+/// Maximum number of bytes of a ULEB128 encoded 32-bit value.
+pub const MAX_LEN_U32: usize = 5;
+
+/// Maximum number of bytes of a ULEB128 encoded 64-bit value.
+pub const MAX_LEN_U64: usize = 10;
+
 /// Writes an unsigned value as ULEB128 into a buffer
 /// and returns the number of bytes written.
 ///
@@ -25,7 +48,7 @@ fn write_unsigned(mut value: u32, bytes: &mut [u8]) -> Result<usize, Error> {
             return Ok(index + 1);
         }
     }
-    Err(Error::new(ErrorKind::NotEnoughData))
+    Err(Error::not_enough_data())
 }
 
 /// Writes an unsigned 8-bit value as ULEB128 into a buffer
@@ -61,7 +84,7 @@ pub fn write_u32(value: u32, bytes: &mut [u8]) -> Result<usize, Error> {
     write_unsigned(value, bytes)
 }
 
-/// Returns an unsigned value deccoded from ULEB128 from a buffer and
+/// Returns an unsigned value decoded from ULEB128 from a buffer and
 /// the number of bytes read.
 ///
 /// # Errors
@@ -78,23 +101,23 @@ fn read_unsigned(
     for (index, byte) in bytes.iter().enumerate() {
         let split: u32 = (byte & !CONTINUE_BIT) as u32;
         if !cfg!(feature = "no_sanity_check") && (shift == shift_max) && (split > last_split_max) {
-            return Err(Error::new(ErrorKind::InvalidData));
+            return Err(Error::invalid_data());
         } else {
             *value |= split.wrapping_shl(shift);
             if (byte & CONTINUE_BIT) == CONTINUE_BIT {
                 shift += 7;
                 if !cfg!(feature = "no_sanity_check") && (shift > shift_max) {
-                    return Err(Error::new(ErrorKind::InvalidData));
+                    return Err(Error::invalid_data());
                 }
             } else {
                 return Ok(index + 1);
             }
         }
     }
-    Err(Error::new(ErrorKind::NotEnoughData))
+    Err(Error::not_enough_data())
 }
 
-/// Returns an unsigned 8-bit value deccoded from ULEB128 from a buffer
+/// Returns an unsigned 8-bit value decoded from ULEB128 from a buffer
 /// and the number of bytes read.
 ///
 /// # Errors
@@ -111,7 +134,7 @@ pub fn read_u8(bytes: &[u8], value: &mut u8) -> Result<usize, Error> {
     result
 }
 
-/// Returns an unsigned 16-bit value deccoded from ULEB128 from a buffer
+/// Returns an unsigned 16-bit value decoded from ULEB128 from a buffer
 /// and the number of bytes read.
 ///
 /// # Errors
@@ -128,7 +151,7 @@ pub fn read_u16(bytes: &[u8], value: &mut u16) -> Result<usize, Error> {
     result
 }
 
-/// Returns an unsigned 32-bit value deccoded from ULEB128 from a buffer
+/// Returns an unsigned 32-bit value decoded from ULEB128 from a buffer
 /// and the number of bytes read.
 ///
 /// # Errors
@@ -141,7 +164,183 @@ pub fn read_u32(bytes: &[u8], value: &mut u32) -> Result<usize, Error> {
     read_unsigned(bytes, 0x0F, 28, value)
 }
 
+/// Writes an unsigned 64-bit value as ULEB128 into a buffer
+/// and returns the number of bytes written.
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required.
+#[allow(unused)]
+pub fn write_u64(mut value: u64, bytes: &mut [u8]) -> Result<usize, Error> {
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        let split = (value & 0x7F) as u8;
+        value >>= 7;
+        if value > 0 {
+            *byte = split | CONTINUE_BIT;
+        } else {
+            *byte = split;
+            return Ok(index + 1);
+        }
+    }
+    Err(Error::not_enough_data())
+}
+
+/// Returns an unsigned 64-bit value decoded from ULEB128 from a buffer
+/// and the number of bytes read.
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required or if the decoded value is
+/// greater than the max value of the expected type.
+#[allow(unused)]
+pub fn read_u64(bytes: &[u8], value: &mut u64) -> Result<usize, Error> {
+    *value = 0;
+    let mut shift: u32 = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        let split = (byte & !CONTINUE_BIT) as u64;
+        if !cfg!(feature = "no_sanity_check") && (shift == 63) && (split > 0x01) {
+            return Err(Error::invalid_data());
+        }
+        *value |= split.wrapping_shl(shift);
+        if (byte & CONTINUE_BIT) == 0 {
+            return Ok(index + 1);
+        }
+        shift += 7;
+        if !cfg!(feature = "no_sanity_check") && (shift > 63) {
+            return Err(Error::invalid_data());
+        }
+    }
+    Err(Error::not_enough_data())
+}
+
+/// Writes a signed 32-bit value as SLEB128 into a buffer
+/// and returns the number of bytes written.
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required.
+pub fn write_i32(mut value: i32, bytes: &mut [u8]) -> Result<usize, Error> {
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        let split = (value & 0x7F) as u8;
+        // Arithmetic shift, so negative values end at -1.
+        value >>= 7;
+        let sign = split & 0x40 != 0;
+        if (value == 0 && !sign) || (value == -1 && sign) {
+            *byte = split;
+            return Ok(index + 1);
+        }
+        *byte = split | CONTINUE_BIT;
+    }
+    Err(Error::not_enough_data())
+}
+
+/// Returns a signed 32-bit value decoded from SLEB128 from a buffer
+/// and the number of bytes read.
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required or if the decoded value is
+/// out of the range of the expected type.
+pub fn read_i32(bytes: &[u8], value: &mut i32) -> Result<usize, Error> {
+    let mut result: u32 = 0;
+    let mut shift: u32 = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        let split = byte & !CONTINUE_BIT;
+        // The last byte holds the top 4 bits, the others must extend its
+        // sign.
+        if !cfg!(feature = "no_sanity_check")
+            && shift == 28
+            && (byte & CONTINUE_BIT != 0 || (split > 0x07 && split < 0x78))
+        {
+            return Err(Error::invalid_data());
+        }
+        result |= (split as u32).wrapping_shl(shift);
+        shift += 7;
+        if (byte & CONTINUE_BIT) == 0 {
+            if shift < 32 && split & 0x40 != 0 {
+                result |= u32::MAX << shift;
+            }
+            *value = result as i32;
+            return Ok(index + 1);
+        }
+        if !cfg!(feature = "no_sanity_check") && shift > 28 {
+            return Err(Error::invalid_data());
+        }
+    }
+    Err(Error::not_enough_data())
+}
+
+/// Encodes a 32-bit value as ULEB128 into a buffer and returns the number of
+/// bytes written, at most [MAX_LEN_U32](constant.MAX_LEN_U32.html).
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required.
+pub fn encode_u32(value: u32, bytes: &mut [u8]) -> Result<usize, Error> {
+    write_unsigned(value, bytes)
+}
+
+/// Decodes a ULEB128 encoded 32-bit value from the start of a buffer and
+/// returns it with the number of bytes read.
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required or if the decoded value is
+/// greater than `u32::MAX`.
+pub fn decode_u32(bytes: &[u8]) -> Result<(u32, usize), Error> {
+    let mut value = 0;
+    let read = read_u32(bytes, &mut value)?;
+    Ok((value, read))
+}
+
+/// Encodes a 64-bit value as ULEB128 into a buffer and returns the number of
+/// bytes written, at most [MAX_LEN_U64](constant.MAX_LEN_U64.html).
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required.
+pub fn encode_u64(value: u64, bytes: &mut [u8]) -> Result<usize, Error> {
+    write_u64(value, bytes)
+}
+
+/// Decodes a ULEB128 encoded 64-bit value from the start of a buffer and
+/// returns it with the number of bytes read.
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required or if the decoded value is
+/// greater than `u64::MAX`.
+pub fn decode_u64(bytes: &[u8]) -> Result<(u64, usize), Error> {
+    let mut value = 0;
+    let read = read_u64(bytes, &mut value)?;
+    Ok((value, read))
+}
+
+/// Encodes a signed 32-bit value as SLEB128 into a buffer and returns the
+/// number of bytes written, at most [MAX_LEN_U32](constant.MAX_LEN_U32.html).
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required.
+pub fn encode_i32(value: i32, bytes: &mut [u8]) -> Result<usize, Error> {
+    write_i32(value, bytes)
+}
+
+/// Decodes a SLEB128 encoded signed 32-bit value from the start of a buffer
+/// and returns it with the number of bytes read.
+///
+/// # Errors
+///
+/// If the provided buffer is smaller than required or if the decoded value is
+/// out of the range of `i32`.
+pub fn decode_i32(bytes: &[u8]) -> Result<(i32, usize), Error> {
+    let mut value = 0;
+    let read = read_i32(bytes, &mut value)?;
+    Ok((value, read))
+}
+
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
     use super::*;
     use rand::prelude::*;
@@ -151,7 +350,7 @@ mod tests {
         let mut buffer: [u8; 2] = [0; 2];
 
         // 1 byte
-        assert_eq!(write_u8(0, &mut buffer[0..0]).is_err(), true);
+        assert!(write_u8(0, &mut buffer[0..0]).is_err());
 
         assert_eq!(write_u8(0, &mut buffer).unwrap(), 1);
         assert_eq!(buffer[0], 0);
@@ -160,7 +359,7 @@ mod tests {
         assert_eq!(buffer[0], 0x7F);
 
         // 2 bytes
-        assert_eq!(write_u8(0x80, &mut buffer[0..1]).is_err(), true);
+        assert!(write_u8(0x80, &mut buffer[0..1]).is_err());
 
         assert_eq!(write_u8(CONTINUE_BIT, &mut buffer).unwrap(), 2);
         assert_eq!(buffer[0], 0x00 | CONTINUE_BIT);
@@ -176,7 +375,7 @@ mod tests {
         let mut buffer: [u8; 3] = [0; 3];
 
         // 1 byte
-        assert_eq!(write_u16(0, &mut buffer[0..0]).is_err(), true);
+        assert!(write_u16(0, &mut buffer[0..0]).is_err());
 
         assert_eq!(write_u16(0, &mut buffer).unwrap(), 1);
         assert_eq!(buffer[0], 0);
@@ -185,7 +384,7 @@ mod tests {
         assert_eq!(buffer[0], 0x7F);
 
         // 2 bytes
-        assert_eq!(write_u16(0x80, &mut buffer[0..1]).is_err(), true);
+        assert!(write_u16(0x80, &mut buffer[0..1]).is_err());
 
         assert_eq!(write_u16(0x80, &mut buffer).unwrap(), 2);
         assert_eq!(buffer[0], 0x00 | CONTINUE_BIT);
@@ -200,7 +399,7 @@ mod tests {
         assert_eq!(buffer[1], 0x7F);
 
         // 3 bytes
-        assert_eq!(write_u16(0x40_00, &mut buffer[0..2]).is_err(), true);
+        assert!(write_u16(0x40_00, &mut buffer[0..2]).is_err());
 
         assert_eq!(write_u16(0x40_00, &mut buffer).unwrap(), 3);
         assert_eq!(buffer[0], 0x00 | CONTINUE_BIT);
@@ -218,7 +417,7 @@ mod tests {
         let mut buffer: [u8; 5] = [0; 5];
 
         // 1 byte
-        assert_eq!(write_u32(0, &mut buffer[0..0]).is_err(), true);
+        assert!(write_u32(0, &mut buffer[0..0]).is_err());
 
         assert_eq!(write_u32(0, &mut buffer).unwrap(), 1);
         assert_eq!(buffer[0], 0);
@@ -227,7 +426,7 @@ mod tests {
         assert_eq!(buffer[0], 0x7F);
 
         // 2 bytes
-        assert_eq!(write_u32(0x80, &mut buffer[0..1]).is_err(), true);
+        assert!(write_u32(0x80, &mut buffer[0..1]).is_err());
 
         assert_eq!(write_u32(0x80, &mut buffer).unwrap(), 2);
         assert_eq!(buffer[0], 0x00 | CONTINUE_BIT);
@@ -242,7 +441,7 @@ mod tests {
         assert_eq!(buffer[1], 0x7F);
 
         // 3 bytes
-        assert_eq!(write_u32(0x40_00, &mut buffer[0..2]).is_err(), true);
+        assert!(write_u32(0x40_00, &mut buffer[0..2]).is_err());
 
         assert_eq!(write_u32(0x40_00, &mut buffer).unwrap(), 3);
         assert_eq!(buffer[0], 0x00 | CONTINUE_BIT);
@@ -260,7 +459,7 @@ mod tests {
         assert_eq!(buffer[2], 0x7F);
 
         // 4 bytes
-        assert_eq!(write_u32(0x20_00_00, &mut buffer[0..3]).is_err(), true);
+        assert!(write_u32(0x20_00_00, &mut buffer[0..3]).is_err());
 
         assert_eq!(write_u32(0x20_00_00, &mut buffer).unwrap(), 4);
         assert_eq!(buffer[0], 0x00 | CONTINUE_BIT);
@@ -275,7 +474,7 @@ mod tests {
         assert_eq!(buffer[3], 0x7F);
 
         // 5 bytes
-        assert_eq!(write_u32(0x10_00_00_00, &mut buffer[0..4]).is_err(), true);
+        assert!(write_u32(0x10_00_00_00, &mut buffer[0..4]).is_err());
 
         assert_eq!(write_u32(0x10_00_00_00, &mut buffer).unwrap(), 5);
         assert_eq!(buffer[0], 0x00 | CONTINUE_BIT);
@@ -302,17 +501,11 @@ mod tests {
     fn test_read_u8() {
         let mut value: u8 = 0;
 
-        assert_eq!(read_u8(&[0x00; 0], &mut value).is_err(), true);
-        assert_eq!(read_u8(&[CONTINUE_BIT], &mut value).is_err(), true);
-        assert_eq!(
-            read_u8(&[CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err(),
-            true
-        );
+        assert!(read_u8(&[0x00; 0], &mut value).is_err());
+        assert!(read_u8(&[CONTINUE_BIT], &mut value).is_err());
+        assert!(read_u8(&[CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err());
         #[cfg(not(feature = "no_sanity_check"))]
-        assert_eq!(
-            read_u8(&[CONTINUE_BIT, CONTINUE_BIT, 0], &mut value).is_err(),
-            true
-        );
+        assert!(read_u8(&[CONTINUE_BIT, CONTINUE_BIT, 0], &mut value).is_err());
 
         // 1 byte
         assert_eq!(read_u8(&[0x00], &mut value).unwrap(), 1);
@@ -329,31 +522,19 @@ mod tests {
 
         // Out-of-range
         #[cfg(not(feature = "no_sanity_check"))]
-        assert_eq!(
-            read_u8(&[0x7F | CONTINUE_BIT, 0x02], &mut value).is_err(),
-            true
-        );
+        assert!(read_u8(&[0x7F | CONTINUE_BIT, 0x02], &mut value).is_err());
     }
 
     #[test]
     fn test_read_u16() {
         let mut value: u16 = 0;
 
-        assert_eq!(read_u16(&[0x00; 0], &mut value).is_err(), true);
-        assert_eq!(read_u16(&[CONTINUE_BIT], &mut value).is_err(), true);
-        assert_eq!(
-            read_u16(&[CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err(),
-            true
-        );
-        assert_eq!(
-            read_u16(&[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err(),
-            true
-        );
+        assert!(read_u16(&[0x00; 0], &mut value).is_err());
+        assert!(read_u16(&[CONTINUE_BIT], &mut value).is_err());
+        assert!(read_u16(&[CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err());
+        assert!(read_u16(&[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err());
         #[cfg(not(feature = "no_sanity_check"))]
-        assert_eq!(
-            read_u16(&[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT, 0], &mut value).is_err(),
-            true
-        );
+        assert!(read_u16(&[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT, 0], &mut value).is_err());
 
         // 1 byte
         assert_eq!(read_u16(&[0x00], &mut value).unwrap(), 1);
@@ -395,68 +576,50 @@ mod tests {
 
         // Out-of-range
         #[cfg(not(feature = "no_sanity_check"))]
-        assert_eq!(
-            read_u16(
-                &[0x7F | CONTINUE_BIT, 0x7F | CONTINUE_BIT, 0x04],
-                &mut value
-            )
-            .is_err(),
-            true
-        );
+        assert!(read_u16(
+            &[0x7F | CONTINUE_BIT, 0x7F | CONTINUE_BIT, 0x04],
+            &mut value
+        )
+        .is_err());
     }
 
     #[test]
     fn test_read_u32() {
         let mut value: u32 = 0;
 
-        assert_eq!(read_u32(&[0x00; 0], &mut value).is_err(), true);
-        assert_eq!(read_u32(&[CONTINUE_BIT], &mut value).is_err(), true);
-        assert_eq!(
-            read_u32(&[CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err(),
-            true
-        );
-        assert_eq!(
-            read_u32(&[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err(),
-            true
-        );
-        assert_eq!(
-            read_u32(
-                &[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT],
-                &mut value
-            )
-            .is_err(),
-            true
-        );
-        assert_eq!(
-            read_u32(
-                &[
-                    CONTINUE_BIT,
-                    CONTINUE_BIT,
-                    CONTINUE_BIT,
-                    CONTINUE_BIT,
-                    CONTINUE_BIT
-                ],
-                &mut value
-            )
-            .is_err(),
-            true
-        );
+        assert!(read_u32(&[0x00; 0], &mut value).is_err());
+        assert!(read_u32(&[CONTINUE_BIT], &mut value).is_err());
+        assert!(read_u32(&[CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err());
+        assert!(read_u32(&[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT], &mut value).is_err());
+        assert!(read_u32(
+            &[CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT, CONTINUE_BIT],
+            &mut value
+        )
+        .is_err());
+        assert!(read_u32(
+            &[
+                CONTINUE_BIT,
+                CONTINUE_BIT,
+                CONTINUE_BIT,
+                CONTINUE_BIT,
+                CONTINUE_BIT
+            ],
+            &mut value
+        )
+        .is_err());
         #[cfg(not(feature = "no_sanity_check"))]
-        assert_eq!(
-            read_u32(
-                &[
-                    CONTINUE_BIT,
-                    CONTINUE_BIT,
-                    CONTINUE_BIT,
-                    CONTINUE_BIT,
-                    CONTINUE_BIT,
-                    0
-                ],
-                &mut value
-            )
-            .is_err(),
-            true
-        );
+        assert!(read_u32(
+            &[
+                CONTINUE_BIT,
+                CONTINUE_BIT,
+                CONTINUE_BIT,
+                CONTINUE_BIT,
+                CONTINUE_BIT,
+                0
+            ],
+            &mut value
+        )
+        .is_err());
 
         // 1 byte
         assert_eq!(read_u32(&[0x00], &mut value).unwrap(), 1);
@@ -560,20 +723,103 @@ mod tests {
 
         // Out-of-range
         #[cfg(not(feature = "no_sanity_check"))]
-        assert_eq!(
-            read_u32(
-                &[
-                    0x7F | CONTINUE_BIT,
-                    0x7F | CONTINUE_BIT,
-                    0x7F | CONTINUE_BIT,
-                    0x7F | CONTINUE_BIT,
-                    0x1F
-                ],
-                &mut value
-            )
-            .is_err(),
-            true
-        );
+        assert!(read_u32(
+            &[
+                0x7F | CONTINUE_BIT,
+                0x7F | CONTINUE_BIT,
+                0x7F | CONTINUE_BIT,
+                0x7F | CONTINUE_BIT,
+                0x1F
+            ],
+            &mut value
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_write_read_u64() {
+        let mut buffer: [u8; 10] = [0; 10];
+        for value in [0, 0x7F, 0x80, 0xFFFF_FFFF, 0x1_0000_0000, u64::MAX].iter() {
+            let written = write_u64(*value, &mut buffer).unwrap();
+            let mut read = 1;
+            assert_eq!(read_u64(&buffer[..written], &mut read).unwrap(), written);
+            assert_eq!(read, *value);
+        }
+        assert_eq!(write_u64(u64::MAX, &mut buffer).unwrap(), 10);
+        assert_eq!(buffer[9], 0x01);
+        assert!(write_u64(u64::MAX, &mut buffer[..9]).is_err());
+
+        let mut value = 0;
+        assert!(read_u64(&buffer[..9], &mut value).is_err());
+        #[cfg(not(feature = "no_sanity_check"))]
+        {
+            buffer[9] = 0x02;
+            assert!(read_u64(&buffer, &mut value).is_err());
+            buffer[9] = 0x81;
+            assert!(read_u64(&buffer, &mut value).is_err());
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut buffer = [0; MAX_LEN_U64];
+        assert_eq!(encode_u32(u32::MAX, &mut buffer).unwrap(), MAX_LEN_U32);
+        assert_eq!(decode_u32(&buffer).unwrap(), (u32::MAX, MAX_LEN_U32));
+        assert!(encode_u32(0x80, &mut buffer[..1]).is_err());
+        assert!(decode_u32(&buffer[..4]).is_err());
+
+        assert_eq!(encode_u64(u64::MAX, &mut buffer).unwrap(), MAX_LEN_U64);
+        assert_eq!(decode_u64(&buffer).unwrap(), (u64::MAX, MAX_LEN_U64));
+        assert_eq!(encode_u64(0x7F, &mut buffer).unwrap(), 1);
+        assert_eq!(decode_u64(&buffer).unwrap(), (0x7F, 1));
+        assert!(decode_u64(&[CONTINUE_BIT]).is_err());
+    }
+
+    #[test]
+    fn test_write_read_i32() {
+        let mut buffer: [u8; 5] = [0; 5];
+        for (value, encoded) in [
+            (0, &[0x00][..]),
+            (2, &[0x02][..]),
+            (-2, &[0x7E][..]),
+            (63, &[0x3F][..]),
+            (64, &[0xC0, 0x00][..]),
+            (-64, &[0x40][..]),
+            (-65, &[0xBF, 0x7F][..]),
+            (-123456, &[0xC0, 0xBB, 0x78][..]),
+            (i32::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0x07][..]),
+            (i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x78][..]),
+        ]
+        .iter()
+        {
+            let written = write_i32(*value, &mut buffer).unwrap();
+            assert_eq!(buffer[..written], **encoded);
+            assert_eq!(decode_i32(encoded).unwrap(), (*value, encoded.len()));
+        }
+        assert!(write_i32(64, &mut buffer[..1]).is_err());
+        assert!(encode_i32(i32::MIN, &mut buffer[..4]).is_err());
+
+        let mut value = 0;
+        assert!(read_i32(&[0x00; 0], &mut value).is_err());
+        assert!(read_i32(&[0xC0], &mut value).is_err());
+        #[cfg(not(feature = "no_sanity_check"))]
+        {
+            // Top bits not extending the sign of bit 31.
+            assert!(read_i32(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F], &mut value).is_err());
+            assert!(read_i32(&[0x80, 0x80, 0x80, 0x80, 0x70], &mut value).is_err());
+            assert!(read_i32(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00], &mut value).is_err());
+        }
+    }
+
+    #[test]
+    fn test_random_i32() {
+        let mut rng = rand::thread_rng();
+        let mut buffer: [u8; 5] = [0; 5];
+        for _ in 0..4096 {
+            let value: i32 = rng.gen();
+            let written = write_i32(value, &mut buffer).unwrap();
+            assert_eq!(decode_i32(&buffer[..written]).unwrap(), (value, written));
+        }
     }
 
     #[test]
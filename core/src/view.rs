@@ -0,0 +1,262 @@
+//! ABI-stable views of compressed sections
+//!
+//! This module provides `#[repr(C)]` structures matching the fixed-size parts
+//! of the wire format, so boot code written in C can overlay a compressed
+//! section with the structures declared in `include/relox.h` and cooperate
+//! with the decoder. All structures have an alignment of 1 and hold words as
+//! byte arrays in the byte order of the section, so they can be placed at any
+//! offset.
+
+use core::mem::size_of;
+
+use crate::error::Error;
+use crate::layout::{BASED, DIRECTORY};
+
+/// Header of a compressed section, `struct relox_header` in C.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32CRelHeaderView {
+    base_address: [u8; 4],
+    count: u8,
+}
+
+impl Elf32CRelHeaderView {
+    /// Overlays the header at the start of `data`.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is smaller than the header.
+    pub fn from_bytes(data: &[u8]) -> Result<&Self, Error> {
+        overlay(data)
+    }
+
+    /// Returns the base address of the relocations.
+    pub fn base_address(&self) -> u32 {
+        u32::from_ne_bytes(self.base_address)
+    }
+
+    /// Returns the group count, or the marker of the layout if it is 0xF5 or
+    /// greater.
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+}
+
+/// Header of a compressed section using the directory or the based layout,
+/// `struct relox_directory_header` in C.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32CRelDirectoryHeaderView {
+    header: Elf32CRelHeaderView,
+    count: u8,
+}
+
+impl Elf32CRelDirectoryHeaderView {
+    /// Overlays the header at the start of `data`.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is smaller than the header, or `Unsupported` if the section
+    /// uses neither the directory nor the based layout.
+    pub fn from_bytes(data: &[u8]) -> Result<&Self, Error> {
+        let view: &Self = overlay(data)?;
+        match view.header.count {
+            DIRECTORY | BASED => Ok(view),
+            _ => Err(Error::unsupported()),
+        }
+    }
+
+    /// Returns the common part of the header.
+    pub fn header(&self) -> &Elf32CRelHeaderView {
+        &self.header
+    }
+
+    /// Returns the number of groups.
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+}
+
+/// Entry of the group directory, `struct relox_directory_entry` in C.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32CRelDirectoryEntryView {
+    relocation_type: u8,
+    count: [u8; 4],
+    stream: [u8; 4],
+}
+
+impl Elf32CRelDirectoryEntryView {
+    /// Overlays the group directory of a compressed section using the
+    /// directory layout.
+    ///
+    /// # Errors
+    ///
+    /// If `data` ends before the end of the directory, or `Unsupported` if
+    /// the section does not use the directory layout.
+    pub fn directory(data: &[u8]) -> Result<&[Self], Error> {
+        let header = Elf32CRelDirectoryHeaderView::from_bytes(data)?;
+        if header.header.count != DIRECTORY {
+            return Err(Error::unsupported());
+        }
+        let offset = size_of::<Elf32CRelDirectoryHeaderView>();
+        let count = header.count as usize;
+        if data.len() < offset + count * size_of::<Self>() {
+            return Err(Error::not_enough_data());
+        }
+        // The entries are within `data` and have an alignment of 1.
+        Ok(unsafe { core::slice::from_raw_parts(data.as_ptr().add(offset) as *const Self, count) })
+    }
+
+    /// Returns the type of the relocations of the group.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the number of relocations of the group.
+    pub fn count(&self) -> u32 {
+        u32::from_ne_bytes(self.count)
+    }
+
+    /// Returns the offset of the group's offsets relative to the end of the
+    /// directory.
+    pub fn stream(&self) -> u32 {
+        u32::from_ne_bytes(self.stream)
+    }
+}
+
+/// Fixed-size part of a group of a compressed section using the based layout,
+/// `struct relox_based_group` in C. It is followed by the number of
+/// relocations and the offsets, both encoded as ULEB128.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32CRelBasedGroupView {
+    relocation_type: u8,
+    base_address: [u8; 4],
+}
+
+impl Elf32CRelBasedGroupView {
+    /// Overlays the group at the start of `data`.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is smaller than the fixed-size part of the group.
+    pub fn from_bytes(data: &[u8]) -> Result<&Self, Error> {
+        overlay(data)
+    }
+
+    /// Returns the type of the relocations of the group.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the base address of the relocations of the group.
+    pub fn base_address(&self) -> u32 {
+        u32::from_ne_bytes(self.base_address)
+    }
+}
+
+/// Overlays a view at the start of `data`.
+fn overlay<T>(data: &[u8]) -> Result<&T, Error> {
+    if data.len() < size_of::<T>() {
+        return Err(Error::not_enough_data());
+    }
+    // Views consist of bytes only, so they have an alignment of 1.
+    Ok(unsafe { &*(data.as_ptr() as *const T) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use core::mem::{align_of, offset_of};
+
+    const CREL_DIRECTORY: [u8; 30] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0xFE, // count, directory
+        0x02, // groups
+        0x02, // directory[0].relocation_type
+        0x02, 0x00, 0x00, 0x00, // directory[0].count
+        0x00, 0x00, 0x00, 0x00, // directory[0].stream
+        0x16, // directory[1].relocation_type
+        0x03, 0x00, 0x00, 0x00, // directory[1].count
+        0x03, 0x00, 0x00, 0x00, // directory[1].stream
+        0x04, 0x80, 0x01, // streams[0]
+        0x00, 0x04, 0x04, // streams[1]
+    ];
+
+    #[test]
+    fn test_view_layout() {
+        assert_eq!(size_of::<Elf32CRelHeaderView>(), 5);
+        assert_eq!(align_of::<Elf32CRelHeaderView>(), 1);
+        assert_eq!(offset_of!(Elf32CRelHeaderView, base_address), 0);
+        assert_eq!(offset_of!(Elf32CRelHeaderView, count), 4);
+
+        assert_eq!(size_of::<Elf32CRelDirectoryHeaderView>(), 6);
+        assert_eq!(align_of::<Elf32CRelDirectoryHeaderView>(), 1);
+        assert_eq!(offset_of!(Elf32CRelDirectoryHeaderView, header), 0);
+        assert_eq!(offset_of!(Elf32CRelDirectoryHeaderView, count), 5);
+
+        assert_eq!(size_of::<Elf32CRelDirectoryEntryView>(), 9);
+        assert_eq!(align_of::<Elf32CRelDirectoryEntryView>(), 1);
+        assert_eq!(offset_of!(Elf32CRelDirectoryEntryView, relocation_type), 0);
+        assert_eq!(offset_of!(Elf32CRelDirectoryEntryView, count), 1);
+        assert_eq!(offset_of!(Elf32CRelDirectoryEntryView, stream), 5);
+
+        assert_eq!(size_of::<Elf32CRelBasedGroupView>(), 5);
+        assert_eq!(align_of::<Elf32CRelBasedGroupView>(), 1);
+        assert_eq!(offset_of!(Elf32CRelBasedGroupView, relocation_type), 0);
+        assert_eq!(offset_of!(Elf32CRelBasedGroupView, base_address), 1);
+    }
+
+    #[test]
+    fn test_view_header() {
+        let header = Elf32CRelHeaderView::from_bytes(&CREL_DIRECTORY).unwrap();
+        assert_eq!(
+            header.base_address(),
+            u32::from_ne_bytes([0x00, 0x10, 0x00, 0x00])
+        );
+        assert_eq!(header.count(), DIRECTORY);
+        let err = Elf32CRelHeaderView::from_bytes(&CREL_DIRECTORY[..4]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[test]
+    fn test_view_directory() {
+        let header = Elf32CRelDirectoryHeaderView::from_bytes(&CREL_DIRECTORY).unwrap();
+        assert_eq!(header.count(), 2);
+        let directory = Elf32CRelDirectoryEntryView::directory(&CREL_DIRECTORY).unwrap();
+        assert_eq!(directory.len(), 2);
+        assert_eq!(directory[1].relocation_type(), 0x16);
+        assert_eq!(
+            directory[1].count(),
+            u32::from_ne_bytes([0x03, 0x00, 0x00, 0x00])
+        );
+        assert_eq!(
+            directory[1].stream(),
+            u32::from_ne_bytes([0x03, 0x00, 0x00, 0x00])
+        );
+        let err = Elf32CRelDirectoryEntryView::directory(&CREL_DIRECTORY[..23]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let mut data = CREL_DIRECTORY;
+        data[4] = 0x02;
+        let err = Elf32CRelDirectoryEntryView::directory(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        data[4] = BASED;
+        let err = Elf32CRelDirectoryEntryView::directory(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_view_based_group() {
+        let data = [0x16, 0x00, 0x20, 0x00, 0x00, 0x03];
+        let group = Elf32CRelBasedGroupView::from_bytes(&data).unwrap();
+        assert_eq!(group.relocation_type(), 0x16);
+        assert_eq!(
+            group.base_address(),
+            u32::from_ne_bytes([0x00, 0x20, 0x00, 0x00])
+        );
+        let err = Elf32CRelBasedGroupView::from_bytes(&data[..4]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(any(feature = "embedded", feature = "embedded_minimal"))]
 #[test]
 fn test_elf32_relocate() {
-    use relox::elf32_relocate;
+    use relox_core::elf32_relocate;
 
     const CREL1: [u8; 31] = [
         0x00, 0x08, 0x00, 0x40, 0x01, 0x02, 0x18, 0x00, 0x10, 0x08, 0x08, 0x08, 0x10, 0x08, 0x08,
@@ -0,0 +1,407 @@
+//! Read and write Android packed relocation sections
+//!
+//! This module can be used to convert relocation sections packed by the
+//! Android toolchain using the `APS2` encoding
+//! ([SHT_ANDROID_REL](../elf/constant.SHT_ANDROID_REL.html)) from and to
+//! regular ELF32 relocation sections, so they can be compressed by relox and
+//! their sizes compared.
+//!
+//! An `APS2` section starts with the `APS2` magic followed by SLEB128 values:
+//! the number of relocations, the initial offset and groups of relocations.
+//! Every group starts with its size and flags marking whether all of its
+//! relocations share the same `r_info` (1), offset delta (2) or addend (4),
+//! and whether it has addends at all (8). The shared values follow the
+//! flags, the others follow for every relocation of the group.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::crel::{read_sleb128, write_sleb128};
+use crate::{Error, ErrorKind};
+
+/// Size of a regular ELF32 relocation entry.
+const ELF32_REL_SIZE: usize = 8;
+
+/// Magic starting every `APS2` section.
+pub const APS2_MAGIC: [u8; 4] = *b"APS2";
+
+/// Group flag marking relocations sharing `r_info`.
+const GROUPED_BY_INFO: i64 = 1;
+/// Group flag marking relocations sharing the offset delta.
+const GROUPED_BY_OFFSET_DELTA: i64 = 2;
+/// Group flag marking relocations sharing the addend.
+const GROUPED_BY_ADDEND: i64 = 4;
+/// Group flag marking relocations with addends.
+const GROUP_HAS_ADDEND: i64 = 8;
+
+/// Minimum number of relocations sharing `r_info`, offset delta and addend
+/// written as a single group by [aps2_encode](fn.aps2_encode.html).
+const MIN_GROUP_SIZE: usize = 3;
+
+/// A relocation of an `APS2` section.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Aps2Relocation {
+    offset: u32,
+    info: u32,
+    addend: i32,
+}
+
+impl Aps2Relocation {
+    /// Creates a new `Aps2Relocation` instance.
+    pub fn new(offset: u32, info: u32, addend: i32) -> Self {
+        Self {
+            offset,
+            info,
+            addend,
+        }
+    }
+
+    /// Returns the offset of the relocation, `r_offset`.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the symbol index and type of the relocation, `r_info`.
+    pub fn info(&self) -> u32 {
+        self.info
+    }
+
+    /// Returns the index of the symbol the relocation references.
+    pub fn symbol(&self) -> u32 {
+        self.info >> 8
+    }
+
+    /// Returns the type of the relocation.
+    pub fn relocation_type(&self) -> u8 {
+        self.info as u8
+    }
+
+    /// Returns the addend of the relocation, 0 for sections without addends.
+    pub fn addend(&self) -> i32 {
+        self.addend
+    }
+}
+
+/// Decodes an `APS2` section.
+///
+/// Returns the relocations and whether any group of the section has addends.
+///
+/// # Errors
+///
+/// `InvalidData` if the section does not start with
+/// [APS2_MAGIC](constant.APS2_MAGIC.html) or is malformed, or `NotEnoughData`
+/// if it is truncated.
+pub fn aps2_decode(data: &[u8]) -> Result<(Vec<Aps2Relocation>, bool), Error> {
+    if !data.starts_with(&APS2_MAGIC) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let mut index = APS2_MAGIC.len();
+    let count = read_sleb128(data, &mut index)?;
+    if count < 0 {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let mut remaining = count as u64;
+    // Grouped relocations take no space, so the count is not bounded by the
+    // size of the section.
+    let mut relocations = Vec::with_capacity((count as usize).min(data.len()));
+    let mut relocation = Aps2Relocation {
+        offset: read_sleb128(data, &mut index)? as u32,
+        ..Aps2Relocation::default()
+    };
+    let mut addends = false;
+    while remaining > 0 {
+        let size = read_sleb128(data, &mut index)?;
+        let flags = read_sleb128(data, &mut index)?;
+        if size <= 0 || size as u64 > remaining || flags & !0xF != 0 {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        remaining -= size as u64;
+        let mut offset_delta = 0;
+        if flags & GROUPED_BY_OFFSET_DELTA != 0 {
+            offset_delta = read_sleb128(data, &mut index)? as u32;
+        }
+        if flags & GROUPED_BY_INFO != 0 {
+            relocation.info = read_sleb128(data, &mut index)? as u32;
+        }
+        let has_addend = flags & GROUP_HAS_ADDEND != 0;
+        addends |= has_addend;
+        if !has_addend {
+            relocation.addend = 0;
+        } else if flags & GROUPED_BY_ADDEND != 0 {
+            relocation.addend = relocation
+                .addend
+                .wrapping_add(read_sleb128(data, &mut index)? as i32);
+        }
+        for _ in 0..size {
+            if flags & GROUPED_BY_OFFSET_DELTA == 0 {
+                offset_delta = read_sleb128(data, &mut index)? as u32;
+            }
+            relocation.offset = relocation.offset.wrapping_add(offset_delta);
+            if flags & GROUPED_BY_INFO == 0 {
+                relocation.info = read_sleb128(data, &mut index)? as u32;
+            }
+            if has_addend && flags & GROUPED_BY_ADDEND == 0 {
+                relocation.addend = relocation
+                    .addend
+                    .wrapping_add(read_sleb128(data, &mut index)? as i32);
+            }
+            relocations.push(relocation);
+        }
+    }
+    Ok((relocations, addends))
+}
+
+/// Encodes relocations as an `APS2` section, with addends if `addends` is
+/// set.
+///
+/// Runs of relocations sharing `r_info`, offset delta and addend are written
+/// as a single group, the other relocations are written one by one.
+///
+/// # Errors
+///
+/// `InvalidData` if a relocation has an addend and `addends` is not set.
+pub fn aps2_encode(relocations: &[Aps2Relocation], addends: bool) -> Result<Vec<u8>, Error> {
+    if !addends && relocations.iter().any(|relocation| relocation.addend != 0) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let mut data = APS2_MAGIC.to_vec();
+    write_sleb128(&mut data, relocations.len() as i64);
+    write_sleb128(&mut data, 0);
+    let mut encoder = Encoder {
+        data,
+        addends,
+        previous: Aps2Relocation::default(),
+    };
+    let mut single = 0;
+    let mut index = 0;
+    while index < relocations.len() {
+        let previous = match index {
+            0 => 0,
+            _ => relocations[index - 1].offset,
+        };
+        let delta = relocations[index].offset.wrapping_sub(previous);
+        let run = relocations[index + 1..]
+            .iter()
+            .zip(relocations[index..].iter())
+            .take_while(|(relocation, previous)| {
+                relocation.offset.wrapping_sub(previous.offset) == delta
+                    && relocation.info == previous.info
+                    && relocation.addend == previous.addend
+            })
+            .count()
+            + 1;
+        if run < MIN_GROUP_SIZE {
+            index += 1;
+            continue;
+        }
+        encoder.write_singles(&relocations[single..index]);
+        encoder.write_run(&relocations[index..index + run], delta);
+        index += run;
+        single = index;
+    }
+    encoder.write_singles(&relocations[single..]);
+    Ok(encoder.data)
+}
+
+/// State of [aps2_encode](fn.aps2_encode.html).
+struct Encoder {
+    data: Vec<u8>,
+    addends: bool,
+    /// Last written relocation, as seen by the decoder.
+    previous: Aps2Relocation,
+}
+
+impl Encoder {
+    /// Writes relocations sharing `r_info`, offset delta and addend as a
+    /// single group.
+    fn write_run(&mut self, relocations: &[Aps2Relocation], delta: u32) {
+        let first = relocations[0];
+        let mut flags = GROUPED_BY_INFO | GROUPED_BY_OFFSET_DELTA;
+        if self.addends {
+            flags |= GROUPED_BY_ADDEND | GROUP_HAS_ADDEND;
+        }
+        write_sleb128(&mut self.data, relocations.len() as i64);
+        write_sleb128(&mut self.data, flags);
+        write_sleb128(&mut self.data, i64::from(delta as i32));
+        write_sleb128(&mut self.data, i64::from(first.info as i32));
+        if self.addends {
+            self.write_addend(first.addend);
+        }
+        self.previous = relocations[relocations.len() - 1];
+    }
+
+    /// Writes relocations one by one as a single group, sharing `r_info` if
+    /// all of them have the same.
+    fn write_singles(&mut self, relocations: &[Aps2Relocation]) {
+        let first = match relocations.first() {
+            Some(first) => *first,
+            None => return,
+        };
+        let same_info = relocations
+            .iter()
+            .all(|relocation| relocation.info == first.info);
+        let mut flags = 0;
+        if self.addends {
+            flags |= GROUP_HAS_ADDEND;
+        }
+        if same_info {
+            flags |= GROUPED_BY_INFO;
+        }
+        write_sleb128(&mut self.data, relocations.len() as i64);
+        write_sleb128(&mut self.data, flags);
+        if same_info {
+            write_sleb128(&mut self.data, i64::from(first.info as i32));
+        }
+        for relocation in relocations {
+            let delta = relocation.offset.wrapping_sub(self.previous.offset);
+            write_sleb128(&mut self.data, i64::from(delta as i32));
+            if !same_info {
+                write_sleb128(&mut self.data, i64::from(relocation.info as i32));
+            }
+            if self.addends {
+                self.write_addend(relocation.addend);
+            }
+            self.previous.offset = relocation.offset;
+        }
+        self.previous = relocations[relocations.len() - 1];
+    }
+
+    /// Writes the delta of an addend from the previous one.
+    fn write_addend(&mut self, addend: i32) {
+        let delta = addend.wrapping_sub(self.previous.addend);
+        write_sleb128(&mut self.data, i64::from(delta));
+        self.previous.addend = addend;
+    }
+}
+
+/// Converts an `APS2` section without addends into a regular little-endian
+/// ELF32 relocation section.
+///
+/// # Errors
+///
+/// If the section is malformed, or `Unsupported` if it has addends.
+pub fn aps2_to_rel(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let (relocations, addends) = aps2_decode(data)?;
+    if addends {
+        return Err(Error::new(ErrorKind::Unsupported));
+    }
+    let mut rel = vec![0; relocations.len() * ELF32_REL_SIZE];
+    for (entry, relocation) in rel.chunks_mut(ELF32_REL_SIZE).zip(relocations.iter()) {
+        LittleEndian::write_u32(&mut entry[0..4], relocation.offset);
+        LittleEndian::write_u32(&mut entry[4..8], relocation.info);
+    }
+    Ok(rel)
+}
+
+/// Converts a regular little-endian ELF32 relocation section into an `APS2`
+/// section without addends.
+///
+/// # Errors
+///
+/// `InvalidData` if the size of the section is not a multiple of the size of
+/// a relocation.
+pub fn rel_to_aps2(rel: &[u8]) -> Result<Vec<u8>, Error> {
+    if !rel.len().is_multiple_of(ELF32_REL_SIZE) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let relocations: Vec<Aps2Relocation> = rel
+        .chunks(ELF32_REL_SIZE)
+        .map(|entry| {
+            Aps2Relocation::new(
+                LittleEndian::read_u32(&entry[0..4]),
+                LittleEndian::read_u32(&entry[4..8]),
+                0,
+            )
+        })
+        .collect();
+    aps2_encode(&relocations, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Five R_ARM_RELATIVE relocations from 0x1000 to 0x1010 and an
+    // R_ARM_ABS32 against symbol 3 at 0x1020.
+    const APS2: [u8; 22] = [
+        0x41, 0x50, 0x53, 0x32, // magic
+        0x06, // count
+        0x00, // initial offset
+        0x01, 0x01, 0x17, // group of 1, by info, type 23
+        0x80, 0x20, // 0x1000
+        0x04, 0x03, 0x04, 0x17, // group of 4, by info and offset delta 4
+        0x01, 0x01, 0x82, 0x06, // group of 1, by info, symbol 3, type 2
+        0x10, // 0x1020
+        0x00, 0x00, // trailing padding
+    ];
+
+    #[test]
+    fn test_aps2_decode() {
+        let (relocations, addends) = aps2_decode(&APS2).unwrap();
+        assert!(!addends);
+        assert_eq!(
+            relocations,
+            [
+                Aps2Relocation::new(0x1000, 0x17, 0),
+                Aps2Relocation::new(0x1004, 0x17, 0),
+                Aps2Relocation::new(0x1008, 0x17, 0),
+                Aps2Relocation::new(0x100C, 0x17, 0),
+                Aps2Relocation::new(0x1010, 0x17, 0),
+                Aps2Relocation::new(0x1020, 0x302, 0),
+            ]
+        );
+        assert_eq!(relocations[5].symbol(), 3);
+        assert_eq!(relocations[5].relocation_type(), 2);
+        let err = aps2_decode(&APS2[..17]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = aps2_decode(&APS2[1..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut data = APS2;
+        data[6] = 0x07;
+        let err = aps2_decode(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_aps2_encode() {
+        let (relocations, _) = aps2_decode(&APS2).unwrap();
+        assert_eq!(aps2_encode(&relocations, false).unwrap(), APS2[..20]);
+        let err = aps2_encode(&[Aps2Relocation::new(0, 2, 4)], false).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_aps2_encode_addends() {
+        let relocations = [
+            Aps2Relocation::new(0x2000, 0x17, -8),
+            Aps2Relocation::new(0x1000, 0x102, 0x1234),
+            Aps2Relocation::new(0x1008, 0x102, 0x1234),
+            Aps2Relocation::new(0x1010, 0x102, 0x1234),
+            Aps2Relocation::new(0x1018, 0x102, 0x1234),
+            Aps2Relocation::new(0x8000_0000, 0x17, 0),
+        ];
+        let data = aps2_encode(&relocations, true).unwrap();
+        assert_eq!(aps2_decode(&data).unwrap(), (relocations.to_vec(), true));
+        let data = aps2_encode(&relocations[5..], true).unwrap();
+        assert_eq!(
+            aps2_decode(&data).unwrap(),
+            (relocations[5..].to_vec(), true)
+        );
+    }
+
+    #[test]
+    fn test_aps2_rel() {
+        let rel = aps2_to_rel(&APS2).unwrap();
+        assert_eq!(rel.len(), 48);
+        assert_eq!(
+            rel[40..48],
+            [0x20, 0x10, 0x00, 0x00, 0x02, 0x03, 0x00, 0x00]
+        );
+        assert_eq!(rel_to_aps2(&rel).unwrap(), APS2[..20]);
+
+        let data = aps2_encode(&[Aps2Relocation::new(0, 2, 4)], true).unwrap();
+        let err = aps2_to_rel(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = rel_to_aps2(&rel[..7]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,260 @@
+//! Read uClinux FLAT binaries
+//!
+//! This module can be used to read the relocation table of uClinux FLAT
+//! (bFLT) binaries, used by MMU-less Linux targets, and to compress it.
+//! [bflt_relocate](../fn.bflt_relocate.html) applies the compressed table at
+//! load time.
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::{Elf32Relocs, Endianness, Error, ErrorKind, BFLT_RELOCATION_TYPE};
+
+/// Magic number of bFLT headers.
+pub const BFLT_MAGIC: &[u8; 4] = b"bFLT";
+/// Supported revision of the bFLT format.
+pub const BFLT_VERSION: u32 = 4;
+/// Flag marking bFLT binaries whose text and data are compressed.
+pub const FLAT_FLAG_GZIP: u32 = 0x4;
+/// Flag marking bFLT binaries whose data is compressed.
+pub const FLAT_FLAG_GZDATA: u32 = 0x8;
+
+/// Size of the bFLT header.
+const BFLT_HEADER_SIZE: usize = 64;
+
+/// A uClinux FLAT binary.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BfltFile<'a> {
+    data: &'a [u8],
+    entry: u32,
+    data_start: u32,
+    data_end: u32,
+    bss_end: u32,
+    reloc_start: u32,
+    reloc_count: u32,
+    flags: u32,
+}
+
+impl<'a> BfltFile<'a> {
+    /// Parses a bFLT binary.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is not a bFLT binary, or `Unsupported` if it is not of
+    /// revision 4 or if it is compressed.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let header = data
+            .get(..BFLT_HEADER_SIZE)
+            .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+        if &header[0..4] != BFLT_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let flags = BigEndian::read_u32(&header[36..40]);
+        if BigEndian::read_u32(&header[4..8]) != BFLT_VERSION
+            || flags & (FLAT_FLAG_GZIP | FLAT_FLAG_GZDATA) != 0
+        {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        let file = Self {
+            data,
+            entry: BigEndian::read_u32(&header[8..12]),
+            data_start: BigEndian::read_u32(&header[12..16]),
+            data_end: BigEndian::read_u32(&header[16..20]),
+            bss_end: BigEndian::read_u32(&header[20..24]),
+            reloc_start: BigEndian::read_u32(&header[28..32]),
+            reloc_count: BigEndian::read_u32(&header[32..36]),
+            flags,
+        };
+        if file.data_start > file.data_end
+            || file.data_end > file.bss_end
+            || (file.data_end as usize) > data.len()
+        {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        file.relocation_table()?;
+        Ok(file)
+    }
+
+    /// Returns the offset of the entry point in the text segment.
+    pub fn entry(&self) -> u32 {
+        self.entry
+    }
+
+    /// Returns the offset of the data segment, which is also the size of the
+    /// text segment.
+    pub fn data_start(&self) -> u32 {
+        self.data_start
+    }
+
+    /// Returns the offset of the end of the data segment.
+    pub fn data_end(&self) -> u32 {
+        self.data_end
+    }
+
+    /// Returns the offset of the end of the bss segment.
+    pub fn bss_end(&self) -> u32 {
+        self.bss_end
+    }
+
+    /// Returns the flags of the binary.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns the relocation table, `reloc_count` big-endian offsets of the
+    /// relocated words relative to the start of the text segment.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation table is outside of the file.
+    pub fn relocation_table(&self) -> Result<&'a [u8], Error> {
+        let start = self.reloc_start as usize;
+        (self.reloc_count as usize)
+            .checked_mul(4)
+            .and_then(|size| start.checked_add(size))
+            .and_then(|end| self.data.get(start..end))
+            .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))
+    }
+
+    /// Returns the offsets of the relocated words.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation table is outside of the file.
+    pub fn relocations(&self) -> Result<Vec<u32>, Error> {
+        Ok(self
+            .relocation_table()?
+            .chunks(4)
+            .map(BigEndian::read_u32)
+            .collect())
+    }
+
+    /// Compresses the relocation table for a target of the given byte order.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation table is outside of the file, or `InvalidData` if a
+    /// relocated word is outside of the segments.
+    pub fn compress_relocations(&self, endianness: Endianness) -> Result<Vec<u8>, Error> {
+        let mut relocations = self.relocations()?;
+        if relocations
+            .iter()
+            .any(|offset| offset.checked_add(4).is_none_or(|end| end > self.bss_end))
+        {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        relocations.sort_unstable();
+        let mut entries = vec![0; relocations.len() * 8];
+        for (entry, offset) in entries.chunks_mut(8).zip(relocations.iter()) {
+            let info = u32::from(BFLT_RELOCATION_TYPE);
+            match endianness {
+                Endianness::Little => {
+                    LittleEndian::write_u32(&mut entry[0..4], *offset);
+                    LittleEndian::write_u32(&mut entry[4..8], info);
+                }
+                Endianness::Big => {
+                    BigEndian::write_u32(&mut entry[0..4], *offset);
+                    BigEndian::write_u32(&mut entry[4..8], info);
+                }
+            }
+        }
+        let mut output = Vec::new();
+        let mut relocs = Elf32Relocs::new(&entries);
+        relocs.set_endianness(endianness);
+        relocs.compress_to_writer(&mut output)?;
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a bFLT binary with the given text, data and relocation table.
+    fn build_bflt(text: &[u8], data: &[u8], relocations: &[u32]) -> Vec<u8> {
+        let data_start = (BFLT_HEADER_SIZE + text.len()) as u32;
+        let data_end = data_start + data.len() as u32;
+        let mut file = vec![0; BFLT_HEADER_SIZE];
+        file[0..4].copy_from_slice(BFLT_MAGIC);
+        for (index, value) in [
+            BFLT_VERSION,
+            BFLT_HEADER_SIZE as u32,
+            data_start,
+            data_end,
+            data_end + 16,
+            4096,
+            data_end,
+            relocations.len() as u32,
+        ]
+        .iter()
+        .enumerate()
+        {
+            BigEndian::write_u32(&mut file[4 + index * 4..8 + index * 4], *value);
+        }
+        file.extend_from_slice(text);
+        file.extend_from_slice(data);
+        for offset in relocations.iter() {
+            file.extend_from_slice(&offset.to_be_bytes());
+        }
+        file
+    }
+
+    #[test]
+    fn test_bflt_parse() {
+        let data = build_bflt(&[0; 8], &[0; 8], &[0x48, 0x40]);
+        let file = BfltFile::parse(&data).unwrap();
+        assert_eq!(file.entry(), 64);
+        assert_eq!(file.data_start(), 72);
+        assert_eq!(file.data_end(), 80);
+        assert_eq!(file.bss_end(), 96);
+        assert_eq!(file.flags(), 0);
+        assert_eq!(file.relocations().unwrap(), [0x48, 0x40]);
+
+        let err = BfltFile::parse(&data[..data.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let mut data = data;
+        data[7] = 2;
+        let err = BfltFile::parse(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        data[0] = b'B';
+        let err = BfltFile::parse(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_bflt_compress_relocations() {
+        let data = build_bflt(&[0; 8], &[0; 8], &[0x48, 0x40, 0x4C]);
+        let file = BfltFile::parse(&data).unwrap();
+        let compressed = file.compress_relocations(Endianness::Little).unwrap();
+        assert_eq!(
+            compressed,
+            [
+                0x40,
+                0x00,
+                0x00,
+                0x00,
+                0x01,
+                BFLT_RELOCATION_TYPE,
+                0x03,
+                0x00,
+                0x08,
+                0x04
+            ]
+        );
+        let mut text = data[..72].to_vec();
+        let mut segment = data[72..80].to_vec();
+        segment.resize(32, 0);
+        segment[0..4].copy_from_slice(&0x44u32.to_ne_bytes());
+        segment[4..8].copy_from_slice(&0x4Cu32.to_ne_bytes());
+        crate::bflt_relocate(&compressed, &mut text, &mut segment, 0x1000, 0x8000).unwrap();
+        assert_eq!(text[0x40..0x44], 0x1000u32.to_ne_bytes());
+        assert_eq!(segment[0..4], 0x1044u32.to_ne_bytes());
+        assert_eq!(segment[4..8], 0x8004u32.to_ne_bytes());
+        let compressed = file.compress_relocations(Endianness::Big).unwrap();
+        assert_eq!(compressed[..4], [0x00, 0x00, 0x00, 0x40]);
+
+        let data = build_bflt(&[0; 8], &[0; 8], &[0x5E]);
+        let file = BfltFile::parse(&data).unwrap();
+        let err = file.compress_relocations(Endianness::Little).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,53 @@
+//! `relox append` command
+//!
+//! Appends the relocations of a regular relocation section as new groups to a
+//! compressed relocation section, e.g. to add plugin relocations after the
+//! base image is built.
+
+use relox::{Elf32Rel, Elf32Relocs};
+
+use crate::batch::{read_file, status, write_file, STDIO};
+use crate::format::{read_input, Format};
+
+/// Runs the `append` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut section = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("-o requires a file name")?),
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            _ if inputs.len() < 2 => inputs.push(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let compressed = inputs.first().ok_or("missing compressed input file")?;
+    let input = inputs.get(1).ok_or("missing input file")?;
+    let output = output.ok_or("missing output file")?;
+    if *compressed == STDIO && *input == STDIO {
+        return Err("standard input can only be read once".to_string());
+    }
+
+    let blob = read_file(compressed)?;
+    let data = read_input(input, section.map(String::as_str))?;
+    // The compressor uses the first relocation as lowest offset.
+    let mut relocations = Format::Rel.decode(&data)?;
+    relocations.sort_by_key(Elf32Rel::offset);
+    let rel = Format::Rel.encode(&relocations)?;
+    let appended = Elf32Relocs::new(&rel)
+        .append_to(&blob)
+        .map_err(|err| format!("appending failed: {:?}", err.kind()))?;
+    write_file(output, &appended)?;
+    status(
+        output,
+        &format!(
+            "{} relocations appended, {} bytes -> {} bytes",
+            relocations.len(),
+            blob.len(),
+            appended.len()
+        ),
+    );
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Batch processing and standard stream support
+//!
+//! Commands accept several inputs, including wildcard patterns expanded here
+//! for build systems which do not run a shell, and `-` to read an input from
+//! standard input or write an output to standard output, so they can be used
+//! in pipelines without temporary files.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// File name standing for standard input or output.
+pub const STDIO: &str = "-";
+
+/// Expands the wildcards `*` and `?` in the file names of `patterns`, in
+/// sorted order. Patterns without wildcards are kept as they are.
+pub fn expand_inputs(patterns: &[&String]) -> Result<Vec<String>, String> {
+    let mut inputs = Vec::new();
+    for pattern in patterns.iter() {
+        if !pattern.contains(['*', '?']) {
+            inputs.push(pattern.to_string());
+            continue;
+        }
+        let path = Path::new(pattern.as_str());
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        if directory.to_string_lossy().contains(['*', '?']) {
+            return Err(format!(
+                "{}: wildcards are only supported in file names",
+                pattern
+            ));
+        }
+        let entries = fs::read_dir(directory).map_err(|err| format!("{}: {}", pattern, err))?;
+        let mut matches = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|err| format!("{}: {}", pattern, err))?;
+            let file_name = entry.file_name();
+            let file_name = match file_name.to_str() {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+            if wildcard_match(name.as_bytes(), file_name.as_bytes()) && entry.path().is_file() {
+                matches.push(
+                    path.with_file_name(file_name)
+                        .to_string_lossy()
+                        .into_owned(),
+                );
+            }
+        }
+        if matches.is_empty() {
+            return Err(format!("{}: no matching files", pattern));
+        }
+        matches.sort();
+        inputs.append(&mut matches);
+    }
+    if inputs.iter().filter(|input| *input == STDIO).count() > 1 {
+        return Err("standard input can only be read once".to_string());
+    }
+    Ok(inputs)
+}
+
+/// Returns `true` if `name` matches `pattern`, in which `*` matches any
+/// sequence of characters and `?` any single character.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| wildcard_match(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((byte, rest)) => name.first() == Some(byte) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+/// Reads a file, or standard input if `path` is `-`.
+pub fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    if path != STDIO {
+        return fs::read(path).map_err(|err| format!("{}: {}", path, err));
+    }
+    let mut data = Vec::new();
+    io::stdin()
+        .read_to_end(&mut data)
+        .map_err(|err| format!("standard input: {}", err))?;
+    Ok(data)
+}
+
+/// Writes a file, or standard output if `path` is `-`.
+pub fn write_file(path: &str, data: &[u8]) -> Result<(), String> {
+    if path != STDIO {
+        return fs::write(path, data).map_err(|err| format!("{}: {}", path, err));
+    }
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(data)
+        .and_then(|_| stdout.flush())
+        .map_err(|err| format!("standard output: {}", err))
+}
+
+/// Prints a status message about writing `output`, to standard error if the
+/// output goes to standard output.
+pub fn status(output: &str, message: &str) {
+    match output {
+        STDIO => eprintln!("{}", message),
+        _ => println!("{}", message),
+    }
+}
+
+/// Returns the output file of `input` in `directory` in a batch, named after
+/// the input with the given extension.
+pub fn batch_output(directory: &str, input: &str, extension: &str) -> Result<String, String> {
+    if input == STDIO {
+        return Err("standard input cannot be used with --out-dir".to_string());
+    }
+    let stem = Path::new(input)
+        .file_stem()
+        .ok_or_else(|| format!("{}: not a file name", input))?;
+    let output = Path::new(directory).join(stem).with_extension(extension);
+    Ok(output.to_string_lossy().into_owned())
+}
+
+/// Prints the header of the output of `input` if a batch has several inputs.
+pub fn print_header(inputs: &[String], input: &str) {
+    if inputs.len() > 1 {
+        println!("==> {} <==", input);
+    }
+}
@@ -0,0 +1,190 @@
+//! `relox bench` command
+//!
+//! Times compression and host-simulated decompression of a raw ELF32
+//! relocation section for every available codec.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use relox::{elf32_relocate_symbols, Codec as OffsetCodec, Elf32Relocs, Error};
+
+use crate::format::{max_compressed_size, read_input, ELF32_REL_SIZE};
+
+/// Default number of iterations.
+const DEFAULT_ITERATIONS: u32 = 1000;
+
+/// A compression scheme which can be benchmarked.
+struct Codec {
+    /// Name of the codec.
+    name: &'static str,
+    /// Compresses a regular relocation section into the provided buffer.
+    compress: fn(&[u8], &mut [u8]) -> Result<usize, Error>,
+    /// Decompresses a compressed relocation section.
+    decompress: fn(&[u8]) -> Result<usize, Error>,
+}
+
+/// Available codecs.
+const CODECS: &[Codec] = &[
+    Codec {
+        name: "uleb128",
+        compress: compress_uleb128,
+        decompress: decompress_section,
+    },
+    Codec {
+        name: "group-varint",
+        compress: compress_group_varint,
+        decompress: decompress_section,
+    },
+    Codec {
+        name: "bitmap",
+        compress: compress_bitmap,
+        decompress: decompress_section,
+    },
+    Codec {
+        name: "repeat",
+        compress: compress_repeat,
+        decompress: decompress_section,
+    },
+    Codec {
+        name: "scaled",
+        compress: compress_scaled,
+        decompress: decompress_section,
+    },
+];
+
+/// Compresses a relocation section using the ULEB128 codec only.
+fn compress_uleb128(input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut relocs = Elf32Relocs::new(input);
+    relocs.set_repeat_offsets(false);
+    relocs.compress(output)
+}
+
+/// Compresses a relocation section using the group-varint codec.
+fn compress_group_varint(input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut relocs = Elf32Relocs::new(input);
+    relocs.set_codec(OffsetCodec::GroupVarint);
+    relocs.set_repeat_offsets(false);
+    relocs.compress(output)
+}
+
+/// Compresses a relocation section using the bitmap codec.
+fn compress_bitmap(input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut relocs = Elf32Relocs::new(input);
+    relocs.set_codec(OffsetCodec::Bitmap);
+    relocs.set_repeat_offsets(false);
+    relocs.compress(output)
+}
+
+/// Compresses a relocation section using the default format, with
+/// repeated offsets where they are smaller.
+fn compress_repeat(input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    Elf32Relocs::new(input).compress(output)
+}
+
+/// Compresses a relocation section using the ULEB128 codec with scaled
+/// offsets.
+fn compress_scaled(input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut relocs = Elf32Relocs::new(input);
+    relocs.set_repeat_offsets(false);
+    relocs.set_scaled_offsets(true);
+    relocs.compress(output)
+}
+
+/// Decompresses a relocation section using any codec.
+fn decompress_section(input: &[u8]) -> Result<usize, Error> {
+    let mut checksum: u32 = 0;
+    let read = elf32_relocate_symbols(input, &mut |relocation_type, address, symbol| {
+        checksum = checksum.wrapping_add(address ^ relocation_type as u32 ^ symbol << 8);
+        Ok(())
+    })?;
+    black_box(checksum);
+    Ok(read)
+}
+
+/// Formats the throughput and per relocation cost of a measurement.
+fn format_rate(bytes: usize, relocations: usize, elapsed: Duration, iterations: u32) -> String {
+    let seconds = elapsed.as_secs_f64();
+    let total_bytes = bytes as f64 * iterations as f64;
+    let total_relocations = relocations as f64 * iterations as f64;
+    let mb_per_s = if seconds > 0.0 {
+        total_bytes / seconds / 1_000_000.0
+    } else {
+        f64::INFINITY
+    };
+    let ns_per_relocation = if total_relocations > 0.0 {
+        elapsed.as_nanos() as f64 / total_relocations
+    } else {
+        0.0
+    };
+    format!("{:>11.2} {:>11.2}", mb_per_s, ns_per_relocation)
+}
+
+/// Benchmarks a single codec and prints the results.
+fn bench_codec(codec: &Codec, input: &[u8], iterations: u32) -> Result<(), String> {
+    let relocations = input.len() / ELF32_REL_SIZE;
+    let mut output = vec![0; max_compressed_size(input.len())];
+
+    let start = Instant::now();
+    let mut written = 0;
+    for _ in 0..iterations {
+        written = (codec.compress)(black_box(input), &mut output)
+            .map_err(|err| format!("{}: compression failed: {:?}", codec.name, err.kind()))?;
+    }
+    let compress_elapsed = start.elapsed();
+
+    let compressed = &output[..written];
+    let start = Instant::now();
+    for _ in 0..iterations {
+        (codec.decompress)(black_box(compressed))
+            .map_err(|err| format!("{}: decompression failed: {:?}", codec.name, err.kind()))?;
+    }
+    let decompress_elapsed = start.elapsed();
+
+    println!(
+        "{:<12} {:>10} {} {}",
+        codec.name,
+        written,
+        format_rate(input.len(), relocations, compress_elapsed, iterations),
+        format_rate(input.len(), relocations, decompress_elapsed, iterations),
+    );
+    Ok(())
+}
+
+/// Runs the `bench` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input = None;
+    let mut section = None;
+    let mut iterations = DEFAULT_ITERATIONS;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" => {
+                iterations = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|value| *value > 0)
+                    .ok_or("--iterations requires a positive number")?;
+            }
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let input = input.ok_or("missing input file")?;
+    let data = read_input(input, section.map(String::as_str))?;
+
+    println!(
+        "{} relocations, {} bytes, {} iterations",
+        data.len() / ELF32_REL_SIZE,
+        data.len(),
+        iterations
+    );
+    println!(
+        "{:<12} {:>10} {:>11} {:>11} {:>11} {:>11}",
+        "codec", "size", "comp MB/s", "ns/reloc", "decomp MB/s", "ns/reloc"
+    );
+    for codec in CODECS {
+        bench_codec(codec, &data, iterations)?;
+    }
+    Ok(())
+}
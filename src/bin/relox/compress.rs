@@ -0,0 +1,81 @@
+//! `relox compress` command
+//!
+//! Compresses a relocation section of an ELF file in place, as a post-link
+//! step: the compressed section is added next to the original one, which is
+//! kept, zero-filled or removed.
+
+use relox::elf::{Elf32File, OriginalSection};
+
+use crate::batch::{status, write_file};
+use crate::format::read_elf;
+
+/// Section compressed if no section is requested.
+const DEFAULT_SECTION: &str = ".rel.dyn";
+
+/// Runs the `compress` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input = None;
+    let mut output = None;
+    let mut section = None;
+    let mut output_section = None;
+    let mut original = OriginalSection::Keep;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("-o requires a file name")?),
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            "--output-section" => {
+                output_section = Some(args.next().ok_or("--output-section requires a name")?)
+            }
+            "--zero-fill" => original = OriginalSection::ZeroFill,
+            "--remove" => original = OriginalSection::Remove,
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let input = input.ok_or("missing input file")?;
+    let output = output.ok_or("missing output file")?;
+    let section = section.map_or(DEFAULT_SECTION, String::as_str);
+    let output_section = match output_section {
+        Some(name) => name.clone(),
+        None => compressed_name(section),
+    };
+
+    let data = read_elf(input)?;
+    let file = Elf32File::parse(&data).map_err(|_| format!("{}: malformed ELF file", input))?;
+    let original_size = file
+        .section_by_name(section)
+        .ok_or_else(|| format!("{}: no section named `{}`", input, section))?
+        .size();
+    if file.section_by_name(&output_section).is_some() {
+        return Err(format!(
+            "{}: section `{}` already exists",
+            input, output_section
+        ));
+    }
+    let replaced = file
+        .replace_relocation_section(section, &output_section, original)
+        .map_err(|err| format!("{}: compression failed: {:?}", input, err.kind()))?;
+    let compressed_size = Elf32File::parse(&replaced)
+        .ok()
+        .and_then(|file| file.section_by_name(&output_section).map(|s| s.size()))
+        .unwrap_or(0);
+    write_file(output, &replaced)?;
+    status(
+        output,
+        &format!(
+            "`{}` ({} bytes) -> `{}` ({} bytes)",
+            section, original_size, output_section, compressed_size
+        ),
+    );
+    Ok(())
+}
+
+/// Returns the name of the compressed variant of a relocation section, e.g.
+/// `.crel.dyn` for `.rel.dyn`.
+fn compressed_name(section: &str) -> String {
+    match section.strip_prefix(".rel") {
+        Some(suffix) => format!(".crel{}", suffix),
+        None => format!(".crel{}", section),
+    }
+}
@@ -0,0 +1,138 @@
+//! `relox convert` command
+//!
+//! Transcodes relocation sections from one format to another, and
+//! recompresses compressed sections with other compressor options.
+
+use relox::elf::relative_type;
+use relox::{Codec, Encoding};
+
+use crate::batch::{batch_output, expand_inputs, status, write_file, STDIO};
+use crate::format::{read_input, Format, FormatOptions};
+
+/// Runs the `convert` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut section = None;
+    let mut output = None;
+    let mut out_dir = None;
+    let mut from = None;
+    let mut to = None;
+    let mut options = FormatOptions::default();
+    let mut compressor_options = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("-o requires a file name")?),
+            "--from" => {
+                from = Some(Format::from_name(
+                    args.next().ok_or("--from requires a format")?,
+                )?)
+            }
+            "--to" => {
+                to = Some(Format::from_name(
+                    args.next().ok_or("--to requires a format")?,
+                )?)
+            }
+            "--machine" => {
+                let value = args.next().ok_or("--machine requires a number")?;
+                let machine = value
+                    .parse::<u16>()
+                    .map_err(|_| format!("invalid machine `{}`", value))?;
+                options.relative_type = Some(relative_type(machine).ok_or_else(|| {
+                    format!("unknown relative relocation type of machine {}", machine)
+                })?);
+            }
+            "--encoding" => {
+                options.encoding =
+                    parse_encoding(args.next().ok_or("--encoding requires a name")?)?;
+                compressor_options = true;
+            }
+            "--codec" => {
+                options.codec = parse_codec(args.next().ok_or("--codec requires a name")?)?;
+                compressor_options = true;
+            }
+            "--no-repeat-offsets" => {
+                options.repeat_offsets = false;
+                compressor_options = true;
+            }
+            "--scaled-offsets" => {
+                options.scaled_offsets = true;
+                compressor_options = true;
+            }
+            "--out-dir" => out_dir = Some(args.next().ok_or("--out-dir requires a directory")?),
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            _ if arg.starts_with('-') && arg != STDIO => {
+                return Err(format!("unexpected argument `{}`", arg))
+            }
+            _ => inputs.push(arg),
+        }
+    }
+    let inputs = expand_inputs(&inputs)?;
+    if inputs.is_empty() {
+        return Err("missing input file".to_string());
+    }
+    let from = from.ok_or("missing input format")?;
+    let to = to.ok_or("missing output format")?;
+    if compressor_options && to != Format::Relox {
+        return Err("compressor options require --to relox".to_string());
+    }
+    let outputs = match (output, out_dir) {
+        (Some(_), Some(_)) => return Err("-o and --out-dir are exclusive".to_string()),
+        (Some(output), None) if inputs.len() == 1 => vec![output.to_string()],
+        (Some(_), None) => return Err("several inputs require --out-dir".to_string()),
+        (None, Some(directory)) => inputs
+            .iter()
+            .map(|input| batch_output(directory, input, to.name()))
+            .collect::<Result<_, _>>()?,
+        (None, None) => return Err("missing output file".to_string()),
+    };
+
+    for (input, output) in inputs.iter().zip(outputs.iter()) {
+        let data = read_input(input, section.map(String::as_str))?;
+        let relocations = from.decode_with(&data, &options)?;
+        let converted = to.encode_with(&relocations, &options)?;
+        write_file(output, &converted)?;
+        let prefix = match inputs.len() {
+            1 => String::new(),
+            _ => format!("{} -> {}: ", input, output),
+        };
+        status(
+            output,
+            &format!(
+                "{}{} relocations, {} bytes -> {} bytes",
+                prefix,
+                relocations.len(),
+                data.len(),
+                converted.len()
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Returns the encoding with the given name.
+fn parse_encoding(name: &str) -> Result<Encoding, String> {
+    match name {
+        "auto" => Ok(Encoding::Auto),
+        "grouped" => Ok(Encoding::Grouped),
+        "interleaved" => Ok(Encoding::Interleaved),
+        "directory" => Ok(Encoding::Directory),
+        _ => Err(format!(
+            "unknown encoding `{}` (expected auto, grouped, interleaved or directory)",
+            name
+        )),
+    }
+}
+
+/// Returns the codec with the given name.
+fn parse_codec(name: &str) -> Result<Codec, String> {
+    match name {
+        "uleb128" => Ok(Codec::Uleb128),
+        "group-varint" => Ok(Codec::GroupVarint),
+        "bitmap" => Ok(Codec::Bitmap),
+        _ => Err(format!(
+            "unknown codec `{}` (expected uleb128, group-varint or bitmap)",
+            name
+        )),
+    }
+}
@@ -0,0 +1,128 @@
+//! `relox explain` command
+//!
+//! Prints an annotated hex dump of a compressed relocation section, e.g. to
+//! debug format mismatches between versions of relox.
+
+use relox::{explain, Annotation, Endianness};
+
+use crate::batch::{expand_inputs, print_header, STDIO};
+use crate::format::{read_input, Format};
+use crate::json;
+
+/// Number of bytes printed per line.
+const BYTES_PER_LINE: usize = 8;
+
+/// Runs the `explain` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut section = None;
+    let mut machine = None;
+    let mut output_json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => output_json = true,
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            "--machine" => {
+                let value = args.next().ok_or("--machine requires a number")?;
+                machine = Some(
+                    value
+                        .parse::<u16>()
+                        .map_err(|_| format!("invalid machine `{}`", value))?,
+                );
+            }
+            _ if arg.starts_with('-') && arg != STDIO => {
+                return Err(format!("unexpected argument `{}`", arg))
+            }
+            _ => inputs.push(arg),
+        }
+    }
+    let inputs = expand_inputs(&inputs)?;
+    if inputs.is_empty() {
+        return Err("missing input file".to_string());
+    }
+
+    for input in inputs.iter() {
+        let data = read_input(input, section.map(String::as_str))?;
+        if !output_json {
+            print_header(&inputs, input);
+        }
+        explain_section(input, &data, machine, output_json)?;
+    }
+    Ok(())
+}
+
+/// Explains a compressed relocation section read from `input`.
+fn explain_section(
+    input: &str,
+    data: &[u8],
+    machine: Option<u16>,
+    output_json: bool,
+) -> Result<(), String> {
+    let annotations = explain(data, Endianness::Little, machine);
+    if output_json {
+        return print_json(data, &annotations);
+    }
+    for annotation in annotations.iter() {
+        let bytes = annotation.bytes(data);
+        let mut lines = bytes.chunks(BYTES_PER_LINE);
+        let first = lines.next().unwrap_or(&[]);
+        println!(
+            "{:08x}  {:<width$}  {}",
+            annotation.offset(),
+            hex(first),
+            annotation.description(),
+            width = 3 * BYTES_PER_LINE - 1
+        );
+        for (number, line) in lines.enumerate() {
+            let offset = annotation.offset() + (number + 1) * BYTES_PER_LINE;
+            println!("{:08x}  {}", offset, hex(line));
+        }
+    }
+    match annotations.last() {
+        Some(annotation) if annotation.is_error() => Err(format!(
+            "{}: {} at offset {:#x}",
+            input,
+            annotation.description(),
+            annotation.offset()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Prints the decoded relocations, a summary of their groups, the result of
+/// validating the section and its annotations as JSON.
+fn print_json(data: &[u8], annotations: &[Annotation]) -> Result<(), String> {
+    let decoded = Format::Relox.decode(data);
+    let relocations = decoded.as_ref().map(Vec::as_slice).unwrap_or(&[]);
+    let [valid, error] = json::validation(decoded.as_ref().err().map(String::as_str));
+    let annotations = json::array(annotations.iter().map(|annotation| {
+        json::object(&[
+            ("offset", annotation.offset().to_string()),
+            ("length", annotation.length().to_string()),
+            ("bytes", json::string(&hex(annotation.bytes(data)))),
+            ("description", json::string(annotation.description())),
+        ])
+    }));
+    println!(
+        "{}",
+        json::object(&[
+            ("size", data.len().to_string()),
+            valid,
+            error,
+            ("relocations", json::relocations(relocations)),
+            ("groups", json::groups(relocations)),
+            ("annotations", annotations),
+        ])
+    );
+    decoded.map(|_| ())
+}
+
+/// Formats bytes as space separated hexadecimal numbers.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
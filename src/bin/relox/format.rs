@@ -0,0 +1,298 @@
+//! Relocation formats understood by the command line interface
+
+use relox::aps2::{aps2_to_rel, rel_to_aps2};
+use relox::crel::{crel_to_rel, rel_to_crel};
+use relox::elf::{Elf32File, ElfClass, ElfIdent, DT_REL};
+use relox::relr::{rel_to_relr, relr_to_rel};
+use relox::{elf32_relocate_symbols, Codec, Elf32Rel, Elf32RelocsBuilder, Encoding, Endianness};
+
+use crate::batch::read_file;
+
+/// Section read from ELF inputs if none is requested.
+pub const DEFAULT_SECTION: &str = ".rel.dyn";
+
+/// Size of a regular ELF32 relocation entry.
+pub const ELF32_REL_SIZE: usize = 8;
+
+/// Supported relocation formats.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    /// Regular ELF32 relocation section (`.rel.*`).
+    Rel,
+    /// Compressed relocation section produced by relox.
+    Relox,
+    /// CREL relocation section packed by LLVM or binutils (`.crel.*`).
+    Crel,
+    /// Android packed relocation section (`APS2`).
+    Aps2,
+    /// RELR relative relocation section (`.relr.*`).
+    Relr,
+}
+
+/// Options of the formats whose encoding can be tuned.
+#[derive(Copy, Clone, Debug)]
+pub struct FormatOptions {
+    /// Type of the relocations decoded from RELR sections, the type of
+    /// relative relocations of the target.
+    pub relative_type: Option<u8>,
+    /// Encoding of compressed sections.
+    pub encoding: Encoding,
+    /// Codec of the offsets of compressed sections.
+    pub codec: Codec,
+    /// Whether compressed sections repeat offsets with opcodes.
+    pub repeat_offsets: bool,
+    /// Whether compressed sections scale offsets.
+    pub scaled_offsets: bool,
+}
+
+impl Default for FormatOptions {
+    /// Returns the defaults of the compressor.
+    fn default() -> Self {
+        Self {
+            relative_type: None,
+            encoding: Encoding::Auto,
+            codec: Codec::Uleb128,
+            repeat_offsets: true,
+            scaled_offsets: false,
+        }
+    }
+}
+
+impl Format {
+    /// All supported formats holding any relocation, unlike RELR which only
+    /// holds relative relocations.
+    pub const ALL: [Format; 4] = [Format::Rel, Format::Relox, Format::Crel, Format::Aps2];
+
+    /// Returns the name of the format.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Rel => "rel",
+            Format::Relox => "relox",
+            Format::Crel => "crel",
+            Format::Aps2 => "aps2",
+            Format::Relr => "relr",
+        }
+    }
+
+    /// Returns the format with the given name.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "rel" => Ok(Format::Rel),
+            "relox" => Ok(Format::Relox),
+            "crel" => Ok(Format::Crel),
+            "aps2" => Ok(Format::Aps2),
+            "relr" => Ok(Format::Relr),
+            _ => Err(format!(
+                "unknown format `{}` (expected rel, relox, crel, aps2 or relr)",
+                name
+            )),
+        }
+    }
+
+    /// Decodes all relocations from data encoded in this format.
+    pub fn decode(self, data: &[u8]) -> Result<Vec<Elf32Rel>, String> {
+        self.decode_with(data, &FormatOptions::default())
+    }
+
+    /// Decodes all relocations from data encoded in this format with the
+    /// given options.
+    pub fn decode_with(
+        self,
+        data: &[u8],
+        options: &FormatOptions,
+    ) -> Result<Vec<Elf32Rel>, String> {
+        match self {
+            Format::Rel => {
+                if !data.len().is_multiple_of(ELF32_REL_SIZE) {
+                    return Err("size of relocation section is not a multiple of 8".to_string());
+                }
+                Ok(data
+                    .chunks(ELF32_REL_SIZE)
+                    .map(|entry| {
+                        let offset = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+                        let symbol = u32::from_le_bytes([entry[5], entry[6], entry[7], 0]);
+                        Elf32Rel::with_symbol(offset, entry[4], symbol)
+                    })
+                    .collect())
+            }
+            Format::Relox => {
+                let mut relocations = Vec::new();
+                elf32_relocate_symbols(data, &mut |relocation_type, address, symbol| {
+                    relocations.push(Elf32Rel::with_symbol(address, relocation_type, symbol));
+                    Ok(())
+                })
+                .map_err(|err| format!("decompression failed: {:?}", err.kind()))?;
+                Ok(relocations)
+            }
+            Format::Crel => {
+                let rel = crel_to_rel(data)
+                    .map_err(|err| format!("CREL decoding failed: {:?}", err.kind()))?;
+                Format::Rel.decode(&rel)
+            }
+            Format::Aps2 => {
+                let rel = aps2_to_rel(data)
+                    .map_err(|err| format!("APS2 decoding failed: {:?}", err.kind()))?;
+                Format::Rel.decode(&rel)
+            }
+            Format::Relr => {
+                let relative_type = options
+                    .relative_type
+                    .ok_or("decoding RELR requires the machine of the target")?;
+                let rel = relr_to_rel(data, relative_type)
+                    .map_err(|err| format!("RELR decoding failed: {:?}", err.kind()))?;
+                Format::Rel.decode(&rel)
+            }
+        }
+    }
+
+    /// Encodes relocations in this format.
+    pub fn encode(self, relocations: &[Elf32Rel]) -> Result<Vec<u8>, String> {
+        self.encode_with(relocations, &FormatOptions::default())
+    }
+
+    /// Encodes relocations in this format with the given options.
+    pub fn encode_with(
+        self,
+        relocations: &[Elf32Rel],
+        options: &FormatOptions,
+    ) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Rel => {
+                let mut data = Vec::with_capacity(relocations.len() * ELF32_REL_SIZE);
+                for relocation in relocations {
+                    data.extend_from_slice(&relocation.to_bytes());
+                }
+                Ok(data)
+            }
+            Format::Relox => {
+                // The compressor uses the first relocation as base address.
+                let mut sorted = relocations.to_vec();
+                sorted.sort_by_key(Elf32Rel::offset);
+                let builder: Elf32RelocsBuilder = sorted.into_iter().collect();
+                let mut relocs = builder.build();
+                relocs.set_encoding(options.encoding);
+                relocs.set_codec(options.codec);
+                relocs.set_repeat_offsets(options.repeat_offsets);
+                relocs.set_scaled_offsets(options.scaled_offsets);
+                let mut data = Vec::new();
+                relocs
+                    .compress_to_writer(&mut data)
+                    .map_err(|err| format!("compression failed: {:?}", err.kind()))?;
+                Ok(data)
+            }
+            Format::Crel => rel_to_crel(&Format::Rel.encode(relocations)?)
+                .map_err(|err| format!("CREL encoding failed: {:?}", err.kind())),
+            Format::Aps2 => rel_to_aps2(&Format::Rel.encode(relocations)?)
+                .map_err(|err| format!("APS2 encoding failed: {:?}", err.kind())),
+            Format::Relr => {
+                if let Some(relative_type) = options.relative_type {
+                    if relocations
+                        .iter()
+                        .any(|relocation| relocation.relocation_type() != relative_type)
+                    {
+                        return Err("RELR only holds relative relocations".to_string());
+                    }
+                }
+                rel_to_relr(&Format::Rel.encode(relocations)?)
+                    .map_err(|err| format!("RELR encoding failed: {:?}", err.kind()))
+            }
+        }
+    }
+}
+
+/// Reads an input file, or standard input if `path` is `-`.
+///
+/// If the file is an ELF file, the contents of `section` are returned, or
+/// those of `.rel.dyn` if no section is requested. Files without `.rel.dyn`,
+/// e.g. stripped ones without section headers, fall back to the `DT_REL`
+/// table of the dynamic segment.
+pub fn read_input(path: &str, section: Option<&str>) -> Result<Vec<u8>, String> {
+    input_section(path, read_file(path)?, section)
+}
+
+/// Returns the contents of `section` of the input file `path` read as
+/// `data`, like [read_input](fn.read_input.html).
+pub fn input_section(path: &str, data: Vec<u8>, section: Option<&str>) -> Result<Vec<u8>, String> {
+    if !ElfIdent::is_elf(&data) {
+        return match section {
+            Some(_) => Err(format!("{}: --section requires an ELF input", path)),
+            None => Ok(data),
+        };
+    }
+    check_elf(path, &data)?;
+    let file = Elf32File::parse(&data).map_err(|_| format!("{}: malformed ELF file", path))?;
+    if file.ident().endianness() != Endianness::Little {
+        return Err(format!(
+            "{}: big-endian ELF files are not supported by this command",
+            path
+        ));
+    }
+    if section.is_none() && file.section_by_name(DEFAULT_SECTION).is_none() {
+        if let Some(contents) = read_dynamic_table(path, &file)? {
+            return Ok(contents.to_vec());
+        }
+    }
+    let name = section.unwrap_or(DEFAULT_SECTION);
+    let section = file
+        .section_by_name(name)
+        .ok_or_else(|| format!("{}: no section named `{}`", path, name))?;
+    let contents = file
+        .section_data(section)
+        .map_err(|_| format!("{}: section `{}` is outside of the file", path, name))?;
+    Ok(contents.to_vec())
+}
+
+/// Reads the `DT_REL` table of the dynamic segment of an ELF file, if any.
+fn read_dynamic_table<'a>(path: &str, file: &Elf32File<'a>) -> Result<Option<&'a [u8]>, String> {
+    let tables = file
+        .dynamic_tables()
+        .map_err(|_| format!("{}: malformed dynamic segment", path))?;
+    let table = match tables.iter().find(|table| table.tag() == DT_REL) {
+        Some(table) => table,
+        None => return Ok(None),
+    };
+    if table.entry_size() as usize != ELF32_REL_SIZE {
+        return Err(format!(
+            "{}: unsupported DT_RELENT {}",
+            path,
+            table.entry_size()
+        ));
+    }
+    file.dynamic_table_data(table)
+        .map(Some)
+        .map_err(|_| format!("{}: DT_REL table is outside of the file", path))
+}
+
+/// Reads an ELF32 file, or standard input if `path` is `-`.
+pub fn read_elf(path: &str) -> Result<Vec<u8>, String> {
+    let data = read_file(path)?;
+    if !ElfIdent::is_elf(&data) {
+        return Err(format!("{}: not an ELF file", path));
+    }
+    check_elf(path, &data)?;
+    Ok(data)
+}
+
+/// Checks that an ELF file is supported.
+fn check_elf(path: &str, data: &[u8]) -> Result<(), String> {
+    let ident = ElfIdent::parse(data).map_err(|_| format!("{}: malformed ELF file", path))?;
+    if ident.class() != ElfClass::Elf32 {
+        return Err(format!(
+            "{}: unsupported ELF file ({:?}, {:?} endian, machine {}), \
+             only ELF32 files are supported",
+            path,
+            ident.class(),
+            ident.endianness(),
+            ident.machine()
+        ));
+    }
+    Ok(())
+}
+
+/// Returns an upper bound of the compressed size of a relocation section.
+pub fn max_compressed_size(input_len: usize) -> usize {
+    // Header, at most 256 group headers and 5 bytes for every offset, or
+    // header, run count and a run header for every offset if interleaved.
+    let entries = input_len / ELF32_REL_SIZE;
+    (5 + 256 * 6 + entries * 5).max(10 + entries * 11)
+}
@@ -0,0 +1,165 @@
+//! `relox inspect` command
+//!
+//! Prints a summary of a compressed relocation section: its header and the
+//! relocations of every type, without dumping the encoded bytes like
+//! `relox explain` does.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use relox::elf::relocation_type_name;
+use relox::layout::{
+    ALIGNED, BASED, BITMAP, DIRECTORY, GROUP_VARINT, INTERLEAVED, MACHINE, REPEAT, SCALED, SIGNED,
+    SYMBOLS,
+};
+use relox::{Elf32CRel, Elf32CRelInfo};
+
+use crate::batch::{expand_inputs, print_header, STDIO};
+use crate::format::read_input;
+
+/// Relocations of one type: count, lowest and highest address.
+type Summary = (usize, u32, u32);
+
+/// Runs the `inspect` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut section = None;
+    let mut machine = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            "--machine" => {
+                let value = args.next().ok_or("--machine requires a number")?;
+                machine = Some(
+                    value
+                        .parse::<u16>()
+                        .map_err(|_| format!("invalid machine `{}`", value))?,
+                );
+            }
+            _ if arg.starts_with('-') && arg != STDIO => {
+                return Err(format!("unexpected argument `{}`", arg))
+            }
+            _ => inputs.push(arg),
+        }
+    }
+    let inputs = expand_inputs(&inputs)?;
+    if inputs.is_empty() {
+        return Err("missing input file".to_string());
+    }
+
+    for input in inputs.iter() {
+        let data = read_input(input, section.map(String::as_str))?;
+        print_header(&inputs, input);
+        inspect_section(input, &data, machine)?;
+    }
+    Ok(())
+}
+
+/// Prints the summary of a compressed relocation section read from `input`,
+/// naming relocation types after the machine recorded in the section or
+/// `machine`.
+fn inspect_section(input: &str, data: &[u8], machine: Option<u16>) -> Result<(), String> {
+    let crel = Elf32CRel::try_from(data)
+        .map_err(|err| format!("{}: invalid header: {:?}", input, err.kind()))?;
+    let info = Elf32CRelInfo::parse(data)
+        .map_err(|err| format!("{}: decompression failed: {:?}", input, err.kind()))?;
+    let mut types = BTreeMap::<u8, Summary>::new();
+    for relocation in crel.iter() {
+        let relocation = relocation
+            .map_err(|err| format!("{}: decompression failed: {:?}", input, err.kind()))?;
+        let offset = relocation.offset();
+        let summary = types
+            .entry(relocation.relocation_type())
+            .or_insert((0, offset, offset));
+        *summary = (summary.0 + 1, summary.1.min(offset), summary.2.max(offset));
+    }
+    let (layout, codec, symbols, signed, alignment, repeat, scaled) = layout(data);
+    println!("size:         {} bytes", data.len());
+    if info.size() < data.len() {
+        println!("trailing:     {} bytes", data.len() - info.size());
+    }
+    println!("base address: {:#010x}", info.base_address());
+    match info.machine() {
+        Some(machine) => println!("machine:      {}", machine),
+        None => println!("machine:      any"),
+    }
+    println!("layout:       {}", layout);
+    println!("codec:        {}", codec);
+    println!("symbols:      {}", if symbols { "yes" } else { "no" });
+    println!("signed:       {}", if signed { "yes" } else { "no" });
+    println!("alignment:    {}", alignment);
+    println!("repeat:       {}", if repeat { "yes" } else { "no" });
+    println!("scaled:       {}", if scaled { "yes" } else { "no" });
+    println!(
+        "{:<13} {}",
+        match layout {
+            "interleaved" => "runs:",
+            _ => "groups:",
+        },
+        info.group_count()
+    );
+    println!("relocations:  {}", info.relocation_count());
+    for (relocation_type, (count, lowest, highest)) in types.iter() {
+        let name = info
+            .machine()
+            .or(machine)
+            .and_then(|machine| relocation_type_name(machine, *relocation_type))
+            .map_or_else(String::new, |name| format!(" {}", name));
+        println!(
+            "  type {:>3}{}: {} relocations, {:#010x}..={:#010x}",
+            relocation_type, name, count, lowest, highest
+        );
+    }
+    Ok(())
+}
+
+/// Returns the layout, offset codec, whether symbol indices and signed
+/// offsets are encoded, the group alignment and whether repeat opcodes and
+/// scales are encoded, from the prefixes of a compressed section with a valid
+/// header.
+fn layout(data: &[u8]) -> (&'static str, &'static str, bool, bool, u8, bool, bool) {
+    let mut index = 4;
+    if data[index] == MACHINE {
+        index += 3;
+    }
+    let codec = match data[index] {
+        GROUP_VARINT => {
+            index += 1;
+            "group-varint"
+        }
+        BITMAP => {
+            index += 1;
+            "bitmap"
+        }
+        _ => "uleb128",
+    };
+    let symbols = data[index] == SYMBOLS;
+    if symbols {
+        index += 1;
+    }
+    let signed = data[index] == SIGNED;
+    if signed {
+        index += 1;
+    }
+    let mut alignment = 1;
+    if data[index] == ALIGNED {
+        alignment = data[index + 1];
+        index += 2;
+    }
+    let repeat = data[index] == REPEAT;
+    if repeat {
+        index += 1;
+    }
+    let scaled = data[index] == SCALED;
+    if scaled {
+        index += 1;
+    }
+    let layout = match data[index] {
+        INTERLEAVED => "interleaved",
+        DIRECTORY => "directory",
+        BASED => "based",
+        _ => "grouped",
+    };
+    (layout, codec, symbols, signed, alignment, repeat, scaled)
+}
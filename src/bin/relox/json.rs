@@ -0,0 +1,86 @@
+//! JSON output of the command line interface
+//!
+//! Commands accepting `--json` print a JSON document per input instead of text,
+//! so build tooling can consume their output. The document is written by
+//! hand to keep the tool free of dependencies.
+
+use std::fmt::Write;
+
+use relox::Elf32Rel;
+
+/// Returns `value` as a JSON string literal.
+pub fn string(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            character if (character as u32) < 0x20 => {
+                let _ = write!(literal, "\\u{:04x}", character as u32);
+            }
+            character => literal.push(character),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Returns the members as a JSON object.
+pub fn object(members: &[(&str, String)]) -> String {
+    let members: Vec<String> = members
+        .iter()
+        .map(|(name, value)| format!("{}: {}", string(name), value))
+        .collect();
+    format!("{{{}}}", members.join(", "))
+}
+
+/// Returns the values as a JSON array.
+pub fn array<I: IntoIterator<Item = String>>(values: I) -> String {
+    let values: Vec<String> = values.into_iter().collect();
+    format!("[{}]", values.join(", "))
+}
+
+/// Returns the relocations as a JSON array of objects.
+pub fn relocations(relocations: &[Elf32Rel]) -> String {
+    array(relocations.iter().map(|relocation| {
+        object(&[
+            ("type", relocation.relocation_type().to_string()),
+            ("offset", relocation.offset().to_string()),
+        ])
+    }))
+}
+
+/// Returns a summary of every run of relocations of the same type, in
+/// decoding order, as a JSON array of objects.
+pub fn groups(relocations: &[Elf32Rel]) -> String {
+    let mut groups: Vec<(u8, usize, u32, u32)> = Vec::new();
+    for relocation in relocations.iter() {
+        let offset = relocation.offset();
+        match groups.last_mut() {
+            Some((last_type, count, _, last)) if *last_type == relocation.relocation_type() => {
+                *count += 1;
+                *last = offset;
+            }
+            _ => groups.push((relocation.relocation_type(), 1, offset, offset)),
+        }
+    }
+    array(groups.iter().map(|(relocation_type, count, first, last)| {
+        object(&[
+            ("type", relocation_type.to_string()),
+            ("count", count.to_string()),
+            ("first", first.to_string()),
+            ("last", last.to_string()),
+        ])
+    }))
+}
+
+/// Returns the outcome of a validation, given its error if any, as the
+/// `valid` and `error` JSON members.
+pub fn validation(error: Option<&str>) -> [(&'static str, String); 2] {
+    match error {
+        None => [("valid", "true".to_string()), ("error", "null".to_string())],
+        Some(message) => [("valid", "false".to_string()), ("error", string(message))],
+    }
+}
@@ -0,0 +1,112 @@
+//! Command line interface of relox
+//!
+//! This binary exposes the compression and decompression methods of the
+//! library so build systems can use them without writing a wrapper program.
+
+use std::env;
+use std::process;
+
+mod append;
+mod batch;
+mod bench;
+mod compress;
+mod convert;
+mod explain;
+mod format;
+mod inspect;
+mod json;
+mod mcuboot;
+mod object;
+mod size;
+mod strip;
+mod verify;
+
+/// Usage of the command line interface.
+const USAGE: &str = "\
+Usage: relox <command> [options]
+
+Commands:
+  append <compressed> <input> [--section <name>] -o <output>
+      Append the relocations of a relocation section as new groups to a
+      compressed relocation section.
+  bench <input> [--section <name>] [--iterations <count>]
+      Time compression and decompression of a relocation section.
+  compress <input> -o <output> [--section <name>] [--output-section <name>]
+           [--zero-fill | --remove]
+      Compress a relocation section (default: .rel.dyn) of an ELF32 file into
+      a new section (default: .crel.dyn for .rel.dyn), keeping, zero-filling
+      or removing the original one.
+  convert <input>... [--section <name>] (-o <output> | --out-dir <directory>)
+          --from <format> --to <format> [--machine <number>]
+          [--encoding <encoding>] [--codec <codec>] [--no-repeat-offsets]
+          [--scaled-offsets]
+      Convert relocation sections between formats (rel, relox, crel, aps2,
+      relr), or recompress compressed ones. RELR sections only hold relative
+      relocations, whose type is that of --machine. Compressed outputs use
+      the encoding (auto, grouped, interleaved, directory) and offset codec
+      (uleb128, group-varint, bitmap) given. With several inputs, outputs are
+      written to the directory named after the inputs with the output format
+      as extension.
+  explain <input>... [--section <name>] [--machine <number>] [--json]
+      Print an annotated hex dump of a compressed relocation section: its
+      header, group headers and every offset with the address it yields.
+  inspect <input>... [--section <name>] [--machine <number>]
+      Print a summary of a compressed relocation section: its header and the
+      number and address range of the relocations of every type.
+  mcuboot <image> <elf> [--section <name>] [--load-address <address>]
+          -o <output>
+      Compress the relocation section of the ELF32 file embedded in the
+      payload of an MCUboot image, updating its header and hash TLV.
+  object <input> [--section <name> | --all] -o <output> [--machine <number>]
+         [--output-section <name>] [--symbols <start> <end>]
+      Compress a relocation section, or all .rel.* sections of an ELF32 file
+      merged, into an ELF32 object file, placing it in section .crel between
+      symbols __crel_start and __crel_end.
+  size <input>... [--json]
+      Compare the size of the relocation sections of an ELF32 file in every
+      format.
+  strip <input> -o <output> [--section <name>]...
+      Remove relocation sections (default: all .rel.* sections) of an ELF32
+      file.
+  verify <compressed> <input> [--section <name>]
+         [--compressed-section <name>]
+      Check that a compressed relocation section (default: .crel.dyn of ELF
+      files) decodes to the relocations of a relocation section.
+
+Inputs are raw sections or ELF32 files; the section of ELF files is read from
+`--section` (default: .rel.dyn, or the DT_REL table of the dynamic segment if
+there is no such section). Wildcards `*` and `?` in input file names are
+expanded. An input or output named `-` is read from standard input or written
+to standard output, in which case status messages go to standard error.
+
+With --json, commands print a JSON document instead of text: `explain` the
+decoded relocations, a summary of their groups, the validation result and the
+annotations, and `size` the sizes of every section. With several inputs, one
+document is printed per line.";
+
+/// Runs the command requested by the command line arguments.
+fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("append") => append::run(&args[1..]),
+        Some("bench") => bench::run(&args[1..]),
+        Some("compress") => compress::run(&args[1..]),
+        Some("convert") => convert::run(&args[1..]),
+        Some("explain") => explain::run(&args[1..]),
+        Some("inspect") => inspect::run(&args[1..]),
+        Some("mcuboot") => mcuboot::run(&args[1..]),
+        Some("object") => object::run(&args[1..]),
+        Some("size") => size::run(&args[1..]),
+        Some("strip") => strip::run(&args[1..]),
+        Some("verify") => verify::run(&args[1..]),
+        Some(command) => Err(format!("unknown command `{}`\n\n{}", command, USAGE)),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(message) = run(&args) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,92 @@
+//! `relox mcuboot` command
+//!
+//! Compresses the relocation section embedded in the payload of an MCUboot
+//! image, using the ELF file the payload was built from to locate it.
+
+use std::fs;
+
+use relox::elf::{Elf32File, PT_LOAD};
+use relox::mcuboot::McubootImage;
+use relox::Endianness;
+
+use crate::format::{read_elf, DEFAULT_SECTION};
+
+/// Runs the `mcuboot` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut output = None;
+    let mut section = None;
+    let mut load_address = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("-o requires a file name")?),
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            "--load-address" => {
+                let value = args.next().ok_or("--load-address requires an address")?;
+                let address = match value.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => value.parse::<u32>(),
+                };
+                load_address = Some(address.map_err(|_| format!("invalid address `{}`", value))?);
+            }
+            _ if inputs.len() < 2 => inputs.push(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let image_path = inputs.first().ok_or("missing image file")?;
+    let elf_path = inputs.get(1).ok_or("missing ELF file")?;
+    let output = output.ok_or("missing output file")?;
+
+    let elf = read_elf(elf_path)?;
+    let file = Elf32File::parse(&elf).map_err(|_| format!("{}: malformed ELF file", elf_path))?;
+    if file.ident().endianness() != Endianness::Little {
+        return Err(format!(
+            "{}: big-endian ELF files are not supported by this command",
+            elf_path
+        ));
+    }
+    let name = section.map_or(DEFAULT_SECTION, String::as_str);
+    let rel = file
+        .section_by_name(name)
+        .ok_or_else(|| format!("{}: no section named `{}`", elf_path, name))?;
+    // The payload is the binary image of the loaded segments.
+    let load_address = match load_address {
+        Some(address) => address,
+        None => file
+            .segments()
+            .iter()
+            .filter(|segment| segment.segment_type() == PT_LOAD && segment.file_size() > 0)
+            .map(|segment| segment.address())
+            .min()
+            .ok_or_else(|| format!("{}: no loadable segment, use --load-address", elf_path))?,
+    };
+    let offset = rel
+        .address()
+        .checked_sub(load_address)
+        .ok_or_else(|| format!("{}: section `{}` is below the load address", elf_path, name))?;
+
+    let data = fs::read(image_path).map_err(|err| format!("{}: {}", image_path, err))?;
+    let image = McubootImage::parse(&data).map_err(|err| {
+        format!(
+            "{}: unsupported MCUboot image: {:?}",
+            image_path,
+            err.kind()
+        )
+    })?;
+    let processed = image
+        .compress_relocations(offset as usize, rel.size() as usize)
+        .map_err(|err| format!("{}: compression failed: {:?}", image_path, err.kind()))?;
+    fs::write(output, &processed).map_err(|err| format!("{}: {}", output, err))?;
+    let processed_image =
+        McubootImage::parse(&processed).map_err(|err| format!("{}: {:?}", output, err.kind()))?;
+    if image.is_signed() {
+        println!("warning: signature dropped, sign the image again");
+    }
+    println!(
+        "payload {} bytes -> {} bytes",
+        image.image_size(),
+        processed_image.image_size()
+    );
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! `relox object` command
+//!
+//! Compresses a relocation section into a relocatable object file, which is
+//! linked into the firmware so startup code can locate the compressed section
+//! using its start and end symbols.
+
+use std::fs;
+
+use relox::elf::{Elf32File, Elf32Object};
+use relox::Endianness;
+
+use crate::format::{read_elf, read_input, Format};
+
+/// Runs the `object` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input = None;
+    let mut section = None;
+    let mut output = None;
+    let mut machine = None;
+    let mut output_section = None;
+    let mut symbols = None;
+    let mut all = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("-o requires a file name")?),
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            "--all" => all = true,
+            "--machine" => {
+                let value = args.next().ok_or("--machine requires a number")?;
+                machine = Some(
+                    value
+                        .parse::<u16>()
+                        .map_err(|_| format!("invalid machine `{}`", value))?,
+                );
+            }
+            "--output-section" => {
+                output_section = Some(args.next().ok_or("--output-section requires a name")?)
+            }
+            "--symbols" => {
+                let start = args.next().ok_or("--symbols requires two names")?;
+                let end = args.next().ok_or("--symbols requires two names")?;
+                symbols = Some((start, end));
+            }
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let input = input.ok_or("missing input file")?;
+    let output = output.ok_or("missing output file")?;
+
+    if all && section.is_some() {
+        return Err("--all and --section are mutually exclusive".to_string());
+    }
+    let data = if all {
+        read_elf(input)?
+    } else {
+        read_input(input, section.map(String::as_str))?
+    };
+    let (machine, flags) = match machine {
+        Some(machine) => (machine, 0),
+        None => {
+            let elf = read_elf(input).map_err(|_| "missing --machine for raw input")?;
+            let file =
+                Elf32File::parse(&elf).map_err(|_| format!("{}: malformed ELF file", input))?;
+            (file.ident().machine(), file.flags())
+        }
+    };
+    let (size, compressed, endianness) = if all {
+        let file = Elf32File::parse(&data).map_err(|_| format!("{}: malformed ELF file", input))?;
+        let sections = file.relocation_sections();
+        if sections.is_empty() {
+            return Err(format!("{}: no relocation sections", input));
+        }
+        let size = sections.iter().map(|s| s.section().size() as usize).sum();
+        let compressed = file
+            .compress_relocation_sections_merged()
+            .map_err(|err| format!("compression failed: {:?}", err.kind()))?;
+        (size, compressed, file.ident().endianness())
+    } else {
+        let compressed = Format::Relox.encode(&Format::Rel.decode(&data)?)?;
+        (data.len(), compressed, Endianness::Little)
+    };
+    let mut object = Elf32Object::new(&compressed, machine);
+    object.set_endianness(endianness);
+    object.set_flags(flags);
+    if let Some(name) = output_section {
+        object.set_section(name);
+    }
+    if let Some((start, end)) = symbols {
+        object.set_symbols(start, end);
+    }
+    let object = object.write();
+    fs::write(output, &object).map_err(|err| format!("{}: {}", output, err))?;
+    println!(
+        "{} bytes -> {} bytes, object {} bytes",
+        size,
+        compressed.len(),
+        object.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! `relox size` command
+//!
+//! Compares the size of the relocation sections of an ELF file in every
+//! supported format.
+
+use relox::elf::{Elf32File, SHT_REL};
+use relox::{Elf32Rel, Endianness};
+
+use crate::batch::{expand_inputs, print_header, STDIO};
+use crate::format::{read_elf, Format};
+use crate::json;
+
+/// Runs the `size` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut output_json = false;
+    for arg in args.iter() {
+        match arg.as_str() {
+            "--json" => output_json = true,
+            _ if arg.starts_with('-') && arg != STDIO => {
+                return Err(format!("unexpected argument `{}`", arg))
+            }
+            _ => inputs.push(arg),
+        }
+    }
+    let inputs = expand_inputs(&inputs)?;
+    if inputs.is_empty() {
+        return Err("missing input file".to_string());
+    }
+
+    for input in inputs.iter() {
+        if !output_json {
+            print_header(&inputs, input);
+        }
+        print_sizes(input, output_json)?;
+    }
+    Ok(())
+}
+
+/// Prints the sizes of the relocation sections of the ELF file `input`.
+fn print_sizes(input: &str, output_json: bool) -> Result<(), String> {
+    let data = read_elf(input)?;
+    let file = Elf32File::parse(&data).map_err(|_| format!("{}: malformed ELF file", input))?;
+    let mut rows = Vec::new();
+    let mut merged = Vec::new();
+    for section in file.sections() {
+        if section.section_type() != SHT_REL {
+            continue;
+        }
+        let contents = file.section_data(section).map_err(|_| {
+            format!(
+                "{}: section `{}` is outside of the file",
+                input,
+                section.name()
+            )
+        })?;
+        let relocations = match file.ident().endianness() {
+            Endianness::Little => Format::Rel.decode(contents)?,
+            // Only the byte order of the base address differs once compressed.
+            Endianness::Big => {
+                let mut contents = contents.to_vec();
+                for word in contents.chunks_exact_mut(4) {
+                    word.reverse();
+                }
+                Format::Rel.decode(&contents)?
+            }
+        };
+        rows.push((section.name().to_string(), sizes(&relocations)?));
+        merged.extend_from_slice(&relocations);
+    }
+    if rows.is_empty() {
+        return Err(format!("{}: no relocation sections", input));
+    }
+
+    let mut total = vec![0; Format::ALL.len()];
+    for (_, sizes) in rows.iter() {
+        for (total, size) in total.iter_mut().zip(sizes.iter()) {
+            *total += size;
+        }
+    }
+    if output_json {
+        let merged = match rows.len() {
+            1 => None,
+            _ => Some(sizes(&merged)?),
+        };
+        print_json(&rows, &total, merged.as_deref());
+        return Ok(());
+    }
+    print!("{:<20}", "section");
+    for format in Format::ALL.iter() {
+        print!(" {:>10}", format.name());
+    }
+    println!();
+    rows.push(("total".to_string(), total));
+    if rows.len() > 2 {
+        rows.push(("merged".to_string(), sizes(&merged)?));
+    }
+    for (name, sizes) in rows.iter() {
+        print!("{:<20}", name);
+        for size in sizes.iter() {
+            print!(" {:>10}", size);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Prints the sizes of the sections, their total and the size of their
+/// relocations merged into one section, if there are several, as JSON.
+fn print_json(rows: &[(String, Vec<usize>)], total: &[usize], merged: Option<&[usize]>) {
+    let sizes = |sizes: &[usize]| {
+        let members: Vec<(&str, String)> = Format::ALL
+            .iter()
+            .zip(sizes.iter())
+            .map(|(format, size)| (format.name(), size.to_string()))
+            .collect();
+        json::object(&members)
+    };
+    let sections =
+        json::array(rows.iter().map(|(name, row)| {
+            json::object(&[("name", json::string(name)), ("sizes", sizes(row))])
+        }));
+    println!(
+        "{}",
+        json::object(&[
+            ("sections", sections),
+            ("total", sizes(total)),
+            ("merged", merged.map_or("null".to_string(), sizes)),
+        ])
+    );
+}
+
+/// Returns the size of relocations encoded in every supported format.
+fn sizes(relocations: &[Elf32Rel]) -> Result<Vec<usize>, String> {
+    Format::ALL
+        .iter()
+        .map(|format| format.encode(relocations).map(|data| data.len()))
+        .collect()
+}
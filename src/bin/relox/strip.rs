@@ -0,0 +1,56 @@
+//! `relox strip` command
+//!
+//! Removes the regular relocation sections of an ELF file once they are
+//! replaced by their compressed variant.
+
+use std::fs;
+
+use relox::elf::{Elf32File, SHT_REL};
+
+use crate::format::read_elf;
+
+/// Runs the `strip` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut input = None;
+    let mut output = None;
+    let mut sections = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("-o requires a file name")?),
+            "--section" => sections.push(args.next().ok_or("--section requires a name")?),
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let input = input.ok_or("missing input file")?;
+    let output = output.ok_or("missing output file")?;
+
+    let data = read_elf(input)?;
+    let file = Elf32File::parse(&data).map_err(|_| format!("{}: malformed ELF file", input))?;
+    for name in sections.iter() {
+        if file.section_by_name(name).is_none() {
+            return Err(format!("{}: no section named `{}`", input, name));
+        }
+    }
+    let mut removed = Vec::new();
+    let stripped = file
+        .strip_sections(|section| {
+            let remove = if sections.is_empty() {
+                section.section_type() == SHT_REL && section.name().starts_with(".rel")
+            } else {
+                sections.iter().any(|name| *name == section.name())
+            };
+            if remove {
+                removed.push((section.name().to_string(), section.size()));
+            }
+            remove
+        })
+        .map_err(|err| format!("{}: strip failed: {:?}", input, err.kind()))?;
+    fs::write(output, &stripped).map_err(|err| format!("{}: {}", output, err))?;
+    for (name, size) in removed.iter() {
+        println!("removed `{}` ({} bytes)", name, size);
+    }
+    println!("{} bytes -> {} bytes", data.len(), stripped.len());
+    Ok(())
+}
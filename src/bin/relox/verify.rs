@@ -0,0 +1,129 @@
+//! `relox verify` command
+//!
+//! Checks that a compressed relocation section decodes to exactly the
+//! relocations of the regular relocation section it was produced from, e.g.
+//! in CI after a post-link compression step.
+
+use std::convert::TryFrom;
+
+use relox::elf::ElfIdent;
+use relox::Elf32CRel;
+
+use crate::batch::{read_file, STDIO};
+use crate::format::{input_section, read_input, ELF32_REL_SIZE};
+
+/// Compressed section read from ELF inputs if no section is requested.
+const DEFAULT_COMPRESSED_SECTION: &str = ".crel.dyn";
+
+/// Relocation type, offset and symbol index.
+type Entry = (u8, u32, u32);
+
+/// Runs the `verify` command.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut section = None;
+    let mut compressed_section = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--section" => section = Some(args.next().ok_or("--section requires a name")?),
+            "--compressed-section" => {
+                compressed_section =
+                    Some(args.next().ok_or("--compressed-section requires a name")?)
+            }
+            _ if inputs.len() < 2 => inputs.push(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let compressed = inputs.first().ok_or("missing compressed input file")?;
+    let input = inputs.get(1).ok_or("missing input file")?;
+    if *compressed == STDIO && *input == STDIO {
+        return Err("standard input can only be read once".to_string());
+    }
+
+    let blob = read_file(compressed)?;
+    let compressed_section = match compressed_section {
+        Some(name) => Some(name.as_str()),
+        None if ElfIdent::is_elf(&blob) => Some(DEFAULT_COMPRESSED_SECTION),
+        None => None,
+    };
+    let blob = input_section(compressed, blob, compressed_section)?;
+    let data = read_input(input, section.map(String::as_str))?;
+
+    let mut expected = decode_rel(&data)?;
+    let mut decoded = decode_relox(&blob)
+        .map_err(|err| format!("{}: decompression failed: {}", compressed, err))?;
+    // Groups are ordered by relocation type, so only the sets are compared.
+    expected.sort_unstable();
+    decoded.sort_unstable();
+    if let Some((index, (expected, decoded))) = expected
+        .iter()
+        .zip(decoded.iter())
+        .enumerate()
+        .find(|(_, (expected, decoded))| expected != decoded)
+    {
+        return Err(format!(
+            "{}: relocation {} differs: expected {}, decoded {}",
+            compressed,
+            index,
+            describe(expected),
+            describe(decoded)
+        ));
+    }
+    if expected.len() != decoded.len() {
+        return Err(format!(
+            "{}: {} relocations decoded, {} expected",
+            compressed,
+            decoded.len(),
+            expected.len()
+        ));
+    }
+    println!(
+        "{} relocations match, {} bytes -> {} bytes",
+        expected.len(),
+        data.len(),
+        blob.len()
+    );
+    Ok(())
+}
+
+/// Decodes the entries of a regular relocation section.
+fn decode_rel(data: &[u8]) -> Result<Vec<Entry>, String> {
+    if !data.len().is_multiple_of(ELF32_REL_SIZE) {
+        return Err("size of relocation section is not a multiple of 8".to_string());
+    }
+    Ok(data
+        .chunks(ELF32_REL_SIZE)
+        .map(|entry| {
+            let offset = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+            let symbol = u32::from_le_bytes([entry[5], entry[6], entry[7], 0]);
+            (entry[4], offset, symbol)
+        })
+        .collect())
+}
+
+/// Decodes the entries of a compressed relocation section.
+fn decode_relox(data: &[u8]) -> Result<Vec<Entry>, String> {
+    let crel = Elf32CRel::try_from(data).map_err(|err| format!("{:?}", err.kind()))?;
+    crel.iter()
+        .map(|relocation| {
+            relocation
+                .map(|relocation| {
+                    (
+                        relocation.relocation_type(),
+                        relocation.offset(),
+                        relocation.symbol(),
+                    )
+                })
+                .map_err(|err| format!("{:?}", err.kind()))
+        })
+        .collect()
+}
+
+/// Formats an entry for error messages.
+fn describe((relocation_type, offset, symbol): &Entry) -> String {
+    format!(
+        "type {} at {:#010x} (symbol {})",
+        relocation_type, offset, symbol
+    )
+}
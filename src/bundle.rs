@@ -0,0 +1,156 @@
+//! Bundles of compressed relocation sections
+//!
+//! [Elf32CRelBundleBuilder](struct.Elf32CRelBundleBuilder.html) stores
+//! several compressed relocation sections, e.g. one per overlay, behind a
+//! table of contents laid out as described in the `bundle` module of
+//! `relox-core`, whose [bundle_relocate](fn.bundle_relocate.html) applies
+//! one of them on the target.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use crate::{bundle_section_id, Endianness, Error, ErrorKind, BUNDLE_MAGIC};
+
+/// Builder of a bundle of compressed ELF32 relocation sections.
+///
+/// ```
+/// use relox::{bundle_relocate, bundle_section_id, compress_sections, Elf32CRelBundleBuilder};
+///
+/// # fn main() -> Result<(), relox::Error> {
+/// let overlay1 = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+/// let overlay2 = [0x00, 0x20, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+/// let mut builder = Elf32CRelBundleBuilder::new();
+/// for section in compress_sections(&[(".rel.overlay1", &overlay1), (".rel.overlay2", &overlay2)])? {
+///     builder.push_named(section.name(), section.data());
+/// }
+/// let bundle = builder.build()?;
+///
+/// let mut addresses = Vec::new();
+/// bundle_relocate(&bundle, bundle_section_id(".rel.overlay2"), &mut |_, address| {
+///     addresses.push(address);
+///     Ok(())
+/// })?;
+/// assert_eq!(addresses, [0x2000]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Elf32CRelBundleBuilder {
+    endianness: Endianness,
+    sections: Vec<(u32, Vec<u8>)>,
+}
+
+impl Default for Elf32CRelBundleBuilder {
+    fn default() -> Self {
+        Self {
+            endianness: Endianness::Little,
+            sections: Vec::new(),
+        }
+    }
+}
+
+impl Elf32CRelBundleBuilder {
+    /// Creates a new, empty `Elf32CRelBundleBuilder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the byte order of the table of contents, which should match that
+    /// of the compressed sections. The default is little-endian.
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+
+    /// Adds the compressed section `section` identified by `id`, e.g. the
+    /// ordinal of an overlay.
+    pub fn push(&mut self, id: u32, section: &[u8]) -> &mut Self {
+        self.sections.push((id, section.to_vec()));
+        self
+    }
+
+    /// Adds the compressed section `section` identified by the hash of
+    /// `name`, see [bundle_section_id](fn.bundle_section_id.html).
+    pub fn push_named(&mut self, name: &str, section: &[u8]) -> &mut Self {
+        self.push(bundle_section_id(name), section)
+    }
+
+    /// Returns the bundle holding the sections added so far, in the order
+    /// they were added.
+    ///
+    /// # Errors
+    ///
+    /// `InvalidData` if two sections have the same identifier, e.g. names
+    /// with the same hash, or `Unsupported` if the bundle would be larger
+    /// than 4 GiB.
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut ids = HashSet::new();
+        if !self.sections.iter().all(|(id, _)| ids.insert(*id)) {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let size =
+            |value: usize| u32::try_from(value).map_err(|_| Error::new(ErrorKind::Unsupported));
+        let toc = BUNDLE_MAGIC.len() + 4 + self.sections.len() * 12;
+        let sections: usize = self.sections.iter().map(|(_, data)| data.len()).sum();
+        let total = toc + sections;
+        size(total)?;
+
+        let mut bundle = Vec::with_capacity(total);
+        bundle.extend_from_slice(&BUNDLE_MAGIC);
+        self.write_word(&mut bundle, size(self.sections.len())?);
+        let mut offset = toc;
+        for (id, data) in self.sections.iter() {
+            self.write_word(&mut bundle, *id);
+            self.write_word(&mut bundle, size(offset)?);
+            self.write_word(&mut bundle, size(data.len())?);
+            offset += data.len();
+        }
+        for (_, data) in self.sections.iter() {
+            bundle.extend_from_slice(data);
+        }
+        Ok(bundle)
+    }
+
+    /// Appends a word in the byte order of the bundle.
+    fn write_word(&self, bundle: &mut Vec<u8>, word: u32) {
+        bundle.extend_from_slice(&self.endianness.convert(word).to_ne_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Elf32CRelBundle;
+
+    const SECTION: [u8; 9] = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x00, 0x04];
+
+    #[test]
+    fn test_bundle_builder() {
+        let mut builder = Elf32CRelBundleBuilder::new();
+        builder
+            .push(7, &SECTION)
+            .push_named(".rel.overlay1", &SECTION[..5]);
+        let bundle = builder.build().unwrap();
+        assert_eq!(bundle.len(), 8 + 2 * 12 + 14);
+        assert_eq!(
+            bundle[..20],
+            [
+                0x52, 0x58, 0x42, 0x4E, // magic
+                0x02, 0x00, 0x00, 0x00, // count
+                0x07, 0x00, 0x00, 0x00, // entries[0].id
+                0x20, 0x00, 0x00, 0x00, // entries[0].offset
+                0x09, 0x00, 0x00, 0x00, // entries[0].size
+            ]
+        );
+        let parsed = Elf32CRelBundle::parse(&bundle).unwrap();
+        assert_eq!(parsed.section(7).unwrap(), SECTION);
+        let id = bundle_section_id(".rel.overlay1");
+        assert_eq!(parsed.section(id).unwrap(), &SECTION[..5]);
+
+        builder.set_endianness(Endianness::Big);
+        assert_eq!(builder.build().unwrap()[4..12], [0, 0, 0, 2, 0, 0, 0, 7]);
+
+        builder.push(7, &SECTION);
+        let err = builder.build().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,181 @@
+//! Generate Rust modules embedding compressed relocation sections
+//!
+//! This module can be used by build scripts to generate, in `OUT_DIR`, a Rust
+//! module containing a compressed ELF32 relocation section as a static placed
+//! in a dedicated link section, together with typed accessors. The firmware
+//! crate includes the generated module, so the compressed data and the code
+//! using it are kept in sync automatically:
+//!
+//! ```ignore
+//! // build.rs
+//! let mut relocs = relox::Elf32Relocs::new(&rel_dyn);
+//! let written = relocs.compress(&mut blob)?;
+//! relox::codegen::Module::new(&blob[..written])?.write_to_out_dir("crel.rs")?;
+//!
+//! // main.rs
+//! include!(concat!(env!("OUT_DIR"), "/crel.rs"));
+//! ```
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, ErrorKind};
+
+/// Size of the header of a compressed ELF32 relocation section.
+const HEADER_SIZE: usize = 5;
+/// Number of bytes per line of the generated static.
+const BYTES_PER_LINE: usize = 12;
+
+/// Generator of a Rust module embedding a compressed ELF32 relocation section.
+pub struct Module<'a> {
+    blob: &'a [u8],
+    name: String,
+    section: String,
+}
+
+impl<'a> Module<'a> {
+    /// Creates a new `Module` instance embedding the compressed ELF32
+    /// relocation section `blob` as static `CREL` in link section `.crel`.
+    ///
+    /// # Errors
+    ///
+    /// If `blob` is too small to hold the header of a compressed section.
+    pub fn new(blob: &'a [u8]) -> Result<Self, Error> {
+        if blob.len() < HEADER_SIZE {
+            return Err(Error::new(ErrorKind::NotEnoughData));
+        }
+        Ok(Self {
+            blob,
+            name: String::from("CREL"),
+            section: String::from(".crel"),
+        })
+    }
+
+    /// Sets the name of the generated static. Accessors are named after its
+    /// lowercase variant.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = String::from(name);
+    }
+
+    /// Sets the link section the generated static is placed in.
+    pub fn set_section(&mut self, section: &str) {
+        self.section = String::from(section);
+    }
+
+    /// Returns the source code of the generated module.
+    pub fn generate(&self) -> String {
+        let bytes: Vec<String> = self
+            .blob
+            .chunks(BYTES_PER_LINE)
+            .map(|line| {
+                let line: Vec<String> =
+                    line.iter().map(|byte| format!("0x{:02X},", byte)).collect();
+                format!("    {}\n", line.join(" "))
+            })
+            .collect();
+        format!(
+            "// Generated by relox. Do not edit.
+
+/// Compressed ELF32 relocation section.
+#[link_section = \"{section}\"]
+#[used]
+pub static {name}: [u8; {len}] = [
+{bytes}];
+
+/// Returns the compressed ELF32 relocation section.
+pub fn {accessor}() -> &'static [u8] {{
+    &{name}
+}}
+
+/// Returns the base address of the compressed ELF32 relocation section.
+pub fn {accessor}_base_address() -> u32 {{
+    u32::from_ne_bytes([{name}[0], {name}[1], {name}[2], {name}[3]])
+}}
+
+/// Returns the number of relocation groups of the compressed ELF32
+/// relocation section.
+pub fn {accessor}_group_count() -> u8 {{
+    {name}[4]
+}}
+",
+            section = self.section,
+            name = self.name,
+            len = self.blob.len(),
+            bytes = bytes.concat(),
+            accessor = self.name.to_lowercase(),
+        )
+    }
+
+    /// Writes the generated module to `path`.
+    ///
+    /// # Errors
+    ///
+    /// If writing the file fails.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.generate())
+    }
+
+    /// Writes the generated module to `file_name` in the `OUT_DIR` directory
+    /// of the running build script and returns the path of the file.
+    ///
+    /// # Errors
+    ///
+    /// If `OUT_DIR` is not set or if writing the file fails.
+    pub fn write_to_out_dir(&self, file_name: &str) -> io::Result<PathBuf> {
+        let out_dir = env::var_os("OUT_DIR")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+        let path = Path::new(&out_dir).join(file_name);
+        self.write(&path)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREL: [u8; 9] = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x00, 0x04];
+
+    #[test]
+    fn test_module_new_small() {
+        let err = Module::new(&CREL[..4]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[test]
+    fn test_module_generate() {
+        let source = Module::new(&CREL).unwrap().generate();
+        assert!(source.contains("#[link_section = \".crel\"]\n#[used]\n"));
+        assert!(source.contains(
+            "pub static CREL: [u8; 9] = [\n    \
+             0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x00, 0x04,\n];\n"
+        ));
+        assert!(source.contains("pub fn crel() -> &'static [u8] {\n    &CREL\n}\n"));
+        assert!(source.contains("pub fn crel_base_address() -> u32 {\n"));
+        assert!(source.contains("pub fn crel_group_count() -> u8 {\n    CREL[4]\n}\n"));
+    }
+
+    #[test]
+    fn test_module_generate_names() {
+        let blob: [u8; 13] = [0; 13];
+        let mut module = Module::new(&blob).unwrap();
+        module.set_name("BOOT_CREL");
+        module.set_section(".rodata.crel");
+        let source = module.generate();
+        assert!(source.contains("#[link_section = \".rodata.crel\"]\n"));
+        assert!(source.contains("pub static BOOT_CREL: [u8; 13] = [\n"));
+        assert!(source.contains("    0x00,\n];\n"));
+        assert!(source.contains("pub fn boot_crel_base_address() -> u32 {\n"));
+    }
+
+    #[test]
+    fn test_module_write() {
+        let path = env::temp_dir().join(format!("relox-codegen-{}.rs", std::process::id()));
+        Module::new(&CREL).unwrap().write(&path).unwrap();
+        let source = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(source, Module::new(&CREL).unwrap().generate());
+    }
+}
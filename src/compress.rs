@@ -2,180 +2,1841 @@
 //!
 //! This module can be used to compress ELF32 relocation sections post-link time.
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use std::collections::BTreeMap;
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{Cursor, Write};
+use std::iter::FromIterator;
 
-use crate::error::{Error, ErrorKind};
-use crate::uleb128;
+use relox_core::layout::{
+    ALIGNED, BASED, BITMAP, DIRECTORY, GROUP_VARINT, INTERLEAVED, MACHINE, REPEAT, SCALED, SIGNED,
+    SYMBOLS,
+};
+use relox_core::uleb128;
 
-// Type of a relocation.
-type Elf32RelType = u8;
+#[cfg(feature = "decompress")]
+use crate::{debug_map, DebugMap};
+use crate::{
+    CompressionStats, Elf32Rel, Endianness, Error, ErrorKind, GroupStats, Metadata, METADATA_MAGIC,
+};
 
-/// Representation of a regular ELF32 relocation.
-#[derive(Debug)]
-pub struct Elf32Rel {
-    offset: u32,
-    relocation_type: Elf32RelType,
+/// Encoding of a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Encoding {
+    /// Uses the encoding yielding the smaller result.
+    Auto,
+    /// Groups relocations by type. Groups of types with a base address set by
+    /// [set_type_base_address](struct.Elf32Relocs.html#method.set_type_base_address)
+    /// carry their base address.
+    Grouped,
+    /// Keeps relocations in address order and marks runs of relocations of
+    /// the same type, which preserves delta locality if types are
+    /// interleaved. Requires a relocation section sorted by offset.
+    Interleaved,
+    /// Groups relocations by type and places all group headers in a
+    /// directory of fixed-size entries before the offset streams of the
+    /// groups, so any group can be located without decoding the others.
+    /// Never selected automatically.
+    Directory,
 }
 
-impl Elf32Rel {
-    /// Constructs an `Elf32Rel` instace from an in-memory buffer.
-    pub fn from_memory(data: &mut Cursor<&[u8]>) -> Result<Self, Error> {
-        let offset = data
-            .read_u32::<LittleEndian>()
-            .map_err(|_| Error::new(ErrorKind::NotEnoughData))?;
-        let info = data
-            .read_u32::<LittleEndian>()
-            .map_err(|_| Error::new(ErrorKind::NotEnoughData))?;
-        Ok(Self {
-            offset: offset,
-            relocation_type: info as u8,
-        })
-    }
+/// Codec of the offsets of a compressed ELF32 relocation section.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Codec {
+    /// Encodes every offset as ULEB128.
+    Uleb128,
+    /// Encodes every four offsets with a shared tag byte holding their
+    /// lengths, which decodes with fewer branches on in-order cores.
+    GroupVarint,
+    /// Encodes the first offset of every run of word-aligned offsets as
+    /// ULEB128, followed by bitmap bytes marking the other offsets of the
+    /// run seven words at a time, like `SHT_RELR` sections. Dense runs, e.g.
+    /// of `R_ARM_RELATIVE` relocations, take about a bit per word.
+    Bitmap,
+}
 
-    /// Returns the offset of the relocation.
-    pub fn offset(&self) -> u32 {
-        self.offset
-    }
+/// Relocations of a section to compress.
+#[derive(Copy, Clone)]
+enum Source<'a> {
+    /// Regular ELF32 relocation section.
+    Section(&'a [u8]),
+    /// Relocations provided as entries.
+    Entries(&'a [Elf32Rel]),
+}
 
-    /// Returns the type of the relocation.
-    pub fn relocation_type(&self) -> Elf32RelType {
-        self.relocation_type
+/// Iterator over the relocations of a `Source`.
+struct SourceEntries<'a> {
+    source: Source<'a>,
+    endianness: Endianness,
+    index: usize,
+}
+
+impl Iterator for SourceEntries<'_> {
+    type Item = Elf32Rel;
+
+    fn next(&mut self) -> Option<Elf32Rel> {
+        let entry = match self.source {
+            Source::Section(data) => {
+                let mut cursor = Cursor::new(data.get(self.index * 8..)?);
+                Elf32Rel::from_memory_endian(&mut cursor, self.endianness).ok()
+            }
+            Source::Entries(entries) => entries.get(self.index).copied(),
+        };
+        self.index += 1;
+        entry
     }
 }
 
 /// Representation of a regular ELF32 relocation section.
 pub struct Elf32Relocs<'a> {
-    entries: BTreeMap<Elf32RelType, Vec<Elf32Rel>>,
-    data: &'a [u8],
+    entries: BTreeMap<u8, Vec<Elf32Rel>>,
+    counts: BTreeMap<u8, u32>,
+    source: Source<'a>,
     base_address: u32,
+    set_base_address: Option<u32>,
+    lowest_offset: u32,
+    memory_limit: Option<usize>,
+    endianness: Endianness,
+    encoding: Encoding,
+    codec: Codec,
+    repeat_offsets: bool,
+    scaled_offsets: bool,
+    streaming: bool,
+    collected: bool,
+    sorted: bool,
+    sort_offsets: bool,
+    groups_sorted: bool,
+    runs: u32,
+    symbol_types: BTreeSet<u8>,
+    signed_types: BTreeSet<u8>,
+    repeat_types: BTreeSet<u8>,
+    scales: BTreeMap<u8, u32>,
+    first_offsets: BTreeMap<u8, u32>,
+    type_base_addresses: BTreeMap<u8, u32>,
+    machine: Option<u16>,
+    alignment: u8,
+    metadata: Option<Vec<u8>>,
 }
 
 impl<'a> Elf32Relocs<'a> {
     /// Creates a new `Elf32Relocs` instance.
     pub fn new(data: &'a [u8]) -> Self {
+        Self::with_source(Source::Section(data))
+    }
+
+    /// Creates a new `Elf32Relocs` instance compressing `entries` as if they
+    /// were the entries of a regular ELF32 relocation section, without
+    /// serializing them first.
+    ///
+    /// The byte order set by [set_endianness](#method.set_endianness) only
+    /// applies to the compressed data.
+    pub fn from_entries(entries: &'a [Elf32Rel]) -> Self {
+        Self::with_source(Source::Entries(entries))
+    }
+
+    /// Creates a new `Elf32Relocs` instance compressing the regular
+    /// relocation section `name` of an ELF file parsed by the `object` crate,
+    /// e.g. in a `build.rs` script.
+    ///
+    /// # Errors
+    ///
+    /// `Unsupported` if the file is not a little-endian ELF32 file,
+    /// `SectionNotFound` if there is no regular relocation section named
+    /// `name`, or `NotEnoughData` if the section is outside of the file.
+    #[cfg(feature = "object")]
+    pub fn from_object_section(file: &object::File<'a>, name: &str) -> Result<Self, Error> {
+        use object::read::elf::SectionHeader;
+        use object::{Object, ObjectSection};
+
+        let file = match file {
+            object::File::Elf32(file) if file.is_little_endian() => file,
+            _ => return Err(Error::new(ErrorKind::Unsupported)),
+        };
+        let section = file
+            .section_by_name(name)
+            .filter(|section| {
+                section.elf_section_header().sh_type(file.endian()) == object::elf::SHT_REL
+            })
+            .ok_or_else(|| Error::new(ErrorKind::SectionNotFound))?;
+        let data = section
+            .data()
+            .map_err(|_| Error::new(ErrorKind::NotEnoughData))?;
+        Ok(Self::new(data))
+    }
+
+    fn with_source(source: Source<'a>) -> Self {
         Self {
             entries: BTreeMap::new(),
-            data: data,
-            base_address: u32::max_value(),
+            counts: BTreeMap::new(),
+            source,
+            base_address: u32::MAX,
+            set_base_address: None,
+            lowest_offset: u32::MAX,
+            memory_limit: None,
+            endianness: Endianness::Little,
+            encoding: Encoding::Auto,
+            codec: Codec::Uleb128,
+            repeat_offsets: true,
+            scaled_offsets: false,
+            streaming: false,
+            collected: false,
+            sorted: true,
+            sort_offsets: false,
+            groups_sorted: true,
+            runs: 0,
+            symbol_types: BTreeSet::new(),
+            signed_types: BTreeSet::new(),
+            repeat_types: BTreeSet::new(),
+            scales: BTreeMap::new(),
+            first_offsets: BTreeMap::new(),
+            type_base_addresses: BTreeMap::new(),
+            machine: None,
+            alignment: 1,
+            metadata: None,
+        }
+    }
+
+    /// Limits the memory used to hold relocation entries during compression
+    /// to about `limit` bytes.
+    ///
+    /// If the entries of the relocation section would exceed the limit, the
+    /// compressor does not hold them but reads the relocation section once
+    /// for every relocation type instead, which only needs memory for the
+    /// number of relocations of every type.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        self.memory_limit = Some(limit);
+        self.collected = false;
+    }
+
+    /// Sorts the relocations of every group by offset before encoding them,
+    /// and uses the lowest offset as base address, disabled by default.
+    ///
+    /// Linkers emit relocations in section order, which is not necessarily
+    /// address order. Without sorting, groups are encoded in input order, the
+    /// offsets of groups which are not ascending from their base address as
+    /// signed deltas, at a small size cost.
+    ///
+    /// Sorting needs the entries in memory: if the memory limit set by
+    /// [set_memory_limit](#method.set_memory_limit) is exceeded and a group
+    /// is not sorted, compression fails with `Unsupported`.
+    pub fn set_sort_offsets(&mut self, sort: bool) {
+        self.sort_offsets = sort;
+        self.collected = false;
+    }
+
+    /// Sets the byte order of the relocation section and of the compressed
+    /// data, little-endian by default.
+    ///
+    /// The byte order must match the data byte order of the target, e.g. big
+    /// for ARM BE8 images whose instructions are little-endian but whose data
+    /// is big-endian.
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+        self.collected = false;
+    }
+
+    /// Sets the encoding of the compressed data, `Encoding::Auto` by default.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Sets the codec of the offsets, `Codec::Uleb128` by default. Other
+    /// codecs are recorded in the compressed header.
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+
+    /// Encodes the offsets of a group as opcodes, which repeat an offset a
+    /// number of times, if this makes the compressed data smaller, enabled
+    /// by default. Hundreds of word-aligned relocations in a row then take
+    /// two bytes.
+    ///
+    /// Decoders without support for opcodes, `elf32_relocate_raw` and
+    /// `elf32_relocate_tiny` of `relox-core`, need it disabled.
+    pub fn set_repeat_offsets(&mut self, repeat: bool) {
+        self.repeat_offsets = repeat;
+    }
+
+    /// Records a scale per group, the largest number dividing its offset
+    /// deltas, and encodes the offsets divided by it if this makes the
+    /// compressed data smaller, disabled by default. Word-aligned
+    /// relocations then have their deltas stored divided by 4.
+    ///
+    /// The bitmap codec, whose bits already mark words, and the interleaved
+    /// and directory layouts ignore it.
+    pub fn set_scaled_offsets(&mut self, scaled: bool) {
+        self.scaled_offsets = scaled;
+    }
+
+    /// Records the target machine (`e_machine`) in the compressed header, so
+    /// decoders can verify that the section is applied on the right target.
+    pub fn set_machine(&mut self, machine: u16) {
+        self.machine = Some(machine);
+    }
+
+    /// Pads groups and runs with zeros so they start at multiples of
+    /// `alignment` bytes from the start of the compressed section, 1 by
+    /// default, e.g. for word-aligned reads on cores without unaligned
+    /// access like Cortex-M0. The alignment is recorded in the compressed
+    /// header. The directory layout does not support an alignment.
+    ///
+    /// # Errors
+    ///
+    /// If `alignment` is not a power of two.
+    pub fn set_group_alignment(&mut self, alignment: u8) -> Result<(), Error> {
+        if !alignment.is_power_of_two() {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        self.alignment = alignment;
+        Ok(())
+    }
+
+    /// Appends a metadata area to the compressed section, which decoders
+    /// skip but [read_metadata](fn.read_metadata.html) can read.
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be serialized.
+    pub fn set_metadata(&mut self, metadata: &Metadata) -> Result<(), Error> {
+        self.metadata = Some(metadata.to_bytes()?);
+        Ok(())
+    }
+
+    /// Returns the base address the compressor uses.
+    ///
+    /// Unless set by [set_base_address](#method.set_base_address), this is the
+    /// offset of the first relocation.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section is malformed.
+    pub fn base_address(&mut self) -> Result<u32, Error> {
+        self.collect_entries()?;
+        Ok(self.base_address)
+    }
+
+    /// Sets the base address the compressor uses, e.g. to align it down to a
+    /// segment boundary.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section is malformed or if `base_address` is greater
+    /// than the offset of any relocation. The base address is kept by setters
+    /// which collect the relocations again, e.g.
+    /// [set_endianness](#method.set_endianness), which then fail to compress
+    /// with `InvalidData` if it is greater than the offset of any relocation.
+    pub fn set_base_address(&mut self, base_address: u32) -> Result<(), Error> {
+        self.collect_entries()?;
+        if base_address > self.lowest_offset {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        self.base_address = base_address;
+        self.set_base_address = Some(base_address);
+        Ok(())
+    }
+
+    /// Sets the base address the offsets of relocations of `relocation_type`
+    /// are encoded relative to, e.g. the start of the GOT, which keeps deltas
+    /// small for types clustering in a region far from the base address.
+    ///
+    /// The base address is carried in the group header, so the grouped
+    /// encoding is used with a base address per group. Other encodings are
+    /// not supported once a type base address is set.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section is malformed or if `base_address` is greater
+    /// than the offset of any relocation of `relocation_type`.
+    pub fn set_type_base_address(
+        &mut self,
+        relocation_type: u8,
+        base_address: u32,
+    ) -> Result<(), Error> {
+        self.collect_entries()?;
+        for entry in self.relocations() {
+            if entry.relocation_type() == relocation_type && base_address > entry.offset() {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
         }
+        self.type_base_addresses
+            .insert(relocation_type, base_address);
+        Ok(())
     }
 
     /// Compresses this regular ELF32 relocation section and writes the
     /// compressed data to the provided in-memory buffer.
     /// Returns the number of bytes written if the compression is successful.
     pub fn compress(&mut self, output: &mut [u8]) -> Result<usize, Error> {
+        self.compress_to_writer(Cursor::new(output))
+    }
+
+    /// Compresses this regular ELF32 relocation section and writes the
+    /// compressed data to `writer`, e.g. a file or a `Vec<u8>`, without
+    /// requiring a buffer of the final size up front.
+    /// Returns the number of bytes written if the compression is successful.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section cannot be compressed, or `BufferSmall` if
+    /// writing to `writer` fails.
+    pub fn compress_to_writer<W: Write>(&mut self, writer: W) -> Result<usize, Error> {
         self.collect_entries()?;
-        let mut writer = Cursor::new(output);
-        self.write_header(&mut writer)?;
-        for key in self.entries.keys() {
+        self.scales = match self.encoding {
+            Encoding::Grouped | Encoding::Auto => self.select_scales()?,
+            Encoding::Interleaved | Encoding::Directory => BTreeMap::new(),
+        };
+        self.repeat_types = match self.encoding {
+            Encoding::Grouped | Encoding::Auto => self.select_repeat_types()?,
+            Encoding::Interleaved | Encoding::Directory => BTreeSet::new(),
+        };
+        let mut writer = CountingWriter { writer, count: 0 };
+        match self.encoding {
+            Encoding::Grouped => self.write_grouped(&mut writer)?,
+            Encoding::Interleaved => self.write_interleaved(&mut writer)?,
+            Encoding::Directory => self.write_directory(&mut writer)?,
+            Encoding::Auto => {
+                let (_, compressed) = self.write_auto()?;
+                writer
+                    .write_all(&compressed)
+                    .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+            }
+        }
+        if let Some(metadata) = &self.metadata {
+            writer
+                .write_all(metadata)
+                .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+        }
+        writer
+            .flush()
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+        Ok(writer.count)
+    }
+
+    /// Returns the exact size of the compressed data, including metadata,
+    /// without writing it, e.g. to allocate the output buffer or reserve
+    /// space in a linker script.
+    ///
+    /// The size is computed by running the compressor into a sink, so it
+    /// always matches what [compress](#method.compress) writes.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section cannot be compressed.
+    pub fn compressed_size_hint(&mut self) -> Result<usize, Error> {
+        self.compress_to_writer(std::io::sink())
+    }
+
+    /// Compresses this regular ELF32 relocation section like
+    /// [compressed_size_hint](#method.compressed_size_hint) and returns
+    /// statistics of the compressed data: its size, the encoding used and the
+    /// count, largest offset delta and compressed size of every relocation
+    /// type.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section cannot be compressed.
+    pub fn stats(&mut self) -> Result<CompressionStats, Error> {
+        let output_size = self.compressed_size_hint()?;
+        let encoding = match self.encoding {
+            Encoding::Auto => self.write_auto()?.0,
+            encoding => encoding,
+        };
+        let sizes = self.type_sizes(encoding)?;
+        let groups = self
+            .counts
+            .iter()
+            .map(|(key, count)| GroupStats::new(*key, *count, self.max_delta(*key), sizes[key]))
+            .collect();
+        Ok(CompressionStats::new(
+            self.len() * 8,
+            output_size,
+            encoding,
+            groups,
+        ))
+    }
+
+    /// Compresses this regular ELF32 relocation section like
+    /// [compress](#method.compress) and returns the debug map of the
+    /// compressed data along with its size.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section cannot be compressed.
+    #[cfg(feature = "decompress")]
+    pub fn compress_with_map(&mut self, output: &mut [u8]) -> Result<(usize, DebugMap), Error> {
+        let size = self.compress(output)?;
+        let map = match self.source {
+            Source::Section(data) => debug_map(data, self.endianness, &output[..size])?,
+            Source::Entries(entries) => {
+                let mut data = Vec::with_capacity(entries.len() * 8);
+                for entry in entries.iter() {
+                    data.extend_from_slice(&entry.to_bytes_endian(self.endianness));
+                }
+                debug_map(&data, self.endianness, &output[..size])?
+            }
+        };
+        Ok((size, map))
+    }
+
+    /// Appends the relocations of this section as new groups to `blob`, a
+    /// compressed ELF32 relocation section using the grouped layout, and
+    /// returns the updated compressed section, e.g. to add the relocations of
+    /// a plugin to a base image compressed before.
+    ///
+    /// New relocations are encoded relative to the base address of `blob`.
+    /// Relocations of a type already present in `blob` are added as a further
+    /// group of that type. A metadata area following the compressed section
+    /// in `blob` is kept, or replaced by the one set by
+    /// [set_metadata](#method.set_metadata). Other bytes following it, e.g.
+    /// padding, are dropped.
+    ///
+    /// # Errors
+    ///
+    /// If `blob` or the relocation section is malformed, if a relocation is
+    /// below the base address of `blob`, `TooManyGroups` if there would be too
+    /// many groups, or `Unsupported` if `blob` does not use the grouped layout
+    /// or if a type base address or a group alignment is set.
+    pub fn append_to(&mut self, blob: &[u8]) -> Result<Vec<u8>, Error> {
+        let (base_address, count, end) = self.read_grouped(blob)?;
+        self.collect_entries()?;
+        self.repeat_types.clear();
+        self.scales.clear();
+        let count = count as usize + self.counts.len();
+        if count >= SCALED as usize {
+            return Err(Error::new(ErrorKind::TooManyGroups));
+        }
+        if self.alignment > 1
+            || !self.type_base_addresses.is_empty()
+            || !self.symbol_types.is_empty()
+            || !self.signed_types.is_empty()
+            || self.codec != Codec::Uleb128
+        {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        if !self.counts.is_empty() && base_address > self.lowest_offset {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let metadata = match &self.metadata {
+            Some(metadata) => metadata.as_slice(),
+            None if blob[end..].starts_with(&METADATA_MAGIC) => {
+                let (_, size) = Metadata::parse(&blob[end..])?;
+                &blob[end..end + size]
+            }
+            None => &[],
+        };
+        let previous = self.base_address;
+        self.base_address = base_address;
+        let mut appended = Vec::with_capacity(end + self.len() * 8 + metadata.len());
+        let result = self.write_header(&mut appended, count as u8).and_then(|_| {
+            appended.extend_from_slice(&blob[5..end]);
+            for key in self.counts.keys() {
+                self.write_group(&mut appended, *key)?;
+            }
+            appended.extend_from_slice(metadata);
+            Ok(())
+        });
+        self.base_address = previous;
+        result.map(|_| appended)
+    }
+
+    /// Reads the header of a compressed section using the grouped layout and
+    /// returns its base address, its group count and its size.
+    fn read_grouped(&self, blob: &[u8]) -> Result<(u32, u8, usize), Error> {
+        let mut cursor = Cursor::new(blob);
+        let base_address = match self.endianness {
+            Endianness::Little => cursor.read_u32::<LittleEndian>(),
+            Endianness::Big => cursor.read_u32::<BigEndian>(),
+        }
+        .map_err(|_| Error::new(ErrorKind::NotEnoughData))?;
+        let count = cursor
+            .read_u8()
+            .map_err(|_| Error::new(ErrorKind::NotEnoughData))?;
+        if count >= SCALED {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        let read_uleb128 = |index: &mut usize| -> Result<u32, Error> {
+            let mut value = 0;
+            let bytes = blob
+                .get(*index..)
+                .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+            *index += uleb128::read_u32(bytes, &mut value)?;
+            Ok(value)
+        };
+        let mut index = 5;
+        for _ in 0..count {
+            // Relocation type.
+            index += 1;
+            for _ in 0..read_uleb128(&mut index)? {
+                read_uleb128(&mut index)?;
+            }
+        }
+        Ok((base_address, count, index))
+    }
+
+    /// Returns an iterator over the relocations to compress.
+    fn relocations(&self) -> SourceEntries<'a> {
+        SourceEntries {
+            source: self.source,
+            endianness: self.endianness,
+            index: 0,
+        }
+    }
+
+    /// Returns the number of relocations to compress.
+    fn len(&self) -> usize {
+        match self.source {
+            Source::Section(data) => data.len() / 8,
+            Source::Entries(entries) => entries.len(),
+        }
+    }
+
+    /// Collects relocation entries unless they are already collected.
+    ///
+    /// Only the number of relocations of every type is collected if holding
+    /// the entries would exceed the memory limit.
+    fn collect_entries(&mut self) -> Result<(), Error> {
+        if self.collected {
+            return Ok(());
+        }
+        let held = self.len() * core::mem::size_of::<Elf32Rel>();
+        self.streaming = self.memory_limit.is_some_and(|limit| held > limit);
+        self.entries.clear();
+        self.counts.clear();
+        self.sorted = true;
+        self.groups_sorted = true;
+        self.runs = 0;
+        self.symbol_types.clear();
+        self.signed_types.clear();
+        self.first_offsets.clear();
+        let mut previous = None;
+        let mut last_offsets = BTreeMap::new();
+        for entry in self.relocations() {
+            if self.counts.is_empty() {
+                self.base_address = entry.offset();
+                self.lowest_offset = entry.offset();
+            } else if self.sort_offsets {
+                self.base_address = self.base_address.min(entry.offset());
+                self.lowest_offset = self.base_address;
+            } else {
+                self.lowest_offset = self.lowest_offset.min(entry.offset());
+            }
+            let last = last_offsets.insert(entry.relocation_type(), entry.offset());
+            self.groups_sorted &= last.is_none_or(|last| last <= entry.offset());
+            if last.is_some_and(|last| last > entry.offset()) && !self.sort_offsets {
+                self.signed_types.insert(entry.relocation_type());
+            }
+            self.first_offsets
+                .entry(entry.relocation_type())
+                .or_insert(entry.offset());
+            match previous {
+                Some((offset, relocation_type)) => {
+                    self.sorted &= offset <= entry.offset();
+                    if relocation_type != entry.relocation_type() {
+                        self.runs += 1;
+                    }
+                }
+                None => self.runs = 1,
+            }
+            previous = Some((entry.offset(), entry.relocation_type()));
+            *self.counts.entry(entry.relocation_type()).or_default() += 1;
+            if entry.symbol() != 0 {
+                self.symbol_types.insert(entry.relocation_type());
+            }
+            if !self.streaming {
+                self.entries
+                    .entry(entry.relocation_type())
+                    .or_default()
+                    .push(entry);
+            }
+        }
+        // Keep the base address set by `set_base_address` if the entries are
+        // collected again, e.g. after the memory limit changed.
+        if let Some(base_address) = self.set_base_address {
+            if !self.counts.is_empty() && base_address > self.lowest_offset {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
+            self.base_address = base_address;
+        }
+        if self.sort_offsets && !self.groups_sorted {
+            if self.streaming {
+                return Err(Error::new(ErrorKind::Unsupported));
+            }
+            for (key, entries) in self.entries.iter_mut() {
+                entries.sort_by_key(Elf32Rel::offset);
+                self.first_offsets.insert(*key, entries[0].offset());
+            }
+        }
+        self.collected = true;
+        Ok(())
+    }
+
+    /// Returns the scale of every group, the largest number dividing its
+    /// offset deltas, if recording them makes the offsets smaller.
+    fn select_scales(&self) -> Result<BTreeMap<u8, u32>, Error> {
+        let mut scales = BTreeMap::new();
+        if !self.scaled_offsets || self.codec == Codec::Bitmap {
+            return Ok(scales);
+        }
+        // The `SCALED` prefix and the scales.
+        let mut cost = 1;
+        let mut gain = 0;
+        for key in self.counts.keys() {
+            let signed = self.is_signed(*key);
+            let mut previous = self.group_base_address(*key);
+            let mut scale = 0;
+            let mut update = |offset: u32| {
+                let delta = offset.wrapping_sub(previous);
+                scale = match signed {
+                    true => gcd(scale, (delta as i32).unsigned_abs()),
+                    false => gcd(scale, delta),
+                };
+                previous = offset;
+            };
+            if self.streaming {
+                for entry in self.relocations() {
+                    if entry.relocation_type() == *key {
+                        update(entry.offset());
+                    }
+                }
+            } else {
+                for entry in self.entries[key].iter() {
+                    update(entry.offset());
+                }
+            }
+            // Signed deltas are divided as such.
+            if scale == 0 || (signed && scale > i32::MAX as u32) {
+                scale = 1;
+            }
+            cost += uleb128_length(scale);
+            if scale > 1 {
+                let size = |scale| -> Result<usize, Error> {
+                    let mut writer = CountingWriter {
+                        writer: std::io::sink(),
+                        count: 0,
+                    };
+                    self.write_offsets_with(&mut writer, *key, false, scale)?;
+                    Ok(writer.count)
+                };
+                gain += size(1)? - size(scale)?;
+            }
+            scales.insert(*key, scale);
+        }
+        match gain > cost {
+            true => Ok(scales),
+            false => Ok(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the types of the groups whose offsets are smaller encoded as
+    /// opcodes repeating equal deltas, if flagging them in every group count
+    /// is worth it.
+    fn select_repeat_types(&self) -> Result<BTreeSet<u8>, Error> {
+        let mut types = BTreeSet::new();
+        if !self.repeat_offsets {
+            return Ok(types);
+        }
+        // The `REPEAT` prefix and the larger counts.
+        let mut cost = 1;
+        let mut gain = 0;
+        for (key, count) in self.counts.iter() {
+            let count = self.count_value(*key, *count);
+            cost += uleb128_length(count << 1) - uleb128_length(count);
+            if self.is_signed(*key) {
+                continue;
+            }
+            let size = |repeat| -> Result<usize, Error> {
+                let mut writer = CountingWriter {
+                    writer: std::io::sink(),
+                    count: 0,
+                };
+                self.write_offsets_with(&mut writer, *key, repeat, self.scale(*key))?;
+                Ok(writer.count)
+            };
+            let (plain, repeated) = (size(false)?, size(true)?);
+            if repeated < plain {
+                types.insert(*key);
+                gain += plain - repeated;
+            }
+        }
+        match gain > cost {
+            true => Ok(types),
+            false => Ok(BTreeSet::new()),
+        }
+    }
+
+    /// Writes the relocations using the encoding yielding the smaller result
+    /// and returns the encoding along with the compressed data.
+    fn write_auto(&self) -> Result<(Encoding, Vec<u8>), Error> {
+        let mut grouped = Vec::new();
+        let grouped = match self.write_grouped(&mut grouped) {
+            Ok(()) => Some(grouped),
+            Err(err) if err.kind().is_unsupported() => None,
+            Err(err) => return Err(err),
+        };
+        let mut interleaved = Vec::new();
+        let interleaved = match self.sorted && self.type_base_addresses.is_empty() {
+            true => Some(
+                self.write_interleaved(&mut interleaved)
+                    .map(|_| interleaved)?,
+            ),
+            false => None,
+        };
+        match (grouped, interleaved) {
+            (Some(grouped), Some(interleaved)) if interleaved.len() < grouped.len() => {
+                Ok((Encoding::Interleaved, interleaved))
+            }
+            (Some(grouped), _) => Ok((Encoding::Grouped, grouped)),
+            (None, Some(interleaved)) => Ok((Encoding::Interleaved, interleaved)),
+            (None, None) => Err(Error::new(ErrorKind::Unsupported)),
+        }
+    }
+
+    /// Writes the relocations grouped by type.
+    fn write_grouped<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if !self.type_base_addresses.is_empty() {
+            return self.write_based(writer);
+        }
+        // The group count is a byte and `SCALED`, `REPEAT`, `ALIGNED`,
+        // `SIGNED`, `BITMAP`, `SYMBOLS`, `GROUP_VARINT`, `MACHINE`, `BASED`,
+        // `DIRECTORY` and `INTERLEAVED` are reserved.
+        if self.counts.len() >= SCALED as usize {
+            return Err(Error::new(ErrorKind::TooManyGroups));
+        }
+        let mut writer = CountingWriter { writer, count: 0 };
+        self.write_header(&mut writer, self.counts.len() as u8)?;
+        for key in self.counts.keys() {
+            self.write_padding(&mut writer)?;
             self.write_group(&mut writer, *key)?;
         }
-        Ok(writer.position() as usize)
+        Ok(())
+    }
+
+    /// Writes the relocations grouped by type with a base address per group.
+    fn write_based<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.counts.len() > u8::MAX as usize {
+            return Err(Error::new(ErrorKind::TooManyGroups));
+        }
+        let mut writer = CountingWriter { writer, count: 0 };
+        self.write_header(&mut writer, BASED)?;
+        writer
+            .write_u8(self.counts.len() as u8)
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+        for key in self.counts.keys() {
+            self.write_padding(&mut writer)?;
+            writer
+                .write_u8(*key)
+                .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+            self.write_word(&mut writer, self.group_base_address(*key))?;
+            self.write_count(&mut writer, *key)?;
+            self.write_offsets(&mut writer, *key)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the relocations in address order as runs of the same type.
+    fn write_interleaved<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if !self.type_base_addresses.is_empty() {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        if !self.sorted {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let mut writer = CountingWriter { writer, count: 0 };
+        self.write_header(&mut writer, INTERLEAVED)?;
+        write_uleb128(&mut writer, self.runs)?;
+        self.for_each_run(|run, address| {
+            self.write_padding(&mut writer)?;
+            write_run(
+                &mut writer,
+                self.codec,
+                !self.symbol_types.is_empty(),
+                run,
+                address,
+            )
+        })
+    }
+
+    /// Calls `op` for every run of relocations of the same type in address
+    /// order with the last offset of the previous run, which returns the last
+    /// offset of the run.
+    fn for_each_run<F>(&self, mut op: F) -> Result<(), Error>
+    where
+        F: FnMut(&[Elf32Rel], u32) -> Result<u32, Error>,
+    {
+        let mut address = self.base_address;
+        let mut run: Vec<Elf32Rel> = Vec::new();
+        for entry in self.relocations() {
+            if run
+                .last()
+                .is_some_and(|last| last.relocation_type() != entry.relocation_type())
+            {
+                address = op(&run, address)?;
+                run.clear();
+            }
+            run.push(entry);
+        }
+        if !run.is_empty() {
+            op(&run, address)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes encoding the relocations of every type
+    /// with `encoding`, without padding.
+    fn type_sizes(&self, encoding: Encoding) -> Result<BTreeMap<u8, usize>, Error> {
+        let mut sizes = BTreeMap::new();
+        let mut writer = CountingWriter {
+            writer: std::io::sink(),
+            count: 0,
+        };
+        if encoding == Encoding::Interleaved {
+            self.for_each_run(|run, address| {
+                let start = writer.count;
+                let symbols = !self.symbol_types.is_empty();
+                let last = write_run(&mut writer, self.codec, symbols, run, address)?;
+                *sizes.entry(run[0].relocation_type()).or_insert(0) += writer.count - start;
+                Ok(last)
+            })?;
+            return Ok(sizes);
+        }
+        for key in self.counts.keys() {
+            let start = writer.count;
+            match encoding {
+                // Directory entries hold the type, the count and the stream.
+                Encoding::Directory => {
+                    self.write_offsets(&mut writer, *key)?;
+                    writer.count += 9;
+                }
+                _ if !self.type_base_addresses.is_empty() => {
+                    writer.count += 1;
+                    self.write_word(&mut writer, self.group_base_address(*key))?;
+                    self.write_count(&mut writer, *key)?;
+                    self.write_offsets(&mut writer, *key)?;
+                }
+                _ => self.write_group(&mut writer, *key)?,
+            }
+            sizes.insert(*key, writer.count - start);
+        }
+        Ok(sizes)
+    }
+
+    /// Returns the largest distance between the offsets of two consecutive
+    /// relocations of a group, the first one measured from the base address
+    /// of the group.
+    fn max_delta(&self, key: u8) -> u32 {
+        let mut previous = self.group_base_address(key);
+        let mut max_delta = 0;
+        let mut update = |offset: u32| {
+            max_delta = max_delta.max(offset.max(previous) - offset.min(previous));
+            previous = offset;
+        };
+        if self.streaming {
+            for entry in self.relocations() {
+                if entry.relocation_type() == key {
+                    update(entry.offset());
+                }
+            }
+        } else {
+            for entry in self.entries[&key].iter() {
+                update(entry.offset());
+            }
+        }
+        max_delta
+    }
+
+    /// Writes the group directory followed by the offset streams.
+    fn write_directory<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.counts.len() >= DIRECTORY as usize
+            || self.alignment > 1
+            || !self.type_base_addresses.is_empty()
+            || !self.symbol_types.is_empty()
+            || self.has_signed()
+        {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        self.write_header(writer, DIRECTORY)?;
+        writer
+            .write_u8(self.counts.len() as u8)
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+        let mut streams = Vec::new();
+        for key in self.counts.keys() {
+            let stream = streams.len() as u32;
+            self.write_offsets(&mut streams, *key)?;
+            writer
+                .write_u8(*key)
+                .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+            self.write_word(writer, self.counts[key])?;
+            self.write_word(writer, stream)?;
+        }
+        writer
+            .write_all(&streams)
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))
+    }
+
+    /// Writes the header.
+    fn write_header<W: Write>(&self, writer: &mut W, count: u8) -> Result<(), Error> {
+        // The signed, aligned, repeat and scaled prefixes follow all others,
+        // so the first of them takes the place of the group count.
+        let mut prefixes = Vec::new();
+        if self.has_signed() {
+            prefixes.push(SIGNED);
+        }
+        if self.alignment > 1 {
+            prefixes.extend_from_slice(&[ALIGNED, self.alignment]);
+        }
+        if !self.repeat_types.is_empty() && count != INTERLEAVED && count != DIRECTORY {
+            prefixes.push(REPEAT);
+        }
+        if !self.scales.is_empty() && count != INTERLEAVED && count != DIRECTORY {
+            prefixes.push(SCALED);
+        }
+        prefixes.push(count);
+        write_header(
+            writer,
+            self.endianness,
+            self.base_address,
+            self.machine,
+            self.codec,
+            !self.symbol_types.is_empty(),
+            prefixes[0],
+        )?;
+        writer
+            .write_all(&prefixes[1..])
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))
+    }
+
+    /// Writes zeros up to the next multiple of the group alignment.
+    fn write_padding<W: Write>(&self, writer: &mut CountingWriter<W>) -> Result<(), Error> {
+        let padding = writer.count.wrapping_neg() & (self.alignment as usize - 1);
+        writer
+            .write_all(&[0; 128][..padding])
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))
+    }
+
+    /// Writes a word in the byte order of the relocation section.
+    fn write_word<W: Write>(&self, writer: &mut W, word: u32) -> Result<(), Error> {
+        match self.endianness {
+            Endianness::Little => writer.write_u32::<LittleEndian>(word),
+            Endianness::Big => writer.write_u32::<BigEndian>(word),
+        }
+        .map_err(|_| Error::new(ErrorKind::BufferSmall))
+    }
+
+    /// Writes a group.
+    fn write_group<W: Write>(&self, writer: &mut W, key: u8) -> Result<(), Error> {
+        writer
+            .write_u8(key)
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+        self.write_count(writer, key)?;
+        self.write_offsets(writer, key)
+    }
+
+    /// Writes the relocation count of a group, flagging and followed by the
+    /// symbol indices of the group if the section carries symbol indices,
+    /// flagging signed offsets if the section has groups with signed offsets,
+    /// flagging repeat opcodes if the section has groups using them, and
+    /// followed by the scale of the group if the section records scales.
+    fn write_count<W: Write>(&self, writer: &mut W, key: u8) -> Result<(), Error> {
+        let symbols = self.symbol_types.contains(&key);
+        let mut count = self.count_value(key, self.counts[&key]);
+        if !self.repeat_types.is_empty() {
+            count = (count << 1) | self.repeat_types.contains(&key) as u32;
+        }
+        write_uleb128(writer, count)?;
+        if !self.scales.is_empty() {
+            write_uleb128(writer, self.scale(key))?;
+        }
+        if !symbols {
+            return Ok(());
+        }
+        let mut previous = 0;
+        if self.streaming {
+            for entry in self.relocations() {
+                if entry.relocation_type() == key {
+                    previous = write_symbol(writer, previous, entry.symbol())?;
+                }
+            }
+        } else {
+            for entry in self.entries[&key].iter() {
+                previous = write_symbol(writer, previous, entry.symbol())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the relocation count `count` of a group with the symbol and
+    /// signed flags of the section.
+    fn count_value(&self, key: u8, count: u32) -> u32 {
+        let mut count = count;
+        if !self.symbol_types.is_empty() {
+            count = (count << 1) | self.symbol_types.contains(&key) as u32;
+        }
+        if self.has_signed() {
+            count = (count << 1) | self.is_signed(key) as u32;
+        }
+        count
+    }
+
+    /// Returns the base address the offsets of a group are relative to.
+    fn group_base_address(&self, key: u8) -> u32 {
+        self.type_base_addresses
+            .get(&key)
+            .copied()
+            .unwrap_or(self.base_address)
+    }
+
+    /// Returns `true` if the offsets of a group are encoded as signed deltas,
+    /// as they are not ascending from the base address of the group.
+    fn is_signed(&self, key: u8) -> bool {
+        self.signed_types.contains(&key) || self.first_offsets[&key] < self.group_base_address(key)
+    }
+
+    /// Returns the scale the offsets of a group are divided by.
+    fn scale(&self, key: u8) -> u32 {
+        self.scales.get(&key).copied().unwrap_or(1)
+    }
+
+    /// Returns `true` if any group has its offsets encoded as signed deltas.
+    fn has_signed(&self) -> bool {
+        self.counts.keys().any(|key| self.is_signed(*key))
+    }
+
+    /// Writes the offsets of a group.
+    fn write_offsets<W: Write>(&self, writer: &mut W, key: u8) -> Result<(), Error> {
+        let repeat = self.repeat_types.contains(&key);
+        self.write_offsets_with(writer, key, repeat, self.scale(key))
+    }
+
+    /// Writes the offsets of a group divided by `scale`, as repeat opcodes if
+    /// `repeat` is set and the offsets are ascending.
+    fn write_offsets_with<W: Write>(
+        &self,
+        writer: &mut W,
+        key: u8,
+        repeat: bool,
+        scale: u32,
+    ) -> Result<(), Error> {
+        let address = self.group_base_address(key);
+        let mut offsets = match (self.is_signed(key), repeat) {
+            (true, _) => OffsetWriter::new_signed(address),
+            (false, true) => OffsetWriter::new_repeat(address),
+            (false, false) => OffsetWriter::new(self.codec, address),
+        }
+        .with_scale(scale);
+        if self.streaming {
+            for entry in self.relocations() {
+                if entry.relocation_type() == key {
+                    offsets.write(writer, entry.offset())?;
+                }
+            }
+        } else {
+            for entry in self.entries[&key].iter() {
+                offsets.write(writer, entry.offset())?;
+            }
+        }
+        offsets.finish(writer)?;
+        Ok(())
+    }
+}
+
+/// Collects relocations to compress, e.g. from tools which already hold
+/// structured relocation data, so they need not serialize them as a regular
+/// ELF32 relocation section first.
+///
+/// ```
+/// use relox::Elf32RelocsBuilder;
+///
+/// # fn main() -> Result<(), relox::Error> {
+/// let mut builder = Elf32RelocsBuilder::new();
+/// builder.push(0x1004, 0x17).push(0x1008, 0x17);
+/// let mut output = Vec::new();
+/// builder.build().compress_to_writer(&mut output)?;
+/// assert_eq!(output, [0x04, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x00, 0x04]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Elf32RelocsBuilder {
+    entries: Vec<Elf32Rel>,
+}
+
+impl Elf32RelocsBuilder {
+    /// Creates a new, empty `Elf32RelocsBuilder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new, empty `Elf32RelocsBuilder` instance with room for
+    /// `capacity` relocations.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Adds a relocation of `relocation_type` at `offset`.
+    pub fn push(&mut self, offset: u32, relocation_type: u8) -> &mut Self {
+        self.push_entry(Elf32Rel::new(offset, relocation_type))
+    }
+
+    /// Adds a relocation entry, e.g. one against a symbol.
+    pub fn push_entry(&mut self, entry: Elf32Rel) -> &mut Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Returns the relocations added so far, in the order they were added.
+    pub fn entries(&self) -> &[Elf32Rel] {
+        &self.entries
+    }
+
+    /// Returns an `Elf32Relocs` instance compressing the relocations added
+    /// so far, in the order they were added.
+    pub fn build(&self) -> Elf32Relocs<'_> {
+        Elf32Relocs::from_entries(&self.entries)
+    }
+}
+
+impl Extend<Elf32Rel> for Elf32RelocsBuilder {
+    fn extend<I: IntoIterator<Item = Elf32Rel>>(&mut self, iter: I) {
+        self.entries.extend(iter);
+    }
+}
+
+impl FromIterator<Elf32Rel> for Elf32RelocsBuilder {
+    fn from_iter<I: IntoIterator<Item = Elf32Rel>>(iter: I) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Writes the header of a compressed section, with the machine and codec
+/// prefixes if needed, followed by the group count or layout marker `count`.
+pub(crate) fn write_header<W: Write>(
+    writer: &mut W,
+    endianness: Endianness,
+    base_address: u32,
+    machine: Option<u16>,
+    codec: Codec,
+    symbols: bool,
+    count: u8,
+) -> Result<(), Error> {
+    match endianness {
+        Endianness::Little => writer.write_u32::<LittleEndian>(base_address),
+        Endianness::Big => writer.write_u32::<BigEndian>(base_address),
+    }
+    .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+    if let Some(machine) = machine {
+        writer
+            .write_u8(MACHINE)
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+        match endianness {
+            Endianness::Little => writer.write_u16::<LittleEndian>(machine),
+            Endianness::Big => writer.write_u16::<BigEndian>(machine),
+        }
+        .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+    }
+    let marker = match codec {
+        Codec::Uleb128 => None,
+        Codec::GroupVarint => Some(GROUP_VARINT),
+        Codec::Bitmap => Some(BITMAP),
+    };
+    if let Some(marker) = marker {
+        writer
+            .write_u8(marker)
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+    }
+    if symbols {
+        writer
+            .write_u8(SYMBOLS)
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+    }
+    writer
+        .write_u8(count)
+        .map_err(|_| Error::new(ErrorKind::BufferSmall))
+}
+
+/// Writes offsets, each relative to the previous one, using a codec.
+pub(crate) struct OffsetWriter {
+    codec: Codec,
+    /// Whether offsets are written as SLEB128 encoded signed deltas instead.
+    signed: bool,
+    address: u32,
+    block: Vec<u32>,
+    /// First offset of the current bitmap run, if any.
+    run: Option<u32>,
+    /// Bitmap bytes of the current run, without continuation bits.
+    bitmap: Vec<u8>,
+    /// Whether offsets are written as opcodes repeating equal deltas instead.
+    repeat: bool,
+    /// Delta and number of repeats of the pending opcode.
+    repeated: (u32, u32),
+    /// Scale the deltas are divided by.
+    scale: u32,
+}
+
+impl OffsetWriter {
+    /// Creates a new `OffsetWriter` instance, the first offset relative to
+    /// `address`.
+    pub(crate) fn new(codec: Codec, address: u32) -> Self {
+        Self {
+            codec,
+            signed: false,
+            address,
+            block: Vec::with_capacity(4),
+            run: None,
+            bitmap: Vec::new(),
+            repeat: false,
+            repeated: (0, 0),
+            scale: 1,
+        }
+    }
+
+    /// Creates a new `OffsetWriter` instance writing SLEB128 encoded signed
+    /// deltas whatever the codec, the first offset relative to `address`.
+    pub(crate) fn new_signed(address: u32) -> Self {
+        Self {
+            signed: true,
+            ..Self::new(Codec::Uleb128, address)
+        }
+    }
+
+    /// Creates a new `OffsetWriter` instance writing opcodes, each either an
+    /// offset or a repeat count followed by the offset to repeat, whatever
+    /// the codec, the first offset relative to `address`.
+    pub(crate) fn new_repeat(address: u32) -> Self {
+        Self {
+            repeat: true,
+            ..Self::new(Codec::Uleb128, address)
+        }
+    }
+
+    /// Divides the deltas by `scale`, which must divide all of them.
+    pub(crate) fn with_scale(self, scale: u32) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// Writes an offset.
+    pub(crate) fn write<W: Write>(&mut self, writer: &mut W, offset: u32) -> Result<(), Error> {
+        if self.signed {
+            let delta = offset.wrapping_sub(self.address) as i32 / self.scale as i32;
+            self.address = offset;
+            return write_sleb128(writer, delta);
+        }
+        let delta = (offset - self.address) / self.scale;
+        if self.repeat {
+            self.address = offset;
+            let (previous, repeats) = self.repeated;
+            // The repeat count shifted left by one must fit the opcode.
+            if repeats > 0 && previous == delta && repeats < u32::MAX >> 1 {
+                self.repeated.1 += 1;
+                return Ok(());
+            }
+            self.flush(writer)?;
+            self.repeated = (delta, 1);
+            return Ok(());
+        }
+        let previous = self.address;
+        self.address = offset;
+        match self.codec {
+            Codec::Uleb128 => write_uleb128(writer, delta),
+            Codec::Bitmap => {
+                if let Some(run) = self.run {
+                    // Word of the offset in the run, the first one being 1.
+                    let word = (offset - run) / 4;
+                    let byte = word.wrapping_sub(1) as usize / 7;
+                    if offset > previous
+                        && (offset - run).is_multiple_of(4)
+                        && byte <= self.bitmap.len()
+                    {
+                        if byte == self.bitmap.len() {
+                            self.bitmap.push(0);
+                        }
+                        self.bitmap[byte] |= 1 << ((word - 1) % 7);
+                        return Ok(());
+                    }
+                    // The run is followed by another one, so it needs a
+                    // bitmap byte even if it has a single offset.
+                    if self.bitmap.is_empty() {
+                        self.bitmap.push(0);
+                    }
+                    self.flush(writer)?;
+                }
+                self.run = Some(offset);
+                write_uleb128(writer, delta)
+            }
+            Codec::GroupVarint => {
+                self.block.push(delta);
+                if self.block.len() == 4 {
+                    self.flush(writer)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the pending offsets and returns the last offset.
+    pub(crate) fn finish<W: Write>(mut self, writer: &mut W) -> Result<u32, Error> {
+        self.flush(writer)?;
+        Ok(self.address)
+    }
+
+    /// Returns the number of bytes the pending offsets take once written.
+    pub(crate) fn pending_size(&self) -> usize {
+        if self.repeat {
+            return repeat_size(self.repeated.0, self.repeated.1)
+                .min(single_size(self.repeated.0, self.repeated.1));
+        }
+        if self.codec == Codec::Bitmap {
+            return self.bitmap.len();
+        }
+        match self.block.is_empty() {
+            true => 0,
+            false => {
+                1 + self
+                    .block
+                    .iter()
+                    .map(|delta| group_varint_length(*delta))
+                    .sum::<usize>()
+            }
+        }
+    }
+
+    /// Writes the pending group-varint block, bitmap bytes or repeat opcode,
+    /// if any.
+    fn flush<W: Write>(&mut self, writer: &mut W) -> Result<(), Error> {
+        if self.repeat {
+            let (delta, repeats) = core::mem::take(&mut self.repeated);
+            if repeats == 0 {
+                return Ok(());
+            }
+            if single_size(delta, repeats) <= repeat_size(delta, repeats) {
+                for _ in 0..repeats {
+                    write_uleb128(writer, delta << 1)?;
+                }
+                return Ok(());
+            }
+            write_uleb128(writer, (repeats << 1) | 1)?;
+            return write_uleb128(writer, delta);
+        }
+        if self.codec == Codec::Bitmap {
+            let last = self.bitmap.len().saturating_sub(1);
+            for byte in self.bitmap[..last].iter_mut() {
+                *byte |= 0x80;
+            }
+            writer
+                .write_all(&self.bitmap)
+                .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+            self.bitmap.clear();
+            return Ok(());
+        }
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        let mut encoded = [0; 17];
+        let mut written = 1;
+        for (lane, delta) in self.block.iter().enumerate() {
+            let length = group_varint_length(*delta);
+            encoded[0] |= ((length - 1) as u8) << (2 * lane);
+            encoded[written..written + length].copy_from_slice(&delta.to_le_bytes()[..length]);
+            written += length;
+        }
+        self.block.clear();
+        writer
+            .write_all(&encoded[..written])
+            .map_err(|_| Error::new(ErrorKind::BufferSmall))
+    }
+}
+
+/// Returns the number of bytes of `repeats` offsets `delta` written as single
+/// offset opcodes, or `usize::MAX` if the offset does not fit an opcode.
+fn single_size(delta: u32, repeats: u32) -> usize {
+    match delta.leading_zeros() {
+        0 => usize::MAX,
+        _ => uleb128_length(delta << 1).saturating_mul(repeats as usize),
+    }
+}
+
+/// Returns the number of bytes of `repeats` offsets `delta` written as a
+/// repeat opcode.
+fn repeat_size(delta: u32, repeats: u32) -> usize {
+    uleb128_length((repeats << 1) | 1) + uleb128_length(delta)
+}
+
+/// Returns the greatest common divisor of `a` and `b`.
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+/// Returns the number of bytes of a ULEB128 encoded value.
+fn uleb128_length(value: u32) -> usize {
+    ((38 - value.leading_zeros() as usize) / 7).max(1)
+}
+
+/// Returns the number of bytes of a group-varint encoded value.
+fn group_varint_length(value: u32) -> usize {
+    (4 - value.leading_zeros() as usize / 8).max(1)
+}
+
+/// Writes a run of relocations of the same type, the first offset relative to
+/// `address`, with their symbol indices if the section carries them, and
+/// returns the last offset.
+fn write_run<W: Write>(
+    writer: &mut W,
+    codec: Codec,
+    symbols: bool,
+    run: &[Elf32Rel],
+    address: u32,
+) -> Result<u32, Error> {
+    writer
+        .write_u8(run[0].relocation_type())
+        .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
+    let count = run.len() as u32;
+    if !symbols {
+        write_uleb128(writer, count)?;
+    } else if run.iter().all(|entry| entry.symbol() == 0) {
+        write_uleb128(writer, count << 1)?;
+    } else {
+        write_uleb128(writer, (count << 1) | 1)?;
+        let mut previous = 0;
+        for entry in run {
+            previous = write_symbol(writer, previous, entry.symbol())?;
+        }
+    }
+    let mut encoder = OffsetWriter::new(codec, address);
+    for entry in run {
+        encoder.write(writer, entry.offset())?;
+    }
+    encoder.finish(writer)
+}
+
+/// Writer counting the bytes written through it.
+struct CountingWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes the symbol index `symbol` following `previous` as the zigzag encoded
+/// difference of the two as ULEB128, and returns `symbol`.
+fn write_symbol<W: Write>(writer: &mut W, previous: u32, symbol: u32) -> Result<u32, Error> {
+    let delta = symbol.wrapping_sub(previous) as i32;
+    write_uleb128(writer, ((delta << 1) ^ (delta >> 31)) as u32)?;
+    Ok(symbol)
+}
+
+/// Writes a value encoded as ULEB128.
+pub(crate) fn write_uleb128<W: Write>(writer: &mut W, value: u32) -> Result<(), Error> {
+    let mut encoded: [u8; 5] = [0; 5];
+    let written = uleb128::write_u32(value, &mut encoded)?;
+    writer
+        .write_all(&encoded[0..written])
+        .map_err(|_| Error::new(ErrorKind::BufferSmall))
+}
+
+/// Writes a value encoded as SLEB128.
+fn write_sleb128<W: Write>(writer: &mut W, value: i32) -> Result<(), Error> {
+    let mut encoded: [u8; 5] = [0; 5];
+    let written = uleb128::write_i32(value, &mut encoded)?;
+    writer
+        .write_all(&encoded[0..written])
+        .map_err(|_| Error::new(ErrorKind::BufferSmall))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elf32relocs_new() {
+        let memory: [u8; 0] = [0; 0];
+        let _ = Elf32Relocs::new(&memory);
+    }
+
+    #[test]
+    fn test_elf32relocs_base_address() {
+        let memory: [u8; 16] = [
+            0x02, 0x01, 0x00, 0x00, // Elf32Rel[0], will become base address
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x01, 0x02, 0x00, 0x00, // Elf32Rel[1]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut relocs = Elf32Relocs::new(&memory);
+        assert_eq!(relocs.base_address().unwrap(), 0x0102);
+    }
+
+    #[test]
+    fn test_elf32relocs_set_base_address() {
+        let memory: [u8; 16] = [
+            0x02, 0x01, 0x00, 0x00, // Elf32Rel[0]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x01, 0x02, 0x00, 0x00, // Elf32Rel[1]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_base_address(0x0100).unwrap();
+        assert_eq!(relocs.base_address().unwrap(), 0x0100);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(written, 10);
+        assert_eq!(output[0..5], [0x00, 0x01, 0x00, 0x00, 0x01]);
+        assert_eq!(output[5..10], [0x05, 0x02, 0x02, 0xFF, 0x01]);
+    }
+
+    #[test]
+    fn test_elf32relocs_set_base_address_too_high() {
+        let memory: [u8; 16] = [
+            0x02, 0x01, 0x00, 0x00, // Elf32Rel[0]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x01, 0x02, 0x00, 0x00, // Elf32Rel[1]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let err = relocs.set_base_address(0x0103).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(relocs.base_address().unwrap(), 0x0102);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_symbols() {
+        let mut memory = Vec::new();
+        for (offset, relocation_type, symbol) in
+            [(0x1000, 0x02, 5), (0x1004, 0x02, 3), (0x1008, 0x17, 0)].iter()
+        {
+            memory.extend_from_slice(
+                &Elf32Rel::with_symbol(*offset, *relocation_type, *symbol).to_bytes(),
+            );
+        }
+        let mut output = [0; 16];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(Encoding::Grouped);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, 0xFA, 0x02, // header
+                0x02, 0x05, 0x0A, 0x03, 0x00, 0x04, // symbols 5, 3
+                0x17, 0x02, 0x08, // no symbols
+            ]
+        );
+
+        let err = relocs.append_to(&output[..written]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_to_writer() {
+        let memory: [u8; 24] = [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00, 0x00, // Elf32Rel[0]
+            0x41, 0x02, 0x03, 0x04, 0x01, 0x00, 0x00, 0x00, // Elf32Rel[1]
+            0x02, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00, 0x00, // Elf32Rel[2]
+        ];
+        let mut expected = [0; 16];
+        let size = Elf32Relocs::new(&memory).compress(&mut expected).unwrap();
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_metadata(&Metadata::new()).unwrap();
+        let mut output = Vec::new();
+        let written = relocs.compress_to_writer(&mut output).unwrap();
+        assert_eq!(written, output.len());
+        assert_eq!(output[..size], expected[..size]);
+        assert_eq!(output[size..], Metadata::new().to_bytes().unwrap()[..]);
+
+        let err = relocs
+            .compress_to_writer(Cursor::new(&mut [0; 4][..]))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
     }
 
-    /// Collects relocation entries.
-    fn collect_entries(&mut self) -> Result<(), Error> {
-        let mut cursor = Cursor::new(self.data);
-        loop {
-            if let Ok(entry) = Elf32Rel::from_memory(&mut cursor) {
-                if self.entries.len() == 0 {
-                    self.base_address = entry.offset();
-                } else if self.base_address > entry.offset() {
-                    return Err(Error::new(ErrorKind::InvalidData));
-                }
-                if !self.entries.contains_key(&entry.relocation_type()) {
-                    self.entries.insert(entry.relocation_type(), Vec::new());
-                }
-                self.entries
-                    .get_mut(&entry.relocation_type())
-                    .unwrap()
-                    .push(entry);
-            } else {
-                break;
+    #[test]
+    fn test_elf32relocs_compressed_size_hint() {
+        let mut memory = Vec::new();
+        for (index, offset) in [0x1000, 0x1004, 0x1100, 0x2_0000, 0x2_0008, 0x2_0010]
+            .iter()
+            .enumerate()
+        {
+            let relocation_type = if index % 2 == 0 { 0x17 } else { 0x02 };
+            let symbol = if index == 3 { 7 } else { 0 };
+            memory.extend_from_slice(
+                &Elf32Rel::with_symbol(*offset, relocation_type, symbol).to_bytes(),
+            );
+        }
+        for encoding in [Encoding::Auto, Encoding::Grouped, Encoding::Interleaved].iter() {
+            for codec in [Codec::Uleb128, Codec::GroupVarint, Codec::Bitmap].iter() {
+                let mut relocs = Elf32Relocs::new(&memory);
+                relocs.set_encoding(*encoding);
+                relocs.set_codec(*codec);
+                relocs.set_machine(40);
+                let hint = relocs.compressed_size_hint().unwrap();
+                let mut output = [0; 64];
+                assert_eq!(relocs.compress(&mut output).unwrap(), hint);
             }
         }
-        Ok(())
-    }
 
-    /// Writes the header.
-    fn write_header(&self, writer: &mut Cursor<&mut [u8]>) -> Result<(), Error> {
-        writer
-            .write_u32::<LittleEndian>(self.base_address)
-            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
-        writer
-            .write_u8(self.entries.keys().len() as u8)
-            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
-        Ok(())
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(Encoding::Directory);
+        let err = relocs.compressed_size_hint().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
     }
 
-    /// Writes a group.
-    fn write_group(&self, writer: &mut Cursor<&mut [u8]>, key: u8) -> Result<(), Error> {
-        writer
-            .write_u8(key)
-            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
-        let mut count: [u8; 5] = [0; 5];
-        let written = uleb128::write_u32(self.entries[&key].len() as u32, &mut count)?;
-        writer
-            .write_all(&count[0..written])
-            .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
-        let mut base_address = self.base_address;
-        for entry in self.entries[&key].iter() {
-            let written = uleb128::write_u32(entry.offset() - base_address, &mut count)?;
-            writer
-                .write_all(&count[0..written])
-                .map_err(|_| Error::new(ErrorKind::BufferSmall))?;
-            base_address = entry.offset();
+    #[test]
+    fn test_elf32relocs_builder() {
+        let memory: [u8; 24] = [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00, 0x00, // Elf32Rel[0]
+            0x41, 0x02, 0x03, 0x04, 0x01, 0x07, 0x00, 0x00, // Elf32Rel[1]
+            0x02, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00, 0x00, // Elf32Rel[2]
+        ];
+        let mut builder = Elf32RelocsBuilder::with_capacity(3);
+        builder
+            .push(0x0403_0201, 0x05)
+            .push_entry(Elf32Rel::with_symbol(0x0403_0241, 0x01, 7));
+        builder.extend(core::iter::once(Elf32Rel::new(0x0403_0202, 0x05)));
+        assert_eq!(builder.entries().len(), 3);
+
+        for encoding in [Encoding::Auto, Encoding::Grouped].iter() {
+            let mut expected = [0; 32];
+            let mut relocs = Elf32Relocs::new(&memory);
+            relocs.set_encoding(*encoding);
+            let size = relocs.compress(&mut expected).unwrap();
+            let mut built = builder.build();
+            built.set_encoding(*encoding);
+            let mut output = Vec::new();
+            assert_eq!(built.compress_to_writer(&mut output).unwrap(), size);
+            assert_eq!(output, expected[..size]);
         }
-        Ok(())
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::error::ErrorKind;
+        let builder: Elf32RelocsBuilder = builder.entries().iter().copied().collect();
+        let mut relocs = builder.build();
+        relocs.set_memory_limit(0);
+        let size = relocs.compress(&mut [0; 32]).unwrap();
+        assert_eq!(relocs.compressed_size_hint().unwrap(), size);
+        #[cfg(feature = "decompress")]
+        {
+            let (_, map) = relocs.compress_with_map(&mut [0; 32]).unwrap();
+            assert_eq!(map.groups().len(), 2);
+        }
+    }
 
     #[test]
-    fn test_elf32rel_std_fmt_debug() {
-        let memory: [u8; 8] = [0; 8];
-        let mut cursor = Cursor::new(&memory[..]);
-        let elf32rel = Elf32Rel::from_memory(&mut cursor).unwrap();
-        println!("{:?}", elf32rel);
+    fn test_elf32relocs_compress_memory_limit() {
+        let memory: [u8; 24] = [
+            0x01, 0x02, 0x03, 0x04, // Elf32Rel[0], will become base address
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x41, 0x02, 0x03, 0x04, // Elf32Rel[1]
+            0x01, 0x00, 0x00, 0x00, // Type is 1
+            0x02, 0x02, 0x03, 0x04, // Elf32Rel[2]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut expected: [u8; 16] = [0; 16];
+        let written = Elf32Relocs::new(&memory).compress(&mut expected).unwrap();
+        let held = 3 * core::mem::size_of::<Elf32Rel>();
+        for limit in &[0, 16, held] {
+            let mut output: [u8; 16] = [0; 16];
+            let mut relocs = Elf32Relocs::new(&memory);
+            relocs.set_memory_limit(*limit);
+            assert_eq!(relocs.compress(&mut output).unwrap(), written);
+            assert_eq!(output, expected);
+            assert_eq!(relocs.streaming, *limit < held);
+        }
     }
 
     #[test]
-    fn test_elf32rel_from_memory_offset_bad() {
-        let memory: [u8; 3] = [0; 3];
-        let mut cursor = Cursor::new(&memory[..]);
-        let err = Elf32Rel::from_memory(&mut cursor).unwrap_err();
-        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    fn test_elf32relocs_set_base_address_memory_limit() {
+        let memory: [u8; 16] = [
+            0x02, 0x01, 0x00, 0x00, // Elf32Rel[0]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x01, 0x02, 0x00, 0x00, // Elf32Rel[1]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut output: [u8; 16] = [0; 16];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_memory_limit(0);
+        relocs.set_base_address(0x0103).unwrap_err();
+        relocs.set_base_address(0x0100).unwrap();
+        assert_eq!(relocs.compress(&mut output).unwrap(), 10);
+        assert_eq!(
+            output[0..10],
+            [0x00, 0x01, 0x00, 0x00, 0x01, 0x05, 0x02, 0x02, 0xFF, 0x01]
+        );
     }
 
     #[test]
-    fn test_elf32rel_from_memory_info_bad() {
-        let memory: [u8; 7] = [0; 7];
-        let mut cursor = Cursor::new(&memory[..]);
-        let err = Elf32Rel::from_memory(&mut cursor).unwrap_err();
-        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    fn test_elf32relocs_set_base_address_recollected() {
+        let memory: [u8; 16] = [
+            0x02, 0x01, 0x00, 0x00, // Elf32Rel[0]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x01, 0x02, 0x00, 0x00, // Elf32Rel[1]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut output: [u8; 16] = [0; 16];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_base_address(0x0100).unwrap();
+        relocs.set_memory_limit(0);
+        assert_eq!(relocs.base_address().unwrap(), 0x0100);
+        relocs.set_sort_offsets(true);
+        assert_eq!(relocs.base_address().unwrap(), 0x0100);
+        relocs.set_endianness(Endianness::Little);
+        assert_eq!(relocs.compress(&mut output).unwrap(), 10);
+        assert_eq!(
+            output[0..10],
+            [0x00, 0x01, 0x00, 0x00, 0x01, 0x05, 0x02, 0x02, 0xFF, 0x01]
+        );
+
+        // The offset read as big-endian is below the base address.
+        let memory: [u8; 8] = [0x00, 0x00, 0x00, 0x10, 0x05, 0x00, 0x00, 0x05];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_base_address(0x1000_0000).unwrap();
+        relocs.set_endianness(Endianness::Big);
+        let err = relocs.compress(&mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
     }
 
     #[test]
-    fn test_elf32rel_from_memory() {
-        let memory: [u8; 8] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
-        let mut cursor = Cursor::new(&memory[..]);
-        let rel = Elf32Rel::from_memory(&mut cursor).unwrap();
-        let offset = rel.offset();
-        let relocation_type = rel.relocation_type();
-        assert_eq!(offset, 0x04030201);
-        assert_eq!(relocation_type, 0x05);
+    fn test_elf32relocs_compress_twice() {
+        let memory: [u8; 8] = [
+            0x01, 0x02, 0x03, 0x04, // Elf32Rel[0], will become base address
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut output1: [u8; 16] = [0; 16];
+        let mut output2: [u8; 16] = [0; 16];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let written1 = relocs.compress(&mut output1).unwrap();
+        let written2 = relocs.compress(&mut output2).unwrap();
+        assert_eq!(written1, written2);
+        assert_eq!(output1, output2);
     }
 
     #[test]
-    fn test_elf32relocs_new() {
-        let memory: [u8; 0] = [0; 0];
-        let _ = Elf32Relocs::new(&memory);
+    fn test_elf32relocs_compress_endianness_big() {
+        let memory: [u8; 16] = [
+            0x00, 0x00, 0x10, 0x00, // Elf32Rel[0], will become base address
+            0x00, 0x00, 0x00, 0x17, // Type is 0x17
+            0x00, 0x00, 0x10, 0x04, // Elf32Rel[1]
+            0x00, 0x00, 0x00, 0x17, // Type is 0x17
+        ];
+        let mut output: [u8; 16] = [0; 16];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_endianness(Endianness::Big);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [0x00, 0x00, 0x10, 0x00, 0x01, 0x17, 0x02, 0x00, 0x04]
+        );
     }
 
     #[test]
@@ -256,8 +1917,97 @@ mod tests {
         ];
         let mut output: [u8; 128] = [0; 128];
         let mut relocs = Elf32Relocs::new(&memory);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x02, 0x00, 0x00, 0x00, 0xF8, 0x01, // header, signed
+                0x05, 0x05, 0x00, 0x7F, // signed group: 0x02, 0x01
+            ]
+        );
+        relocs.set_encoding(Encoding::Directory);
         let err = relocs.compress(&mut output).unwrap_err();
-        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_signed_groups() {
+        let memory: [u8; 32] = [
+            0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[0]
+            0x00, 0x20, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // Elf32Rel[1]
+            0x04, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[2]
+            0x00, 0x0F, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, // Elf32Rel[3]
+        ];
+        let mut output = [0; 32];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_codec(Codec::GroupVarint);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, 0xFB, 0xF8, 0xF6, 0x03, // header
+                0x02, 0x05, 0x80, 0x40, // opcodes: 0x2000
+                0x16, 0x06, 0x80, 0x7E, // signed group: 0x0F00
+                0x17, 0x09, 0x00, 0x08, // opcodes: 0x1000, 0x1004
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_sort_offsets() {
+        let memory: [u8; 32] = [
+            0x08, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[0]
+            0x00, 0x20, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // Elf32Rel[1]
+            0x04, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[2]
+            0x00, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // Elf32Rel[3]
+        ];
+        let mut output = [0; 16];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_sort_offsets(true);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, 0x02, // header
+                0x02, 0x02, 0x00, 0x80, 0x20, // 0x1000, 0x2000
+                0x17, 0x02, 0x04, 0x04, // 0x1004, 0x1008
+            ]
+        );
+        assert_eq!(relocs.base_address().unwrap(), 0x1000);
+
+        relocs.set_memory_limit(0);
+        let err = relocs.compress(&mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+        relocs.set_sort_offsets(false);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x08, 0x10, 0x00, 0x00, 0xF8, 0x02, // header, signed
+                0x02, 0x05, 0xF8, 0x1F, 0x80, 0x60, // 0x2000, 0x1000
+                0x17, 0x05, 0x00, 0x7C, // 0x1008, 0x1004
+            ]
+        );
+        assert_eq!(relocs.base_address().unwrap(), 0x1008);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_all_types() {
+        // ELF32_R_TYPE is the low byte of r_info, so there are 256 types.
+        let mut builder = Elf32RelocsBuilder::new();
+        for relocation_type in 0..=u8::MAX {
+            builder.push(0x1000 + 4 * relocation_type as u32, relocation_type);
+        }
+        let mut relocs = builder.build();
+        relocs.set_encoding(Encoding::Grouped);
+        let err = relocs.compressed_size_hint().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooManyGroups);
+
+        relocs.set_encoding(Encoding::Auto);
+        let mut output = Vec::new();
+        relocs.compress_to_writer(&mut output).unwrap();
+        assert_eq!(output[4..7], [INTERLEAVED, 0x80, 0x02]);
     }
 
     #[test]
@@ -330,4 +2080,555 @@ mod tests {
         //     offsets[1]
         assert_eq!(output[11], 0x01);
     }
+
+    /// Sparse relocations of two interleaved types, which compress better in
+    /// address order.
+    fn interleaved_section() -> Vec<u8> {
+        let relocations: [(u32, u32); 6] = [
+            (0x001000, 0x02),
+            (0x101000, 0x02),
+            (0x101004, 0x17),
+            (0x201004, 0x17),
+            (0x201008, 0x02),
+            (0x301008, 0x02),
+        ];
+        let mut memory = Vec::new();
+        for (offset, info) in relocations.iter() {
+            memory.extend_from_slice(&offset.to_le_bytes());
+            memory.extend_from_slice(&info.to_le_bytes());
+        }
+        memory
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_auto_interleaved() {
+        let memory = interleaved_section();
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, // base_address
+                0xFF, // count, interleaved
+                0x03, // runs
+                0x02, 0x02, 0x00, 0x80, 0x80, 0x40, // run[0]
+                0x17, 0x02, 0x04, 0x80, 0x80, 0x40, // run[1]
+                0x02, 0x02, 0x04, 0x80, 0x80, 0x40, // run[2]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_grouped() {
+        let memory = interleaved_section();
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(Encoding::Grouped);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(written, 25);
+        assert_eq!(output[4], 0x02);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_interleaved_not_sorted() {
+        let memory: [u8; 24] = [
+            0x00, 0x10, 0x00, 0x00, // Elf32Rel[0], will become base address
+            0x02, 0x00, 0x00, 0x00, // Type is 2
+            0x10, 0x10, 0x00, 0x00, // Elf32Rel[1]
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+            0x08, 0x10, 0x00, 0x00, // Elf32Rel[2], below Elf32Rel[1]
+            0x02, 0x00, 0x00, 0x00, // Type is 2
+        ];
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(written, 12);
+        assert_eq!(output[4], 0x02);
+        relocs.set_encoding(Encoding::Interleaved);
+        let err = relocs.compress(&mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_too_many_groups() {
+        let mut memory = Vec::new();
+        for relocation_type in 0..255u32 {
+            memory.extend_from_slice(&(0x1000 + relocation_type * 4).to_le_bytes());
+            memory.extend_from_slice(&relocation_type.to_le_bytes());
+        }
+        let mut output: [u8; 2048] = [0; 2048];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(output[4], 0xFF);
+        assert_eq!(written, 5 + 2 + 255 * 3);
+        relocs.set_encoding(Encoding::Grouped);
+        let err = relocs.compress(&mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooManyGroups);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_machine() {
+        let memory = interleaved_section();
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let untagged = relocs.compress(&mut output).unwrap();
+        relocs.set_machine(0x28);
+        relocs.set_endianness(Endianness::Little);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(written, untagged + 3);
+        assert_eq!(output[4..8], [0xFC, 0x28, 0x00, 0xFF]);
+        relocs.set_endianness(Endianness::Big);
+        relocs.compress(&mut output).unwrap();
+        assert_eq!(output[4..7], [0xFC, 0x00, 0x28]);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_group_varint() {
+        let memory = interleaved_section();
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(Encoding::Grouped);
+        relocs.set_codec(Codec::GroupVarint);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, // base_address
+                0xFB, // group-varint codec
+                0x02, // count
+                0x02, 0x04, // group[0].relocation_type, count
+                0xA8, // group[0].offsets tag, lengths 1, 3, 3 and 3
+                0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x10, 0x00, 0x00, 0x10, 0x17,
+                0x02, // group[1].relocation_type, count
+                0x0A, // group[1].offsets tag, lengths 3 and 3
+                0x04, 0x00, 0x10, 0x00, 0x00, 0x10,
+            ]
+        );
+        relocs.set_machine(0x28);
+        relocs.compress(&mut output).unwrap();
+        assert_eq!(output[4..9], [0xFC, 0x28, 0x00, 0xFB, 0x02]);
+        let err = relocs.append_to(&output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_bitmap() {
+        let mut memory = Vec::new();
+        for index in 0..9 {
+            memory.extend_from_slice(&Elf32Rel::new(0x1000 + 4 * index, 0x17).to_bytes());
+        }
+        memory.extend_from_slice(&Elf32Rel::new(0x1100, 0x02).to_bytes());
+        let mut output: [u8; 32] = [0; 32];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(Encoding::Grouped);
+        relocs.set_codec(Codec::Bitmap);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, // base_address
+                0xF9, // bitmap codec
+                0x02, // count
+                0x02, 0x01, // group[0].relocation_type, count
+                0x80, 0x02, // group[0].offsets, last offset without bitmap
+                0x17, 0x09, // group[1].relocation_type, count
+                0x00, 0xFF, 0x01, // group[1].offsets, offset and bitmap
+            ]
+        );
+
+        let mut memory = Vec::new();
+        for offset in [0x1000, 0x1004, 0x1006, 0x1006, 0x1024, 0x1040].iter() {
+            memory.extend_from_slice(&Elf32Rel::new(*offset, 0x17).to_bytes());
+        }
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_codec(Codec::Bitmap);
+        relocs.set_repeat_offsets(false);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[6..written],
+            [
+                0x17, 0x06, // group[0].relocation_type, count
+                0x00, 0x01, // offset and bitmap
+                0x02, 0x00, // unaligned offset and empty bitmap
+                0x00, 0x00, // repeated offset and empty bitmap
+                0x1E, 0x40, // offset unaligned to the run and bitmap
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_repeat() {
+        let mut memory = Vec::new();
+        for index in 0..200 {
+            memory.extend_from_slice(&Elf32Rel::new(0x1000 + 4 * index, 0x17).to_bytes());
+        }
+        memory.extend_from_slice(&Elf32Rel::new(0x2000, 0x02).to_bytes());
+        let mut output = [0; 256];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, // base_address
+                0xF6, // repeated offsets
+                0x02, // count
+                0x02, 0x02, // group[0].relocation_type, count
+                0x80, 0x20, // group[0].offsets
+                0x17, 0x91, 0x03, // group[1].relocation_type, count, repeated
+                0x00, 0x8F, 0x03, 0x04, // group[1].offsets: +0x0, 199 x +0x4
+            ]
+        );
+        #[cfg(feature = "decompress")]
+        {
+            let mut count = 0;
+            crate::elf32_relocate_strict(&output[..written], &mut |_, _| {
+                count += 1;
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(count, 201);
+        }
+        let stats = relocs.stats().unwrap();
+        assert_eq!(stats.output_size(), written);
+
+        relocs.set_repeat_offsets(false);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!((written, output[4]), (5 + 4 + 203, 0x02));
+        relocs.set_repeat_offsets(true);
+        relocs.set_encoding(Encoding::Interleaved);
+        let written = relocs.compress(&mut output).unwrap();
+        assert!(!output[..written].contains(&REPEAT));
+
+        // Two repeats do not make up for the prefix.
+        let mut memory = Vec::new();
+        for offset in [0x1000, 0x1004, 0x1008].iter() {
+            memory.extend_from_slice(&Elf32Rel::new(*offset, 0x17).to_bytes());
+        }
+        let written = Elf32Relocs::new(&memory).compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x03, 0x00, 0x04, 0x04]
+        );
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_scaled() {
+        let offsets = [0x1000, 0x1100, 0x1300, 0x1600, 0x1A00, 0x1F00];
+        let mut memory = Vec::new();
+        for offset in offsets.iter() {
+            memory.extend_from_slice(&Elf32Rel::new(*offset, 0x17).to_bytes());
+        }
+        let mut output = [0; 64];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(written, 5 + 2 + 11);
+        relocs.set_scaled_offsets(true);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, // base_address
+                0xF5, // scaled offsets
+                0x01, // count
+                0x17, 0x06, // group[0].relocation_type, count
+                0x80, 0x02, // group[0].scale
+                0x00, 0x01, 0x02, 0x03, 0x04, 0x05, // group[0].offsets
+            ]
+        );
+        relocs.set_encoding(Encoding::Interleaved);
+        let written = relocs.compress(&mut output).unwrap();
+        assert!(!output[..written].contains(&SCALED));
+
+        // Signed and repeated offsets are divided as well.
+        let mut memory = Vec::new();
+        for offset in offsets.iter().rev() {
+            memory.extend_from_slice(&Elf32Rel::new(*offset, 0x17).to_bytes());
+        }
+        for index in 0..8 {
+            memory.extend_from_slice(&Elf32Rel::new(0x2000 + 0x3C * index, 0x02).to_bytes());
+        }
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_scaled_offsets(true);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(output[4..7], [SIGNED, REPEAT, SCALED]);
+        #[cfg(feature = "decompress")]
+        {
+            let mut decoded = Vec::new();
+            crate::elf32_relocate(&output[..written], &mut |relocation_type, address| {
+                decoded.push((relocation_type, address));
+                Ok(())
+            })
+            .unwrap();
+            let mut expected: Vec<(u8, u32)> =
+                (0..8).map(|index| (0x02, 0x2000 + 0x3C * index)).collect();
+            expected.extend(offsets.iter().rev().map(|offset| (0x17, *offset)));
+            assert_eq!(decoded, expected);
+        }
+        assert_eq!(relocs.stats().unwrap().output_size(), written);
+
+        // Scales are not recorded with the bitmap codec.
+        relocs.set_codec(Codec::Bitmap);
+        let written = relocs.compress(&mut output).unwrap();
+        assert!(!output[..written].contains(&SCALED));
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_directory() {
+        let memory: [u8; 24] = [
+            0x01, 0x02, 0x03, 0x04, // Elf32Rel[0], will become base address
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x02, 0x02, 0x03, 0x04, // Elf32Rel[1]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x41, 0x02, 0x03, 0x04, // Elf32Rel[2]
+            0x01, 0x00, 0x00, 0x00, // Type is 1
+        ];
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(Encoding::Directory);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x01, 0x02, 0x03, 0x04, // base_address
+                0xFE, // count, directory
+                0x02, // groups
+                0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // directory[0]
+                0x05, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // directory[1]
+                0x40, // streams[0]
+                0x00, 0x01, // streams[1]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_type_base_address() {
+        let memory: [u8; 32] = [
+            0x00, 0x10, 0x00, 0x00, // Elf32Rel[0], will become base address
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+            0x00, 0x00, 0x08, 0x00, // Elf32Rel[1], in the GOT
+            0x15, 0x00, 0x00, 0x00, // Type is 21
+            0x04, 0x10, 0x00, 0x00, // Elf32Rel[2]
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+            0x04, 0x00, 0x08, 0x00, // Elf32Rel[3], in the GOT
+            0x15, 0x00, 0x00, 0x00, // Type is 21
+        ];
+        let mut output: [u8; 128] = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let err = relocs.set_type_base_address(0x15, 0x80001).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        relocs.set_type_base_address(0x15, 0x80000).unwrap();
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, // base_address
+                0xFD, // count, based
+                0x02, // groups
+                0x15, // group[0].relocation_type
+                0x00, 0x00, 0x08, 0x00, // group[0].base_address
+                0x02, // group[0].count
+                0x00, 0x04, // group[0].offsets
+                0x17, // group[1].relocation_type
+                0x00, 0x10, 0x00, 0x00, // group[1].base_address
+                0x02, // group[1].count
+                0x00, 0x04, // group[1].offsets
+            ]
+        );
+        for encoding in &[Encoding::Interleaved, Encoding::Directory] {
+            relocs.set_encoding(*encoding);
+            let err = relocs.compress(&mut output).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Unsupported);
+        }
+    }
+
+    #[test]
+    fn test_elf32relocs_compress_group_alignment() {
+        let memory: [u8; 32] = [
+            0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[0]
+            0x04, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // Elf32Rel[1]
+            0x08, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[2]
+            0x0C, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[3]
+        ];
+        let mut output = [0; 32];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let err = relocs.set_group_alignment(3).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        relocs.set_group_alignment(4).unwrap();
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, 0xF7, 0x04, 0x02, 0x00, // header, aligned
+                0x02, 0x01, 0x04, 0x00, // groups[0], padded
+                0x17, 0x03, 0x00, 0x08, 0x04, // groups[1]
+            ]
+        );
+        relocs.set_encoding(Encoding::Interleaved);
+        let written = relocs.compress(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, 0xF7, 0x04, 0xFF, 0x03, // header, aligned
+                0x17, 0x01, 0x00, 0x00, // runs[0], padded
+                0x02, 0x01, 0x04, 0x00, // runs[1], padded
+                0x17, 0x02, 0x04, 0x04, // runs[2]
+            ]
+        );
+        relocs.set_encoding(Encoding::Directory);
+        let err = relocs.compress(&mut output).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = relocs
+            .append_to(&[0x00, 0x10, 0x00, 0x00, 0x00])
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_elf32relocs_stats() {
+        let memory: [u8; 32] = [
+            0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[0]
+            0x04, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // Elf32Rel[1]
+            0x08, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[2]
+            0x0C, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[3]
+        ];
+        let mut relocs = Elf32Relocs::new(&memory);
+        let stats = relocs.stats().unwrap();
+        assert_eq!(
+            (stats.input_size(), stats.output_size(), stats.encoding()),
+            (32, 13, Encoding::Grouped)
+        );
+        assert_eq!(
+            stats.groups(),
+            [
+                GroupStats::new(0x02, 1, 4, 3),
+                GroupStats::new(0x17, 3, 8, 5)
+            ]
+        );
+        for (encoding, output_size, sizes) in [
+            (Encoding::Interleaved, 16, [3, 7]),
+            (Encoding::Directory, 28, [10, 12]),
+        ]
+        .iter()
+        {
+            relocs.set_encoding(*encoding);
+            let stats = relocs.stats().unwrap();
+            assert_eq!(
+                (stats.output_size(), stats.encoding()),
+                (*output_size, *encoding)
+            );
+            let group_sizes: Vec<_> = stats.groups().iter().map(GroupStats::size).collect();
+            assert_eq!(group_sizes, sizes);
+        }
+        relocs.set_encoding(Encoding::Auto);
+        relocs.set_memory_limit(0);
+        assert_eq!(relocs.stats().unwrap().groups()[1].max_delta(), 8);
+    }
+
+    #[test]
+    fn test_elf32relocs_append_to() {
+        let base: [u8; 16] = [
+            0x01, 0x02, 0x03, 0x04, // Elf32Rel[0], will become base address
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x02, 0x02, 0x03, 0x04, // Elf32Rel[1]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let plugin: [u8; 16] = [
+            0x10, 0x02, 0x03, 0x04, // Elf32Rel[0]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+            0x41, 0x02, 0x03, 0x04, // Elf32Rel[1]
+            0x01, 0x00, 0x00, 0x00, // Type is 1
+        ];
+        let mut output: [u8; 128] = [0; 128];
+        let written = Elf32Relocs::new(&base).compress(&mut output).unwrap();
+        let mut relocs = Elf32Relocs::new(&plugin);
+        let appended = relocs.append_to(&output[..written + 2]).unwrap();
+        assert_eq!(
+            appended,
+            [
+                0x01, 0x02, 0x03, 0x04, // base_address
+                0x03, // count
+                0x05, 0x02, 0x00, 0x01, // groups[0]
+                0x01, 0x01, 0x40, // groups[1]
+                0x05, 0x01, 0x0F, // groups[2]
+            ]
+        );
+        // The base address of the compressor itself is unchanged.
+        assert_eq!(relocs.base_address().unwrap(), 0x04030210);
+    }
+
+    #[test]
+    fn test_elf32relocs_append_to_metadata() {
+        let base: [u8; 8] = [
+            0x01, 0x02, 0x03, 0x04, // Elf32Rel[0], will become base address
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let plugin: [u8; 8] = [
+            0x41, 0x02, 0x03, 0x04, // Elf32Rel[0]
+            0x01, 0x00, 0x00, 0x00, // Type is 1
+        ];
+        let mut metadata = Metadata::new();
+        metadata.set_target("thumbv7em-none-eabihf");
+        let mut relocs = Elf32Relocs::new(&base);
+        relocs.set_metadata(&metadata).unwrap();
+        let mut blob = Vec::new();
+        relocs.compress_to_writer(&mut blob).unwrap();
+        blob.extend_from_slice(&[0, 0]);
+
+        let mut relocs = Elf32Relocs::new(&plugin);
+        let appended = relocs.append_to(&blob).unwrap();
+        let metadata = metadata.to_bytes().unwrap();
+        assert_eq!(
+            appended[..11],
+            [
+                0x01, 0x02, 0x03, 0x04, // base_address
+                0x02, // count
+                0x05, 0x01, 0x00, // groups[0]
+                0x01, 0x01, 0x40, // groups[1]
+            ]
+        );
+        assert_eq!(appended[11..], metadata[..]);
+
+        let replaced = Metadata::new();
+        relocs.set_metadata(&replaced).unwrap();
+        let appended = relocs.append_to(&blob).unwrap();
+        assert_eq!(appended[11..], replaced.to_bytes().unwrap()[..]);
+    }
+
+    #[test]
+    fn test_elf32relocs_append_to_below_base_address() {
+        let blob: [u8; 5] = [0x00, 0x10, 0x00, 0x00, 0x00];
+        let memory: [u8; 8] = [
+            0x00, 0x0F, 0x00, 0x00, // Elf32Rel[0], below the base address
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let err = Elf32Relocs::new(&memory).append_to(&blob).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_elf32relocs_append_to_malformed() {
+        let memory: [u8; 8] = [
+            0x00, 0x10, 0x00, 0x00, // Elf32Rel[0]
+            0x05, 0x00, 0x00, 0x00, // Type is 5
+        ];
+        let mut relocs = Elf32Relocs::new(&memory);
+        for (blob, kind) in &[
+            (&[0x00, 0x10, 0x00, 0x00][..], ErrorKind::NotEnoughData),
+            (
+                &[0x00, 0x10, 0x00, 0x00, 0x01, 0x05, 0x02, 0x00][..],
+                ErrorKind::NotEnoughData,
+            ),
+            (
+                &[0x00, 0x10, 0x00, 0x00, 0xFE, 0x00][..],
+                ErrorKind::Unsupported,
+            ),
+            (
+                &[0x00, 0x10, 0x00, 0x00, 0xFF, 0x00][..],
+                ErrorKind::Unsupported,
+            ),
+        ] {
+            assert_eq!(relocs.append_to(blob).unwrap_err().kind(), *kind);
+        }
+    }
 }
@@ -0,0 +1,122 @@
+//! Compress ELF64 relocation sections
+//!
+//! This module can be used to compress ELF64 relocation sections post-link
+//! time, e.g. of AArch64 or RISC-V 64 images.
+
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+
+use relox_core::uleb128;
+
+use crate::{Elf64Rel, Endianness, Error, ErrorKind};
+
+/// Representation of a regular ELF64 relocation section.
+pub struct Elf64Relocs<'a> {
+    data: &'a [u8],
+    endianness: Endianness,
+}
+
+impl<'a> Elf64Relocs<'a> {
+    /// Creates a new `Elf64Relocs` instance.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            endianness: Endianness::Little,
+        }
+    }
+
+    /// Sets the byte order of the relocation section, little-endian by
+    /// default. The compressed data does not depend on the byte order.
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+
+    /// Compresses this regular ELF64 relocation section and writes the
+    /// compressed data to the provided in-memory buffer.
+    /// Returns the number of bytes written if the compression is successful.
+    ///
+    /// Relocations are grouped by type and sorted by offset within a group,
+    /// relative to the lowest offset as base address.
+    ///
+    /// # Errors
+    ///
+    /// `BufferSmall` if the compressed data does not fit in `output`.
+    pub fn compress(&mut self, output: &mut [u8]) -> Result<usize, Error> {
+        let mut groups: BTreeMap<u32, Vec<u64>> = BTreeMap::new();
+        let mut cursor = Cursor::new(self.data);
+        while let Ok(entry) = Elf64Rel::from_memory_endian(&mut cursor, self.endianness) {
+            groups
+                .entry(entry.relocation_type())
+                .or_default()
+                .push(entry.offset());
+        }
+        let base_address = groups.values().flatten().min().copied().unwrap_or_default();
+
+        let mut writer = Cursor::new(output);
+        write_uleb128_u64(&mut writer, base_address)?;
+        write_uleb128_u64(&mut writer, groups.len() as u64)?;
+        for (relocation_type, offsets) in groups.iter_mut() {
+            offsets.sort_unstable();
+            write_uleb128_u64(&mut writer, *relocation_type as u64)?;
+            write_uleb128_u64(&mut writer, offsets.len() as u64)?;
+            let mut previous = base_address;
+            for offset in offsets.iter() {
+                write_uleb128_u64(&mut writer, offset - previous)?;
+                previous = *offset;
+            }
+        }
+        Ok(writer.position() as usize)
+    }
+}
+
+/// Writes a value as ULEB128.
+fn write_uleb128_u64<W: Write>(writer: &mut W, value: u64) -> Result<(), Error> {
+    let mut buffer = [0; 10];
+    let length = uleb128::write_u64(value, &mut buffer)?;
+    writer
+        .write_all(&buffer[..length])
+        .map_err(|_| Error::new(ErrorKind::BufferSmall))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rel_section(entries: &[(u64, u32)]) -> Vec<u8> {
+        let mut rel = Vec::new();
+        for (offset, relocation_type) in entries.iter() {
+            rel.extend_from_slice(&Elf64Rel::new(*offset, *relocation_type).to_bytes());
+        }
+        rel
+    }
+
+    #[test]
+    fn test_elf64relocs_compress() {
+        let rel = rel_section(&[
+            (0x1_0001_0000, 0x01),
+            (0x1_0000_0008, 0x403),
+            (0x1_0000_0000, 0x01),
+        ]);
+        let mut output = [0; 16];
+        let size = Elf64Relocs::new(&rel).compress(&mut output).unwrap();
+        assert_eq!(
+            output[..size],
+            [
+                0x80, 0x80, 0x80, 0x80, 0x10, 0x02, 0x01, 0x02, 0x00, 0x80, 0x80, 0x04, 0x83, 0x08,
+                0x01, 0x08
+            ]
+        );
+
+        let err = Elf64Relocs::new(&rel)
+            .compress(&mut output[..15])
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+    }
+
+    #[test]
+    fn test_elf64relocs_compress_empty() {
+        let mut output = [0xFF; 2];
+        let size = Elf64Relocs::new(&[]).compress(&mut output).unwrap();
+        assert_eq!(output[..size], [0x00, 0x00]);
+    }
+}
@@ -0,0 +1,365 @@
+//! Read and write CREL relocation sections
+//!
+//! This module can be used to convert relocation sections packed by LLVM and
+//! binutils using the CREL encoding
+//! ([SHT_CREL](../elf/constant.SHT_CREL.html)) from and to regular ELF32
+//! relocation sections, so toolchain-packed sections can be compressed and
+//! compressed ones unpacked for tools which expect CREL.
+//!
+//! A CREL section starts with a ULEB128 header holding the number of
+//! relocations shifted left by 3, a flag marking addends (4) and the number
+//! of trailing zero bits shared by all offsets. Every relocation starts with
+//! a byte holding the offset delta and flags marking whether the symbol
+//! index (1), the type (2) and the addend (4) changed, each followed by the
+//! SLEB128 encoded delta if it did.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::{Error, ErrorKind};
+
+/// Size of a regular ELF32 relocation entry.
+const ELF32_REL_SIZE: usize = 8;
+
+/// A relocation of a CREL section.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CrelRelocation {
+    offset: u32,
+    symbol: u32,
+    relocation_type: u32,
+    addend: i32,
+}
+
+impl CrelRelocation {
+    /// Creates a new `CrelRelocation` instance.
+    pub fn new(offset: u32, symbol: u32, relocation_type: u32, addend: i32) -> Self {
+        Self {
+            offset,
+            symbol,
+            relocation_type,
+            addend,
+        }
+    }
+
+    /// Returns the offset of the relocation, `r_offset`.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the index of the symbol the relocation references.
+    pub fn symbol(&self) -> u32 {
+        self.symbol
+    }
+
+    /// Returns the type of the relocation.
+    pub fn relocation_type(&self) -> u32 {
+        self.relocation_type
+    }
+
+    /// Returns the addend of the relocation, 0 for sections without addends.
+    pub fn addend(&self) -> i32 {
+        self.addend
+    }
+}
+
+/// Decodes a CREL section.
+///
+/// Returns the relocations and whether the section has addends.
+///
+/// # Errors
+///
+/// If the section is malformed.
+pub fn crel_decode(data: &[u8]) -> Result<(Vec<CrelRelocation>, bool), Error> {
+    let mut index = 0;
+    let header = read_uleb128(data, &mut index)?;
+    let count = header >> 3;
+    let addends = header & 4 != 0;
+    let flag_bits = if addends { 3 } else { 2 };
+    let shift = (header & 3) as u32;
+    if count > (data.len() - index) as u64 {
+        return Err(Error::new(ErrorKind::NotEnoughData));
+    }
+    let mut relocations = Vec::with_capacity(count as usize);
+    let mut relocation = CrelRelocation::default();
+    let mut offset: u64 = 0;
+    for _ in 0..count {
+        let byte = *data
+            .get(index)
+            .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+        index += 1;
+        offset = offset.wrapping_add(u64::from(byte >> flag_bits));
+        if byte & 0x80 != 0 {
+            let high = read_uleb128(data, &mut index)?;
+            offset = offset
+                .wrapping_add(high.wrapping_shl(7 - flag_bits))
+                .wrapping_sub(0x80 >> flag_bits);
+        }
+        if byte & 1 != 0 {
+            relocation.symbol = relocation
+                .symbol
+                .wrapping_add(read_sleb128(data, &mut index)? as u32);
+        }
+        if byte & 2 != 0 {
+            relocation.relocation_type = relocation
+                .relocation_type
+                .wrapping_add(read_sleb128(data, &mut index)? as u32);
+        }
+        if addends && byte & 4 != 0 {
+            relocation.addend = relocation
+                .addend
+                .wrapping_add(read_sleb128(data, &mut index)? as i32);
+        }
+        relocation.offset = (offset << shift) as u32;
+        relocations.push(relocation);
+    }
+    Ok((relocations, addends))
+}
+
+/// Encodes relocations as a CREL section, with addends if `addends` is set.
+///
+/// # Errors
+///
+/// `InvalidData` if a relocation has an addend and `addends` is not set.
+pub fn crel_encode(relocations: &[CrelRelocation], addends: bool) -> Result<Vec<u8>, Error> {
+    if !addends && relocations.iter().any(|relocation| relocation.addend != 0) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let flag_bits = if addends { 3 } else { 2 };
+    let shift = relocations
+        .iter()
+        .fold(8, |mask, relocation| mask | relocation.offset)
+        .trailing_zeros();
+    let mut data = Vec::new();
+    let header = (relocations.len() as u64) << 3 | if addends { 4 } else { 0 } | u64::from(shift);
+    write_uleb128(&mut data, header);
+    let mut previous = CrelRelocation::default();
+    for relocation in relocations {
+        let delta = u64::from(relocation.offset.wrapping_sub(previous.offset) >> shift);
+        let symbol = relocation.symbol != previous.symbol;
+        let relocation_type = relocation.relocation_type != previous.relocation_type;
+        let addend = relocation.addend != previous.addend;
+        let byte = (delta << flag_bits) as u8
+            | u8::from(symbol)
+            | u8::from(relocation_type) << 1
+            | u8::from(addend) << 2;
+        if delta < 0x80 >> flag_bits {
+            data.push(byte);
+        } else {
+            data.push(byte | 0x80);
+            write_uleb128(&mut data, delta >> (7 - flag_bits));
+        }
+        if symbol {
+            write_sleb128(
+                &mut data,
+                i64::from(relocation.symbol.wrapping_sub(previous.symbol) as i32),
+            );
+        }
+        if relocation_type {
+            write_sleb128(
+                &mut data,
+                i64::from(
+                    relocation
+                        .relocation_type
+                        .wrapping_sub(previous.relocation_type) as i32,
+                ),
+            );
+        }
+        if addend {
+            write_sleb128(
+                &mut data,
+                i64::from(relocation.addend.wrapping_sub(previous.addend)),
+            );
+        }
+        previous = *relocation;
+    }
+    Ok(data)
+}
+
+/// Converts a CREL section without addends into a regular little-endian
+/// ELF32 relocation section.
+///
+/// # Errors
+///
+/// If the section is malformed, `Unsupported` if it has addends, or
+/// `UnsupportedRelocationType` if a relocation type does not fit into
+/// `r_info`.
+pub fn crel_to_rel(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let (relocations, addends) = crel_decode(data)?;
+    if addends {
+        return Err(Error::new(ErrorKind::Unsupported));
+    }
+    if relocations.iter().any(|r| r.relocation_type > 0xFF) {
+        return Err(Error::new(ErrorKind::UnsupportedRelocationType));
+    }
+    let mut rel = vec![0; relocations.len() * ELF32_REL_SIZE];
+    for (entry, relocation) in rel.chunks_mut(ELF32_REL_SIZE).zip(relocations.iter()) {
+        LittleEndian::write_u32(&mut entry[0..4], relocation.offset);
+        LittleEndian::write_u32(
+            &mut entry[4..8],
+            relocation.symbol << 8 | relocation.relocation_type,
+        );
+    }
+    Ok(rel)
+}
+
+/// Converts a regular little-endian ELF32 relocation section into a CREL
+/// section without addends.
+///
+/// # Errors
+///
+/// `InvalidData` if the size of the section is not a multiple of the size of
+/// a relocation.
+pub fn rel_to_crel(rel: &[u8]) -> Result<Vec<u8>, Error> {
+    if !rel.len().is_multiple_of(ELF32_REL_SIZE) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let relocations: Vec<CrelRelocation> = rel
+        .chunks(ELF32_REL_SIZE)
+        .map(|entry| {
+            let info = LittleEndian::read_u32(&entry[4..8]);
+            CrelRelocation::new(
+                LittleEndian::read_u32(&entry[0..4]),
+                info >> 8,
+                info & 0xFF,
+                0,
+            )
+        })
+        .collect();
+    crel_encode(&relocations, false)
+}
+
+/// Reads a ULEB128 encoded value at `*index` and advances `*index` past it.
+fn read_uleb128(data: &[u8], index: &mut usize) -> Result<u64, Error> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data
+            .get(*index)
+            .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+        *index += 1;
+        if shift >= 64 {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        value |= u64::from(byte & 0x7F) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Reads an SLEB128 encoded value at `*index` and advances `*index` past it.
+pub(crate) fn read_sleb128(data: &[u8], index: &mut usize) -> Result<i64, Error> {
+    let mut value: i64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data
+            .get(*index)
+            .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+        *index += 1;
+        if shift >= 64 {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        value |= i64::from(byte & 0x7F) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                value |= -1 << shift;
+            }
+            return Ok(value);
+        }
+    }
+}
+
+/// Writes a value encoded as ULEB128.
+fn write_uleb128(data: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            data.push(byte);
+            return;
+        }
+        data.push(byte | 0x80);
+    }
+}
+
+/// Writes a value encoded as SLEB128.
+pub(crate) fn write_sleb128(data: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            data.push(byte);
+            return;
+        }
+        data.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Three R_ARM_RELATIVE relocations at 0x1000, 0x1004 and 0x1010 and an
+    // R_ARM_ABS32 against symbol 3 at 0x1014.
+    const CREL: [u8; 9] = [
+        0x22, // count 4, no addends, shift 2
+        0x82, 0x20, 0x17, // 0x1000, type 23
+        0x04, // 0x1004
+        0x0C, // 0x1010
+        0x07, 0x03, 0x6B, // 0x1014, symbol +3, type -21
+    ];
+
+    #[test]
+    fn test_crel_decode() {
+        let (relocations, addends) = crel_decode(&CREL).unwrap();
+        assert!(!addends);
+        assert_eq!(
+            relocations,
+            [
+                CrelRelocation::new(0x1000, 0, 23, 0),
+                CrelRelocation::new(0x1004, 0, 23, 0),
+                CrelRelocation::new(0x1010, 0, 23, 0),
+                CrelRelocation::new(0x1014, 3, 2, 0),
+            ]
+        );
+        let err = crel_decode(&CREL[..6]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[test]
+    fn test_crel_encode() {
+        let (relocations, _) = crel_decode(&CREL).unwrap();
+        assert_eq!(crel_encode(&relocations, false).unwrap(), CREL);
+        let err = crel_encode(&[CrelRelocation::new(0, 0, 2, 4)], false).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_crel_encode_addends() {
+        let relocations = [
+            CrelRelocation::new(0x1001, 1, 2, -8),
+            CrelRelocation::new(0x4000_0000, 0, 300, 0x1234),
+            CrelRelocation::new(0x4000_0000, 0, 300, 0x1234),
+        ];
+        let data = crel_encode(&relocations, true).unwrap();
+        assert_eq!(crel_decode(&data).unwrap(), (relocations.to_vec(), true));
+    }
+
+    #[test]
+    fn test_crel_rel() {
+        let rel = crel_to_rel(&CREL).unwrap();
+        assert_eq!(rel.len(), 32);
+        assert_eq!(
+            rel[24..32],
+            [0x14, 0x10, 0x00, 0x00, 0x02, 0x03, 0x00, 0x00]
+        );
+        assert_eq!(rel_to_crel(&rel).unwrap(), CREL);
+
+        let data = crel_encode(&[CrelRelocation::new(0, 0, 2, 4)], true).unwrap();
+        let err = crel_to_rel(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = rel_to_crel(&rel[..7]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
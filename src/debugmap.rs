@@ -0,0 +1,270 @@
+//! Debug maps of compressed relocation sections
+//!
+//! A debug map correlates every group of a compressed ELF32 relocation
+//! section with the entries of the regular relocation section it encodes, so
+//! a compressed byte range flagged by verification can be traced back to the
+//! linker emitted entries which produced it.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::io::Cursor;
+use std::ops::Range;
+
+use relox_core::layout::{BITMAP, DIRECTORY, GROUP_VARINT, MACHINE, SYMBOLS};
+
+use crate::{
+    elf32_group_count, elf32_index_groups, elf32_relocate_symbols, Elf32CRelGroupIndex, Elf32Rel,
+    Endianness, Error, ErrorKind,
+};
+
+/// Size of an entry of a regular ELF32 relocation section.
+const ELF32_REL_SIZE: usize = 8;
+
+/// A group of a compressed section and the entries it encodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugMapGroup {
+    relocation_type: u8,
+    range: Range<usize>,
+    entries: Vec<usize>,
+}
+
+impl DebugMapGroup {
+    /// Returns the type of the relocations of the group.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the bytes of the compressed section encoding the group: its
+    /// header and offsets, or only its offsets if the section uses the
+    /// directory layout, whose group headers are held by the directory.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the indices of the entries of the regular relocation section
+    /// encoded by the group, in decoding order.
+    pub fn entries(&self) -> &[usize] {
+        &self.entries
+    }
+
+    /// Returns the offsets of the entries of the regular relocation section
+    /// encoded by the group, in decoding order.
+    pub fn file_offsets(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entries.iter().map(|entry| entry * ELF32_REL_SIZE)
+    }
+}
+
+/// Map of the groups of a compressed section to the entries of the regular
+/// relocation section they encode.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DebugMap {
+    groups: Vec<DebugMapGroup>,
+}
+
+impl DebugMap {
+    /// Returns the groups, runs or directory entries in decoding order.
+    pub fn groups(&self) -> &[DebugMapGroup] {
+        &self.groups
+    }
+
+    /// Returns the group encoded by the byte at `offset` of the compressed
+    /// section, if any.
+    pub fn group_at(&self, offset: usize) -> Option<&DebugMapGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.range.contains(&offset))
+    }
+
+    /// Returns the map as text, one line per group, e.g. to store it next to
+    /// the compressed section.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (number, group) in self.groups.iter().enumerate() {
+            let _ = write!(
+                text,
+                "group {} type 0x{:02x} bytes {}..{} entries",
+                number, group.relocation_type, group.range.start, group.range.end
+            );
+            for offset in group.file_offsets() {
+                let _ = write!(text, " 0x{:x}", offset);
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Builds the debug map of `compressed`, the compressed section of
+/// `rel_section`, a regular ELF32 relocation section in the given byte order.
+///
+/// Entries with the same type and offset are mapped in the order they appear
+/// in the relocation section.
+///
+/// # Errors
+///
+/// If the compressed section is malformed, or `InvalidData` if it encodes a
+/// relocation which is not in the relocation section.
+pub fn debug_map(
+    rel_section: &[u8],
+    endianness: Endianness,
+    compressed: &[u8],
+) -> Result<DebugMap, Error> {
+    let mut pending: BTreeMap<Elf32Rel, VecDeque<usize>> = BTreeMap::new();
+    let mut cursor = Cursor::new(rel_section);
+    let mut number = 0;
+    while let Ok(entry) = Elf32Rel::from_memory_endian(&mut cursor, endianness) {
+        pending.entry(entry).or_default().push_back(number);
+        number += 1;
+    }
+    let mut entries = Vec::with_capacity(number);
+    let end = elf32_relocate_symbols(compressed, &mut |relocation_type, address, symbol| {
+        let entry = pending
+            .get_mut(&Elf32Rel::with_symbol(address, relocation_type, symbol))
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        entries.push(entry);
+        Ok(())
+    })?;
+
+    let mut index = vec![Elf32CRelGroupIndex::default(); elf32_group_count(compressed)? as usize];
+    elf32_index_groups(compressed, &mut index)?;
+    let ranges = match is_directory(compressed) {
+        true => stream_ranges(compressed, &index, end),
+        false => index
+            .iter()
+            .enumerate()
+            .map(|(number, group)| {
+                let next = index.get(number + 1).map_or(end, |next| next.offset());
+                group.offset()..next
+            })
+            .collect(),
+    };
+    let groups = index
+        .iter()
+        .zip(ranges)
+        .map(|(group, range)| {
+            let first = group.first() as usize;
+            DebugMapGroup {
+                relocation_type: compressed[group.offset()],
+                range,
+                entries: entries[first..first + group.count() as usize].to_vec(),
+            }
+        })
+        .collect();
+    Ok(DebugMap { groups })
+}
+
+/// Returns `true` if a valid compressed section uses the directory layout.
+fn is_directory(compressed: &[u8]) -> bool {
+    let mut marker = 4;
+    if compressed[marker] == MACHINE {
+        marker += 3;
+    }
+    if compressed[marker] == GROUP_VARINT || compressed[marker] == BITMAP {
+        marker += 1;
+    }
+    if compressed[marker] == SYMBOLS {
+        marker += 1;
+    }
+    compressed[marker] == DIRECTORY
+}
+
+/// Returns the offset streams of the groups of a valid compressed section
+/// using the directory layout, ending at `end`.
+fn stream_ranges(
+    compressed: &[u8],
+    index: &[Elf32CRelGroupIndex],
+    end: usize,
+) -> Vec<Range<usize>> {
+    // The decoder reads directory words in native endianness.
+    let streams = index.first().map_or(end, |first| first.offset()) + index.len() * 9;
+    let starts: Vec<usize> = index
+        .iter()
+        .map(|group| {
+            let mut stream = [0; 4];
+            stream.copy_from_slice(&compressed[group.offset() + 5..group.offset() + 9]);
+            streams + u32::from_ne_bytes(stream) as usize
+        })
+        .collect();
+    starts
+        .iter()
+        .map(|start| {
+            let next = starts.iter().filter(|next| *next > start).min();
+            *start..next.copied().unwrap_or(end)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Elf32Relocs, Encoding};
+
+    /// Entries of a relocation section with a duplicate.
+    const ENTRIES: [(u32, u8); 5] = [
+        (0x1000, 0x17),
+        (0x1004, 0x02),
+        (0x1004, 0x02),
+        (0x1008, 0x17),
+        (0x100C, 0x17),
+    ];
+
+    fn rel_section() -> Vec<u8> {
+        let mut rel = Vec::new();
+        for (offset, relocation_type) in ENTRIES.iter() {
+            rel.extend_from_slice(&Elf32Rel::new(*offset, *relocation_type).to_bytes());
+        }
+        rel
+    }
+
+    fn compress(rel: &[u8], encoding: Encoding) -> (Vec<u8>, DebugMap) {
+        let mut relocs = Elf32Relocs::new(rel);
+        relocs.set_encoding(encoding);
+        let mut compressed = vec![0; 64];
+        let (size, map) = relocs.compress_with_map(&mut compressed).unwrap();
+        compressed.truncate(size);
+        (compressed, map)
+    }
+
+    #[test]
+    fn test_debug_map_grouped() {
+        let rel = rel_section();
+        let (compressed, map) = compress(&rel, Encoding::Grouped);
+        assert_eq!(map.groups().len(), 2);
+        assert_eq!(map.groups()[0].relocation_type(), 0x02);
+        assert_eq!(map.groups()[0].range(), 5..9);
+        assert_eq!(map.groups()[0].entries(), [1, 2]);
+        assert_eq!(map.groups()[1].range(), 9..compressed.len());
+        assert_eq!(map.groups()[1].entries(), [0, 3, 4]);
+        assert_eq!(map.group_at(10), Some(&map.groups()[1]));
+        assert_eq!(map.group_at(4), None);
+        assert_eq!(
+            map.to_text(),
+            "group 0 type 0x02 bytes 5..9 entries 0x8 0x10\n\
+             group 1 type 0x17 bytes 9..14 entries 0x0 0x18 0x20\n"
+        );
+    }
+
+    #[test]
+    fn test_debug_map_layouts() {
+        let rel = rel_section();
+        let (compressed, map) = compress(&rel, Encoding::Interleaved);
+        let entries: Vec<&[usize]> = map.groups().iter().map(DebugMapGroup::entries).collect();
+        assert_eq!(entries, [&[0][..], &[1, 2], &[3, 4]]);
+        assert_eq!(map.groups()[2].range().end, compressed.len());
+
+        let (compressed, map) = compress(&rel, Encoding::Directory);
+        assert_eq!(map.groups()[0].range(), 24..26);
+        assert_eq!(map.groups()[0].entries(), [1, 2]);
+        assert_eq!(map.groups()[1].range(), 26..compressed.len());
+        assert_eq!(map.groups()[1].entries(), [0, 3, 4]);
+    }
+
+    #[test]
+    fn test_debug_map_mismatch() {
+        let rel = rel_section();
+        let (compressed, _) = compress(&rel, Encoding::Grouped);
+        let err = debug_map(&rel[8..], Endianness::Little, &compressed).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,181 @@
+//! Deltas between compressed relocation sections
+//!
+//! [diff_blobs](fn.diff_blobs.html) creates the delta from the compressed
+//! relocation section of a running image to that of an update, laid out as
+//! described in [apply_delta](fn.apply_delta.html), which applies it on the
+//! target.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::compress::write_uleb128;
+use crate::{apply_delta, delta_checksum, delta_new_size, Error, ErrorKind, DELTA_MAGIC};
+
+/// Shortest run of old bytes copied instead of inserted.
+const MIN_COPY: usize = 4;
+
+/// Number of earlier occurrences of a run compared, bounding the time spent
+/// on repetitive sections.
+const MAX_CANDIDATES: usize = 64;
+
+/// Delta between two compressed relocation sections.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+    data: Vec<u8>,
+}
+
+impl Patch {
+    /// Creates a new `Patch` instance from a serialized delta, e.g. one
+    /// received by an update pipeline.
+    ///
+    /// # Errors
+    ///
+    /// If `data` does not start with a delta header.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, Error> {
+        delta_new_size(&data)?;
+        Ok(Self { data })
+    }
+
+    /// Returns the serialized delta.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the size of the serialized delta in bytes.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the delta is empty, which never happens as it holds
+    /// a header.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// Converts a size to the `u32` recorded in deltas.
+fn size(value: usize) -> Result<u32, Error> {
+    u32::try_from(value).map_err(|_| Error::new(ErrorKind::Unsupported))
+}
+
+/// Writes an operation inserting `bytes`.
+fn write_insert(data: &mut Vec<u8>, bytes: &[u8]) -> Result<(), Error> {
+    if !bytes.is_empty() {
+        write_uleb128(data, size(bytes.len())? << 1 | 1)?;
+        data.extend_from_slice(bytes);
+    }
+    Ok(())
+}
+
+/// Creates the delta turning `old` into `new`.
+///
+/// Runs of at least four bytes found in `old` are copied, other bytes are
+/// inserted, so the delta stays small if a few relocations change.
+///
+/// # Errors
+///
+/// `Unsupported` if a section is larger than 2 GiB.
+pub fn diff_blobs(old: &[u8], new: &[u8]) -> Result<Patch, Error> {
+    if old.len() > (u32::MAX >> 1) as usize || new.len() > (u32::MAX >> 1) as usize {
+        return Err(Error::new(ErrorKind::Unsupported));
+    }
+    let mut data = DELTA_MAGIC.to_vec();
+    write_uleb128(&mut data, size(old.len())?)?;
+    data.extend_from_slice(&delta_checksum(old).to_le_bytes());
+    write_uleb128(&mut data, size(new.len())?)?;
+    data.extend_from_slice(&delta_checksum(new).to_le_bytes());
+
+    let mut positions: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (offset, window) in old.windows(MIN_COPY).enumerate() {
+        positions.entry(window).or_default().push(offset);
+    }
+    let mut index = 0;
+    let mut pending = 0;
+    while index < new.len() {
+        let candidates = new
+            .get(index..index + MIN_COPY)
+            .and_then(|window| positions.get(window));
+        let best = candidates
+            .into_iter()
+            .flatten()
+            .take(MAX_CANDIDATES)
+            .map(|offset| {
+                let length = old[*offset..]
+                    .iter()
+                    .zip(new[index..].iter())
+                    .take_while(|(old, new)| old == new)
+                    .count();
+                (length, *offset)
+            })
+            .max_by_key(|(length, offset)| (*length, usize::MAX - offset));
+        match best {
+            Some((length, offset)) => {
+                write_insert(&mut data, &new[pending..index])?;
+                write_uleb128(&mut data, size(length)? << 1)?;
+                write_uleb128(&mut data, size(offset)?)?;
+                index += length;
+                pending = index;
+            }
+            None => index += 1,
+        }
+    }
+    write_insert(&mut data, &new[pending..])?;
+    Ok(Patch { data })
+}
+
+/// Applies `patch` to `old`, the compressed section it was created from, and
+/// returns the new section.
+///
+/// # Errors
+///
+/// If the delta is malformed or was not created from `old`.
+pub fn apply_patch(old: &[u8], patch: &Patch) -> Result<Vec<u8>, Error> {
+    let mut new = vec![0; delta_new_size(patch.as_bytes())?];
+    apply_delta(old, patch.as_bytes(), &mut new)?;
+    Ok(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, 0x02, 0x02, 0x02, 0x04, 0x80, 0x01, 0x16, 0x03, 0x00, 0x04, 0x04,
+    ];
+
+    #[test]
+    fn test_diff_blobs() {
+        let mut new = OLD.to_vec();
+        new[7] = 0x08;
+        let patch = diff_blobs(&OLD, &new).unwrap();
+        assert_eq!(
+            patch.as_bytes()[14..],
+            [
+                0x0E, 0x00, // copy 7 bytes from 0
+                0x03, 0x08, // insert 1 byte
+                0x0E, 0x08, // copy 7 bytes from 8
+            ]
+        );
+        assert_eq!(apply_patch(&OLD, &patch).unwrap(), new);
+
+        let patch = diff_blobs(&OLD, &OLD).unwrap();
+        assert_eq!(patch.len(), 14 + 2);
+        assert_eq!(apply_patch(&OLD, &patch).unwrap(), OLD);
+        let patch = diff_blobs(&[], &OLD).unwrap();
+        assert_eq!(apply_patch(&[], &patch).unwrap(), OLD);
+        let patch = diff_blobs(&OLD, &[]).unwrap();
+        assert!(apply_patch(&OLD, &patch).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_patch_from_bytes() {
+        let patch = diff_blobs(&OLD, &OLD[..9]).unwrap();
+        let received = Patch::from_bytes(patch.as_bytes().to_vec()).unwrap();
+        assert_eq!(received, patch);
+        assert!(!received.is_empty());
+        let err = Patch::from_bytes(OLD.to_vec()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = apply_patch(&OLD[..9], &patch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
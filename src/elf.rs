@@ -0,0 +1,2149 @@
+//! Read ELF files
+//!
+//! This module can be used to identify ELF files and to locate their
+//! relocation sections during post-link time processing.
+
+use std::collections::BTreeMap;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::aps2::aps2_to_rel;
+use crate::compress_pointer_table;
+use crate::crel::crel_to_rel;
+use crate::{Elf32Relocs, Endianness, Error, ErrorKind};
+
+mod names;
+pub use names::{relative_type, relocation_type_name};
+
+/// ELF magic number.
+const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+/// Size of the ELF identification.
+const EI_NIDENT: usize = 16;
+/// Size of an ELF32 file header.
+const ELF32_EHDR_SIZE: usize = 52;
+/// Size of an ELF32 section header.
+const ELF32_SHDR_SIZE: usize = 40;
+/// Size of an ELF32 program header.
+const ELF32_PHDR_SIZE: usize = 32;
+/// Size of a regular ELF32 relocation entry.
+const ELF32_REL_SIZE: usize = 8;
+/// Size of an ELF32 symbol.
+const ELF32_SYM_SIZE: usize = 16;
+/// Section flag of sections occupying memory during execution.
+const SHF_ALLOC: u32 = 0x2;
+/// Section flag marking `sh_info` as a section header table index.
+const SHF_INFO_LINK: u32 = 0x40;
+/// Type of relocatable object files.
+const ET_REL: u16 = 1;
+/// Symbol binding of global symbols, shifted into `st_info`.
+const STB_GLOBAL: u8 = 0x10;
+/// Symbol type of data objects.
+const STT_OBJECT: u8 = 1;
+/// Symbol type of section symbols.
+const STT_SECTION: u8 = 3;
+/// Lower bound of reserved section indices.
+const SHN_LORESERVE: u16 = 0xFF00;
+/// Section index of absolute symbols.
+const SHN_ABS: u16 = 0xFFF1;
+
+/// Intel 80386.
+pub const EM_386: u16 = 3;
+/// PowerPC.
+pub const EM_PPC: u16 = 20;
+/// ARM.
+pub const EM_ARM: u16 = 40;
+/// Tensilica Xtensa.
+pub const EM_XTENSA: u16 = 94;
+/// RISC-V.
+pub const EM_RISCV: u16 = 243;
+
+/// Segment type of loadable segments.
+pub const PT_LOAD: u32 = 1;
+/// Segment type of the dynamic segment.
+pub const PT_DYNAMIC: u32 = 2;
+
+/// Marks the end of the dynamic segment.
+pub const DT_NULL: u32 = 0;
+/// Size of the PLT relocation table.
+pub const DT_PLTRELSZ: u32 = 2;
+/// Address of the relocation table with addends.
+pub const DT_RELA: u32 = 7;
+/// Size of the relocation table with addends.
+pub const DT_RELASZ: u32 = 8;
+/// Size of the entries of the relocation table with addends.
+pub const DT_RELAENT: u32 = 9;
+/// Address of the relocation table without addends.
+pub const DT_REL: u32 = 17;
+/// Size of the relocation table without addends.
+pub const DT_RELSZ: u32 = 18;
+/// Size of the entries of the relocation table without addends.
+pub const DT_RELENT: u32 = 19;
+/// Type of the PLT relocation table (`DT_REL` or `DT_RELA`).
+pub const DT_PLTREL: u32 = 20;
+/// Address of the PLT relocation table.
+pub const DT_JMPREL: u32 = 23;
+
+/// Section type of sections holding program defined information.
+pub const SHT_PROGBITS: u32 = 1;
+/// Section type of symbol tables.
+pub const SHT_SYMTAB: u32 = 2;
+/// Section type of string tables.
+pub const SHT_STRTAB: u32 = 3;
+/// Section type of relocation sections with addends.
+pub const SHT_RELA: u32 = 4;
+/// Section type of relocation sections without addends.
+pub const SHT_REL: u32 = 9;
+/// Section type of dynamic symbol tables.
+pub const SHT_DYNSYM: u32 = 11;
+/// Section type of sections occupying no space in the file.
+pub const SHT_NOBITS: u32 = 8;
+/// Section type of arrays of initialization function pointers.
+pub const SHT_INIT_ARRAY: u32 = 14;
+/// Section type of arrays of termination function pointers.
+pub const SHT_FINI_ARRAY: u32 = 15;
+/// Section type of arrays of pre-initialization function pointers.
+pub const SHT_PREINIT_ARRAY: u32 = 16;
+/// Section type of CREL relocation sections.
+pub const SHT_CREL: u32 = 0x4000_0014;
+/// Section type of Android packed relocation sections without addends.
+pub const SHT_ANDROID_REL: u32 = 0x6000_0001;
+
+/// File class of an ELF file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ElfClass {
+    /// 32-bit objects.
+    Elf32,
+    /// 64-bit objects.
+    Elf64,
+}
+
+/// Identity of an ELF file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ElfIdent {
+    class: ElfClass,
+    endianness: Endianness,
+    machine: u16,
+}
+
+impl ElfIdent {
+    /// Returns `true` if `data` starts with the ELF magic number.
+    pub fn is_elf(data: &[u8]) -> bool {
+        data.starts_with(&ELF_MAGIC)
+    }
+
+    /// Reads the identity of an ELF file from `e_ident` and `e_machine`.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is not an ELF file.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if !Self::is_elf(data) || data.len() < EI_NIDENT + 4 {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let class = match data[4] {
+            1 => ElfClass::Elf32,
+            2 => ElfClass::Elf64,
+            _ => return Err(Error::new(ErrorKind::InvalidData)),
+        };
+        let (endianness, machine) = match data[5] {
+            1 => (Endianness::Little, LittleEndian::read_u16(&data[18..20])),
+            2 => (Endianness::Big, BigEndian::read_u16(&data[18..20])),
+            _ => return Err(Error::new(ErrorKind::InvalidData)),
+        };
+        Ok(Self {
+            class,
+            endianness,
+            machine,
+        })
+    }
+
+    /// Returns the file class.
+    pub fn class(&self) -> ElfClass {
+        self.class
+    }
+
+    /// Returns the byte order.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Returns the target machine (`e_machine`).
+    pub fn machine(&self) -> u16 {
+        self.machine
+    }
+
+    /// Returns the type of relative relocations of the target machine, if
+    /// known.
+    pub fn relative_type(&self) -> Option<u8> {
+        relative_type(self.machine)
+    }
+
+    /// Returns the name of a relocation type of the target machine, e.g.
+    /// `R_ARM_RELATIVE`, if known.
+    pub fn relocation_type_name(&self, relocation_type: u8) -> Option<&'static str> {
+        relocation_type_name(self.machine, relocation_type)
+    }
+}
+
+/// Section header of an ELF32 file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Elf32Section {
+    name: String,
+    section_type: u32,
+    flags: u32,
+    address: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+    info: u32,
+    alignment: u32,
+    entry_size: u32,
+}
+
+impl Elf32Section {
+    /// Returns the name of the section.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the section (`sh_type`).
+    pub fn section_type(&self) -> u32 {
+        self.section_type
+    }
+
+    /// Returns the flags of the section (`sh_flags`).
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns the address of the section in memory (`sh_addr`).
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Returns the offset of the section in the file (`sh_offset`).
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the size of the section (`sh_size`).
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the section header table index link (`sh_link`).
+    pub fn link(&self) -> u32 {
+        self.link
+    }
+
+    /// Returns the extra information of the section (`sh_info`).
+    pub fn info(&self) -> u32 {
+        self.info
+    }
+
+    /// Returns the alignment of the section (`sh_addralign`).
+    pub fn alignment(&self) -> u32 {
+        self.alignment
+    }
+
+    /// Returns the size of the entries of the section (`sh_entsize`).
+    pub fn entry_size(&self) -> u32 {
+        self.entry_size
+    }
+}
+
+/// Program header of an ELF32 file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32Segment {
+    segment_type: u32,
+    offset: u32,
+    address: u32,
+    file_size: u32,
+    memory_size: u32,
+}
+
+impl Elf32Segment {
+    /// Returns the type of the segment (`p_type`).
+    pub fn segment_type(&self) -> u32 {
+        self.segment_type
+    }
+
+    /// Returns the offset of the segment in the file (`p_offset`).
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the virtual address of the segment (`p_vaddr`).
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Returns the size of the segment in the file (`p_filesz`).
+    pub fn file_size(&self) -> u32 {
+        self.file_size
+    }
+
+    /// Returns the size of the segment in memory (`p_memsz`).
+    pub fn memory_size(&self) -> u32 {
+        self.memory_size
+    }
+}
+
+/// Relocation table located through the dynamic segment.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32DynamicTable {
+    tag: u32,
+    section_type: u32,
+    address: u32,
+    size: u32,
+    entry_size: u32,
+}
+
+impl Elf32DynamicTable {
+    /// Returns the dynamic tag locating the table (`DT_REL`, `DT_RELA` or
+    /// `DT_JMPREL`).
+    pub fn tag(&self) -> u32 {
+        self.tag
+    }
+
+    /// Returns the type of the relocations (`SHT_REL` or `SHT_RELA`).
+    pub fn section_type(&self) -> u32 {
+        self.section_type
+    }
+
+    /// Returns the address of the table.
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Returns the size of the table in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the size of the entries of the table.
+    pub fn entry_size(&self) -> u32 {
+        self.entry_size
+    }
+}
+
+/// A relocation section and the section its relocations apply to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elf32RelocationSection<'s> {
+    section: &'s Elf32Section,
+    target: Option<&'s Elf32Section>,
+}
+
+impl<'s> Elf32RelocationSection<'s> {
+    /// Returns the relocation section.
+    pub fn section(&self) -> &'s Elf32Section {
+        self.section
+    }
+
+    /// Returns the section the relocations apply to (`sh_info`), if any.
+    ///
+    /// Sections of dynamic relocations, e.g. `.rel.dyn`, apply to the whole
+    /// image and have no target section.
+    pub fn target(&self) -> Option<&'s Elf32Section> {
+        self.target
+    }
+}
+
+/// An ELF32 file of either byte order.
+pub struct Elf32File<'a> {
+    data: &'a [u8],
+    ident: ElfIdent,
+    flags: u32,
+    segments: Vec<Elf32Segment>,
+    sections: Vec<Elf32Section>,
+}
+
+impl<'a> Elf32File<'a> {
+    /// Parses the header and section header table of an ELF file.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is not a valid ELF file or `ErrorKind::Unsupported` if it is
+    /// not an ELF32 file.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let ident = ElfIdent::parse(data)?;
+        if ident.class() != ElfClass::Elf32 {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        let endianness = ident.endianness();
+        let header = slice(data, 0, ELF32_EHDR_SIZE)?;
+        let phoff = read_u32(endianness, &header[28..32]) as usize;
+        let shoff = read_u32(endianness, &header[32..36]) as usize;
+        let phentsize = read_u16(endianness, &header[42..44]) as usize;
+        let phnum = read_u16(endianness, &header[44..46]) as usize;
+        let shentsize = read_u16(endianness, &header[46..48]) as usize;
+        let shnum = read_u16(endianness, &header[48..50]) as usize;
+        let shstrndx = read_u16(endianness, &header[50..52]) as usize;
+        if (shnum > 0 && shentsize < ELF32_SHDR_SIZE) || (phnum > 0 && phentsize < ELF32_PHDR_SIZE)
+        {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let mut segments = Vec::with_capacity(phnum);
+        for index in 0..phnum {
+            let header = slice(data, phoff + index * phentsize, ELF32_PHDR_SIZE)?;
+            let field = |index: usize| read_u32(endianness, &header[index * 4..index * 4 + 4]);
+            segments.push(Elf32Segment {
+                segment_type: field(0),
+                offset: field(1),
+                address: field(2),
+                file_size: field(4),
+                memory_size: field(5),
+            });
+        }
+        let mut headers = Vec::with_capacity(shnum);
+        for index in 0..shnum {
+            headers.push(slice(data, shoff + index * shentsize, ELF32_SHDR_SIZE)?);
+        }
+        let names = match headers.get(shstrndx) {
+            Some(header) if shstrndx != 0 => slice(
+                data,
+                read_u32(endianness, &header[16..20]) as usize,
+                read_u32(endianness, &header[20..24]) as usize,
+            )?,
+            _ => &[],
+        };
+        let mut sections = Vec::with_capacity(shnum);
+        for header in headers {
+            let field = |index: usize| read_u32(endianness, &header[index * 4..index * 4 + 4]);
+            sections.push(Elf32Section {
+                name: read_name(names, field(0) as usize)?,
+                section_type: field(1),
+                flags: field(2),
+                address: field(3),
+                offset: field(4),
+                size: field(5),
+                link: field(6),
+                info: field(7),
+                alignment: field(8),
+                entry_size: field(9),
+            });
+        }
+        Ok(Self {
+            data,
+            ident,
+            flags: read_u32(endianness, &header[36..40]),
+            segments,
+            sections,
+        })
+    }
+
+    /// Returns the identity of the file.
+    pub fn ident(&self) -> ElfIdent {
+        self.ident
+    }
+
+    /// Returns the processor-specific flags of the file (`e_flags`).
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns the program headers.
+    pub fn segments(&self) -> &[Elf32Segment] {
+        &self.segments
+    }
+
+    /// Returns the section headers.
+    pub fn sections(&self) -> &[Elf32Section] {
+        &self.sections
+    }
+
+    /// Returns the first section with the given name.
+    pub fn section_by_name(&self, name: &str) -> Option<&Elf32Section> {
+        self.sections.iter().find(|section| section.name() == name)
+    }
+
+    /// Returns every relocation section without addends named `.rel*`, e.g.
+    /// the `.rel.<output-section>` sections kept by `--emit-relocs`, with the
+    /// section its relocations apply to.
+    pub fn relocation_sections(&self) -> Vec<Elf32RelocationSection<'_>> {
+        self.sections
+            .iter()
+            .filter(|section| match section.section_type() {
+                SHT_REL | SHT_ANDROID_REL => section.name().starts_with(".rel"),
+                SHT_CREL => section.name().starts_with(".crel"),
+                _ => false,
+            })
+            .map(|section| Elf32RelocationSection {
+                section,
+                target: match section.info() {
+                    0 => None,
+                    info => self.sections.get(info as usize),
+                },
+            })
+            .collect()
+    }
+
+    /// Returns the regular relocation entries of a relocation section in the
+    /// byte order of the file, decoding CREL and Android packed sections.
+    ///
+    /// # Errors
+    ///
+    /// If the section is outside of the file or if a CREL or Android packed
+    /// section is malformed or has addends, or if a CREL section has
+    /// relocation types beyond 255.
+    pub fn relocation_entries(
+        &self,
+        relocations: &Elf32RelocationSection<'_>,
+    ) -> Result<Vec<u8>, Error> {
+        let section = relocations.section();
+        let data = self.section_data(section)?;
+        let mut entries = match section.section_type() {
+            SHT_CREL => crel_to_rel(data)?,
+            SHT_ANDROID_REL => aps2_to_rel(data)?,
+            _ => return Ok(data.to_vec()),
+        };
+        // Decoded entries are little-endian.
+        if self.ident.endianness() == Endianness::Big {
+            for word in entries.chunks_exact_mut(4) {
+                word.reverse();
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Compresses every relocation section returned by
+    /// [relocation_sections](#method.relocation_sections) separately and
+    /// returns the compressed sections with the names of the original ones.
+    ///
+    /// # Errors
+    ///
+    /// If a relocation section is malformed.
+    pub fn compress_relocation_sections(&self) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let mut compressed = Vec::new();
+        for relocations in self.relocation_sections() {
+            let section = relocations.section();
+            let entries = self.relocation_entries(&relocations)?;
+            let data = compress_sorted(entries, self.ident.endianness())?;
+            compressed.push((section.name().to_string(), data));
+        }
+        Ok(compressed)
+    }
+
+    /// Compresses the relocations of every relocation section returned by
+    /// [relocation_sections](#method.relocation_sections) into a single
+    /// compressed section.
+    ///
+    /// # Errors
+    ///
+    /// If a relocation section is malformed.
+    pub fn compress_relocation_sections_merged(&self) -> Result<Vec<u8>, Error> {
+        let mut entries = Vec::new();
+        for relocations in self.relocation_sections() {
+            entries.extend_from_slice(&self.relocation_entries(&relocations)?);
+        }
+        compress_sorted(entries, self.ident.endianness())
+    }
+
+    /// Packs the relocation sections of a Zephyr LLEXT extension, a
+    /// relocatable object, into a packed LLEXT relocation table, which
+    /// `llext_relocate` decodes.
+    ///
+    /// The relocations of every section without addends are compressed
+    /// separately for every symbol they reference, so the symbol indices are
+    /// kept.
+    ///
+    /// # Errors
+    ///
+    /// If a relocation section is malformed or has no target section.
+    pub fn compress_llext_relocations(&self) -> Result<Vec<u8>, Error> {
+        let endianness = self.ident.endianness();
+        let mut tables: BTreeMap<(u16, u32), Vec<u8>> = BTreeMap::new();
+        for section in self.sections.iter() {
+            if section.section_type() != SHT_REL {
+                continue;
+            }
+            let target = section.info();
+            if target == 0 || target as usize >= self.sections.len() {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
+            let data = self.section_data(section)?;
+            if !data.len().is_multiple_of(ELF32_REL_SIZE) {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
+            for entry in data.chunks(ELF32_REL_SIZE) {
+                let info = read_u32(endianness, &entry[4..8]);
+                // The symbol is recorded per table, not per entry.
+                let table = tables.entry((target as u16, info >> 8)).or_default();
+                table.extend_from_slice(&entry[..4]);
+                table.extend_from_slice(&u32_bytes(endianness, info & 0xFF));
+            }
+        }
+        let mut packed = Vec::new();
+        for ((target, symbol), entries) in tables {
+            packed.extend_from_slice(&u32_bytes(endianness, symbol));
+            packed.extend_from_slice(&u16_bytes(endianness, target));
+            packed.extend_from_slice(&compress_sorted(entries, endianness)?);
+        }
+        Ok(packed)
+    }
+
+    /// Packs the relocation sections of an ARM FDPIC executable into a packed
+    /// FDPIC relocation table, which `fdpic_relocate` applies.
+    ///
+    /// The relocations of every relocation section returned by
+    /// [relocation_sections](#method.relocation_sections) are compressed
+    /// separately for every symbol they reference, so the symbol indices are
+    /// kept.
+    ///
+    /// # Errors
+    ///
+    /// If a relocation section is malformed.
+    pub fn compress_fdpic_relocations(&self) -> Result<Vec<u8>, Error> {
+        let endianness = self.ident.endianness();
+        let mut tables: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+        for relocations in self.relocation_sections() {
+            let data = self.relocation_entries(&relocations)?;
+            if !data.len().is_multiple_of(ELF32_REL_SIZE) {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
+            for entry in data.chunks(ELF32_REL_SIZE) {
+                let info = read_u32(endianness, &entry[4..8]);
+                // The symbol is recorded per table, not per entry.
+                let table = tables.entry(info >> 8).or_default();
+                table.extend_from_slice(&entry[..4]);
+                table.extend_from_slice(&u32_bytes(endianness, info & 0xFF));
+            }
+        }
+        let mut packed = Vec::new();
+        for (symbol, entries) in tables {
+            packed.extend_from_slice(&u32_bytes(endianness, symbol));
+            packed.extend_from_slice(&compress_sorted(entries, endianness)?);
+        }
+        Ok(packed)
+    }
+
+    /// Compresses the pointer table section `name`, e.g. `.init_array`, which
+    /// `decompress_pointer_table` rebuilds.
+    ///
+    /// # Errors
+    ///
+    /// If the section is malformed, or `SectionNotFound` if there is no
+    /// `SHT_INIT_ARRAY`, `SHT_FINI_ARRAY` or `SHT_PREINIT_ARRAY` section named
+    /// `name`.
+    pub fn compress_pointer_table(&self, name: &str) -> Result<Vec<u8>, Error> {
+        let section = self
+            .section_by_name(name)
+            .filter(|section| {
+                [SHT_INIT_ARRAY, SHT_FINI_ARRAY, SHT_PREINIT_ARRAY]
+                    .contains(&section.section_type())
+            })
+            .ok_or_else(|| Error::new(ErrorKind::SectionNotFound))?;
+        compress_pointer_table(self.section_data(section)?, self.ident.endianness())
+    }
+
+    /// Returns the relocation tables referenced by the dynamic segment, which
+    /// locates them even if the file has no section header table.
+    ///
+    /// # Errors
+    ///
+    /// If the dynamic segment is malformed.
+    pub fn dynamic_tables(&self) -> Result<Vec<Elf32DynamicTable>, Error> {
+        let endianness = self.ident.endianness();
+        let dynamic = match self
+            .segments
+            .iter()
+            .find(|segment| segment.segment_type() == PT_DYNAMIC)
+        {
+            Some(segment) => slice(
+                self.data,
+                segment.offset() as usize,
+                segment.file_size() as usize,
+            )?,
+            None => return Ok(Vec::new()),
+        };
+        let mut values = [None; DT_JMPREL as usize + 1];
+        for entry in dynamic.chunks_exact(8) {
+            let tag = read_u32(endianness, &entry[0..4]);
+            if tag == DT_NULL {
+                break;
+            }
+            if let Some(value) = values.get_mut(tag as usize) {
+                *value = Some(read_u32(endianness, &entry[4..8]));
+            }
+        }
+        let value = |tag: u32| values[tag as usize];
+        let mut tables = Vec::new();
+        for (tag, size, entry_size, section_type) in &[
+            (DT_REL, DT_RELSZ, value(DT_RELENT), SHT_REL),
+            (DT_RELA, DT_RELASZ, value(DT_RELAENT), SHT_RELA),
+            (DT_JMPREL, DT_PLTRELSZ, None, SHT_REL),
+        ] {
+            let (address, size) = match (value(*tag), value(*size)) {
+                (Some(address), Some(size)) => (address, size),
+                (None, None) => continue,
+                _ => return Err(Error::new(ErrorKind::InvalidData)),
+            };
+            let section_type = match (*tag, value(DT_PLTREL)) {
+                (DT_JMPREL, Some(DT_RELA)) => SHT_RELA,
+                (DT_JMPREL, Some(DT_REL)) | (DT_JMPREL, None) => SHT_REL,
+                (DT_JMPREL, Some(_)) => return Err(Error::new(ErrorKind::InvalidData)),
+                _ => *section_type,
+            };
+            let default_size = if section_type == SHT_RELA { 12 } else { 8 };
+            tables.push(Elf32DynamicTable {
+                tag: *tag,
+                section_type,
+                address,
+                size,
+                entry_size: entry_size.unwrap_or(default_size),
+            });
+        }
+        Ok(tables)
+    }
+
+    /// Returns the contents of a relocation table located through the
+    /// dynamic segment.
+    ///
+    /// # Errors
+    ///
+    /// If the table is not loaded from the file by a segment.
+    pub fn dynamic_table_data(&self, table: &Elf32DynamicTable) -> Result<&'a [u8], Error> {
+        let size = table.size() as usize;
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| {
+                segment.segment_type() == PT_LOAD
+                    && table.address().wrapping_sub(segment.address()) < segment.file_size()
+            })
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        let offset = (table.address() - segment.address()) as usize;
+        if segment.file_size() as usize - offset < size {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        slice(self.data, segment.offset() as usize + offset, size)
+    }
+
+    /// Returns the byte-level patches which replace the relocation section
+    /// `name` with its compressed variant in place, without writing anything,
+    /// e.g. for image assembly tools applying the changes themselves.
+    ///
+    /// The compressed section is padded with zeros to the size of the
+    /// original one, its header is changed to describe a `SHT_PROGBITS`
+    /// section of the compressed size, and the size of the dynamic relocation
+    /// table referencing it, if any, is cleared so no loader applies it.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section is malformed, or `SectionNotFound` if there
+    /// is no relocation section without addends named `name`.
+    pub fn compress_section_patches(&self, name: &str) -> Result<Vec<Elf32Patch>, Error> {
+        let index = self
+            .sections
+            .iter()
+            .position(|section| section.name() == name && section.section_type() == SHT_REL)
+            .ok_or_else(|| Error::new(ErrorKind::SectionNotFound))?;
+        let section = &self.sections[index];
+        let endianness = self.ident.endianness();
+        let mut contents = compress_sorted(self.section_data(section)?.to_vec(), endianness)?;
+        let size = contents.len() as u32;
+        if contents.len() > section.size() as usize {
+            return Err(Error::new(ErrorKind::BufferSmall));
+        }
+        contents.resize(section.size() as usize, 0);
+
+        let header = slice(self.data, 0, ELF32_EHDR_SIZE)?;
+        let shoff = read_u32(endianness, &header[32..36]) as usize;
+        let shentsize = read_u16(endianness, &header[46..48]) as usize;
+        let header = shoff + index * shentsize;
+        let mut patches = vec![
+            Elf32Patch::new(
+                section.offset() as usize,
+                contents,
+                format!("compressed contents of `{}`", name),
+            ),
+            Elf32Patch::word(
+                header + 4,
+                endianness,
+                SHT_PROGBITS,
+                format!("sh_type of `{}`", name),
+            ),
+            Elf32Patch::word(
+                header + 20,
+                endianness,
+                size,
+                format!("sh_size of `{}`", name),
+            ),
+            Elf32Patch::word(
+                header + 36,
+                endianness,
+                0,
+                format!("sh_entsize of `{}`", name),
+            ),
+        ];
+
+        if let Some(segment) = self
+            .segments
+            .iter()
+            .find(|segment| segment.segment_type() == PT_DYNAMIC)
+        {
+            let offset = segment.offset() as usize;
+            let dynamic = slice(self.data, offset, segment.file_size() as usize)?;
+            let entries: Vec<(usize, u32, u32)> = dynamic
+                .chunks_exact(8)
+                .enumerate()
+                .map(|(index, entry)| {
+                    (
+                        offset + index * 8,
+                        read_u32(endianness, &entry[0..4]),
+                        read_u32(endianness, &entry[4..8]),
+                    )
+                })
+                .take_while(|(_, tag, _)| *tag != DT_NULL)
+                .collect();
+            for (address_tag, size_tag, size_name) in &[
+                (DT_REL, DT_RELSZ, "DT_RELSZ"),
+                (DT_JMPREL, DT_PLTRELSZ, "DT_PLTRELSZ"),
+            ] {
+                let references = entries
+                    .iter()
+                    .any(|(_, tag, value)| tag == address_tag && *value == section.address());
+                if !references {
+                    continue;
+                }
+                for (entry, _, _) in entries.iter().filter(|(_, tag, _)| tag == size_tag) {
+                    patches.push(Elf32Patch::word(
+                        entry + 4,
+                        endianness,
+                        0,
+                        format!("{} of the dynamic segment", size_name),
+                    ));
+                }
+            }
+        }
+        Ok(patches)
+    }
+
+    /// Returns the contents of a section.
+    ///
+    /// # Errors
+    ///
+    /// If the section is outside of the file.
+    pub fn section_data(&self, section: &Elf32Section) -> Result<&'a [u8], Error> {
+        if section.section_type() == SHT_NOBITS {
+            return Ok(&[]);
+        }
+        slice(
+            self.data,
+            section.offset() as usize,
+            section.size() as usize,
+        )
+    }
+
+    /// Returns the contents of a segment stored in the file, which are
+    /// followed by `memory_size - file_size` zero bytes in memory.
+    ///
+    /// # Errors
+    ///
+    /// If the segment is outside of the file.
+    pub fn segment_data(&self, segment: &Elf32Segment) -> Result<&'a [u8], Error> {
+        slice(
+            self.data,
+            segment.offset() as usize,
+            segment.file_size() as usize,
+        )
+    }
+
+    /// Compresses the regular relocation section `name` and returns the
+    /// rewritten file with the compressed section added as a new section
+    /// named `new_name`, e.g. `.crel.dyn`, and the original section kept,
+    /// zero-filled or removed.
+    ///
+    /// The compressed section is appended to the file with the section name
+    /// string table and the section header table. It is not allocated, so
+    /// placing it in the image is left to the build, e.g. `objcopy`.
+    /// Dynamic table entries are not changed.
+    ///
+    /// # Errors
+    ///
+    /// If the file or the relocation section is malformed, or
+    /// `SectionNotFound` if there is no regular relocation section named
+    /// `name`.
+    pub fn replace_relocation_section(
+        &self,
+        name: &str,
+        new_name: &str,
+        original: OriginalSection,
+    ) -> Result<Vec<u8>, Error> {
+        let (index, section) = self
+            .sections
+            .iter()
+            .enumerate()
+            .find(|(_, section)| section.name() == name && section.section_type() == SHT_REL)
+            .ok_or_else(|| Error::new(ErrorKind::SectionNotFound))?;
+        let endianness = self.ident.endianness();
+        let compressed = compress_sorted(self.section_data(section)?.to_vec(), endianness)?;
+
+        let header = slice(self.data, 0, ELF32_EHDR_SIZE)?;
+        let shoff = read_u32(endianness, &header[32..36]) as usize;
+        let shentsize = read_u16(endianness, &header[46..48]) as usize;
+        let shstrndx = read_u16(endianness, &header[50..52]) as usize;
+        let names = match self.sections.get(shstrndx) {
+            Some(names) if shstrndx != 0 => names,
+            _ => return Err(Error::new(ErrorKind::InvalidData)),
+        };
+
+        let mut output = self.data.to_vec();
+        if original == OriginalSection::ZeroFill && section.section_type() != SHT_NOBITS {
+            let offset = section.offset() as usize;
+            output[offset..offset + section.size() as usize].fill(0);
+        }
+        align(&mut output, 4);
+        let offset = output.len() as u32;
+        output.extend_from_slice(&compressed);
+        let names_offset = output.len() as u32;
+        output.extend_from_slice(self.section_data(names)?);
+        let name_offset = names.size();
+        output.extend_from_slice(new_name.as_bytes());
+        output.push(0);
+        let names_size = output.len() as u32 - names_offset;
+
+        align(&mut output, 4);
+        let new_shoff = output.len() as u32;
+        for index in 0..self.sections.len() {
+            let start = output.len();
+            output.extend_from_slice(slice(
+                self.data,
+                shoff + index * shentsize,
+                ELF32_SHDR_SIZE,
+            )?);
+            if index == shstrndx {
+                write_u32(
+                    endianness,
+                    &mut output[start + 16..start + 20],
+                    names_offset,
+                );
+                write_u32(endianness, &mut output[start + 20..start + 24], names_size);
+            }
+        }
+        for field in &[
+            name_offset,
+            SHT_PROGBITS,
+            0,
+            0,
+            offset,
+            compressed.len() as u32,
+            0,
+            0,
+            1,
+            0,
+        ] {
+            output.extend_from_slice(&u32_bytes(endianness, *field));
+        }
+        write_u32(endianness, &mut output[32..36], new_shoff);
+        write_u16(endianness, &mut output[46..48], ELF32_SHDR_SIZE as u16);
+        write_u16(
+            endianness,
+            &mut output[48..50],
+            self.sections.len() as u16 + 1,
+        );
+
+        if original != OriginalSection::Remove {
+            return Ok(output);
+        }
+        let rewritten = Elf32File::parse(&output)?;
+        let replaced = &rewritten.sections[index];
+        rewritten.strip_sections(|section| core::ptr::eq(section, replaced))
+    }
+
+    /// Removes the sections selected by `remove` and returns the rewritten
+    /// file. The null section and the section name string table are never
+    /// removed.
+    ///
+    /// Remaining sections are renumbered and section indices in section
+    /// headers (`sh_link`, `sh_info`) and in symbol tables are fixed; symbols
+    /// defined in removed sections become absolute. The contents of removed
+    /// sections which are not loaded by a segment are dropped from the file,
+    /// the contents of loaded sections are kept since dropping them would
+    /// change the memory layout of the image.
+    ///
+    /// # Errors
+    ///
+    /// If the file is malformed.
+    pub fn strip_sections<P>(&self, mut remove: P) -> Result<Vec<u8>, Error>
+    where
+        P: FnMut(&Elf32Section) -> bool,
+    {
+        let endianness = self.ident.endianness();
+        let header = slice(self.data, 0, ELF32_EHDR_SIZE)?;
+        let phoff = read_u32(endianness, &header[28..32]) as usize;
+        let shoff = read_u32(endianness, &header[32..36]) as usize;
+        let phentsize = read_u16(endianness, &header[42..44]) as usize;
+        let shentsize = read_u16(endianness, &header[46..48]) as usize;
+        let shstrndx = read_u16(endianness, &header[50..52]) as usize;
+
+        // Everything up to the last byte loaded by a segment stays in place.
+        let mut fixed = ELF32_EHDR_SIZE;
+        if !self.segments.is_empty() {
+            fixed = fixed.max(phoff + self.segments.len() * phentsize);
+        }
+        for segment in self.segments.iter() {
+            fixed = fixed.max(segment.offset() as usize + segment.file_size() as usize);
+        }
+
+        let mut indices = Vec::with_capacity(self.sections.len());
+        let mut kept = Vec::with_capacity(self.sections.len());
+        for (index, section) in self.sections.iter().enumerate() {
+            if index != 0 && index != shstrndx && remove(section) {
+                indices.push(None);
+            } else {
+                indices.push(Some(kept.len() as u32));
+                kept.push(index);
+            }
+        }
+        let new_index = |index: u32| indices.get(index as usize).copied().flatten();
+
+        let mut layout = kept.clone();
+        layout.sort_by_key(|index| self.sections[*index].offset());
+        for index in layout.iter() {
+            let section = &self.sections[*index];
+            if section.section_type() != SHT_NOBITS && (section.offset() as usize) < fixed {
+                fixed = fixed.max(section.offset() as usize + section.size() as usize);
+            }
+        }
+        let mut output = slice(self.data, 0, fixed)?.to_vec();
+        let mut offsets = vec![0; self.sections.len()];
+        for index in layout.iter() {
+            let section = &self.sections[*index];
+            offsets[*index] = if (section.offset() as usize) < fixed {
+                section.offset()
+            } else if section.section_type() == SHT_NOBITS {
+                output.len() as u32
+            } else {
+                align(&mut output, section.alignment() as usize);
+                let offset = output.len() as u32;
+                output.extend_from_slice(self.section_data(section)?);
+                offset
+            };
+        }
+
+        for index in kept.iter() {
+            let section = &self.sections[*index];
+            if section.section_type() != SHT_SYMTAB && section.section_type() != SHT_DYNSYM {
+                continue;
+            }
+            let entry_size = match section.entry_size() as usize {
+                0 => ELF32_SYM_SIZE,
+                size if size >= ELF32_SYM_SIZE => size,
+                _ => return Err(Error::new(ErrorKind::InvalidData)),
+            };
+            let offset = offsets[*index] as usize;
+            for entry in 0..section.size() as usize / entry_size {
+                let field = offset + entry * entry_size + 14;
+                let field = output
+                    .get_mut(field..field + 2)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+                let section_index = read_u16(endianness, field);
+                if section_index != 0 && section_index < SHN_LORESERVE {
+                    let section_index = new_index(section_index as u32)
+                        .map(|index| index as u16)
+                        .unwrap_or(SHN_ABS);
+                    write_u16(endianness, field, section_index);
+                }
+            }
+        }
+
+        align(&mut output, 4);
+        let new_shoff = output.len() as u32;
+        for index in kept.iter() {
+            let section = &self.sections[*index];
+            let mut header = [0; ELF32_SHDR_SIZE];
+            header.copy_from_slice(slice(
+                self.data,
+                shoff + index * shentsize,
+                ELF32_SHDR_SIZE,
+            )?);
+            write_u32(endianness, &mut header[16..20], offsets[*index]);
+            if section.link() != 0 {
+                write_u32(
+                    endianness,
+                    &mut header[24..28],
+                    new_index(section.link()).unwrap_or(0),
+                );
+            }
+            if section.info() != 0
+                && (section.section_type() == SHT_REL
+                    || section.section_type() == SHT_RELA
+                    || section.flags() & SHF_INFO_LINK != 0)
+            {
+                write_u32(
+                    endianness,
+                    &mut header[28..32],
+                    new_index(section.info()).unwrap_or(0),
+                );
+            }
+            output.extend_from_slice(&header);
+        }
+        write_u32(endianness, &mut output[32..36], new_shoff);
+        write_u16(endianness, &mut output[46..48], ELF32_SHDR_SIZE as u16);
+        write_u16(endianness, &mut output[48..50], kept.len() as u16);
+        write_u16(
+            endianness,
+            &mut output[50..52],
+            new_index(shstrndx as u32).unwrap_or(0) as u16,
+        );
+        Ok(output)
+    }
+}
+
+/// What becomes of a relocation section replaced by its compressed variant.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OriginalSection {
+    /// Keeps the section and its contents.
+    Keep,
+    /// Keeps the section but fills its contents with zeros, e.g. so a loader
+    /// finds no regular relocations while the layout of the file is kept.
+    ZeroFill,
+    /// Removes the section like
+    /// [strip_sections](struct.Elf32File.html#method.strip_sections).
+    Remove,
+}
+
+/// A byte-level change to an ELF file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Elf32Patch {
+    offset: usize,
+    data: Vec<u8>,
+    description: String,
+}
+
+impl Elf32Patch {
+    /// Creates a new `Elf32Patch` instance writing `data` at `offset`.
+    fn new(offset: usize, data: Vec<u8>, description: String) -> Self {
+        Self {
+            offset,
+            data,
+            description,
+        }
+    }
+
+    /// Creates a new `Elf32Patch` instance writing a word in the given byte
+    /// order.
+    fn word(offset: usize, endianness: Endianness, value: u32, description: String) -> Self {
+        Self::new(offset, u32_bytes(endianness, value).to_vec(), description)
+    }
+
+    /// Returns the offset in the file the patch is written to.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the bytes written by the patch.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns a human-readable description of the patch.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Applies the patch to the contents of a file.
+    ///
+    /// # Errors
+    ///
+    /// If the patch is outside of `file`.
+    pub fn apply(&self, file: &mut [u8]) -> Result<(), Error> {
+        self.offset
+            .checked_add(self.data.len())
+            .and_then(|end| file.get_mut(self.offset..end))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?
+            .copy_from_slice(&self.data);
+        Ok(())
+    }
+}
+
+/// Relocatable ELF32 object file holding a compressed relocation section,
+/// which is linked into the firmware so startup code can locate the section
+/// using its start and end symbols without linker script edits.
+pub struct Elf32Object<'a> {
+    blob: &'a [u8],
+    machine: u16,
+    endianness: Endianness,
+    flags: u32,
+    section: String,
+    start_symbol: String,
+    end_symbol: String,
+}
+
+impl<'a> Elf32Object<'a> {
+    /// Creates a new `Elf32Object` instance for target machine `machine`
+    /// placing the compressed relocation section `blob` in section `.crel`
+    /// between symbols `__crel_start` and `__crel_end`.
+    pub fn new(blob: &'a [u8], machine: u16) -> Self {
+        Self {
+            blob,
+            machine,
+            endianness: Endianness::Little,
+            flags: 0,
+            section: String::from(".crel"),
+            start_symbol: String::from("__crel_start"),
+            end_symbol: String::from("__crel_end"),
+        }
+    }
+
+    /// Sets the byte order of the object file, which must match the one of
+    /// the target (default: little-endian).
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+
+    /// Sets the processor-specific flags (`e_flags`), which must be
+    /// compatible with those of the objects it is linked with, e.g. the EABI
+    /// version on ARM.
+    pub fn set_flags(&mut self, flags: u32) {
+        self.flags = flags;
+    }
+
+    /// Sets the name of the section holding the compressed relocation
+    /// section.
+    pub fn set_section(&mut self, name: &str) {
+        self.section = String::from(name);
+    }
+
+    /// Sets the names of the symbols marking the start and the end of the
+    /// compressed relocation section.
+    pub fn set_symbols(&mut self, start: &str, end: &str) {
+        self.start_symbol = String::from(start);
+        self.end_symbol = String::from(end);
+    }
+
+    /// Returns the contents of the object file.
+    pub fn write(&self) -> Vec<u8> {
+        let endianness = self.endianness;
+        let mut strtab = vec![0];
+        let mut symbols = vec![[0; ELF32_SYM_SIZE]];
+        // The section symbol is local and precedes the global symbols.
+        symbols.push(symbol(endianness, 0, 0, 0, STT_SECTION, 1));
+        for (name, value, size, info) in &[
+            (
+                &self.start_symbol,
+                0,
+                self.blob.len() as u32,
+                STB_GLOBAL | STT_OBJECT,
+            ),
+            (&self.end_symbol, self.blob.len() as u32, 0, STB_GLOBAL),
+        ] {
+            symbols.push(symbol(
+                endianness,
+                strtab.len() as u32,
+                *value,
+                *size,
+                *info,
+                1,
+            ));
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+        }
+        let mut shstrtab = vec![0];
+        let mut names = Vec::new();
+        for name in &[self.section.as_str(), ".symtab", ".strtab", ".shstrtab"] {
+            names.push(shstrtab.len() as u32);
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+        }
+
+        let symtab = symbols.concat();
+        let contents: [&[u8]; 4] = [self.blob, &symtab, &strtab, &shstrtab];
+        // Type, flags, link, info, alignment and entry size of every section.
+        let attributes = [
+            (SHT_PROGBITS, SHF_ALLOC, 0, 0, 4, 0),
+            (SHT_SYMTAB, 0, 3, 2, 4, ELF32_SYM_SIZE as u32),
+            (SHT_STRTAB, 0, 0, 0, 1, 0),
+            (SHT_STRTAB, 0, 0, 0, 1, 0),
+        ];
+        let mut data = vec![0; ELF32_EHDR_SIZE];
+        let mut offsets = Vec::new();
+        for contents in contents.iter() {
+            align(&mut data, 4);
+            offsets.push(data.len() as u32);
+            data.extend_from_slice(contents);
+        }
+        align(&mut data, 4);
+        let shoff = data.len() as u32;
+        data.extend_from_slice(&[0; ELF32_SHDR_SIZE]);
+        for (index, attributes) in attributes.iter().enumerate() {
+            let (section_type, flags, link, info, alignment, entry_size) = *attributes;
+            let size = contents[index].len() as u32;
+            for field in &[
+                names[index],
+                section_type,
+                flags,
+                0,
+                offsets[index],
+                size,
+                link,
+                info,
+                alignment,
+                entry_size,
+            ] {
+                data.extend_from_slice(&u32_bytes(endianness, *field));
+            }
+        }
+
+        data[0..4].copy_from_slice(&ELF_MAGIC);
+        data[4] = 1;
+        data[5] = match endianness {
+            Endianness::Little => 1,
+            Endianness::Big => 2,
+        };
+        data[6] = 1;
+        write_u16(endianness, &mut data[16..18], ET_REL);
+        write_u16(endianness, &mut data[18..20], self.machine);
+        write_u32(endianness, &mut data[20..24], 1);
+        write_u32(endianness, &mut data[32..36], shoff);
+        write_u32(endianness, &mut data[36..40], self.flags);
+        write_u16(endianness, &mut data[40..42], ELF32_EHDR_SIZE as u16);
+        write_u16(endianness, &mut data[46..48], ELF32_SHDR_SIZE as u16);
+        write_u16(endianness, &mut data[48..50], 5);
+        write_u16(endianness, &mut data[50..52], 4);
+        data
+    }
+}
+
+/// Sorts regular ELF32 relocation entries in the given byte order by offset,
+/// as required by the compressor, and compresses them for a target of that
+/// byte order.
+fn compress_sorted(entries: Vec<u8>, endianness: Endianness) -> Result<Vec<u8>, Error> {
+    if !entries.len().is_multiple_of(ELF32_REL_SIZE) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let mut sorted: Vec<&[u8]> = entries.chunks(ELF32_REL_SIZE).collect();
+    sorted.sort_by_key(|entry| read_u32(endianness, &entry[0..4]));
+    let sorted = sorted.concat();
+    let mut output = Vec::new();
+    let mut relocs = Elf32Relocs::new(&sorted);
+    relocs.set_endianness(endianness);
+    relocs.compress_to_writer(&mut output)?;
+    Ok(output)
+}
+
+/// Returns an ELF32 symbol.
+fn symbol(
+    endianness: Endianness,
+    name: u32,
+    value: u32,
+    size: u32,
+    info: u8,
+    section: u16,
+) -> [u8; ELF32_SYM_SIZE] {
+    let mut symbol = [0; ELF32_SYM_SIZE];
+    write_u32(endianness, &mut symbol[0..4], name);
+    write_u32(endianness, &mut symbol[4..8], value);
+    write_u32(endianness, &mut symbol[8..12], size);
+    symbol[12] = info;
+    write_u16(endianness, &mut symbol[14..16], section);
+    symbol
+}
+
+/// Pads `data` with zeros to a multiple of `alignment`.
+fn align(data: &mut Vec<u8>, alignment: usize) {
+    while alignment > 1 && !data.len().is_multiple_of(alignment) {
+        data.push(0);
+    }
+}
+
+/// Returns a sub-slice of `data` or an error if it is out of bounds.
+fn slice(data: &[u8], offset: usize, size: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(size)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData))
+}
+
+/// Reads a 16-bit field in the given byte order.
+fn read_u16(endianness: Endianness, bytes: &[u8]) -> u16 {
+    match endianness {
+        Endianness::Little => LittleEndian::read_u16(bytes),
+        Endianness::Big => BigEndian::read_u16(bytes),
+    }
+}
+
+/// Reads a 32-bit field in the given byte order.
+fn read_u32(endianness: Endianness, bytes: &[u8]) -> u32 {
+    match endianness {
+        Endianness::Little => LittleEndian::read_u32(bytes),
+        Endianness::Big => BigEndian::read_u32(bytes),
+    }
+}
+
+/// Writes a 16-bit field in the given byte order.
+fn write_u16(endianness: Endianness, bytes: &mut [u8], value: u16) {
+    match endianness {
+        Endianness::Little => LittleEndian::write_u16(bytes, value),
+        Endianness::Big => BigEndian::write_u16(bytes, value),
+    }
+}
+
+/// Writes a 32-bit field in the given byte order.
+fn write_u32(endianness: Endianness, bytes: &mut [u8], value: u32) {
+    match endianness {
+        Endianness::Little => LittleEndian::write_u32(bytes, value),
+        Endianness::Big => BigEndian::write_u32(bytes, value),
+    }
+}
+
+/// Returns a 16-bit field in the given byte order.
+fn u16_bytes(endianness: Endianness, value: u16) -> [u8; 2] {
+    let mut bytes = [0; 2];
+    write_u16(endianness, &mut bytes, value);
+    bytes
+}
+
+/// Returns a 32-bit field in the given byte order.
+fn u32_bytes(endianness: Endianness, value: u32) -> [u8; 4] {
+    let mut bytes = [0; 4];
+    write_u32(endianness, &mut bytes, value);
+    bytes
+}
+
+/// Reads a NUL-terminated name from a string table.
+fn read_name(names: &[u8], offset: usize) -> Result<String, Error> {
+    if names.is_empty() {
+        return Ok(String::new());
+    }
+    let name = names
+        .get(offset..)
+        .and_then(|name| name.split(|byte| *byte == 0).next())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+    String::from_utf8(name.to_vec()).map_err(|_| Error::new(ErrorKind::InvalidData))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a little-endian ELF32 file for ARM with the given sections.
+    pub(crate) fn build_elf32(sections: &[(&str, u32, u32, &[u8])]) -> Vec<u8> {
+        build_elf32_endian(sections, Endianness::Little)
+    }
+
+    /// Builds an ELF32 file for ARM of the given byte order with the given
+    /// sections.
+    fn build_elf32_endian(sections: &[(&str, u32, u32, &[u8])], endianness: Endianness) -> Vec<u8> {
+        let mut names = vec![0];
+        let mut name_offsets = Vec::new();
+        for (name, _, _, _) in sections.iter() {
+            name_offsets.push(names.len() as u32);
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        let shstrtab_name = names.len() as u32;
+        names.extend_from_slice(b".shstrtab\0");
+
+        let mut data = vec![0; ELF32_EHDR_SIZE];
+        let mut offsets = Vec::new();
+        for (_, _, _, contents) in sections.iter() {
+            offsets.push(data.len() as u32);
+            data.extend_from_slice(contents);
+        }
+        let names_offset = data.len() as u32;
+        data.extend_from_slice(&names);
+        while !data.len().is_multiple_of(4) {
+            data.push(0);
+        }
+        let shoff = data.len() as u32;
+        let mut header = |name: u32, section_type: u32, address: u32, offset: u32, size: u32| {
+            for field in &[name, section_type, 0, address, offset, size, 0, 0, 4, 0] {
+                data.extend_from_slice(&u32_bytes(endianness, *field));
+            }
+        };
+        header(0, 0, 0, 0, 0);
+        for (index, (_, section_type, address, contents)) in sections.iter().enumerate() {
+            header(
+                name_offsets[index],
+                *section_type,
+                *address,
+                offsets[index],
+                contents.len() as u32,
+            );
+        }
+        header(shstrtab_name, 3, 0, names_offset, names.len() as u32);
+
+        let shnum = sections.len() as u16 + 2;
+        data[0..4].copy_from_slice(&ELF_MAGIC);
+        data[4] = 1;
+        data[5] = match endianness {
+            Endianness::Little => 1,
+            Endianness::Big => 2,
+        };
+        data[6] = 1;
+        write_u16(endianness, &mut data[16..18], 2);
+        write_u16(endianness, &mut data[18..20], EM_ARM);
+        write_u32(endianness, &mut data[20..24], 1);
+        write_u32(endianness, &mut data[32..36], shoff);
+        write_u16(endianness, &mut data[40..42], ELF32_EHDR_SIZE as u16);
+        write_u16(endianness, &mut data[46..48], ELF32_SHDR_SIZE as u16);
+        write_u16(endianness, &mut data[48..50], shnum);
+        write_u16(endianness, &mut data[50..52], shnum - 1);
+        data
+    }
+
+    /// Builds a little-endian ELF32 file for ARM without section headers,
+    /// whose dynamic segment references the given `DT_REL` and `DT_JMPREL`
+    /// tables.
+    pub(crate) fn build_elf32_dynamic(rel: &[u8], jmprel: &[u8]) -> Vec<u8> {
+        let base_address = 0x8000;
+        let mut data = vec![0; 52 + 2 * 32];
+        let rel_address = base_address + data.len() as u32;
+        data.extend_from_slice(rel);
+        let jmprel_address = base_address + data.len() as u32;
+        data.extend_from_slice(jmprel);
+        let mut entries = vec![(17, rel_address), (18, rel.len() as u32), (19, 8)];
+        if !jmprel.is_empty() {
+            entries.extend_from_slice(&[(23, jmprel_address), (2, jmprel.len() as u32), (20, 17)]);
+        }
+        entries.push((0, 0));
+        let dynamic_offset = data.len() as u32;
+        for (tag, value) in entries.iter() {
+            data.extend_from_slice(&(*tag as u32).to_le_bytes());
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        let size = data.len() as u32;
+        let dynamic_size = size - dynamic_offset;
+        let mut segments = Vec::new();
+        for field in &[1, 0, base_address, base_address, size, size, 6, 4] {
+            segments.extend_from_slice(&field.to_le_bytes());
+        }
+        let dynamic_address = base_address + dynamic_offset;
+        for field in &[
+            2,
+            dynamic_offset,
+            dynamic_address,
+            dynamic_address,
+            dynamic_size,
+            dynamic_size,
+            6,
+            4,
+        ] {
+            segments.extend_from_slice(&field.to_le_bytes());
+        }
+        data[52..116].copy_from_slice(&segments);
+        data[0..4].copy_from_slice(&[0x7F, b'E', b'L', b'F']);
+        data[4] = 1;
+        data[5] = 1;
+        data[6] = 1;
+        data[16..18].copy_from_slice(&3u16.to_le_bytes());
+        data[18..20].copy_from_slice(&40u16.to_le_bytes());
+        data[20..24].copy_from_slice(&1u32.to_le_bytes());
+        data[28..32].copy_from_slice(&52u32.to_le_bytes());
+        data[40..42].copy_from_slice(&52u16.to_le_bytes());
+        data[42..44].copy_from_slice(&32u16.to_le_bytes());
+        data[44..46].copy_from_slice(&2u16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_ident_parse() {
+        let elf = build_elf32(&[]);
+        let ident = ElfIdent::parse(&elf).unwrap();
+        assert_eq!(ident.class(), ElfClass::Elf32);
+        assert_eq!(ident.endianness(), Endianness::Little);
+        assert_eq!(ident.machine(), EM_ARM);
+        assert_eq!(ident.relative_type(), Some(23));
+        assert_eq!(ident.relocation_type_name(23), Some("R_ARM_RELATIVE"));
+    }
+
+    #[test]
+    fn test_ident_parse_big_endian_elf64() {
+        let mut ident = [0; 20];
+        ident[0..4].copy_from_slice(&ELF_MAGIC);
+        ident[4] = 2;
+        ident[5] = 2;
+        ident[18..20].copy_from_slice(&EM_RISCV.to_be_bytes());
+        let ident = ElfIdent::parse(&ident).unwrap();
+        assert_eq!(ident.class(), ElfClass::Elf64);
+        assert_eq!(ident.endianness(), Endianness::Big);
+        assert_eq!(ident.machine(), EM_RISCV);
+        assert_eq!(ident.relative_type(), Some(3));
+    }
+
+    #[test]
+    fn test_ident_parse_bad() {
+        assert!(!ElfIdent::is_elf(&[0x7F, b'E', b'L']));
+        let err = ElfIdent::parse(&[0; 20]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut elf = build_elf32(&[]);
+        elf[4] = 3;
+        let err = ElfIdent::parse(&elf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_elf32file_parse() {
+        let rel = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+        let elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0; 16]),
+            (".rel.dyn", SHT_REL, 0, &rel),
+        ]);
+        let file = Elf32File::parse(&elf).unwrap();
+        assert_eq!(file.ident().machine(), EM_ARM);
+        assert_eq!(file.sections().len(), 4);
+        let text = file.section_by_name(".text").unwrap();
+        assert_eq!(text.address(), 0x1000);
+        assert_eq!(text.size(), 16);
+        let section = file.section_by_name(".rel.dyn").unwrap();
+        assert_eq!(section.section_type(), SHT_REL);
+        assert_eq!(file.section_data(section).unwrap(), &rel[..]);
+        assert!(file.section_by_name(".rel.plt").is_none());
+    }
+
+    #[test]
+    fn test_elf32file_parse_unsupported() {
+        let mut elf = build_elf32(&[]);
+        elf[4] = 2;
+        let err = Elf32File::parse(&elf).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_elf32file_big_endian() {
+        let entries = [(0x1008, 0x17), (0x1000, 0x17), (0x1004, 0x02)];
+        let mut rel = Vec::new();
+        let mut rel_be = Vec::new();
+        for (offset, relocation_type) in entries.iter() {
+            let entry = crate::Elf32Rel::new(*offset, *relocation_type);
+            rel.extend_from_slice(&entry.to_bytes());
+            rel_be.extend_from_slice(&entry.to_bytes_endian(Endianness::Big));
+        }
+        let text = [0; 16];
+        let elf = build_elf32(&[(".text", 1, 0x1000, &text), (".rel.dyn", 9, 0, &rel)]);
+        let elf_be = build_elf32_endian(
+            &[(".text", 1, 0x1000, &text), (".rel.dyn", 9, 0, &rel_be)],
+            Endianness::Big,
+        );
+
+        let file = Elf32File::parse(&elf).unwrap();
+        let file_be = Elf32File::parse(&elf_be).unwrap();
+        assert_eq!(file_be.ident().endianness(), Endianness::Big);
+        assert_eq!(file_be.ident().machine(), EM_ARM);
+        assert_eq!(file_be.sections().len(), 4);
+        assert_eq!(file_be.section_by_name(".text").unwrap().address(), 0x1000);
+
+        // Only the base address of the compressed sections differs.
+        let compressed = file.compress_relocation_sections().unwrap();
+        let compressed_be = file_be.compress_relocation_sections().unwrap();
+        assert_eq!(compressed_be[0].0, ".rel.dyn");
+        let (data, data_be) = (&compressed[0].1, &compressed_be[0].1);
+        assert_eq!(data_be[..4], [0x00, 0x00, 0x10, 0x00]);
+        assert_eq!(data_be[4..], data[4..]);
+
+        let replaced = file_be
+            .replace_relocation_section(".rel.dyn", ".crel.dyn", OriginalSection::Remove)
+            .unwrap();
+        let replaced = Elf32File::parse(&replaced).unwrap();
+        assert!(replaced.section_by_name(".rel.dyn").is_none());
+        let section = replaced.section_by_name(".crel.dyn").unwrap();
+        assert_eq!(replaced.section_data(section).unwrap(), &data_be[..]);
+
+        let patches = file_be.compress_section_patches(".rel.dyn").unwrap();
+        let mut patched = elf_be.clone();
+        for patch in patches.iter() {
+            patch.apply(&mut patched).unwrap();
+        }
+        let patched = Elf32File::parse(&patched).unwrap();
+        let section = patched.section_by_name(".rel.dyn").unwrap();
+        assert_eq!(section.section_type(), SHT_PROGBITS);
+        assert_eq!(section.size() as usize, data_be.len());
+    }
+
+    #[test]
+    fn test_elf32file_parse_truncated() {
+        let elf = build_elf32(&[(".text", 1, 0x1000, &[0; 16])]);
+        let err = Elf32File::parse(&elf[..elf.len() - 1]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    /// Builds symbol table contents with one symbol per section index.
+    fn build_symtab(section_indices: &[u16]) -> Vec<u8> {
+        let mut symtab = Vec::new();
+        for section_index in section_indices {
+            symtab.extend_from_slice(&[0; 14]);
+            symtab.extend_from_slice(&section_index.to_le_bytes());
+        }
+        symtab
+    }
+
+    /// Sets a field of a section header of an ELF file built by `build_elf32`.
+    fn set_section_field(elf: &mut [u8], index: usize, field: usize, value: u32) {
+        let offset = LittleEndian::read_u32(&elf[32..36]) as usize + index * ELF32_SHDR_SIZE;
+        LittleEndian::write_u32(&mut elf[offset + field * 4..offset + field * 4 + 4], value);
+    }
+
+    #[test]
+    fn test_elf32file_strip_sections() {
+        let rel = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+        let symtab = build_symtab(&[0, 1, 2, 3, SHN_ABS]);
+        let mut elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0xAA; 16]),
+            (".rel.dyn", SHT_REL, 0, &rel),
+            (".data", 1, 0x2000, &[0xBB; 8]),
+            (".symtab", SHT_SYMTAB, 0, &symtab),
+        ]);
+        set_section_field(&mut elf, 2, 7, 1);
+        set_section_field(&mut elf, 4, 6, 5);
+        let file = Elf32File::parse(&elf).unwrap();
+        let stripped = file
+            .strip_sections(|section| section.section_type() == SHT_REL)
+            .unwrap();
+        assert_eq!(stripped.len(), elf.len() - rel.len() - ELF32_SHDR_SIZE);
+
+        let file = Elf32File::parse(&stripped).unwrap();
+        let names: Vec<&str> = file.sections().iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["", ".text", ".data", ".symtab", ".shstrtab"]);
+        let data = file.section_by_name(".data").unwrap();
+        assert_eq!(file.section_data(data).unwrap(), &[0xBB; 8]);
+        let symtab = file.section_by_name(".symtab").unwrap();
+        assert_eq!(symtab.link(), 4);
+        assert_eq!(
+            file.section_data(symtab).unwrap(),
+            &build_symtab(&[0, 1, SHN_ABS, 2, SHN_ABS])[..]
+        );
+    }
+
+    #[test]
+    fn test_elf32file_replace_relocation_section() {
+        let rel = [
+            0x08, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // 0x1008
+            0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // 0x1000
+        ];
+        let elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0xAA; 16]),
+            (".rel.dyn", SHT_REL, 0, &rel),
+        ]);
+        let file = Elf32File::parse(&elf).unwrap();
+        let compressed = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x00, 0x08];
+
+        let rewritten = file
+            .replace_relocation_section(".rel.dyn", ".crel.dyn", OriginalSection::Keep)
+            .unwrap();
+        assert_eq!(
+            rewritten[ELF32_EHDR_SIZE..elf.len()],
+            elf[ELF32_EHDR_SIZE..]
+        );
+        let file = Elf32File::parse(&rewritten).unwrap();
+        let names: Vec<&str> = file.sections().iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["", ".text", ".rel.dyn", ".shstrtab", ".crel.dyn"]);
+        let crel = file.section_by_name(".crel.dyn").unwrap();
+        assert_eq!(crel.section_type(), SHT_PROGBITS);
+        assert_eq!(file.section_data(crel).unwrap(), compressed);
+        let rel_dyn = file.section_by_name(".rel.dyn").unwrap();
+        assert_eq!(file.section_data(rel_dyn).unwrap(), rel);
+
+        let file = Elf32File::parse(&elf).unwrap();
+        let rewritten = file
+            .replace_relocation_section(".rel.dyn", ".crel.dyn", OriginalSection::ZeroFill)
+            .unwrap();
+        let file = Elf32File::parse(&rewritten).unwrap();
+        let rel_dyn = file.section_by_name(".rel.dyn").unwrap();
+        assert_eq!(file.section_data(rel_dyn).unwrap(), [0; 16]);
+
+        let file = Elf32File::parse(&elf).unwrap();
+        let rewritten = file
+            .replace_relocation_section(".rel.dyn", ".crel.dyn", OriginalSection::Remove)
+            .unwrap();
+        let file = Elf32File::parse(&rewritten).unwrap();
+        let names: Vec<&str> = file.sections().iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["", ".text", ".shstrtab", ".crel.dyn"]);
+        let crel = file.section_by_name(".crel.dyn").unwrap();
+        assert_eq!(file.section_data(crel).unwrap(), compressed);
+
+        let err = file
+            .replace_relocation_section(".text", ".crel.text", OriginalSection::Keep)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SectionNotFound);
+    }
+
+    #[test]
+    fn test_elf32file_strip_sections_loaded() {
+        let rel = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+        let mut elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0xAA; 16]),
+            (".rel.dyn", SHT_REL, 0x1010, &rel),
+        ]);
+        let size = elf.len() as u32;
+        let phoff = elf.len() as u32;
+        for field in &[1, 0, 0x1000, 0x1000, size, size, 5, 4] {
+            elf.extend_from_slice(&field.to_le_bytes());
+        }
+        LittleEndian::write_u32(&mut elf[28..32], phoff);
+        LittleEndian::write_u16(&mut elf[42..44], ELF32_PHDR_SIZE as u16);
+        LittleEndian::write_u16(&mut elf[44..46], 1);
+        let file = Elf32File::parse(&elf).unwrap();
+        let stripped = file
+            .strip_sections(|section| section.name() == ".rel.dyn")
+            .unwrap();
+        assert_eq!(stripped[ELF32_EHDR_SIZE..elf.len()], elf[ELF32_EHDR_SIZE..]);
+
+        let file = Elf32File::parse(&stripped).unwrap();
+        assert_eq!(file.sections().len(), 3);
+        assert!(file.section_by_name(".rel.dyn").is_none());
+        let text = file.section_by_name(".text").unwrap();
+        assert_eq!(text.offset(), ELF32_EHDR_SIZE as u32);
+    }
+
+    #[test]
+    fn test_elf32object_write() {
+        let blob = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x00, 0x04];
+        let mut object = Elf32Object::new(&blob, EM_ARM);
+        object.set_flags(0x0500_0000);
+        let data = object.write();
+        let file = Elf32File::parse(&data).unwrap();
+        assert_eq!(file.ident().machine(), EM_ARM);
+        assert_eq!(file.flags(), 0x0500_0000);
+        let names: Vec<&str> = file.sections().iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["", ".crel", ".symtab", ".strtab", ".shstrtab"]);
+        let crel = file.section_by_name(".crel").unwrap();
+        assert_eq!(crel.flags(), SHF_ALLOC);
+        assert_eq!(file.section_data(crel).unwrap(), &blob[..]);
+
+        let symtab = file.section_by_name(".symtab").unwrap();
+        assert_eq!((symtab.link(), symtab.info()), (3, 2));
+        let symbols = file.section_data(symtab).unwrap();
+        let strtab = file.section_data(&file.sections()[3]).unwrap();
+        let start = &symbols[2 * ELF32_SYM_SIZE..3 * ELF32_SYM_SIZE];
+        let end = &symbols[3 * ELF32_SYM_SIZE..];
+        assert_eq!(
+            start[..],
+            symbol(Endianness::Little, 1, 0, 9, STB_GLOBAL | STT_OBJECT, 1)[..]
+        );
+        assert_eq!(
+            end[..],
+            symbol(Endianness::Little, 14, 9, 0, STB_GLOBAL, 1)[..]
+        );
+        assert_eq!(read_name(strtab, 1).unwrap(), "__crel_start");
+        assert_eq!(read_name(strtab, 14).unwrap(), "__crel_end");
+
+        object.set_endianness(Endianness::Big);
+        let data = object.write();
+        let file = Elf32File::parse(&data).unwrap();
+        assert_eq!(file.ident().endianness(), Endianness::Big);
+        assert_eq!(
+            (file.ident().machine(), file.flags()),
+            (EM_ARM, 0x0500_0000)
+        );
+        let crel = file.section_by_name(".crel").unwrap();
+        assert_eq!(file.section_data(crel).unwrap(), &blob[..]);
+        let symbols = file.section_data(file.section_by_name(".symtab").unwrap());
+        assert_eq!(
+            symbols.unwrap()[3 * ELF32_SYM_SIZE..],
+            symbol(Endianness::Big, 14, 9, 0, STB_GLOBAL, 1)[..]
+        );
+    }
+
+    #[test]
+    fn test_elf32object_write_names() {
+        let blob = [0; 5];
+        let mut object = Elf32Object::new(&blob, EM_RISCV);
+        object.set_section(".rodata.crel");
+        object.set_symbols("crel_begin", "crel_finish");
+        let data = object.write();
+        let file = Elf32File::parse(&data).unwrap();
+        assert!(file.section_by_name(".rodata.crel").is_some());
+        let strtab = file.section_data(&file.sections()[3]).unwrap();
+        assert_eq!(strtab, b"\0crel_begin\0crel_finish\0");
+    }
+
+    #[test]
+    fn test_elf32file_dynamic_tables() {
+        let rel = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+        let jmprel = [0x00, 0x20, 0x00, 0x00, 0x16, 0x01, 0x00, 0x00];
+        let elf = build_elf32_dynamic(&rel, &jmprel);
+        let file = Elf32File::parse(&elf).unwrap();
+        assert!(file.sections().is_empty());
+        assert_eq!(file.segments().len(), 2);
+        assert_eq!(file.segments()[1].segment_type(), PT_DYNAMIC);
+        let tables = file.dynamic_tables().unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].tag(), DT_REL);
+        assert_eq!(tables[0].section_type(), SHT_REL);
+        assert_eq!(tables[0].address(), 0x8074);
+        assert_eq!(tables[0].size(), 8);
+        assert_eq!(tables[0].entry_size(), 8);
+        assert_eq!(file.dynamic_table_data(&tables[0]).unwrap(), &rel[..]);
+        assert_eq!(tables[1].tag(), DT_JMPREL);
+        assert_eq!(tables[1].section_type(), SHT_REL);
+        assert_eq!(file.dynamic_table_data(&tables[1]).unwrap(), &jmprel[..]);
+    }
+
+    #[test]
+    fn test_elf32file_dynamic_tables_none() {
+        let elf = build_elf32(&[]);
+        let file = Elf32File::parse(&elf).unwrap();
+        assert!(file.dynamic_tables().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_elf32file_dynamic_table_outside() {
+        let rel = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+        let elf = build_elf32_dynamic(&rel, &[]);
+        let file = Elf32File::parse(&elf).unwrap();
+        let mut table = file.dynamic_tables().unwrap()[0];
+        table.address = 0x4000;
+        let err = file.dynamic_table_data(&table).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        table.address = 0x8074;
+        table.size = 0x1000;
+        let err = file.dynamic_table_data(&table).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_elf32file_relocation_sections() {
+        let rel_text = [
+            0x08, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // .text + 8
+            0x00, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // .text + 0
+        ];
+        let rel_data = [0x04, 0x20, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+        let mut elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0; 16]),
+            (".rel.text", SHT_REL, 0, &rel_text),
+            (".data", 1, 0x2000, &[0; 8]),
+            (".rel.data", SHT_REL, 0, &rel_data),
+            (".relocate", 1, 0x3000, &[0; 4]),
+        ]);
+        set_section_field(&mut elf, 2, 7, 1);
+        set_section_field(&mut elf, 4, 7, 3);
+        let file = Elf32File::parse(&elf).unwrap();
+        let sections = file.relocation_sections();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].section().name(), ".rel.text");
+        assert_eq!(sections[0].target().unwrap().address(), 0x1000);
+        assert_eq!(sections[1].section().name(), ".rel.data");
+        assert_eq!(sections[1].target().unwrap().address(), 0x2000);
+
+        let compressed = file.compress_relocation_sections().unwrap();
+        assert_eq!(compressed.len(), 2);
+        assert_eq!(compressed[0].0, ".rel.text");
+        assert_eq!(
+            compressed[0].1,
+            [0x00, 0x10, 0x00, 0x00, 0x01, 0x02, 0x02, 0x00, 0x08]
+        );
+        assert_eq!(compressed[1].0, ".rel.data");
+        assert_eq!(
+            compressed[1].1,
+            [0x04, 0x20, 0x00, 0x00, 0x01, 0x02, 0x01, 0x00]
+        );
+
+        let merged = file.compress_relocation_sections_merged().unwrap();
+        assert_eq!(
+            merged,
+            [0x00, 0x10, 0x00, 0x00, 0x01, 0x02, 0x03, 0x00, 0x08, 0xFC, 0x1F]
+        );
+    }
+
+    #[test]
+    fn test_elf32file_relocation_sections_crel() {
+        let crel_text = [0x12, 0x82, 0x20, 0x02, 0x08];
+        let mut elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0; 16]),
+            (".crel.text", SHT_CREL, 0, &crel_text),
+        ]);
+        set_section_field(&mut elf, 2, 7, 1);
+        let file = Elf32File::parse(&elf).unwrap();
+        let sections = file.relocation_sections();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(
+            file.relocation_entries(&sections[0]).unwrap(),
+            [
+                0x00, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // .text + 0
+                0x08, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // .text + 8
+            ]
+        );
+        let compressed = file.compress_relocation_sections().unwrap();
+        assert_eq!(compressed[0].0, ".crel.text");
+        assert_eq!(
+            compressed[0].1,
+            [0x00, 0x10, 0x00, 0x00, 0x01, 0x02, 0x02, 0x00, 0x08]
+        );
+    }
+
+    #[test]
+    fn test_elf32file_relocation_sections_aps2() {
+        let aps2_dyn = [
+            0x41, 0x50, 0x53, 0x32, 0x02, 0x00, 0x02, 0x01, 0x02, 0x80, 0x20, 0x08,
+        ];
+        let elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0; 16]),
+            (".rel.dyn", SHT_ANDROID_REL, 0, &aps2_dyn),
+        ]);
+        let file = Elf32File::parse(&elf).unwrap();
+        let sections = file.relocation_sections();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(
+            file.relocation_entries(&sections[0]).unwrap(),
+            [
+                0x00, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // 0x1000
+                0x08, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // 0x1008
+            ]
+        );
+        let compressed = file.compress_relocation_sections().unwrap();
+        assert_eq!(
+            compressed[0].1,
+            [0x00, 0x10, 0x00, 0x00, 0x01, 0x02, 0x02, 0x00, 0x08]
+        );
+    }
+
+    #[test]
+    fn test_elf32file_compress_pointer_table() {
+        let init_array = [
+            0x00, 0x01, 0x00, 0x00, 0x40, 0x01, 0x00, 0x00, 0x20, 0x01, 0x00, 0x00,
+        ];
+        let elf = build_elf32(&[
+            (".init_array", SHT_INIT_ARRAY, 0x2000, &init_array),
+            (".data", 1, 0x3000, &init_array),
+        ]);
+        let file = Elf32File::parse(&elf).unwrap();
+        assert_eq!(
+            file.compress_pointer_table(".init_array").unwrap(),
+            [0x00, 0x01, 0x00, 0x00, 0x03, 0x80, 0x01, 0x3F]
+        );
+        let err = file.compress_pointer_table(".data").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SectionNotFound);
+    }
+
+    #[test]
+    fn test_elf32file_compress_llext_relocations() {
+        let rel_text = [
+            0x08, 0x00, 0x00, 0x00, 0x02, 0x05, 0x00, 0x00, // .text + 8, symbol 5
+            0x10, 0x00, 0x00, 0x00, 0x0A, 0x07, 0x00, 0x00, // .text + 16, symbol 7
+            0x04, 0x00, 0x00, 0x00, 0x02, 0x05, 0x00, 0x00, // .text + 4, symbol 5
+        ];
+        let mut elf = build_elf32(&[
+            (".text", 1, 0, &[0; 32]),
+            (".rel.text", SHT_REL, 0, &rel_text),
+        ]);
+        set_section_field(&mut elf, 2, 7, 1);
+        let file = Elf32File::parse(&elf).unwrap();
+        let packed = file.compress_llext_relocations().unwrap();
+        assert_eq!(
+            packed,
+            [
+                0x05, 0x00, 0x00, 0x00, 0x01, 0x00, // symbol 5, .text
+                0x04, 0x00, 0x00, 0x00, 0x01, 0x02, 0x02, 0x00, 0x04, // +4, +8
+                0x07, 0x00, 0x00, 0x00, 0x01, 0x00, // symbol 7, .text
+                0x10, 0x00, 0x00, 0x00, 0x01, 0x0A, 0x01, 0x00, // +16
+            ]
+        );
+
+        set_section_field(&mut elf, 2, 7, 0);
+        let file = Elf32File::parse(&elf).unwrap();
+        let err = file.compress_llext_relocations().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_elf32file_compress_fdpic_relocations() {
+        let rel_dyn = [
+            0x08, 0x10, 0x00, 0x00, 0xA3, 0x00, 0x00, 0x00, // 0x1008, R_ARM_FUNCDESC
+            0x10, 0x10, 0x00, 0x00, 0xA4, 0x02, 0x00, 0x00, // 0x1010, symbol 2
+            0x00, 0x10, 0x00, 0x00, 0xA4, 0x00, 0x00, 0x00, // 0x1000, R_ARM_FUNCDESC_VALUE
+        ];
+        let elf = build_elf32(&[
+            (".data", 1, 0x1000, &[0; 32]),
+            (".rel.dyn", SHT_REL, 0, &rel_dyn),
+        ]);
+        let file = Elf32File::parse(&elf).unwrap();
+        let packed = file.compress_fdpic_relocations().unwrap();
+        assert_eq!(
+            packed,
+            [
+                0x00, 0x00, 0x00, 0x00, // symbol 0
+                0x00, 0x10, 0x00, 0x00, 0x02, 0xA3, 0x01, 0x08, 0xA4, 0x01, 0x00, // groups
+                0x02, 0x00, 0x00, 0x00, // symbol 2
+                0x10, 0x10, 0x00, 0x00, 0x01, 0xA4, 0x01, 0x00, // group
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elf32file_compress_section_patches() {
+        let rel = [
+            0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[0]
+            0x04, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00, // Elf32Rel[1]
+        ];
+        let mut elf = build_elf32(&[
+            (".text", 1, 0x1000, &[0; 16]),
+            (".rel.dyn", SHT_REL, 0, &rel),
+        ]);
+        set_section_field(&mut elf, 2, 9, 8);
+        let file = Elf32File::parse(&elf).unwrap();
+        let patches = file.compress_section_patches(".rel.dyn").unwrap();
+        let descriptions: Vec<&str> = patches.iter().map(|p| p.description()).collect();
+        assert_eq!(
+            descriptions,
+            [
+                "compressed contents of `.rel.dyn`",
+                "sh_type of `.rel.dyn`",
+                "sh_size of `.rel.dyn`",
+                "sh_entsize of `.rel.dyn`",
+            ]
+        );
+        assert_eq!(patches[0].offset(), ELF32_EHDR_SIZE + 16);
+
+        let mut patched = elf.clone();
+        for patch in patches.iter() {
+            patch.apply(&mut patched).unwrap();
+        }
+        assert_eq!(patched.len(), elf.len());
+        let file = Elf32File::parse(&patched).unwrap();
+        let section = file.section_by_name(".rel.dyn").unwrap();
+        assert_eq!(section.section_type(), SHT_PROGBITS);
+        assert_eq!(section.entry_size(), 0);
+        assert_eq!(
+            file.section_data(section).unwrap(),
+            &[0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x00, 0x04]
+        );
+        assert_eq!(patched[ELF32_EHDR_SIZE + 25..ELF32_EHDR_SIZE + 32], [0; 7]);
+
+        let err = file.compress_section_patches(".rel.dyn").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SectionNotFound);
+        let err = file.compress_section_patches(".rel.plt").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SectionNotFound);
+    }
+
+    #[test]
+    fn test_elf32file_compress_section_patches_dynamic() {
+        let rel = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+        let mut dynamic = Vec::new();
+        for (tag, value) in &[
+            (DT_REL, 0x2000u32),
+            (DT_RELSZ, 8),
+            (DT_RELENT, 8),
+            (DT_NULL, 0),
+        ] {
+            dynamic.extend_from_slice(&tag.to_le_bytes());
+            dynamic.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut elf = build_elf32(&[
+            (".rel.dyn", SHT_REL, 0x2000, &rel),
+            (".dynamic", 6, 0x2008, &dynamic),
+        ]);
+        let phoff = elf.len() as u32;
+        let offset = ELF32_EHDR_SIZE as u32 + 8;
+        for field in &[2, offset, 0x2008, 0x2008, 32, 32, 6, 4] {
+            elf.extend_from_slice(&field.to_le_bytes());
+        }
+        LittleEndian::write_u32(&mut elf[28..32], phoff);
+        LittleEndian::write_u16(&mut elf[42..44], ELF32_PHDR_SIZE as u16);
+        LittleEndian::write_u16(&mut elf[44..46], 1);
+        let file = Elf32File::parse(&elf).unwrap();
+        let patches = file.compress_section_patches(".rel.dyn").unwrap();
+        let patch = patches.last().unwrap();
+        assert_eq!(patch.description(), "DT_RELSZ of the dynamic segment");
+        assert_eq!(patch.offset(), offset as usize + 12);
+        assert_eq!(patch.data(), &[0; 4]);
+    }
+
+    #[test]
+    fn test_elf32patch_apply_outside() {
+        let patch = Elf32Patch::word(6, Endianness::Little, 0, String::new());
+        let err = patch.apply(&mut [0; 8]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
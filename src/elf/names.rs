@@ -0,0 +1,266 @@
+//! Names of relocation types
+
+use super::{EM_386, EM_ARM, EM_PPC, EM_RISCV, EM_XTENSA};
+
+/// Relocation types of Intel 80386.
+const R_386: &[(u8, &str)] = &[
+    (0, "R_386_NONE"),
+    (1, "R_386_32"),
+    (2, "R_386_PC32"),
+    (3, "R_386_GOT32"),
+    (4, "R_386_PLT32"),
+    (5, "R_386_COPY"),
+    (6, "R_386_GLOB_DAT"),
+    (7, "R_386_JMP_SLOT"),
+    (8, "R_386_RELATIVE"),
+    (9, "R_386_GOTOFF"),
+    (10, "R_386_GOTPC"),
+    (11, "R_386_32PLT"),
+    (14, "R_386_TLS_TPOFF"),
+    (15, "R_386_TLS_IE"),
+    (16, "R_386_TLS_GOTIE"),
+    (17, "R_386_TLS_LE"),
+    (18, "R_386_TLS_GD"),
+    (19, "R_386_TLS_LDM"),
+    (20, "R_386_16"),
+    (21, "R_386_PC16"),
+    (22, "R_386_8"),
+    (23, "R_386_PC8"),
+    (35, "R_386_TLS_DTPMOD32"),
+    (36, "R_386_TLS_DTPOFF32"),
+    (37, "R_386_TLS_TPOFF32"),
+    (38, "R_386_SIZE32"),
+    (39, "R_386_TLS_GOTDESC"),
+    (40, "R_386_TLS_DESC_CALL"),
+    (41, "R_386_TLS_DESC"),
+    (42, "R_386_IRELATIVE"),
+    (43, "R_386_GOT32X"),
+];
+
+/// Relocation types of PowerPC.
+const R_PPC: &[(u8, &str)] = &[
+    (0, "R_PPC_NONE"),
+    (1, "R_PPC_ADDR32"),
+    (2, "R_PPC_ADDR24"),
+    (3, "R_PPC_ADDR16"),
+    (4, "R_PPC_ADDR16_LO"),
+    (5, "R_PPC_ADDR16_HI"),
+    (6, "R_PPC_ADDR16_HA"),
+    (7, "R_PPC_ADDR14"),
+    (8, "R_PPC_ADDR14_BRTAKEN"),
+    (9, "R_PPC_ADDR14_BRNTAKEN"),
+    (10, "R_PPC_REL24"),
+    (11, "R_PPC_REL14"),
+    (12, "R_PPC_REL14_BRTAKEN"),
+    (13, "R_PPC_REL14_BRNTAKEN"),
+    (14, "R_PPC_GOT16"),
+    (15, "R_PPC_GOT16_LO"),
+    (16, "R_PPC_GOT16_HI"),
+    (17, "R_PPC_GOT16_HA"),
+    (18, "R_PPC_PLTREL24"),
+    (19, "R_PPC_COPY"),
+    (20, "R_PPC_GLOB_DAT"),
+    (21, "R_PPC_JMP_SLOT"),
+    (22, "R_PPC_RELATIVE"),
+    (23, "R_PPC_LOCAL24PC"),
+    (24, "R_PPC_UADDR32"),
+    (25, "R_PPC_UADDR16"),
+    (26, "R_PPC_REL32"),
+    (68, "R_PPC_DTPMOD32"),
+    (73, "R_PPC_TPREL32"),
+    (78, "R_PPC_DTPREL32"),
+    (248, "R_PPC_IRELATIVE"),
+];
+
+/// Relocation types of ARM.
+const R_ARM: &[(u8, &str)] = &[
+    (0, "R_ARM_NONE"),
+    (1, "R_ARM_PC24"),
+    (2, "R_ARM_ABS32"),
+    (3, "R_ARM_REL32"),
+    (4, "R_ARM_LDR_PC_G0"),
+    (5, "R_ARM_ABS16"),
+    (6, "R_ARM_ABS12"),
+    (7, "R_ARM_THM_ABS5"),
+    (8, "R_ARM_ABS8"),
+    (9, "R_ARM_SBREL32"),
+    (10, "R_ARM_THM_CALL"),
+    (11, "R_ARM_THM_PC8"),
+    (12, "R_ARM_BREL_ADJ"),
+    (13, "R_ARM_TLS_DESC"),
+    (17, "R_ARM_TLS_DTPMOD32"),
+    (18, "R_ARM_TLS_DTPOFF32"),
+    (19, "R_ARM_TLS_TPOFF32"),
+    (20, "R_ARM_COPY"),
+    (21, "R_ARM_GLOB_DAT"),
+    (22, "R_ARM_JUMP_SLOT"),
+    (23, "R_ARM_RELATIVE"),
+    (24, "R_ARM_GOTOFF32"),
+    (25, "R_ARM_BASE_PREL"),
+    (26, "R_ARM_GOT_BREL"),
+    (27, "R_ARM_PLT32"),
+    (28, "R_ARM_CALL"),
+    (29, "R_ARM_JUMP24"),
+    (30, "R_ARM_THM_JUMP24"),
+    (31, "R_ARM_BASE_ABS"),
+    (38, "R_ARM_TARGET1"),
+    (40, "R_ARM_V4BX"),
+    (41, "R_ARM_TARGET2"),
+    (42, "R_ARM_PREL31"),
+    (43, "R_ARM_MOVW_ABS_NC"),
+    (44, "R_ARM_MOVT_ABS"),
+    (45, "R_ARM_MOVW_PREL_NC"),
+    (46, "R_ARM_MOVT_PREL"),
+    (47, "R_ARM_THM_MOVW_ABS_NC"),
+    (48, "R_ARM_THM_MOVT_ABS"),
+    (49, "R_ARM_THM_MOVW_PREL_NC"),
+    (50, "R_ARM_THM_MOVT_PREL"),
+    (51, "R_ARM_THM_JUMP19"),
+    (102, "R_ARM_THM_JUMP11"),
+    (103, "R_ARM_THM_JUMP8"),
+    (104, "R_ARM_TLS_GD32"),
+    (105, "R_ARM_TLS_LDM32"),
+    (106, "R_ARM_TLS_LDO32"),
+    (107, "R_ARM_TLS_IE32"),
+    (108, "R_ARM_TLS_LE32"),
+    (160, "R_ARM_IRELATIVE"),
+    (161, "R_ARM_GOTFUNCDESC"),
+    (162, "R_ARM_GOTOFFFUNCDESC"),
+    (163, "R_ARM_FUNCDESC"),
+    (164, "R_ARM_FUNCDESC_VALUE"),
+];
+
+/// Relocation types of Tensilica Xtensa.
+const R_XTENSA: &[(u8, &str)] = &[
+    (0, "R_XTENSA_NONE"),
+    (1, "R_XTENSA_32"),
+    (2, "R_XTENSA_RTLD"),
+    (3, "R_XTENSA_GLOB_DAT"),
+    (4, "R_XTENSA_JMP_SLOT"),
+    (5, "R_XTENSA_RELATIVE"),
+    (6, "R_XTENSA_PLT"),
+    (8, "R_XTENSA_OP0"),
+    (9, "R_XTENSA_OP1"),
+    (10, "R_XTENSA_OP2"),
+    (11, "R_XTENSA_ASM_EXPAND"),
+    (12, "R_XTENSA_ASM_SIMPLIFY"),
+    (14, "R_XTENSA_32_PCREL"),
+    (15, "R_XTENSA_GNU_VTINHERIT"),
+    (16, "R_XTENSA_GNU_VTENTRY"),
+    (17, "R_XTENSA_DIFF8"),
+    (18, "R_XTENSA_DIFF16"),
+    (19, "R_XTENSA_DIFF32"),
+    (20, "R_XTENSA_SLOT0_OP"),
+    (35, "R_XTENSA_SLOT0_ALT"),
+    (50, "R_XTENSA_TLSDESC_FN"),
+    (51, "R_XTENSA_TLSDESC_ARG"),
+    (52, "R_XTENSA_TLS_DTPOFF"),
+    (53, "R_XTENSA_TLS_TPOFF"),
+    (54, "R_XTENSA_TLS_FUNC"),
+    (55, "R_XTENSA_TLS_ARG"),
+    (56, "R_XTENSA_TLS_CALL"),
+];
+
+/// Relocation types of RISC-V.
+const R_RISCV: &[(u8, &str)] = &[
+    (0, "R_RISCV_NONE"),
+    (1, "R_RISCV_32"),
+    (2, "R_RISCV_64"),
+    (3, "R_RISCV_RELATIVE"),
+    (4, "R_RISCV_COPY"),
+    (5, "R_RISCV_JUMP_SLOT"),
+    (6, "R_RISCV_TLS_DTPMOD32"),
+    (7, "R_RISCV_TLS_DTPMOD64"),
+    (8, "R_RISCV_TLS_DTPREL32"),
+    (9, "R_RISCV_TLS_DTPREL64"),
+    (10, "R_RISCV_TLS_TPREL32"),
+    (11, "R_RISCV_TLS_TPREL64"),
+    (12, "R_RISCV_TLSDESC"),
+    (16, "R_RISCV_BRANCH"),
+    (17, "R_RISCV_JAL"),
+    (18, "R_RISCV_CALL"),
+    (19, "R_RISCV_CALL_PLT"),
+    (20, "R_RISCV_GOT_HI20"),
+    (21, "R_RISCV_TLS_GOT_HI20"),
+    (22, "R_RISCV_TLS_GD_HI20"),
+    (23, "R_RISCV_PCREL_HI20"),
+    (24, "R_RISCV_PCREL_LO12_I"),
+    (25, "R_RISCV_PCREL_LO12_S"),
+    (26, "R_RISCV_HI20"),
+    (27, "R_RISCV_LO12_I"),
+    (28, "R_RISCV_LO12_S"),
+    (29, "R_RISCV_TPREL_HI20"),
+    (30, "R_RISCV_TPREL_LO12_I"),
+    (31, "R_RISCV_TPREL_LO12_S"),
+    (32, "R_RISCV_TPREL_ADD"),
+    (33, "R_RISCV_ADD8"),
+    (34, "R_RISCV_ADD16"),
+    (35, "R_RISCV_ADD32"),
+    (36, "R_RISCV_ADD64"),
+    (37, "R_RISCV_SUB8"),
+    (38, "R_RISCV_SUB16"),
+    (39, "R_RISCV_SUB32"),
+    (40, "R_RISCV_SUB64"),
+    (43, "R_RISCV_ALIGN"),
+    (44, "R_RISCV_RVC_BRANCH"),
+    (45, "R_RISCV_RVC_JUMP"),
+    (51, "R_RISCV_RELAX"),
+    (52, "R_RISCV_SUB6"),
+    (53, "R_RISCV_SET6"),
+    (54, "R_RISCV_SET8"),
+    (55, "R_RISCV_SET16"),
+    (56, "R_RISCV_SET32"),
+    (57, "R_RISCV_32_PCREL"),
+    (58, "R_RISCV_IRELATIVE"),
+];
+
+/// Returns the type of relative relocations of the given machine
+/// (`e_machine`), e.g. 23 (`R_ARM_RELATIVE`), if known.
+pub fn relative_type(machine: u16) -> Option<u8> {
+    match machine {
+        EM_386 => Some(8),
+        EM_PPC => Some(22),
+        EM_ARM => Some(23),
+        EM_XTENSA => Some(5),
+        EM_RISCV => Some(3),
+        _ => None,
+    }
+}
+
+/// Returns the name of a relocation type of the given machine (`e_machine`),
+/// e.g. `R_ARM_RELATIVE`, if known.
+pub fn relocation_type_name(machine: u16, relocation_type: u8) -> Option<&'static str> {
+    let table = match machine {
+        EM_386 => R_386,
+        EM_PPC => R_PPC,
+        EM_ARM => R_ARM,
+        EM_XTENSA => R_XTENSA,
+        EM_RISCV => R_RISCV,
+        _ => return None,
+    };
+    table
+        .iter()
+        .find(|(number, _)| *number == relocation_type)
+        .map(|(_, name)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relocation_type_name() {
+        assert_eq!(relocation_type_name(EM_ARM, 23), Some("R_ARM_RELATIVE"));
+        assert_eq!(relocation_type_name(EM_RISCV, 3), Some("R_RISCV_RELATIVE"));
+        assert_eq!(relocation_type_name(EM_386, 8), Some("R_386_RELATIVE"));
+        assert_eq!(relocation_type_name(EM_ARM, 255), None);
+        assert_eq!(relocation_type_name(0, 0), None);
+    }
+
+    #[test]
+    fn test_relocation_type_name_tables_sorted() {
+        for table in &[R_386, R_PPC, R_ARM, R_XTENSA, R_RISCV] {
+            assert!(table.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+    }
+}
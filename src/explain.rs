@@ -0,0 +1,977 @@
+//! Annotated dump of compressed ELF32 relocation sections
+//!
+//! [explain](fn.explain.html) splits a compressed section into its fields,
+//! e.g. to find where the output of two versions of the compressor diverges.
+
+use std::convert::TryFrom;
+
+use relox_core::layout::{
+    ALIGNED, BASED, BITMAP, DIRECTORY, GROUP_VARINT, INTERLEAVED, MACHINE, REPEAT, SCALED, SIGNED,
+    SYMBOLS,
+};
+use relox_core::uleb128;
+
+use crate::elf::relocation_type_name;
+use crate::{
+    Codec, Endianness, Error, ErrorKind, Metadata, METADATA_BUILD_ID, METADATA_END, METADATA_MAGIC,
+    METADATA_TARGET, METADATA_TOOL_VERSION, METADATA_USER,
+};
+
+/// Bytes of a compressed section and their meaning.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    offset: usize,
+    length: usize,
+    description: String,
+    error: bool,
+}
+
+impl Annotation {
+    /// Returns the offset of the annotated bytes in the section.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of annotated bytes.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Returns the annotated bytes of `data`, the explained section.
+    pub fn bytes<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.offset..self.offset + self.length]
+    }
+
+    /// Returns the meaning of the bytes, e.g. `group[0].count = 2`.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns `true` if the bytes could not be decoded.
+    pub fn is_error(&self) -> bool {
+        self.error
+    }
+}
+
+/// Encoding of the offsets of a group, as flagged by its count.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Body {
+    /// Offsets encoded with the codec of the section.
+    Codec,
+    /// SLEB128 encoded signed offsets.
+    Signed,
+    /// Offsets encoded as opcodes, possibly repeating an offset.
+    Repeat,
+}
+
+/// Cursor over a compressed section recording annotations.
+struct Explainer<'a> {
+    data: &'a [u8],
+    endianness: Endianness,
+    index: usize,
+    annotations: Vec<Annotation>,
+}
+
+impl Explainer<'_> {
+    /// Annotates `length` bytes at the cursor and moves past them.
+    fn annotate(&mut self, length: usize, description: String) {
+        self.annotations.push(Annotation {
+            offset: self.index,
+            length,
+            description,
+            error: false,
+        });
+        self.index += length;
+    }
+
+    /// Returns the `length` bytes at the cursor.
+    fn peek(&self, length: usize) -> Result<&[u8], Error> {
+        self.data
+            .get(self.index..self.index + length)
+            .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))
+    }
+
+    /// Returns the data from the cursor.
+    fn rest(&self) -> &[u8] {
+        self.data.get(self.index..).unwrap_or(&[])
+    }
+
+    /// Reads a byte.
+    fn u8(&mut self, name: &str, describe: impl FnOnce(u8) -> String) -> Result<u8, Error> {
+        let value = self.peek(1)?[0];
+        self.annotate(1, format!("{} = {}", name, describe(value)));
+        Ok(value)
+    }
+
+    /// Reads a 16-bit value in the byte order of the section.
+    fn u16(&mut self, name: &str) -> Result<u16, Error> {
+        let bytes = self.peek(2)?;
+        let value = match self.endianness {
+            Endianness::Little => u16::from_le_bytes([bytes[0], bytes[1]]),
+            Endianness::Big => u16::from_be_bytes([bytes[0], bytes[1]]),
+        };
+        self.annotate(2, format!("{} = {}", name, value));
+        Ok(value)
+    }
+
+    /// Reads a 32-bit value in the byte order of the section.
+    fn u32(&mut self, name: &str, describe: impl FnOnce(u32) -> String) -> Result<u32, Error> {
+        let bytes = self.peek(4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        let value = match self.endianness {
+            Endianness::Little => u32::from_le_bytes(bytes),
+            Endianness::Big => u32::from_be_bytes(bytes),
+        };
+        self.annotate(4, format!("{} = {}", name, describe(value)));
+        Ok(value)
+    }
+
+    /// Annotates the padding up to the next multiple of `alignment`, if any.
+    fn padding(&mut self, alignment: usize) -> Result<(), Error> {
+        let length = self.index.wrapping_neg() & (alignment - 1);
+        if length > 0 {
+            self.peek(length)?;
+            self.annotate(length, "padding".to_string());
+        }
+        Ok(())
+    }
+
+    /// Reads a ULEB128 encoded value.
+    fn uleb128(&mut self, name: &str, describe: impl FnOnce(u32) -> String) -> Result<u32, Error> {
+        let mut value = 0;
+        let length = uleb128::read_u32(self.rest(), &mut value)?;
+        self.annotate(length, format!("{} = {}", name, describe(value)));
+        Ok(value)
+    }
+
+    /// Reads the relocation count of a group, its scale if the section
+    /// records scales, and its symbol indices if the section carries symbol
+    /// indices and the count flags them.
+    ///
+    /// Returns the count, the encoding of the offsets of the group and their
+    /// scale.
+    fn count(
+        &mut self,
+        name: &str,
+        symbols: bool,
+        signed: bool,
+        repeat: bool,
+        scaled: bool,
+    ) -> Result<(u32, Body, u32), Error> {
+        // The lowest bit flags repeat opcodes, the next one signed offsets,
+        // the next one symbol indices.
+        let signed_shift = repeat as u32;
+        let shift = signed_shift + signed as u32;
+        let flags = shift + symbols as u32;
+        let value = self.uleb128(&format!("{}.count", name), |value| {
+            let mut notes = Vec::new();
+            if symbols && (value >> shift) & 1 != 0 {
+                notes.push("with symbols");
+            }
+            if signed && (value >> signed_shift) & 1 != 0 {
+                notes.push("signed");
+            }
+            if repeat && value & 1 != 0 {
+                notes.push("repeated");
+            }
+            match notes.is_empty() {
+                true => (value >> flags).to_string(),
+                false => format!("{} ({})", value >> flags, notes.join(", ")),
+            }
+        })?;
+        let count = value >> flags;
+        let scale = match scaled {
+            true => self.uleb128(&format!("{}.scale", name), |scale| scale.to_string())?,
+            false => 1,
+        };
+        if scale == 0 {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        if symbols && (value >> shift) & 1 != 0 {
+            let mut symbol = 0u32;
+            for number in 0..count {
+                let mut delta = 0;
+                let length = uleb128::read_u32(self.rest(), &mut delta)?;
+                symbol = symbol.wrapping_add((delta >> 1) ^ 0u32.wrapping_sub(delta & 1));
+                self.annotate(length, format!("{}.symbols[{}] = {}", name, number, symbol));
+            }
+        }
+        match (
+            repeat && value & 1 != 0,
+            signed && (value >> signed_shift) & 1 != 0,
+        ) {
+            (true, true) => Err(Error::new(ErrorKind::InvalidData)),
+            (true, false) => Ok((count, Body::Repeat, scale)),
+            (false, true) => Ok((count, Body::Signed, scale)),
+            (false, false) => Ok((count, Body::Codec, scale)),
+        }
+    }
+
+    /// Reads `count` offsets multiplied by `scale`, the first one relative
+    /// to `address`, and returns the last address.
+    fn offsets(
+        &mut self,
+        name: &str,
+        codec: Codec,
+        body: Body,
+        scale: u32,
+        count: u32,
+        mut address: u32,
+    ) -> Result<u32, Error> {
+        let mut describe = |offset: u32| {
+            let offset = offset
+                .checked_mul(scale)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+            address = address
+                .checked_add(offset)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+            Ok(format!("+{:#x} -> {:#010x}", offset, address))
+        };
+        match body {
+            Body::Signed => return self.signed_offsets(name, count, address, scale),
+            Body::Repeat => return self.repeat_offsets(name, count, address, scale),
+            Body::Codec => {}
+        }
+        if codec == Codec::Bitmap {
+            return self.bitmap_offsets(name, count, address);
+        }
+        if codec == Codec::Uleb128 {
+            for number in 0..count {
+                let mut offset = 0;
+                let length = uleb128::read_u32(self.rest(), &mut offset)?;
+                let description = describe(offset)?;
+                self.annotate(
+                    length,
+                    format!("{}.offsets[{}] = {}", name, number, description),
+                );
+            }
+            return Ok(address);
+        }
+        let mut number = 0;
+        while number < count {
+            let lanes = (count - number).min(4);
+            let mut tag = self.u8(&format!("{}.tag", name), |tag| {
+                let lengths: Vec<String> = (0..lanes)
+                    .map(|lane| (((tag >> (2 * lane)) & 0x3) + 1).to_string())
+                    .collect();
+                format!("{:#04x} (lengths {})", tag, lengths.join(", "))
+            })?;
+            for _ in 0..lanes {
+                let length = (tag & 0x3) as usize + 1;
+                tag >>= 2;
+                let offset = self
+                    .peek(length)?
+                    .iter()
+                    .rev()
+                    .fold(0, |offset, byte| offset << 8 | *byte as u32);
+                let description = describe(offset)?;
+                self.annotate(
+                    length,
+                    format!("{}.offsets[{}] = {}", name, number, description),
+                );
+                number += 1;
+            }
+        }
+        Ok(address)
+    }
+
+    /// Reads `count` SLEB128 encoded signed offsets multiplied by `scale`,
+    /// the first one relative to `address`, and returns the last address.
+    fn signed_offsets(
+        &mut self,
+        name: &str,
+        count: u32,
+        mut address: u32,
+        scale: u32,
+    ) -> Result<u32, Error> {
+        let scale = i32::try_from(scale).map_err(|_| Error::new(ErrorKind::InvalidData))?;
+        for number in 0..count {
+            let mut offset = 0;
+            let length = uleb128::read_i32(self.rest(), &mut offset)?;
+            let offset = offset
+                .checked_mul(scale)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+            address = address
+                .checked_add_signed(offset)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+            let sign = if offset < 0 { "-" } else { "+" };
+            self.annotate(
+                length,
+                format!(
+                    "{}.offsets[{}] = {}{:#x} -> {:#010x}",
+                    name,
+                    number,
+                    sign,
+                    offset.unsigned_abs(),
+                    address
+                ),
+            );
+        }
+        Ok(address)
+    }
+
+    /// Reads `count` offsets encoded as opcodes and multiplied by `scale`,
+    /// the first one relative to `address`, and returns the last address.
+    fn repeat_offsets(
+        &mut self,
+        name: &str,
+        count: u32,
+        mut address: u32,
+        scale: u32,
+    ) -> Result<u32, Error> {
+        let scaled = |offset: u32| {
+            offset
+                .checked_mul(scale)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData))
+        };
+        let mut number = 0;
+        while number < count {
+            let mut opcode = 0;
+            let length = uleb128::read_u32(self.rest(), &mut opcode)?;
+            if opcode & 1 == 0 {
+                let offset = scaled(opcode >> 1)?;
+                address = address
+                    .checked_add(offset)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+                self.annotate(
+                    length,
+                    format!(
+                        "{}.offsets[{}] = +{:#x} -> {:#010x}",
+                        name, number, offset, address
+                    ),
+                );
+                number += 1;
+                continue;
+            }
+            let repeats = (opcode >> 1).min(count - number);
+            if repeats == 0 {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
+            let mut offset = 0;
+            let offset_length = uleb128::read_u32(&self.rest()[length..], &mut offset)?;
+            let offset = scaled(offset)?;
+            let first = address;
+            address = offset
+                .checked_mul(repeats)
+                .and_then(|delta| address.checked_add(delta))
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+            self.annotate(
+                length + offset_length,
+                format!(
+                    "{}.offsets[{}..{}] = {} x +{:#x} -> {:#010x}..={:#010x}",
+                    name,
+                    number,
+                    number + repeats,
+                    repeats,
+                    offset,
+                    first.wrapping_add(offset),
+                    address
+                ),
+            );
+            number += repeats;
+        }
+        Ok(address)
+    }
+
+    /// Reads `count` bitmap encoded offsets, the first one relative to
+    /// `address`, and returns the last address.
+    fn bitmap_offsets(&mut self, name: &str, count: u32, mut address: u32) -> Result<u32, Error> {
+        let mut number = 0;
+        while number < count {
+            let mut offset = 0;
+            let length = uleb128::read_u32(self.rest(), &mut offset)?;
+            address = address
+                .checked_add(offset)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+            self.annotate(
+                length,
+                format!(
+                    "{}.offsets[{}] = +{:#x} -> {:#010x}",
+                    name, number, offset, address
+                ),
+            );
+            number += 1;
+            let mut window = address;
+            let mut bitmap = 0x80;
+            while number < count && bitmap & 0x80 != 0 {
+                bitmap = self.peek(1)?[0];
+                let mut addresses = Vec::new();
+                for bit in 0..7 {
+                    if number < count && bitmap & (1 << bit) != 0 {
+                        address = window
+                            .checked_add(4 * (bit + 1))
+                            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+                        addresses.push(format!("{:#010x}", address));
+                        number += 1;
+                    }
+                }
+                window = window.wrapping_add(28);
+                let continued = match bitmap & 0x80 {
+                    0 => "",
+                    _ => ", continued",
+                };
+                self.annotate(
+                    1,
+                    format!(
+                        "{}.bitmap = {:#04x} -> [{}]{}",
+                        name,
+                        bitmap,
+                        addresses.join(", "),
+                        continued
+                    ),
+                );
+            }
+        }
+        Ok(address)
+    }
+
+    /// Returns the end of the annotated bytes.
+    fn end(&self) -> usize {
+        self.annotations
+            .iter()
+            .map(|annotation| annotation.offset + annotation.length)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Annotates the metadata area at the cursor.
+    fn metadata(&mut self) -> Result<(), Error> {
+        // Checks the whole area before annotating its entries.
+        Metadata::parse(self.rest())?;
+        self.annotate(METADATA_MAGIC.len(), "metadata.magic".to_string());
+        loop {
+            let tag = self.peek(1)?[0];
+            if tag == METADATA_END {
+                self.annotate(1, "metadata.end".to_string());
+                return Ok(());
+            }
+            let header = self.index;
+            self.index += 1;
+            let mut length = 0;
+            self.index += uleb128::read_u32(self.rest(), &mut length)?;
+            let value = self.peek(length as usize)?;
+            let description = match tag {
+                METADATA_BUILD_ID => format!("metadata.build_id = {}", hex(value)),
+                METADATA_TARGET => format!("metadata.target = {:?}", text(value)),
+                METADATA_TOOL_VERSION => format!("metadata.tool_version = {:?}", text(value)),
+                METADATA_USER => {
+                    let mut key_length = 0;
+                    let start = uleb128::read_u32(value, &mut key_length)?;
+                    let (key, value) = value[start..].split_at(key_length as usize);
+                    format!("metadata[{:?}] = {:?}", text(key), text(value))
+                }
+                _ => format!("metadata entry with unknown tag {:#04x}", tag),
+            };
+            let size = self.index - header + value.len();
+            self.index = header;
+            self.annotate(size, description);
+        }
+    }
+
+    /// Annotates the whole section.
+    fn section(&mut self, machine: Option<u16>) -> Result<(), Error> {
+        let base_address = self.u32("base_address", |value| format!("{:#010x}", value))?;
+        let mut machine = machine;
+        let mut marker = self.peek(1)?[0];
+        if marker == MACHINE {
+            self.u8("marker", |_| "machine".to_string())?;
+            machine = Some(self.u16("machine")?);
+            marker = self.peek(1)?[0];
+        }
+        let codec = match marker {
+            GROUP_VARINT => Codec::GroupVarint,
+            BITMAP => Codec::Bitmap,
+            _ => Codec::Uleb128,
+        };
+        if codec != Codec::Uleb128 {
+            self.u8("marker", |marker| match marker {
+                BITMAP => "bitmap codec".to_string(),
+                _ => "group-varint codec".to_string(),
+            })?;
+            marker = self.peek(1)?[0];
+        }
+        let symbols = marker == SYMBOLS;
+        if symbols {
+            self.u8("marker", |_| "symbol indices".to_string())?;
+        }
+        let signed = self.peek(1)?[0] == SIGNED;
+        if signed {
+            self.u8("marker", |_| "signed offsets".to_string())?;
+        }
+        let mut alignment = 1;
+        if self.peek(1)?[0] == ALIGNED {
+            self.u8("marker", |_| "aligned groups".to_string())?;
+            alignment = self.u8("alignment", |alignment| alignment.to_string())? as usize;
+            if !alignment.is_power_of_two() {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
+        }
+        let repeat = self.peek(1)?[0] == REPEAT;
+        if repeat {
+            self.u8("marker", |_| "repeated offsets".to_string())?;
+        }
+        // Bitmaps mark words, which are not scaled.
+        let scaled = codec != Codec::Bitmap && self.peek(1)?[0] == SCALED;
+        if scaled {
+            self.u8("marker", |_| "scaled offsets".to_string())?;
+        }
+        let type_name = |relocation_type: u8| match machine
+            .and_then(|machine| relocation_type_name(machine, relocation_type))
+        {
+            Some(name) => format!("{:#04x} ({})", relocation_type, name),
+            None => format!("{:#04x}", relocation_type),
+        };
+
+        match self.peek(1)?[0] {
+            INTERLEAVED => {
+                self.u8("marker", |_| "interleaved layout".to_string())?;
+                let count = self.uleb128("count", |count| count.to_string())?;
+                let mut address = base_address;
+                for number in 0..count {
+                    let name = format!("run[{}]", number);
+                    self.padding(alignment)?;
+                    self.u8(&format!("{}.relocation_type", name), type_name)?;
+                    let (count, body, scale) =
+                        self.count(&name, symbols, signed, repeat, scaled)?;
+                    address = self.offsets(&name, codec, body, scale, count, address)?;
+                }
+            }
+            DIRECTORY if !symbols && !signed && alignment == 1 && !repeat && !scaled => {
+                self.u8("marker", |_| "directory layout".to_string())?;
+                let count = self.u8("count", |count| count.to_string())?;
+                let mut entries = Vec::new();
+                for number in 0..count {
+                    let name = format!("directory[{}]", number);
+                    self.u8(&format!("{}.relocation_type", name), type_name)?;
+                    let count = self.u32(&format!("{}.count", name), |count| count.to_string())?;
+                    let stream = self.u32(&format!("{}.stream", name), |stream| {
+                        format!("{:#x}", stream)
+                    })?;
+                    entries.push((stream as usize, count));
+                }
+                let streams = self.index;
+                let mut end = streams;
+                for (number, (stream, count)) in entries.into_iter().enumerate() {
+                    self.index = streams.saturating_add(stream);
+                    let name = format!("group[{}]", number);
+                    self.offsets(&name, codec, Body::Codec, 1, count, base_address)?;
+                    end = end.max(self.index);
+                }
+                self.index = end;
+            }
+            BASED => {
+                self.u8("marker", |_| "based layout".to_string())?;
+                let count = self.u8("count", |count| count.to_string())?;
+                for number in 0..count {
+                    let name = format!("group[{}]", number);
+                    self.padding(alignment)?;
+                    self.u8(&format!("{}.relocation_type", name), type_name)?;
+                    let address = self.u32(&format!("{}.base_address", name), |address| {
+                        format!("{:#010x}", address)
+                    })?;
+                    let (count, body, scale) =
+                        self.count(&name, symbols, signed, repeat, scaled)?;
+                    self.offsets(&name, codec, body, scale, count, address)?;
+                }
+            }
+            DIRECTORY | MACHINE | GROUP_VARINT | SYMBOLS | BITMAP | SIGNED | ALIGNED | REPEAT
+            | SCALED => return Err(Error::new(ErrorKind::InvalidData)),
+            _ => {
+                let count = self.u8("count", |count| count.to_string())?;
+                for number in 0..count {
+                    let name = format!("group[{}]", number);
+                    self.padding(alignment)?;
+                    self.u8(&format!("{}.relocation_type", name), type_name)?;
+                    let (count, body, scale) =
+                        self.count(&name, symbols, signed, repeat, scaled)?;
+                    self.offsets(&name, codec, body, scale, count, base_address)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a compressed ELF32 relocation section stored in the given byte
+/// order into annotated fields: the header, the group headers and every
+/// offset with the address it yields.
+///
+/// Relocation types are named after the target machine recorded in the
+/// section, or `machine` if it records none.
+///
+/// The annotations are sorted by offset. Bytes following the section are
+/// annotated as trailing data, except for a metadata area, whose entries are
+/// annotated. If the section is malformed, the annotations
+/// end with one covering the rest of the data for which
+/// [is_error](struct.Annotation.html#method.is_error) returns `true`.
+pub fn explain(data: &[u8], endianness: Endianness, machine: Option<u16>) -> Vec<Annotation> {
+    let mut explainer = Explainer {
+        data,
+        endianness,
+        index: 0,
+        annotations: Vec::new(),
+    };
+    let mut result = explainer.section(machine).map_err(|err| ("section", err));
+    if result.is_ok() {
+        explainer.index = explainer.end();
+        if explainer.rest().starts_with(&METADATA_MAGIC) {
+            result = explainer.metadata().map_err(|err| ("metadata", err));
+        }
+    }
+    let end = explainer.end();
+    let mut annotations = explainer.annotations;
+    annotations.sort_by_key(Annotation::offset);
+    if let Err((area, err)) = result {
+        let offset = match err.kind() {
+            // The field at the cursor could not be read.
+            ErrorKind::NotEnoughData => explainer.index.min(data.len()),
+            _ => end,
+        };
+        annotations.push(Annotation {
+            offset,
+            length: data.len() - offset,
+            description: format!("malformed {}: {}", area, error_message(err.kind())),
+            error: true,
+        });
+    } else if end < data.len() {
+        annotations.push(Annotation {
+            offset: end,
+            length: data.len() - end,
+            description: "trailing data".to_string(),
+            error: false,
+        });
+    }
+    annotations
+}
+
+/// Formats bytes as hexadecimal digits.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the text of a metadata entry, which was checked to be UTF-8.
+fn text(value: &[u8]) -> &str {
+    std::str::from_utf8(value).unwrap_or_default()
+}
+
+/// Returns a short description of an error.
+fn error_message(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::InvalidData => "invalid data",
+        ErrorKind::NotEnoughData => "not enough data",
+        ErrorKind::BufferSmall => "buffer is too small",
+        ErrorKind::Unsupported => "unsupported data",
+        ErrorKind::Overflow => "address overflow",
+        ErrorKind::UnsupportedRelocationType => "unsupported relocation type",
+        ErrorKind::TooManyGroups => "too many relocation groups",
+        ErrorKind::SectionNotFound => "section not found",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREL_TWO_GROUPS: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    fn descriptions(annotations: &[Annotation]) -> Vec<&str> {
+        annotations.iter().map(Annotation::description).collect()
+    }
+
+    #[test]
+    fn test_explain() {
+        let annotations = explain(&CREL_TWO_GROUPS, Endianness::Little, Some(40));
+        assert_eq!(
+            descriptions(&annotations),
+            [
+                "base_address = 0x00001000",
+                "count = 2",
+                "group[0].relocation_type = 0x02 (R_ARM_ABS32)",
+                "group[0].count = 2",
+                "group[0].offsets[0] = +0x4 -> 0x00001004",
+                "group[0].offsets[1] = +0x80 -> 0x00001084",
+                "group[1].relocation_type = 0x16 (R_ARM_JUMP_SLOT)",
+                "group[1].count = 3",
+                "group[1].offsets[0] = +0x0 -> 0x00001000",
+                "group[1].offsets[1] = +0x4 -> 0x00001004",
+                "group[1].offsets[2] = +0x4 -> 0x00001008",
+            ]
+        );
+        assert_eq!(annotations[5].bytes(&CREL_TWO_GROUPS), [0x80, 0x01]);
+        assert!(annotations.iter().all(|annotation| !annotation.is_error()));
+    }
+
+    #[test]
+    fn test_explain_directory_group_varint() {
+        const DIRECTORY_GROUP_VARINT: [u8; 20] = [
+            0x00, 0x00, 0x10, 0x00, // base_address
+            0xFB, // group-varint codec
+            0xFE, // marker
+            0x01, // count
+            0x17, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // directory[0]
+            0x04, 0x04, 0x08, 0x00, // streams
+        ];
+        let annotations = explain(&DIRECTORY_GROUP_VARINT, Endianness::Big, None);
+        assert_eq!(
+            descriptions(&annotations)[2..],
+            [
+                "marker = directory layout",
+                "count = 1",
+                "directory[0].relocation_type = 0x17",
+                "directory[0].count = 2",
+                "directory[0].stream = 0x0",
+                "group[0].tag = 0x04 (lengths 1, 2)",
+                "group[0].offsets[0] = +0x4 -> 0x00001004",
+                "group[0].offsets[1] = +0x8 -> 0x0000100c",
+            ]
+        );
+        assert_eq!(annotations[9].bytes(&DIRECTORY_GROUP_VARINT), [0x08, 0x00]);
+    }
+
+    #[test]
+    fn test_explain_bitmap() {
+        const CREL_BITMAP: [u8; 13] = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xF9, // bitmap codec
+            0x01, // count
+            0x17, 0x04, // group[0]
+            0x04, 0x81, 0x02, // offset and bitmap
+            0x40, // last offset
+            0x00, // trailing
+        ];
+        let annotations = explain(&CREL_BITMAP, Endianness::Little, None);
+        assert_eq!(
+            descriptions(&annotations)[1..8],
+            [
+                "marker = bitmap codec",
+                "count = 1",
+                "group[0].relocation_type = 0x17",
+                "group[0].count = 4",
+                "group[0].offsets[0] = +0x4 -> 0x00001004",
+                "group[0].bitmap = 0x81 -> [0x00001008], continued",
+                "group[0].bitmap = 0x02 -> [0x00001028]",
+            ]
+        );
+        assert_eq!(
+            annotations[8].description(),
+            "group[0].offsets[3] = +0x40 -> 0x00001068"
+        );
+    }
+
+    #[test]
+    fn test_explain_symbols() {
+        const CREL_SYMBOLS: [u8; 15] = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xFA, // symbol indices
+            0x02, // count
+            0x02, 0x05, 0x0A, 0x03, 0x00, 0x04, // group[0]
+            0x17, 0x02, 0x08, // group[1]
+        ];
+        let annotations = explain(&CREL_SYMBOLS, Endianness::Little, None);
+        assert_eq!(
+            descriptions(&annotations)[1..],
+            [
+                "marker = symbol indices",
+                "count = 2",
+                "group[0].relocation_type = 0x02",
+                "group[0].count = 2 (with symbols)",
+                "group[0].symbols[0] = 5",
+                "group[0].symbols[1] = 3",
+                "group[0].offsets[0] = +0x0 -> 0x00001000",
+                "group[0].offsets[1] = +0x4 -> 0x00001004",
+                "group[1].relocation_type = 0x17",
+                "group[1].count = 1",
+                "group[1].offsets[0] = +0x8 -> 0x00001008",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_signed() {
+        const CREL_SIGNED: [u8; 18] = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xFA, // symbol indices
+            0xF8, // signed offsets
+            0x02, // count
+            0x02, 0x0B, 0x0A, 0x03, 0x80, 0x7E, 0x84, 0x02, // group[0]
+            0x17, 0x04, 0x08, // group[1]
+        ];
+        let annotations = explain(&CREL_SIGNED, Endianness::Little, None);
+        assert_eq!(
+            descriptions(&annotations)[1..],
+            [
+                "marker = symbol indices",
+                "marker = signed offsets",
+                "count = 2",
+                "group[0].relocation_type = 0x02",
+                "group[0].count = 2 (with symbols, signed)",
+                "group[0].symbols[0] = 5",
+                "group[0].symbols[1] = 3",
+                "group[0].offsets[0] = -0x100 -> 0x00000f00",
+                "group[0].offsets[1] = +0x104 -> 0x00001004",
+                "group[1].relocation_type = 0x17",
+                "group[1].count = 1",
+                "group[1].offsets[0] = +0x8 -> 0x00001008",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_aligned() {
+        const CREL_ALIGNED: [u8; 17] = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xF7, 0x04, // aligned groups
+            0x02, // count
+            0x00, // padding
+            0x02, 0x01, 0x04, 0x00, // group[0]
+            0x17, 0x03, 0x00, 0x08, 0x04, // group[1]
+        ];
+        let annotations = explain(&CREL_ALIGNED, Endianness::Little, None);
+        assert_eq!(
+            descriptions(&annotations)[1..],
+            [
+                "marker = aligned groups",
+                "alignment = 4",
+                "count = 2",
+                "padding",
+                "group[0].relocation_type = 0x02",
+                "group[0].count = 1",
+                "group[0].offsets[0] = +0x4 -> 0x00001004",
+                "padding",
+                "group[1].relocation_type = 0x17",
+                "group[1].count = 3",
+                "group[1].offsets[0] = +0x0 -> 0x00001000",
+                "group[1].offsets[1] = +0x8 -> 0x00001008",
+                "group[1].offsets[2] = +0x4 -> 0x0000100c",
+            ]
+        );
+        let mut data = CREL_ALIGNED;
+        data[5] = 0x03;
+        assert!(explain(&data, Endianness::Little, None)
+            .last()
+            .unwrap()
+            .is_error());
+    }
+
+    #[test]
+    fn test_explain_repeat() {
+        const CREL_REPEAT: [u8; 15] = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xF6, // repeated offsets
+            0x02, // count
+            0x02, 0x04, 0x08, 0x04, // group[0]
+            0x17, 0x09, 0x00, 0x07, 0x04, // group[1]
+        ];
+        let annotations = explain(&CREL_REPEAT, Endianness::Little, None);
+        assert_eq!(
+            descriptions(&annotations)[1..],
+            [
+                "marker = repeated offsets",
+                "count = 2",
+                "group[0].relocation_type = 0x02",
+                "group[0].count = 2",
+                "group[0].offsets[0] = +0x8 -> 0x00001008",
+                "group[0].offsets[1] = +0x4 -> 0x0000100c",
+                "group[1].relocation_type = 0x17",
+                "group[1].count = 4 (repeated)",
+                "group[1].offsets[0] = +0x0 -> 0x00001000",
+                "group[1].offsets[1..4] = 3 x +0x4 -> 0x00001004..=0x0000100c",
+            ]
+        );
+        assert_eq!(annotations[10].bytes(&CREL_REPEAT), [0x07, 0x04]);
+        let mut data = CREL_REPEAT;
+        data[13] = 0x01;
+        assert!(explain(&data, Endianness::Little, None)
+            .last()
+            .unwrap()
+            .is_error());
+    }
+
+    #[test]
+    fn test_explain_scaled() {
+        const CREL_SCALED: [u8; 17] = [
+            0x00, 0x10, 0x00, 0x00, // base_address
+            0xF8, 0xF5, // signed, scaled offsets
+            0x02, // count
+            0x02, 0x04, 0x04, 0x02, 0x01, // group[0]
+            0x17, 0x05, 0x08, 0x02, 0x7F, // group[1]
+        ];
+        let annotations = explain(&CREL_SCALED, Endianness::Little, None);
+        assert_eq!(
+            descriptions(&annotations)[1..],
+            [
+                "marker = signed offsets",
+                "marker = scaled offsets",
+                "count = 2",
+                "group[0].relocation_type = 0x02",
+                "group[0].count = 2",
+                "group[0].scale = 4",
+                "group[0].offsets[0] = +0x8 -> 0x00001008",
+                "group[0].offsets[1] = +0x4 -> 0x0000100c",
+                "group[1].relocation_type = 0x17",
+                "group[1].count = 2 (signed)",
+                "group[1].scale = 8",
+                "group[1].offsets[0] = +0x10 -> 0x00001010",
+                "group[1].offsets[1] = -0x8 -> 0x00001008",
+            ]
+        );
+        let mut data = CREL_SCALED;
+        data[9] = 0x00;
+        assert!(explain(&data, Endianness::Little, None)
+            .last()
+            .unwrap()
+            .is_error());
+    }
+
+    #[test]
+    fn test_explain_metadata() {
+        let mut metadata = Metadata::new();
+        metadata.set_build_id(&[0xAB, 0xCD]);
+        metadata.insert("key", "value");
+        let mut data = CREL_TWO_GROUPS.to_vec();
+        data.extend_from_slice(&metadata.to_bytes().unwrap());
+        let annotations = explain(&data, Endianness::Little, None);
+        assert_eq!(
+            descriptions(&annotations)[11..],
+            [
+                "metadata.magic",
+                "metadata.build_id = abcd",
+                "metadata[\"key\"] = \"value\"",
+                "metadata.end",
+            ]
+        );
+        assert_eq!(annotations[12].bytes(&data), [0x01, 0x02, 0xAB, 0xCD]);
+
+        data.pop();
+        let annotations = explain(&data, Endianness::Little, None);
+        let last = annotations.last().unwrap();
+        assert_eq!(last.description(), "malformed metadata: not enough data");
+        assert_eq!(last.offset(), 15);
+    }
+
+    #[test]
+    fn test_explain_trailing_and_malformed() {
+        let mut data = CREL_TWO_GROUPS.to_vec();
+        data.push(0xAA);
+        let annotations = explain(&data, Endianness::Little, None);
+        let last = annotations.last().unwrap();
+        assert_eq!(last.description(), "trailing data");
+        assert_eq!((last.offset(), last.length()), (15, 1));
+        assert!(!last.is_error());
+
+        let annotations = explain(&CREL_TWO_GROUPS[..13], Endianness::Little, None);
+        let last = annotations.last().unwrap();
+        assert_eq!(last.description(), "malformed section: not enough data");
+        assert_eq!((last.offset(), last.length()), (13, 0));
+        assert!(last.is_error());
+    }
+}
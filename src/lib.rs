@@ -1,7 +1,8 @@
-//! Compress and decompress ELF32 relocation sections
+//! Compress and decompress ELF32 and ELF64 relocation sections
 //!
-//! This crate can be used to compress ELF32 relocation sections post-link time.
-//! It also provides a decompressing method which can be used during relocation.
+//! This crate can be used to compress ELF32 and ELF64 relocation sections
+//! post-link time. It also provides a decompressing method which can be used
+//! during relocation.
 //!
 //! The approach might be useful for embedded system if a relocation section
 //! uses too much static storage.
@@ -14,7 +15,8 @@
 //! ```ignore
 //! /// ELF32 relocations grouped by relocation type.
 //! struct Elf32CRelGroup {
-//!     // Type of the relocation.
+//!     // Type of the relocation, the low byte of `r_info` (`ELF32_R_TYPE`),
+//!     // so every ELF32 relocation type fits.
 //!     relocation_type: u8,
 //!     // Number of relocations encoded as ULEB128.
 //!     count: u32,
@@ -28,11 +30,207 @@
 //! struct Elf32CRel {
 //!     // Base address of all the relocations.
 //!     base_address: u32,
-//!     // Number of relocation groups.
+//!     // Number of relocation groups, less than 0xF5. Sections with more
+//!     // relocation types use `Elf32CRelInterleaved`, whose run count is
+//!     // ULEB128 encoded.
 //!     count: u8,
 //!     // Relocation groups.
 //!     groups: [Elf32CRelGroup; count],
 //! }
+//!
+//! /// A compressed ELF32 relocation section keeping relocations in address order,
+//! /// used if it is smaller than `Elf32CRel`.
+//! struct Elf32CRelInterleaved {
+//!     // Base address of all the relocations.
+//!     base_address: u32,
+//!     // Always 0xFF.
+//!     marker: u8,
+//!     // Number of runs encoded as ULEB128.
+//!     count: u32,
+//!     // Runs of relocations of the same type, laid out as `Elf32CRelGroup`
+//!     // except that the first offset of a run is relative to the last offset
+//!     // of the previous run.
+//!     runs: [Elf32CRelGroup; count],
+//! }//!
+//!
+//! /// Entry of the group directory.
+//! struct Elf32CRelDirectoryEntry {
+//!     // Type of the relocation.
+//!     relocation_type: u8,
+//!     // Number of relocations, in the byte order of `base_address`.
+//!     count: u32,
+//!     // Offset of the group's offsets in `streams`, in the byte order of
+//!     // `base_address`.
+//!     stream: u32,
+//! }
+//!
+//! /// A compressed ELF32 relocation section with a group directory, so groups
+//! /// can be located without decoding the others.
+//! struct Elf32CRelDirectory {
+//!     // Base address of all the relocations.
+//!     base_address: u32,
+//!     // Always 0xFE.
+//!     marker: u8,
+//!     // Number of relocation groups, less than 0xFE.
+//!     count: u8,
+//!     // Group directory.
+//!     directory: [Elf32CRelDirectoryEntry; count],
+//!     // Offsets of the groups, encoded like `Elf32CRelGroup::offsets`.
+//!     streams: [u8],
+//! }
+//!
+//! /// A relocation group with its own base address.
+//! struct Elf32CRelBasedGroup {
+//!     // Type of the relocation.
+//!     relocation_type: u8,
+//!     // Base address of the group's relocations, in the byte order of
+//!     // `Elf32CRelBased::base_address`.
+//!     base_address: u32,
+//!     // Number of relocations encoded as ULEB128.
+//!     count: u32,
+//!     // Offsets encoded like `Elf32CRelGroup::offsets`, the first offset
+//!     // relative to the group's `base_address`.
+//!     offsets: [u32; count],
+//! }
+//!
+//! /// A compressed ELF32 relocation section whose groups carry their base
+//! /// address, e.g. the start of the GOT for GOT relocations.
+//! struct Elf32CRelBased {
+//!     // Lowest base address of the relocations.
+//!     base_address: u32,
+//!     // Always 0xFD.
+//!     marker: u8,
+//!     // Number of relocation groups.
+//!     count: u8,
+//!     // Relocation groups.
+//!     groups: [Elf32CRelBasedGroup; count],
+//! }
+//!
+//! /// Optional prefix of the sections above recording the target machine, which
+//! /// decoders can verify. It replaces the group count or marker, which follows
+//! /// it.
+//! struct Elf32CRelMachine {
+//!     // Base address of all the relocations.
+//!     base_address: u32,
+//!     // Always 0xFC.
+//!     marker: u8,
+//!     // Target machine (`e_machine`), in the byte order of `base_address`.
+//!     machine: u16,
+//! }
+//!
+//! /// Optional prefix of the sections above, following `Elf32CRelMachine` if
+//! /// both are present, selecting the group-varint codec for all offsets: every
+//! /// four offsets share a tag byte holding their lengths less one in two bits
+//! /// each, least significant bits first, followed by the offsets as
+//! /// little-endian values of 1 to 4 bytes. Counts stay ULEB128 encoded.
+//! struct Elf32CRelGroupVarint {
+//!     // Always 0xFB.
+//!     marker: u8,
+//! }
+//!
+//! /// Optional prefix of the sections above in place of `Elf32CRelGroupVarint`,
+//! /// selecting the bitmap codec for all offsets, like `SHT_RELR` sections: the
+//! /// first offset of a run is ULEB128 encoded, relative to the previous offset,
+//! /// and followed by bitmap bytes unless it is the last offset of its group.
+//! /// Bits 0 to 6 of a bitmap byte mark relocations 1 to 7 words after the
+//! /// window start, which is the offset starting the run and moves by 7 words
+//! /// with every byte. Bit 7 continues the bitmap with another byte. The group
+//! /// ends with the byte holding its last relocation.
+//! struct Elf32CRelBitmap {
+//!     // Always 0xF9.
+//!     marker: u8,
+//! }
+//!
+//! /// Optional prefix of the grouped, interleaved and based sections above,
+//! /// following `Elf32CRelGroupVarint` or `Elf32CRelBitmap` if both are present,
+//! /// recording symbol indices (`r_info >> 8`). The count of every group or run
+//! /// is doubled, and its lowest bit set if the relocations of the group or run
+//! /// have symbol indices, which then follow the count, before the offsets.
+//! struct Elf32CRelSymbols {
+//!     // Always 0xFA.
+//!     marker: u8,
+//! }
+//!
+//! /// Symbol indices of a group or run, following its count.
+//! struct Elf32CRelGroupSymbols {
+//!     // Symbol indices are encoded as zigzag ULEB128 of their difference.
+//!     // First symbol index is relative to 0,
+//!     // otherwise symbol[i+1] is relative to symbol[i].
+//!     symbols: [u32; count],
+//! }
+//!
+//! /// Optional prefix of the grouped, interleaved and based sections above,
+//! /// following `Elf32CRelSymbols` if both are present, allowing offsets out of
+//! /// address order. The count of every group or run is doubled again, and its
+//! /// lowest bit set if the offsets of the group or run are encoded as SLEB128
+//! /// signed deltas whatever the codec, e.g. if they are not ascending or if
+//! /// the first one is below the base address.
+//! struct Elf32CRelSigned {
+//!     // Always 0xF8.
+//!     marker: u8,
+//! }
+//!
+//! /// Optional prefix of the grouped, interleaved and based sections above,
+//! /// following `Elf32CRelSigned` if both are present, placing every group or
+//! /// run at a multiple of `alignment` bytes from the start of the section, e.g.
+//! /// for cores without unaligned accesses. Groups and runs are preceded by zero
+//! /// padding as needed.
+//! struct Elf32CRelAligned {
+//!     // Always 0xF7.
+//!     marker: u8,
+//!     // Power of two.
+//!     alignment: u8,
+//! }
+//!
+//! /// Optional prefix of the grouped, interleaved and based sections above,
+//! /// following `Elf32CRelAligned` if both are present, allowing offsets to be
+//! /// encoded as opcodes. The count of every group or run is doubled again, and
+//! /// its lowest bit set if its offsets are encoded as ULEB128 opcodes whatever
+//! /// the codec: an opcode whose lowest bit is clear is an offset shifted left
+//! /// by one, otherwise the opcode shifted right by one is a repeat count and
+//! /// is followed by a ULEB128 offset, which is added that many times. Offsets
+//! /// of a group or run cannot be both repeated and signed.
+//! struct Elf32CRelRepeat {
+//!     // Always 0xF6.
+//!     marker: u8,
+//! }
+//!
+//! /// Optional prefix of the grouped, interleaved and based sections above,
+//! /// following `Elf32CRelRepeat` if both are present, not combined with the
+//! /// bitmap codec. The count of every group or run is followed by a ULEB128
+//! /// encoded scale, not zero, preceding its symbol indices if any. Offsets of
+//! /// the group or run, signed or repeated ones included, are multiplied by
+//! /// the scale.
+//! struct Elf32CRelScaled {
+//!     // Always 0xF5.
+//!     marker: u8,
+//! }
+//! ```
+//!
+//! # Compressed section layout for ELF64
+//!
+//! ```ignore
+//! /// ELF64 relocations grouped by relocation type.
+//! struct Elf64CRelGroup {
+//!     // Type of the relocation encoded as ULEB128.
+//!     relocation_type: u32,
+//!     // Number of relocations encoded as ULEB128.
+//!     count: u32,
+//!     // Offsets are encoded as ULEB128.
+//!     // First offset is relative to `base_address`,
+//!     // otherwise offset[i+1] is relative to offset[i].
+//!     offsets: [u64; count],
+//! }
+//!
+//! /// A compressed ELF64 relocation section.
+//! struct Elf64CRel {
+//!     // Base address of all the relocations encoded as ULEB128.
+//!     base_address: u64,
+//!     // Number of relocation groups encoded as ULEB128.
+//!     count: u32,
+//!     // Relocation groups.
+//!     groups: [Elf64CRelGroup; count],
+//! }
 //! ```
 //!
 //! # Recommended usage
@@ -40,34 +238,134 @@
 //! On host machines, during post-link time processing,
 //! use `host` feature group.
 //!
-//! When targeting embedded devices use either `embedded` or `embedded_minimal`
-//! feature group. The latter one enables `no_bounds_check` and
-//! `no_sanity_check` features to further reduce memory footprint.
+//! When targeting embedded devices use the `relox-core` crate, which holds the
+//! wire format and the decoder, is `no_std` and has no dependencies by
+//! default. This crate re-exports it. Its `embedded` and `embedded_minimal`
+//! feature groups select the decoder, the latter one enabling the
+//! `no_bounds_check` and `no_sanity_check` features to further reduce memory
+//! footprint.
+//!
+//! For code size budgets well below 1 KiB, e.g. boot ROM patches, use the
+//! `embedded_tiny` feature group of `relox-core`, which only includes the
+//! reduced decoder.
 //!
 //! ## List of optional features
 //!
+//! * `cli`: build the `relox` command line tool.
 //! * `compress`: include methods and structures related to compressing.
 //! * `decompress`: include methods and structures related to decompressing.
-//! * `no-std`: do not use standard library.
-//! * `no_bounds_check`: use `unsafe` code instead of bounds-checking variants.
-//! * `no_sanity_check`: do not perform extra sanity checks when processing LEB128
-//!   encodings.
+//! * `detailed_errors`: record the offset into the compressed section, the
+//!   group and the entry of the group being decoded in decompression errors.
+//! * `heapless`: include decoding relocations into a `heapless::Vec`.
+//! * `object`: include compressing relocation sections of files parsed by the
+//!   `object` crate.
+//! * `parallel`: compress several relocation sections concurrently with
+//!   `rayon`.
+//! * `ufmt`: implement `ufmt` formatting traits for error types.
+//! * `serde`: implement `serde` serialization for relocations, parsed sections,
+//!   group summaries, audit records, decode costs, translation regions and
+//!   compression statistics.
 
 #![crate_name = "relox"]
-#![cfg_attr(feature = "no_std", no_std)]
 #![deny(missing_docs, unused, unused_imports)]
 
-mod error;
-mod uleb128;
-
-pub use error::{Error, ErrorKind};
+pub use relox_core::*;
 
-#[cfg(all(feature = "compress", not(feature = "no_std")))]
+#[cfg(feature = "compress")]
 mod compress;
-#[cfg(all(feature = "compress", not(feature = "no_std")))]
+#[cfg(feature = "compress")]
 pub use compress::*;
 
-#[cfg(feature = "decompress")]
-mod decompress;
-#[cfg(feature = "decompress")]
-pub use decompress::*;
+#[cfg(feature = "compress")]
+mod compress64;
+#[cfg(feature = "compress")]
+pub use compress64::*;
+
+#[cfg(all(feature = "compress", feature = "serde"))]
+mod serde_impls;
+
+#[cfg(feature = "compress")]
+mod stats;
+#[cfg(feature = "compress")]
+pub use stats::*;
+
+#[cfg(feature = "compress")]
+mod writer;
+#[cfg(feature = "compress")]
+pub use writer::*;
+
+#[cfg(feature = "compress")]
+pub mod aps2;
+
+#[cfg(feature = "compress")]
+pub mod codegen;
+
+#[cfg(feature = "compress")]
+pub mod crel;
+
+#[cfg(feature = "compress")]
+pub mod elf;
+
+#[cfg(feature = "compress")]
+pub mod relr;
+
+#[cfg(feature = "compress")]
+mod explain;
+#[cfg(feature = "compress")]
+pub use explain::*;
+
+#[cfg(feature = "compress")]
+mod metadata;
+#[cfg(feature = "compress")]
+pub use metadata::*;
+
+#[cfg(feature = "compress")]
+pub mod mcuboot;
+
+#[cfg(feature = "compress")]
+mod pointers;
+#[cfg(feature = "compress")]
+pub use pointers::*;
+
+#[cfg(feature = "compress")]
+mod sections;
+#[cfg(feature = "compress")]
+pub use sections::*;
+
+#[cfg(feature = "compress")]
+mod sorted;
+#[cfg(feature = "compress")]
+pub use sorted::*;
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+pub mod bflt;
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+mod bundle;
+#[cfg(all(feature = "compress", feature = "decompress"))]
+pub use bundle::*;
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+mod debugmap;
+#[cfg(all(feature = "compress", feature = "decompress"))]
+pub use debugmap::*;
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+mod delta;
+#[cfg(all(feature = "compress", feature = "decompress"))]
+pub use delta::*;
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+mod partition;
+#[cfg(all(feature = "compress", feature = "decompress"))]
+pub use partition::*;
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+mod simulate;
+#[cfg(all(feature = "compress", feature = "decompress"))]
+pub use simulate::*;
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+mod roundtrip;
+#[cfg(all(feature = "compress", feature = "decompress"))]
+pub use roundtrip::*;
@@ -0,0 +1,415 @@
+//! Process MCUboot images
+//!
+//! This module can be used to compress the relocation section embedded in the
+//! payload of an image in the format of the MCUboot bootloader, so relox fits
+//! into MCUboot-based update pipelines.
+//!
+//! The image header and the TLV (type-length-value) areas are updated to
+//! describe the processed payload: the SHA-256 hash is recomputed, while
+//! signatures, which cannot be recomputed without the signing key, are
+//! dropped. Process images before signing them, or sign them again afterwards.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::{Elf32Relocs, Error, ErrorKind};
+
+/// Magic number of MCUboot image headers.
+pub const IMAGE_MAGIC: u32 = 0x96F3_B83D;
+/// Magic number of the unprotected TLV area.
+pub const TLV_INFO_MAGIC: u16 = 0x6907;
+/// Magic number of the protected TLV area.
+pub const TLV_PROT_INFO_MAGIC: u16 = 0x6908;
+/// TLV type of the SHA-256 hash of the image.
+pub const TLV_SHA256: u16 = 0x10;
+
+/// Size of the fixed part of an image header.
+const IMAGE_HEADER_SIZE: usize = 32;
+/// Size of the header of a TLV area and of a TLV.
+const TLV_HEADER_SIZE: usize = 4;
+/// Image flags marking encrypted payloads.
+const IMAGE_F_ENCRYPTED: u32 = 0x0000_000C;
+/// Fill value of erased flash, used to pad images.
+const ERASED: u8 = 0xFF;
+
+/// An image in the format of the MCUboot bootloader.
+#[derive(Clone, Debug, PartialEq)]
+pub struct McubootImage<'a> {
+    data: &'a [u8],
+    header_size: usize,
+    image_size: usize,
+    protected_tlv_size: usize,
+    tlvs: Vec<(u16, &'a [u8])>,
+    tlv_end: usize,
+}
+
+impl<'a> McubootImage<'a> {
+    /// Parses an MCUboot image, which may be followed by padding and the
+    /// image trailer.
+    ///
+    /// # Errors
+    ///
+    /// If `data` is not an MCUboot image, or `Unsupported` if its payload is
+    /// encrypted.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let header = slice(data, 0, IMAGE_HEADER_SIZE)?;
+        if LittleEndian::read_u32(&header[0..4]) != IMAGE_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        if LittleEndian::read_u32(&header[16..20]) & IMAGE_F_ENCRYPTED != 0 {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        let header_size = LittleEndian::read_u16(&header[8..10]) as usize;
+        let protected_tlv_size = LittleEndian::read_u16(&header[10..12]) as usize;
+        let image_size = LittleEndian::read_u32(&header[12..16]) as usize;
+        if header_size < IMAGE_HEADER_SIZE {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let payload_end = header_size + image_size;
+        slice(data, 0, payload_end + protected_tlv_size)?;
+        if protected_tlv_size > 0 {
+            let info = slice(data, payload_end, TLV_HEADER_SIZE)?;
+            if LittleEndian::read_u16(&info[0..2]) != TLV_PROT_INFO_MAGIC
+                || LittleEndian::read_u16(&info[2..4]) as usize != protected_tlv_size
+            {
+                return Err(Error::new(ErrorKind::InvalidData));
+            }
+        }
+        let start = payload_end + protected_tlv_size;
+        let info = slice(data, start, TLV_HEADER_SIZE)?;
+        if LittleEndian::read_u16(&info[0..2]) != TLV_INFO_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let tlv_end = start + LittleEndian::read_u16(&info[2..4]) as usize;
+        let mut tlvs = Vec::new();
+        let mut offset = start + TLV_HEADER_SIZE;
+        while offset < tlv_end {
+            let tlv = slice(data, offset, TLV_HEADER_SIZE)?;
+            let length = LittleEndian::read_u16(&tlv[2..4]) as usize;
+            let value = slice(data, offset + TLV_HEADER_SIZE, length)?;
+            tlvs.push((LittleEndian::read_u16(&tlv[0..2]), value));
+            offset += TLV_HEADER_SIZE + length;
+        }
+        if offset != tlv_end {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        Ok(Self {
+            data,
+            header_size,
+            image_size,
+            protected_tlv_size,
+            tlvs,
+            tlv_end,
+        })
+    }
+
+    /// Returns the size of the image header (`ih_hdr_size`).
+    pub fn header_size(&self) -> usize {
+        self.header_size
+    }
+
+    /// Returns the size of the payload (`ih_img_size`).
+    pub fn image_size(&self) -> usize {
+        self.image_size
+    }
+
+    /// Returns the payload.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.data[self.header_size..self.header_size + self.image_size]
+    }
+
+    /// Returns the type and value of the unprotected TLVs.
+    pub fn tlvs(&self) -> &[(u16, &'a [u8])] {
+        &self.tlvs
+    }
+
+    /// Returns `true` if the image is signed.
+    pub fn is_signed(&self) -> bool {
+        self.tlvs
+            .iter()
+            .any(|(tlv_type, _)| is_signature(*tlv_type))
+    }
+
+    /// Compresses the regular ELF32 relocation section found at `offset` of
+    /// the payload and spanning `size` bytes, and returns the updated image.
+    ///
+    /// The compressed section is placed at `offset`. If the relocation section
+    /// ends the payload, the payload is shortened. Otherwise the rest of the
+    /// relocation section is filled with zeros, so the layout of the payload
+    /// is kept. Padding and trailer following the image keep their offset.
+    ///
+    /// # Errors
+    ///
+    /// If the relocation section is outside of the payload or if it is
+    /// malformed, `BufferSmall` if the relocation section does not end the
+    /// payload and compressing it does not save space, or `Unsupported` if
+    /// the image is not hashed with SHA-256.
+    pub fn compress_relocations(&self, offset: usize, size: usize) -> Result<Vec<u8>, Error> {
+        if !self
+            .tlvs
+            .iter()
+            .any(|(tlv_type, _)| *tlv_type == TLV_SHA256)
+        {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        let payload = self.payload();
+        let section = slice(payload, offset, size)?;
+        let mut compressed = Vec::new();
+        let written = Elf32Relocs::new(section).compress_to_writer(&mut compressed)?;
+
+        let mut image = self.data[..self.header_size].to_vec();
+        image.extend_from_slice(&payload[..offset]);
+        image.extend_from_slice(&compressed);
+        if offset + size < payload.len() {
+            if written > size {
+                return Err(Error::new(ErrorKind::BufferSmall));
+            }
+            image.resize(self.header_size + offset + size, 0);
+            image.extend_from_slice(&payload[offset + size..]);
+        }
+        let image_size = image.len() - self.header_size;
+        LittleEndian::write_u32(&mut image[12..16], image_size as u32);
+        let payload_end = self.header_size + self.image_size;
+        image.extend_from_slice(&self.data[payload_end..payload_end + self.protected_tlv_size]);
+        let hash = sha256(&image);
+
+        let mut tlvs = Vec::new();
+        for (tlv_type, value) in self.tlvs.iter() {
+            if is_signature(*tlv_type) {
+                continue;
+            }
+            let value = match *tlv_type {
+                TLV_SHA256 => &hash[..],
+                _ => value,
+            };
+            let mut tlv = [0; TLV_HEADER_SIZE];
+            LittleEndian::write_u16(&mut tlv[0..2], *tlv_type);
+            LittleEndian::write_u16(&mut tlv[2..4], value.len() as u16);
+            tlvs.extend_from_slice(&tlv);
+            tlvs.extend_from_slice(value);
+        }
+        let mut info = [0; TLV_HEADER_SIZE];
+        LittleEndian::write_u16(&mut info[0..2], TLV_INFO_MAGIC);
+        LittleEndian::write_u16(&mut info[2..4], (TLV_HEADER_SIZE + tlvs.len()) as u16);
+        image.extend_from_slice(&info);
+        image.extend_from_slice(&tlvs);
+        if self.tlv_end < self.data.len() {
+            image.resize(self.tlv_end, ERASED);
+            image.extend_from_slice(&self.data[self.tlv_end..]);
+        }
+        Ok(image)
+    }
+}
+
+/// Returns `true` if TLVs of the given type depend on the signing key.
+fn is_signature(tlv_type: u16) -> bool {
+    match tlv_type {
+        // Key hash, public key and signatures.
+        0x01 | 0x02 | 0x20..=0x25 => true,
+        // Boot record, which holds the measured hash.
+        0x60 => true,
+        _ => false,
+    }
+}
+
+/// Returns `size` bytes of `data` at `offset`.
+fn slice(data: &[u8], offset: usize, size: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(size)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))
+}
+
+/// Round constants of SHA-256.
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Computes the SHA-256 hash of `data`, the hash MCUboot verifies.
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let mut v = state;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7]
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+            v = [
+                t1.wrapping_add(t2),
+                v[0],
+                v[1],
+                v[2],
+                v[3].wrapping_add(t1),
+                v[4],
+                v[5],
+                v[6],
+            ];
+        }
+        for (word, value) in state.iter_mut().zip(v.iter()) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+    let mut hash = [0; 32];
+    for (bytes, word) in hash.chunks_mut(4).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REL: [u8; 24] = [
+        0x00, 0x10, 0x00, 0x00, // Elf32Rel[0]
+        0x17, 0x00, 0x00, 0x00, // Type is 23
+        0x04, 0x10, 0x00, 0x00, // Elf32Rel[1]
+        0x17, 0x00, 0x00, 0x00, // Type is 23
+        0x08, 0x10, 0x00, 0x00, // Elf32Rel[2]
+        0x17, 0x00, 0x00, 0x00, // Type is 23
+    ];
+
+    /// Builds an image with a 32-byte header, a payload of code followed by
+    /// `REL` and the given unprotected TLVs.
+    fn build_image(code: &[u8], tlvs: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut image = vec![0; IMAGE_HEADER_SIZE];
+        LittleEndian::write_u32(&mut image[0..4], IMAGE_MAGIC);
+        LittleEndian::write_u16(&mut image[8..10], IMAGE_HEADER_SIZE as u16);
+        LittleEndian::write_u32(&mut image[12..16], (code.len() + REL.len()) as u32);
+        image.extend_from_slice(code);
+        image.extend_from_slice(&REL);
+        let hash = sha256(&image);
+        let mut area = Vec::new();
+        for (tlv_type, value) in tlvs.iter() {
+            let value = match *tlv_type {
+                TLV_SHA256 => &hash[..],
+                _ => value,
+            };
+            area.extend_from_slice(&tlv_type.to_le_bytes());
+            area.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            area.extend_from_slice(value);
+        }
+        image.extend_from_slice(&TLV_INFO_MAGIC.to_le_bytes());
+        image.extend_from_slice(&((area.len() + 4) as u16).to_le_bytes());
+        image.extend_from_slice(&area);
+        image
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256(b"abc")[..8],
+            [0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea]
+        );
+        assert_eq!(
+            sha256(&[0x61; 64])[..8],
+            [0xff, 0xe0, 0x54, 0xfe, 0x7a, 0xe0, 0xcb, 0x6d]
+        );
+    }
+
+    #[test]
+    fn test_mcuboot_parse() {
+        let data = build_image(&[1, 2, 3, 4], &[(TLV_SHA256, &[])]);
+        let image = McubootImage::parse(&data).unwrap();
+        assert_eq!(image.header_size(), 32);
+        assert_eq!(image.image_size(), 28);
+        assert_eq!(image.payload()[..4], [1, 2, 3, 4]);
+        assert_eq!(image.tlvs().len(), 1);
+        assert!(!image.is_signed());
+        let err = McubootImage::parse(&data[..data.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = McubootImage::parse(&data[1..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_mcuboot_compress_relocations() {
+        let data = build_image(&[1, 2, 3, 4], &[(TLV_SHA256, &[]), (0x22, &[0xAA; 8])]);
+        let mut padded = data.clone();
+        padded.extend_from_slice(&[0xFF; 16]);
+        padded.extend_from_slice(&[0x77; 16]);
+        let image = McubootImage::parse(&padded).unwrap();
+        assert!(image.is_signed());
+        let processed = image.compress_relocations(4, REL.len()).unwrap();
+        assert_eq!(processed.len(), padded.len());
+        assert_eq!(processed[processed.len() - 16..], [0x77; 16]);
+        let image = McubootImage::parse(&processed).unwrap();
+        let mut compressed = [0; 64];
+        let written = Elf32Relocs::new(&REL).compress(&mut compressed).unwrap();
+        assert_eq!(image.image_size(), 4 + written);
+        assert_eq!(image.payload()[..4], [1, 2, 3, 4]);
+        assert_eq!(image.payload()[4..], compressed[..written]);
+        assert!(!image.is_signed());
+        assert_eq!(image.tlvs().len(), 1);
+        let end = image.header_size() + image.image_size();
+        assert_eq!(
+            image.tlvs()[0],
+            (TLV_SHA256, &sha256(&processed[..end])[..])
+        );
+    }
+
+    #[test]
+    fn test_mcuboot_compress_relocations_inside_payload() {
+        let data = build_image(&[], &[(TLV_SHA256, &[])]);
+        let image = McubootImage::parse(&data).unwrap();
+        let processed = image.compress_relocations(0, 16).unwrap();
+        let image = McubootImage::parse(&processed).unwrap();
+        assert_eq!(image.image_size(), 24);
+        let mut compressed = [0; 64];
+        let written = Elf32Relocs::new(&REL[..16])
+            .compress(&mut compressed)
+            .unwrap();
+        assert_eq!(image.payload()[..written], compressed[..written]);
+        assert!(image.payload()[written..16].iter().all(|byte| *byte == 0));
+        assert_eq!(image.payload()[16..], REL[16..]);
+        let err = McubootImage::parse(&data)
+            .unwrap()
+            .compress_relocations(8, 24)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+
+    #[test]
+    fn test_mcuboot_compress_relocations_unhashed() {
+        let data = build_image(&[], &[]);
+        let err = McubootImage::parse(&data)
+            .unwrap()
+            .compress_relocations(0, 24)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+}
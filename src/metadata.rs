@@ -0,0 +1,280 @@
+//! Metadata area of compressed ELF32 relocation sections
+//!
+//! The compressor can append a small metadata area to a compressed section,
+//! so that sections found in the field can be traced back to their build.
+//! Decoders stop at the end of the section and never read it.
+//!
+//! ```ignore
+//! /// Metadata entry.
+//! struct Elf32CRelMetadataEntry {
+//!     // One of the `METADATA_*` tags, never 0.
+//!     tag: u8,
+//!     // Length of the value encoded as ULEB128.
+//!     length: u32,
+//!     // Value of the entry: raw bytes for the build ID, UTF-8 text
+//!     // otherwise. User entries hold the ULEB128 encoded length of their
+//!     // key, the key and the value.
+//!     value: [u8; length],
+//! }
+//!
+//! /// Metadata area following the compressed section.
+//! struct Elf32CRelMetadata {
+//!     // Always "RXMD".
+//!     magic: [u8; 4],
+//!     // Entries, terminated by a zero tag.
+//!     entries: [Elf32CRelMetadataEntry],
+//!     end: u8,
+//! }
+//! ```
+//!
+//! Readers skip entries with unknown tags.
+
+use std::convert::TryFrom;
+
+use relox_core::uleb128;
+
+use crate::compress::write_uleb128;
+use crate::{Error, ErrorKind};
+
+/// Magic of a metadata area.
+pub const METADATA_MAGIC: [u8; 4] = *b"RXMD";
+
+/// Tag of the entry terminating a metadata area.
+pub const METADATA_END: u8 = 0;
+/// Tag of the build ID entry, e.g. the contents of `.note.gnu.build-id`.
+pub const METADATA_BUILD_ID: u8 = 1;
+/// Tag of the target triple entry.
+pub const METADATA_TARGET: u8 = 2;
+/// Tag of the tool version entry.
+pub const METADATA_TOOL_VERSION: u8 = 3;
+/// Tag of a user key/value entry.
+pub const METADATA_USER: u8 = 4;
+
+/// Version of this crate, e.g. for
+/// [Metadata::set_tool_version](struct.Metadata.html#method.set_tool_version).
+pub const TOOL_VERSION: &str = concat!("relox ", env!("CARGO_PKG_VERSION"));
+
+/// Metadata of a compressed ELF32 relocation section.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    build_id: Option<Vec<u8>>,
+    target: Option<String>,
+    tool_version: Option<String>,
+    user: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Creates a new, empty `Metadata` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the build ID, if any.
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.build_id.as_deref()
+    }
+
+    /// Sets the build ID.
+    pub fn set_build_id(&mut self, build_id: &[u8]) {
+        self.build_id = Some(build_id.to_vec());
+    }
+
+    /// Returns the target triple, if any.
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// Sets the target triple, e.g. `thumbv7em-none-eabihf`.
+    pub fn set_target(&mut self, target: &str) {
+        self.target = Some(target.to_string());
+    }
+
+    /// Returns the version of the tool which produced the section, if any.
+    pub fn tool_version(&self) -> Option<&str> {
+        self.tool_version.as_deref()
+    }
+
+    /// Sets the version of the tool which produced the section, e.g.
+    /// [TOOL_VERSION](constant.TOOL_VERSION.html).
+    pub fn set_tool_version(&mut self, tool_version: &str) {
+        self.tool_version = Some(tool_version.to_string());
+    }
+
+    /// Returns the user key/value pairs in insertion order.
+    pub fn user(&self) -> &[(String, String)] {
+        &self.user
+    }
+
+    /// Adds a user key/value pair.
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.user.push((key.to_string(), value.to_string()));
+    }
+
+    /// Returns the serialized metadata area.
+    ///
+    /// # Errors
+    ///
+    /// `Unsupported` if a value is longer than `u32::MAX` bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut data = METADATA_MAGIC.to_vec();
+        if let Some(build_id) = &self.build_id {
+            write_entry(&mut data, METADATA_BUILD_ID, build_id)?;
+        }
+        if let Some(target) = &self.target {
+            write_entry(&mut data, METADATA_TARGET, target.as_bytes())?;
+        }
+        if let Some(tool_version) = &self.tool_version {
+            write_entry(&mut data, METADATA_TOOL_VERSION, tool_version.as_bytes())?;
+        }
+        for (key, value) in self.user.iter() {
+            let mut pair = Vec::with_capacity(key.len() + value.len() + 5);
+            write_uleb128(&mut pair, length(key.as_bytes())?)?;
+            pair.extend_from_slice(key.as_bytes());
+            pair.extend_from_slice(value.as_bytes());
+            write_entry(&mut data, METADATA_USER, &pair)?;
+        }
+        data.push(METADATA_END);
+        Ok(data)
+    }
+
+    /// Parses a metadata area.
+    ///
+    /// Returns the metadata and the size of the area.
+    ///
+    /// # Errors
+    ///
+    /// `InvalidData` if `data` does not start with a metadata area or if an
+    /// entry is malformed, or `NotEnoughData` if the area is truncated.
+    pub fn parse(data: &[u8]) -> Result<(Self, usize), Error> {
+        if !data.starts_with(&METADATA_MAGIC) {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        let mut metadata = Self::new();
+        let mut index = METADATA_MAGIC.len();
+        loop {
+            let tag = *data
+                .get(index)
+                .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+            index += 1;
+            if tag == METADATA_END {
+                return Ok((metadata, index));
+            }
+            let length = read_uleb128(data, &mut index)? as usize;
+            let value = data
+                .get(index..index.saturating_add(length))
+                .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+            index += length;
+            match tag {
+                METADATA_BUILD_ID => metadata.build_id = Some(value.to_vec()),
+                METADATA_TARGET => metadata.target = Some(text(value)?),
+                METADATA_TOOL_VERSION => metadata.tool_version = Some(text(value)?),
+                METADATA_USER => {
+                    let mut key_end = 0;
+                    let key_length = read_uleb128(value, &mut key_end)? as usize;
+                    let key = value
+                        .get(key_end..key_end.saturating_add(key_length))
+                        .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+                    let pair = (text(key)?, text(&value[key_end + key_length..])?);
+                    metadata.user.push(pair);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the metadata area following the compressed ELF32 relocation
+/// section at the start of `data`, if any.
+///
+/// # Errors
+///
+/// If the compressed section or the metadata area is malformed.
+#[cfg(feature = "decompress")]
+pub fn read_metadata(data: &[u8]) -> Result<Option<Metadata>, Error> {
+    let end = crate::elf32_relocate(data, &mut |_, _| Ok(()))?;
+    match data[end..].starts_with(&METADATA_MAGIC) {
+        true => Metadata::parse(&data[end..]).map(|(metadata, _)| Some(metadata)),
+        false => Ok(None),
+    }
+}
+
+/// Returns the length of a value.
+fn length(value: &[u8]) -> Result<u32, Error> {
+    u32::try_from(value.len()).map_err(|_| Error::new(ErrorKind::Unsupported))
+}
+
+/// Writes an entry of a metadata area.
+fn write_entry(data: &mut Vec<u8>, tag: u8, value: &[u8]) -> Result<(), Error> {
+    data.push(tag);
+    write_uleb128(data, length(value)?)?;
+    data.extend_from_slice(value);
+    Ok(())
+}
+
+/// Reads a ULEB128 encoded value at `index` and moves past it.
+fn read_uleb128(data: &[u8], index: &mut usize) -> Result<u32, Error> {
+    let mut value = 0;
+    let bytes = data
+        .get(*index..)
+        .ok_or_else(|| Error::new(ErrorKind::NotEnoughData))?;
+    *index += uleb128::read_u32(bytes, &mut value)?;
+    Ok(value)
+}
+
+/// Converts the value of a text entry.
+fn text(value: &[u8]) -> Result<String, Error> {
+    String::from_utf8(value.to_vec()).map_err(|_| Error::new(ErrorKind::InvalidData))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: [u8; 33] = [
+        0x52, 0x58, 0x4D, 0x44, // magic
+        0x01, 0x02, 0xAB, 0xCD, // build ID
+        0x02, 0x05, 0x74, 0x68, 0x75, 0x6D, 0x62, // target
+        0x07, 0x02, 0x00, 0x00, // unknown tag
+        0x04, 0x0A, 0x03, 0x6B, 0x65, 0x79, 0x76, 0x61, 0x6C, 0x75, 0x65, 0x21, // user
+        0x00, // end
+        0xFF, // trailing data
+    ];
+
+    #[test]
+    fn test_metadata_parse() {
+        let (metadata, size) = Metadata::parse(&METADATA).unwrap();
+        assert_eq!(size, METADATA.len() - 1);
+        assert_eq!(metadata.build_id(), Some(&[0xAB, 0xCD][..]));
+        assert_eq!(metadata.target(), Some("thumb"));
+        assert_eq!(metadata.tool_version(), None);
+        assert_eq!(metadata.user(), [("key".to_string(), "value!".to_string())]);
+    }
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let mut metadata = Metadata::new();
+        metadata.set_build_id(&[0xAB, 0xCD]);
+        metadata.set_target("thumb");
+        metadata.set_tool_version(TOOL_VERSION);
+        metadata.insert("key", "value!");
+        metadata.insert("", "");
+        let data = metadata.to_bytes().unwrap();
+        assert_eq!(data[..15], METADATA[..15]);
+        assert_eq!(Metadata::parse(&data).unwrap(), (metadata, data.len()));
+    }
+
+    #[test]
+    fn test_metadata_malformed() {
+        let err = Metadata::parse(&METADATA[1..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = Metadata::parse(&METADATA[..31]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let err = Metadata::parse(&METADATA[..12]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+        let mut data = METADATA;
+        data[10] = 0xFF;
+        data[11] = 0xFE;
+        let err = Metadata::parse(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
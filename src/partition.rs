@@ -0,0 +1,163 @@
+//! Partitioning of compressed relocation sections by address range
+//!
+//! On asymmetric multiprocessing systems every core can apply the
+//! relocations of its own region concurrently at boot if the compressed
+//! section is split into one section per region.
+
+use std::ops::Range;
+
+use crate::{
+    elf32_machine, elf32_relocate_symbols, Elf32CRelWriter, Elf32Rel, Elf32Relocs, Error, ErrorKind,
+};
+
+/// Splits a compressed ELF32 relocation section into one compressed section
+/// per address range, each holding the relocations within its range and
+/// using the lowest of them as base address. Symbol indices are kept.
+///
+/// Sections of ranges without relocations hold no groups and use the start
+/// of their range as base address. The target machine recorded in the
+/// section, if any, is recorded in every partition.
+///
+/// # Errors
+///
+/// If the compressed section is malformed, or `InvalidData` if ranges
+/// overlap or a relocation is outside of all of them.
+pub fn partition_blob(data: &[u8], ranges: &[Range<u32>]) -> Result<Vec<Vec<u8>>, Error> {
+    for (index, range) in ranges.iter().enumerate() {
+        let overlaps = ranges[index + 1..]
+            .iter()
+            .any(|other| range.start < other.end && other.start < range.end);
+        if overlaps {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+    }
+    let machine = elf32_machine(data)?;
+    let mut partitions = vec![Vec::new(); ranges.len()];
+    elf32_relocate_symbols(data, &mut |relocation_type, address, symbol| {
+        let index = ranges
+            .iter()
+            .position(|range| range.contains(&address))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        partitions[index].push(Elf32Rel::with_symbol(address, relocation_type, symbol));
+        Ok(())
+    })?;
+    partitions
+        .iter_mut()
+        .zip(ranges.iter())
+        .map(|(relocations, range)| {
+            if relocations.is_empty() {
+                let mut writer = Elf32CRelWriter::new();
+                writer.set_machine(machine);
+                writer.begin(range.start);
+                let mut compressed = vec![0; writer.size()];
+                writer.finish(&mut compressed)?;
+                return Ok(compressed);
+            }
+            // The compressor uses the first relocation as base address.
+            relocations.sort_unstable();
+            let mut rel = Vec::with_capacity(relocations.len() * 8);
+            for relocation in relocations.iter() {
+                rel.extend_from_slice(&relocation.to_bytes());
+            }
+            let mut relocs = Elf32Relocs::new(&rel);
+            if let Some(machine) = machine {
+                relocs.set_machine(machine);
+            }
+            let mut compressed = Vec::new();
+            relocs.compress_to_writer(&mut compressed)?;
+            Ok(compressed)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf32_relocate;
+
+    const CREL_TWO_GROUPS: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    fn relocations(data: &[u8]) -> Vec<(u8, u32)> {
+        let mut relocations = Vec::new();
+        elf32_relocate(data, &mut |relocation_type, address| {
+            relocations.push((relocation_type, address));
+            Ok(())
+        })
+        .unwrap();
+        relocations.sort_unstable();
+        relocations
+    }
+
+    #[test]
+    fn test_partition_blob() {
+        let ranges = [0x1000..0x1008, 0x1008..0x2000, 0x2000..0x3000];
+        let partitions = partition_blob(&CREL_TWO_GROUPS, &ranges).unwrap();
+        assert_eq!(partitions.len(), 3);
+        assert_eq!(
+            relocations(&partitions[0]),
+            [(0x02, 0x1004), (0x16, 0x1000), (0x16, 0x1004)]
+        );
+        assert_eq!(
+            relocations(&partitions[1]),
+            [(0x02, 0x1084), (0x16, 0x1008)]
+        );
+        assert_eq!(partitions[1][..4], 0x1008u32.to_le_bytes());
+        assert_eq!(partitions[2], [0x00, 0x20, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_partition_blob_symbols() {
+        let entries = [
+            Elf32Rel::with_symbol(0x1000, 0x02, 5),
+            Elf32Rel::new(0x1004, 0x17),
+            Elf32Rel::with_symbol(0x2000, 0x02, 3),
+            Elf32Rel::with_symbol(0x2004, 0x02, 5),
+        ];
+        let mut data = Vec::new();
+        Elf32Relocs::from_entries(&entries)
+            .compress_to_writer(&mut data)
+            .unwrap();
+        let partitions = partition_blob(&data, &[0x1000..0x2000, 0x2000..0x3000]).unwrap();
+        let mut decoded = Vec::new();
+        for partition in partitions.iter() {
+            elf32_relocate_symbols(partition, &mut |relocation_type, address, symbol| {
+                decoded.push(Elf32Rel::with_symbol(address, relocation_type, symbol));
+                Ok(())
+            })
+            .unwrap();
+        }
+        decoded.sort_unstable();
+        assert_eq!(decoded, entries);
+    }
+
+    #[test]
+    fn test_partition_blob_machine() {
+        let mut data = CREL_TWO_GROUPS[..4].to_vec();
+        data.extend_from_slice(&[0xFC, 0x28, 0x00]);
+        data.extend_from_slice(&CREL_TWO_GROUPS[4..]);
+        let partitions = partition_blob(&data, &[0x1000..0x1080, 0x1080..0x1100]).unwrap();
+        for partition in partitions.iter() {
+            assert_eq!(elf32_machine(partition).unwrap(), Some(40));
+        }
+    }
+
+    #[test]
+    fn test_partition_blob_errors() {
+        let ranges = [0x1000..0x1800, 0x1800..0x2000];
+        let err = partition_blob(&CREL_TWO_GROUPS, &[0x1000..0x1080, 0x2000..0x3000]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = partition_blob(&CREL_TWO_GROUPS, &[0x1000..0x1080, 0x1004..0x2000]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = partition_blob(&CREL_TWO_GROUPS[..10], &ranges).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotEnoughData);
+    }
+}
@@ -0,0 +1,70 @@
+//! Compression of pointer tables
+//!
+//! [compress_pointer_table](fn.compress_pointer_table.html) compresses a
+//! table of link-time addresses, e.g. the contents of `.init_array`, into
+//! the layout described in
+//! [decompress_pointer_table](fn.decompress_pointer_table.html), which
+//! rebuilds it at boot.
+
+use std::convert::TryFrom;
+
+use crate::compress::write_uleb128;
+use crate::{Endianness, Error, ErrorKind};
+
+/// Compresses a table of 32-bit pointers stored in the given byte order.
+///
+/// # Errors
+///
+/// `InvalidData` if the size of `table` is not a multiple of 4, or
+/// `Unsupported` if it holds more than `u32::MAX` pointers.
+pub fn compress_pointer_table(table: &[u8], endianness: Endianness) -> Result<Vec<u8>, Error> {
+    if !table.len().is_multiple_of(4) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let pointers: Vec<u32> = table
+        .chunks(4)
+        .map(|word| {
+            let word = [word[0], word[1], word[2], word[3]];
+            match endianness {
+                Endianness::Little => u32::from_le_bytes(word),
+                Endianness::Big => u32::from_be_bytes(word),
+            }
+        })
+        .collect();
+    let count = u32::try_from(pointers.len()).map_err(|_| Error::new(ErrorKind::Unsupported))?;
+    let first = pointers.first().copied().unwrap_or(0);
+    let mut data = match endianness {
+        Endianness::Little => first.to_le_bytes(),
+        Endianness::Big => first.to_be_bytes(),
+    }
+    .to_vec();
+    write_uleb128(&mut data, count)?;
+    for pair in pointers.windows(2) {
+        let delta = pair[1].wrapping_sub(pair[0]) as i32;
+        write_uleb128(&mut data, ((delta << 1) ^ (delta >> 31)) as u32)?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_pointer_table() {
+        let mut table = Vec::new();
+        for pointer in [0x1000u32, 0x1004, 0x1000, 0x1080].iter() {
+            table.extend_from_slice(&pointer.to_be_bytes());
+        }
+        assert_eq!(
+            compress_pointer_table(&table, Endianness::Big).unwrap(),
+            [0x00, 0x00, 0x10, 0x00, 0x04, 0x08, 0x07, 0x80, 0x02]
+        );
+        assert_eq!(
+            compress_pointer_table(&[], Endianness::Little).unwrap(),
+            [0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+        let err = compress_pointer_table(&table[..6], Endianness::Little).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
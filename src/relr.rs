@@ -0,0 +1,206 @@
+//! Read and write RELR relative relocation sections
+//!
+//! This module can be used to convert relative relocation sections packed by
+//! linkers using the RELR encoding (`SHT_RELR`) from and to regular ELF32
+//! relocation sections, so they can be compressed by relox and their sizes
+//! compared.
+//!
+//! A RELR section is a sequence of little-endian words. An even word is the
+//! address of a relocation, an odd word is a bitmap whose bits 1 to 31 mark
+//! relocations at the 31 words following the last address, or following the
+//! words of the previous bitmap. RELR sections only hold relative
+//! relocations, without symbols, at word-aligned addresses.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::{Error, ErrorKind};
+
+/// Size of a regular ELF32 relocation entry.
+const ELF32_REL_SIZE: usize = 8;
+
+/// Size of a RELR word, and of the words it relocates.
+const WORD_SIZE: u32 = 4;
+
+/// Number of words marked by a bitmap.
+const BITMAP_WORDS: u32 = 31;
+
+/// Decodes a RELR section into the offsets of its relocations, in ascending
+/// order.
+///
+/// # Errors
+///
+/// `InvalidData` if the size of the section is not a multiple of the size of
+/// a word, or if it starts with a bitmap.
+pub fn relr_decode(data: &[u8]) -> Result<Vec<u32>, Error> {
+    if !data.len().is_multiple_of(WORD_SIZE as usize) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let mut offsets = Vec::new();
+    let mut next = None;
+    for word in data.chunks(WORD_SIZE as usize).map(LittleEndian::read_u32) {
+        if word & 1 == 0 {
+            offsets.push(word);
+            next = Some(word.wrapping_add(WORD_SIZE));
+            continue;
+        }
+        let base = next.ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        for bit in 0..BITMAP_WORDS {
+            if word & (2 << bit) != 0 {
+                offsets.push(base.wrapping_add(bit * WORD_SIZE));
+            }
+        }
+        next = Some(base.wrapping_add(BITMAP_WORDS * WORD_SIZE));
+    }
+    Ok(offsets)
+}
+
+/// Encodes the offsets of relative relocations as a RELR section.
+///
+/// The offsets are sorted first, as RELR sections hold relocations in
+/// ascending order.
+///
+/// # Errors
+///
+/// `InvalidData` if an offset is not word-aligned or appears twice.
+pub fn relr_encode(offsets: &[u32]) -> Result<Vec<u8>, Error> {
+    let mut sorted = offsets.to_vec();
+    sorted.sort_unstable();
+    if sorted.iter().any(|offset| offset % WORD_SIZE != 0)
+        || sorted.windows(2).any(|pair| pair[0] == pair[1])
+    {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let mut data = Vec::new();
+    let mut index = 0;
+    while index < sorted.len() {
+        let mut word = [0; 4];
+        LittleEndian::write_u32(&mut word, sorted[index]);
+        data.extend_from_slice(&word);
+        let mut base = sorted[index].wrapping_add(WORD_SIZE);
+        index += 1;
+        loop {
+            let mut bitmap = 0;
+            while let Some(offset) = sorted.get(index) {
+                let bit = offset.wrapping_sub(base) / WORD_SIZE;
+                if *offset < base || bit >= BITMAP_WORDS {
+                    break;
+                }
+                bitmap |= 2 << bit;
+                index += 1;
+            }
+            if bitmap == 0 {
+                break;
+            }
+            LittleEndian::write_u32(&mut word, bitmap | 1);
+            data.extend_from_slice(&word);
+            base = base.wrapping_add(BITMAP_WORDS * WORD_SIZE);
+        }
+    }
+    Ok(data)
+}
+
+/// Converts a RELR section into a regular little-endian ELF32 relocation
+/// section, whose relocations are of `relocation_type`, the type of relative
+/// relocations of the target, e.g. 23 (`R_ARM_RELATIVE`).
+///
+/// # Errors
+///
+/// If the section is malformed.
+pub fn relr_to_rel(data: &[u8], relocation_type: u8) -> Result<Vec<u8>, Error> {
+    let offsets = relr_decode(data)?;
+    let mut rel = vec![0; offsets.len() * ELF32_REL_SIZE];
+    for (entry, offset) in rel.chunks_mut(ELF32_REL_SIZE).zip(offsets.iter()) {
+        LittleEndian::write_u32(&mut entry[0..4], *offset);
+        LittleEndian::write_u32(&mut entry[4..8], u32::from(relocation_type));
+    }
+    Ok(rel)
+}
+
+/// Converts a regular little-endian ELF32 relocation section holding
+/// relocations of a single type without symbols, e.g. relative relocations,
+/// into a RELR section.
+///
+/// # Errors
+///
+/// `InvalidData` if the size of the section is not a multiple of the size of
+/// a relocation or if an offset is not word-aligned or appears twice, or
+/// `Unsupported` if the relocations have different types or symbols.
+pub fn rel_to_relr(rel: &[u8]) -> Result<Vec<u8>, Error> {
+    if !rel.len().is_multiple_of(ELF32_REL_SIZE) {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    let info = rel.get(4..8).map(LittleEndian::read_u32);
+    let mut offsets = Vec::with_capacity(rel.len() / ELF32_REL_SIZE);
+    for entry in rel.chunks(ELF32_REL_SIZE) {
+        let entry_info = LittleEndian::read_u32(&entry[4..8]);
+        if Some(entry_info) != info || entry_info >> 8 != 0 {
+            return Err(Error::new(ErrorKind::Unsupported));
+        }
+        offsets.push(LittleEndian::read_u32(&entry[0..4]));
+    }
+    relr_encode(&offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // R_ARM_RELATIVE relocations at 0x1000, 0x1004, 0x100C, 0x1080 and
+    // 0x1084, and at 0x2000.
+    const RELR: [u8; 16] = [
+        0x00, 0x10, 0x00, 0x00, // 0x1000
+        0x0B, 0x00, 0x00, 0x00, // 0x1004, 0x100C
+        0x07, 0x00, 0x00, 0x00, // 0x1080, 0x1084
+        0x00, 0x20, 0x00, 0x00, // 0x2000
+    ];
+
+    const OFFSETS: [u32; 6] = [0x1000, 0x1004, 0x100C, 0x1080, 0x1084, 0x2000];
+
+    #[test]
+    fn test_relr_decode() {
+        assert_eq!(relr_decode(&RELR).unwrap(), OFFSETS);
+        let err = relr_decode(&RELR[..15]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = relr_decode(&RELR[4..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_relr_encode() {
+        assert_eq!(relr_encode(&OFFSETS).unwrap(), RELR);
+        let mut reversed = OFFSETS;
+        reversed.reverse();
+        assert_eq!(relr_encode(&reversed).unwrap(), RELR);
+        assert!(relr_encode(&[]).unwrap().is_empty());
+        let err = relr_encode(&[0x1000, 0x1002]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = relr_encode(&[0x1000, 0x1000]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_relr_rel() {
+        let rel = relr_to_rel(&RELR, 23).unwrap();
+        assert_eq!(rel.len(), 48);
+        assert_eq!(
+            rel[40..48],
+            [0x00, 0x20, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(rel_to_relr(&rel).unwrap(), RELR);
+
+        let mut mixed = rel.clone();
+        mixed[44] = 0x02;
+        let err = rel_to_relr(&mixed).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let mut symbols = rel.clone();
+        symbols
+            .iter_mut()
+            .skip(5)
+            .step_by(8)
+            .for_each(|byte| *byte = 1);
+        let err = rel_to_relr(&symbols).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = rel_to_relr(&rel[..7]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
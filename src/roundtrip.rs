@@ -0,0 +1,128 @@
+//! Compression round trip check
+
+use std::io::Cursor;
+
+use crate::{elf32_relocate_symbols, Elf32Rel, Elf32Relocs, Error, ErrorKind};
+
+/// Outcome of a successful [roundtrip_check](fn.roundtrip_check.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RoundtripReport {
+    relocations: usize,
+    original_size: usize,
+    compressed_size: usize,
+}
+
+impl RoundtripReport {
+    /// Returns the number of relocations.
+    pub fn relocations(&self) -> usize {
+        self.relocations
+    }
+
+    /// Returns the size of the regular relocation section in bytes.
+    pub fn original_size(&self) -> usize {
+        self.original_size
+    }
+
+    /// Returns the size of the compressed relocation section in bytes.
+    pub fn compressed_size(&self) -> usize {
+        self.compressed_size
+    }
+}
+
+/// Compresses a regular ELF32 relocation section, decompresses the result and
+/// checks that it yields the same relocations, with the same symbol indices.
+///
+/// The order of relocations is not compared, as the compressor groups them by
+/// type.
+///
+/// # Errors
+///
+/// If the relocation section cannot be compressed, or `InvalidData` if the
+/// decompressed relocations differ from those of the relocation section.
+pub fn roundtrip_check(rel_section: &[u8]) -> Result<RoundtripReport, Error> {
+    let mut original = Vec::with_capacity(rel_section.len() / 8);
+    let mut cursor = Cursor::new(rel_section);
+    while let Ok(entry) = Elf32Rel::from_memory(&mut cursor) {
+        original.push(entry);
+    }
+    let mut compressed = Vec::new();
+    let compressed_size = Elf32Relocs::new(rel_section).compress_to_writer(&mut compressed)?;
+    let mut decompressed = Vec::with_capacity(original.len());
+    let read = elf32_relocate_symbols(&compressed, &mut |relocation_type, address, symbol| {
+        decompressed.push(Elf32Rel::with_symbol(address, relocation_type, symbol));
+        Ok(())
+    })?;
+    original.sort_unstable();
+    decompressed.sort_unstable();
+    if read != compressed_size || decompressed != original {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    Ok(RoundtripReport {
+        relocations: original.len(),
+        original_size: original.len() * 8,
+        compressed_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_check() {
+        let memory: [u8; 32] = [
+            0x00, 0x10, 0x00, 0x00, // Elf32Rel[0]
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+            0x08, 0x10, 0x00, 0x00, // Elf32Rel[1]
+            0x02, 0x00, 0x00, 0x00, // Type is 2
+            0x04, 0x10, 0x00, 0x00, // Elf32Rel[2]
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+            0x00, 0x20, 0x00, 0x00, // Elf32Rel[3]
+            0x02, 0x00, 0x00, 0x00, // Type is 2
+        ];
+        let report = roundtrip_check(&memory).unwrap();
+        assert_eq!(report.relocations(), 4);
+        assert_eq!(report.original_size(), 32);
+        assert_eq!(report.compressed_size(), 14);
+    }
+
+    #[test]
+    fn test_roundtrip_check_symbols() {
+        let memory: [u8; 24] = [
+            0x00, 0x10, 0x00, 0x00, // Elf32Rel[0]
+            0x02, 0x05, 0x00, 0x00, // Type is 2, symbol is 5
+            0x04, 0x10, 0x00, 0x00, // Elf32Rel[1]
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+            0x08, 0x10, 0x00, 0x00, // Elf32Rel[2]
+            0x02, 0x03, 0x00, 0x00, // Type is 2, symbol is 3
+        ];
+        let report = roundtrip_check(&memory).unwrap();
+        assert_eq!(report.relocations(), 3);
+        assert_eq!(report.original_size(), 24);
+    }
+
+    #[test]
+    fn test_roundtrip_check_not_sorted() {
+        let memory: [u8; 16] = [
+            0x00, 0x10, 0x00, 0x00, // Elf32Rel[0], will become base address
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+            0x00, 0x08, 0x00, 0x00, // Elf32Rel[1], below base address
+            0x17, 0x00, 0x00, 0x00, // Type is 23
+        ];
+        let report = roundtrip_check(&memory).unwrap();
+        assert_eq!(report.relocations(), 2);
+        assert_eq!(report.compressed_size(), 11);
+    }
+
+    #[test]
+    fn test_roundtrip_check_malformed() {
+        // Too many types for the grouped layout, out of address order.
+        let mut memory = Vec::new();
+        for relocation_type in 0..=u8::MAX as u32 {
+            memory.extend_from_slice(&(0x2000 - relocation_type * 4).to_le_bytes());
+            memory.extend_from_slice(&relocation_type.to_le_bytes());
+        }
+        let err = roundtrip_check(&memory).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,130 @@
+//! Compression of several relocation sections
+//!
+//! [compress_sections](fn.compress_sections.html) compresses the regular
+//! relocation sections of an image in one call, e.g. `.rel.dyn`, `.rel.plt`
+//! and the sections of every overlay. With the `parallel` feature the
+//! sections are compressed on the `rayon` thread pool.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{Elf32Relocs, Error};
+
+/// A relocation section compressed by
+/// [compress_sections](fn.compress_sections.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressedSection {
+    name: String,
+    input_size: usize,
+    data: Vec<u8>,
+}
+
+impl CompressedSection {
+    /// Returns the name of the regular relocation section.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the size of the regular relocation section.
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+
+    /// Returns the compressed section.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the compressed section, consuming `self`.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Compresses regular ELF32 relocation sections given by name and contents,
+/// using the default settings of [Elf32Relocs](struct.Elf32Relocs.html).
+/// The compressed sections are returned in the order of `sections`.
+///
+/// With the `parallel` feature the sections are compressed concurrently.
+///
+/// ```
+/// use relox::compress_sections;
+///
+/// # fn main() -> Result<(), relox::Error> {
+/// let dyn_relocs = [0x00, 0x10, 0x00, 0x00, 0x17, 0x00, 0x00, 0x00];
+/// let plt_relocs = [0x00, 0x20, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00];
+/// let compressed = compress_sections(&[(".rel.dyn", &dyn_relocs), (".rel.plt", &plt_relocs)])?;
+/// assert_eq!(compressed[1].name(), ".rel.plt");
+/// assert_eq!(compressed[1].data(), [0x00, 0x20, 0x00, 0x00, 0x01, 0x16, 0x01, 0x00]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If any of the relocation sections cannot be compressed. With the
+/// `parallel` feature, the error of any failing section may be returned.
+pub fn compress_sections(sections: &[(&str, &[u8])]) -> Result<Vec<CompressedSection>, Error> {
+    #[cfg(feature = "parallel")]
+    let sections = sections.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let sections = sections.iter();
+    sections
+        .map(|(name, data)| compress_section(name, data))
+        .collect()
+}
+
+fn compress_section(name: &str, data: &[u8]) -> Result<CompressedSection, Error> {
+    let mut compressed = Vec::with_capacity(data.len());
+    Elf32Relocs::new(data).compress_to_writer(&mut compressed)?;
+    Ok(CompressedSection {
+        name: name.to_string(),
+        input_size: data.len(),
+        data: compressed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_sections() {
+        let sections: Vec<(String, Vec<u8>)> = (0..40u32)
+            .map(|overlay| {
+                let mut data = Vec::new();
+                for index in 0..=overlay {
+                    data.extend_from_slice(&(0x1000 * overlay + 4 * index).to_le_bytes());
+                    data.extend_from_slice(&0x17u32.to_le_bytes());
+                }
+                (format!(".rel.overlay{}", overlay), data)
+            })
+            .collect();
+        let input: Vec<(&str, &[u8])> = sections
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.as_slice()))
+            .collect();
+        let compressed = compress_sections(&input).unwrap();
+        assert_eq!(compressed.len(), sections.len());
+        for (section, (name, data)) in compressed.iter().zip(sections.iter()) {
+            assert_eq!(section.name(), name);
+            assert_eq!(section.input_size(), data.len());
+            let mut expected = Vec::new();
+            Elf32Relocs::new(data)
+                .compress_to_writer(&mut expected)
+                .unwrap();
+            assert_eq!(section.data(), expected.as_slice());
+        }
+        assert!(compress_sections(&[]).unwrap().is_empty());
+
+        // Unsorted relocations of every type fit neither encoding.
+        let mut all_types = Vec::new();
+        for relocation_type in (0..=u8::MAX as u32).rev() {
+            all_types.extend_from_slice(&(4 * relocation_type).to_le_bytes());
+            all_types.extend_from_slice(&relocation_type.to_le_bytes());
+        }
+        let err =
+            compress_sections(&[(".rel.dyn", &[0x00; 8]), (".rel.plt", &all_types)]).unwrap_err();
+        assert!(err.kind().is_unsupported());
+    }
+}
@@ -0,0 +1,103 @@
+//! Serialization of compressor data types with `serde`
+//!
+//! Like the implementations of `relox-core`, these are written out instead of
+//! derived, using the representation `#[derive(Serialize, Deserialize)]` would
+//! produce, so compression statistics can be dumped to JSON and compared
+//! between builds.
+
+use std::fmt;
+
+use relox_core::serialize::Identifier;
+use serde::de::{self, Deserializer, EnumAccess, VariantAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::Encoding;
+
+const ENCODING: &[&str] = &["Auto", "Grouped", "Interleaved", "Directory"];
+
+impl Serialize for Encoding {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let index = *self as u32;
+        serializer.serialize_unit_variant("Encoding", index, ENCODING[index as usize])
+    }
+}
+
+impl<'de> Deserialize<'de> for Encoding {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EncodingVisitor;
+
+        impl EncodingVisitor {
+            fn variant<E: de::Error>(index: usize) -> Result<Encoding, E> {
+                match index {
+                    0 => Ok(Encoding::Auto),
+                    1 => Ok(Encoding::Grouped),
+                    2 => Ok(Encoding::Interleaved),
+                    3 => Ok(Encoding::Directory),
+                    _ => Err(E::custom("unknown encoding")),
+                }
+            }
+        }
+
+        impl<'de> Visitor<'de> for EncodingVisitor {
+            type Value = Encoding;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an encoding")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Encoding, E> {
+                Self::variant(Identifier(ENCODING).visit_str(value)?)
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<Encoding, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                let (index, variant) = data.variant_seed(Identifier(ENCODING))?;
+                variant.unit_variant()?;
+                Self::variant(index)
+            }
+        }
+
+        deserializer.deserialize_enum("Encoding", ENCODING, EncodingVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::value::{Error as ValueError, MapDeserializer, StrDeserializer};
+    use serde::de::IntoDeserializer;
+
+    use super::*;
+    use crate::GroupStats;
+
+    #[test]
+    fn test_deserialize_encoding() {
+        let interleaved: StrDeserializer<ValueError> = "Interleaved".into_deserializer();
+        assert_eq!(
+            Encoding::deserialize(interleaved).unwrap(),
+            Encoding::Interleaved
+        );
+        let sorted: StrDeserializer<ValueError> = "Sorted".into_deserializer();
+        assert!(Encoding::deserialize(sorted).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_group_stats() {
+        let fields = [
+            ("relocation_type", 0x17u32),
+            ("count", 3),
+            ("max_delta", 8),
+            ("size", 5),
+        ];
+        let map = MapDeserializer::<_, ValueError>::new(fields.iter().copied());
+        let stats = GroupStats::deserialize(map).unwrap();
+        assert_eq!(stats, GroupStats::new(0x17, 3, 8, 5));
+    }
+}
@@ -0,0 +1,282 @@
+//! Host-side simulation of applying compressed relocation sections
+//!
+//! Applying a compressed section to an image on the host at a chosen bias
+//! and comparing the result with the same image linked at the biased address
+//! proves end-to-end that compression did not change the semantics of the
+//! relocations.
+
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use crate::elf::{Elf32File, Elf32Segment, PT_LOAD};
+use crate::{elf32_machine, elf32_relocate_symbols, Error, ErrorKind};
+
+/// Outcome of [simulate_apply](fn.simulate_apply.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    relocations: usize,
+    compared: usize,
+    mismatches: Vec<Range<u32>>,
+}
+
+impl SimulationReport {
+    /// Returns the number of relocations applied.
+    pub fn relocations(&self) -> usize {
+        self.relocations
+    }
+
+    /// Returns the number of bytes compared.
+    pub fn compared(&self) -> usize {
+        self.compared
+    }
+
+    /// Returns the link-time address ranges of the relocated image which
+    /// differ from the reference.
+    pub fn mismatches(&self) -> &[Range<u32>] {
+        &self.mismatches
+    }
+
+    /// Returns `true` if the relocated image matches the reference.
+    pub fn is_identical(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Returns the loadable segments of a file.
+fn loadable<'f>(file: &'f Elf32File) -> impl Iterator<Item = &'f Elf32Segment> {
+    file.segments()
+        .iter()
+        .filter(|segment| segment.segment_type() == PT_LOAD && segment.memory_size() > 0)
+}
+
+/// Returns the memory image of a loadable segment.
+fn segment_memory(file: &Elf32File, segment: &Elf32Segment) -> Result<Vec<u8>, Error> {
+    let mut memory = file.segment_data(segment)?.to_vec();
+    if memory.len() > segment.memory_size() as usize {
+        return Err(Error::new(ErrorKind::InvalidData));
+    }
+    memory.resize(segment.memory_size() as usize, 0);
+    Ok(memory)
+}
+
+/// Applies the compressed relocation section `compressed` of `image`, an
+/// ELF32 file before relocation, to its loadable segments at `bias` and
+/// compares the result with `reference`, the same image linked at an address
+/// `bias` higher. Words are patched in the byte order of `image`.
+///
+/// Every relocation must be a relative relocation of the machine of `image`,
+/// against symbol 0.
+/// Every loadable segment of `image` must have a counterpart of the same size
+/// at the biased address in `reference`.
+///
+/// # Errors
+///
+/// If a file or the compressed section is malformed, if a relocation targets
+/// a word outside of the loadable segments, or `Unsupported` if the machine
+/// of `image` has no known relative relocation type, if the section records
+/// another machine or if it holds other relocations.
+pub fn simulate_apply(
+    image: &[u8],
+    compressed: &[u8],
+    bias: u32,
+    reference: &[u8],
+) -> Result<SimulationReport, Error> {
+    let image = Elf32File::parse(image)?;
+    let reference = Elf32File::parse(reference)?;
+    let machine = image.ident().machine();
+    let endianness = image.ident().endianness();
+    let relative_type = image
+        .ident()
+        .relative_type()
+        .ok_or_else(|| Error::new(ErrorKind::Unsupported))?;
+    if elf32_machine(compressed)?.is_some_and(|recorded| recorded != machine) {
+        return Err(Error::new(ErrorKind::Unsupported));
+    }
+
+    let mut memories = Vec::new();
+    for segment in loadable(&image) {
+        memories.push((segment.address(), segment_memory(&image, segment)?));
+    }
+    let mut relocations = 0;
+    elf32_relocate_symbols(compressed, &mut |relocation_type, address, symbol| {
+        if relocation_type != relative_type || symbol != 0 {
+            return Err(Error::new(ErrorKind::UnsupportedRelocationType));
+        }
+        let word = memories
+            .iter_mut()
+            .find_map(|(start, memory)| {
+                let offset = usize::try_from(address.checked_sub(*start)?).ok()?;
+                memory.get_mut(offset..offset.checked_add(4)?)
+            })
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        let value = endianness.convert(u32::from_ne_bytes([word[0], word[1], word[2], word[3]]));
+        word.copy_from_slice(&endianness.convert(value.wrapping_add(bias)).to_ne_bytes());
+        relocations += 1;
+        Ok(())
+    })?;
+
+    let mut compared = 0;
+    let mut mismatches: Vec<Range<u32>> = Vec::new();
+    for (start, memory) in memories.iter() {
+        let counterpart = loadable(&reference)
+            .find(|segment| {
+                segment.address() == start.wrapping_add(bias)
+                    && segment.memory_size() as usize == memory.len()
+            })
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        let expected = segment_memory(&reference, counterpart)?;
+        for (offset, (byte, expected)) in memory.iter().zip(expected.iter()).enumerate() {
+            if byte == expected {
+                continue;
+            }
+            let address = start.wrapping_add(offset as u32);
+            match mismatches.last_mut() {
+                Some(range) if range.end == address => range.end = address.wrapping_add(1),
+                _ => mismatches.push(address..address.wrapping_add(1)),
+            }
+        }
+        compared += memory.len();
+    }
+    Ok(SimulationReport {
+        relocations,
+        compared,
+        mismatches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Elf32CRelWriter, Endianness};
+
+    /// Builds a little-endian ELF32 file for ARM with a loadable segment at
+    /// `address` holding `contents` followed by 4 zero bytes.
+    fn build_image(address: u32, contents: &[u8]) -> Vec<u8> {
+        build_image_endian(address, contents, Endianness::Little)
+    }
+
+    /// Builds an ELF32 file for ARM of the given byte order with a loadable
+    /// segment at `address` holding `contents` followed by 4 zero bytes.
+    fn build_image_endian(address: u32, contents: &[u8], endianness: Endianness) -> Vec<u8> {
+        let half = |value: u16| match endianness {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        };
+        let word = |value: u32| endianness.convert(value).to_ne_bytes();
+        let mut data = vec![0; 52 + 32];
+        data[0..4].copy_from_slice(&[0x7F, b'E', b'L', b'F']);
+        data[4] = 1;
+        data[5] = match endianness {
+            Endianness::Little => 1,
+            Endianness::Big => 2,
+        };
+        data[6] = 1;
+        data[16..18].copy_from_slice(&half(3));
+        data[18..20].copy_from_slice(&half(40));
+        data[20..24].copy_from_slice(&word(1));
+        data[28..32].copy_from_slice(&word(52));
+        data[40..42].copy_from_slice(&half(52));
+        data[42..44].copy_from_slice(&half(32));
+        data[44..46].copy_from_slice(&half(1));
+        let size = contents.len() as u32;
+        for (index, field) in [PT_LOAD, 84, address, address, size, size + 4, 6, 4]
+            .iter()
+            .enumerate()
+        {
+            data[52 + index * 4..56 + index * 4].copy_from_slice(&word(*field));
+        }
+        data.extend_from_slice(contents);
+        data
+    }
+
+    /// Returns a segment holding pointers to its own words at `address`.
+    fn pointers(address: u32) -> Vec<u8> {
+        pointers_endian(address, Endianness::Little)
+    }
+
+    /// Returns a segment holding pointers to its own words at `address` in
+    /// the given byte order.
+    fn pointers_endian(address: u32, endianness: Endianness) -> Vec<u8> {
+        let mut contents = Vec::new();
+        for word in [address + 8, 0x1234, address].iter() {
+            contents.extend_from_slice(&endianness.convert(*word).to_ne_bytes());
+        }
+        contents
+    }
+
+    fn compress(machine: Option<u16>, offsets: &[u32]) -> Vec<u8> {
+        let mut writer = Elf32CRelWriter::new();
+        writer.set_machine(machine);
+        writer.begin(0x8000);
+        writer.group(23).unwrap();
+        let mut previous = 0x8000;
+        for offset in offsets.iter() {
+            writer.offset(offset - previous).unwrap();
+            previous = *offset;
+        }
+        let mut compressed = vec![0; writer.size()];
+        writer.finish(&mut compressed).unwrap();
+        compressed
+    }
+
+    #[test]
+    fn test_simulate_apply() {
+        let image = build_image(0x8000, &pointers(0x8000));
+        let reference = build_image(0x9000, &pointers(0x9000));
+        let compressed = compress(Some(40), &[0x8000, 0x8008]);
+        let report = simulate_apply(&image, &compressed, 0x1000, &reference).unwrap();
+        assert_eq!(report.relocations(), 2);
+        assert_eq!(report.compared(), 16);
+        assert!(report.is_identical());
+
+        let compressed = compress(None, &[0x8000, 0x8004]);
+        let report = simulate_apply(&image, &compressed, 0x1000, &reference).unwrap();
+        assert_eq!(report.mismatches(), [0x8005..0x8006, 0x8009..0x800A]);
+    }
+
+    #[test]
+    fn test_simulate_apply_big_endian() {
+        let image = build_image_endian(
+            0x8000,
+            &pointers_endian(0x8000, Endianness::Big),
+            Endianness::Big,
+        );
+        let reference = build_image_endian(
+            0x9000,
+            &pointers_endian(0x9000, Endianness::Big),
+            Endianness::Big,
+        );
+        let compressed = compress(Some(40), &[0x8000, 0x8008]);
+        let report = simulate_apply(&image, &compressed, 0x1000, &reference).unwrap();
+        assert_eq!(report.relocations(), 2);
+        assert!(report.is_identical());
+    }
+
+    #[test]
+    fn test_simulate_apply_top_of_address_space() {
+        let image = build_image(0xFFFF_FFF0, &[0; 12]);
+        let reference = build_image(0x0100_0FF1, &[0; 12]);
+        let compressed = compress(None, &[0xFFFF_FFFC]);
+        let report = simulate_apply(&image, &compressed, 0x0100_1001, &reference).unwrap();
+        let mismatches = report.mismatches();
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0], 0xFFFF_FFFC..0xFFFF_FFFE);
+        // The range of the last byte ends at the wrapped address.
+        assert_eq!((mismatches[1].start, mismatches[1].end), (0xFFFF_FFFF, 0));
+    }
+
+    #[test]
+    fn test_simulate_apply_errors() {
+        let image = build_image(0x8000, &pointers(0x8000));
+        let reference = build_image(0x9000, &pointers(0x9000));
+        let err =
+            simulate_apply(&image, &compress(Some(3), &[0x8000]), 0x1000, &reference).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err =
+            simulate_apply(&image, &compress(None, &[0x800E]), 0x1000, &reference).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err =
+            simulate_apply(&image, &compress(None, &[0x8000]), 0x2000, &reference).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,49 @@
+//! Packing of sorted `u32` tables
+//!
+//! [pack_sorted_u32s](fn.pack_sorted_u32s.html) packs monotonically
+//! increasing values into the layout described in
+//! [unpack_sorted_u32s](fn.unpack_sorted_u32s.html), independent of ELF
+//! relocations.
+
+use std::convert::TryFrom;
+
+use crate::compress::write_uleb128;
+use crate::{Error, ErrorKind};
+
+/// Packs monotonically increasing values, each encoded as the ULEB128
+/// difference to the previous one.
+///
+/// # Errors
+///
+/// `InvalidData` if `values` is not sorted, or `Unsupported` if it holds
+/// more than `u32::MAX` values.
+pub fn pack_sorted_u32s(values: &[u32]) -> Result<Vec<u8>, Error> {
+    let count = u32::try_from(values.len()).map_err(|_| Error::new(ErrorKind::Unsupported))?;
+    let mut data = Vec::with_capacity(values.len() + 5);
+    write_uleb128(&mut data, count)?;
+    let mut previous = 0;
+    for value in values.iter() {
+        let delta = value
+            .checked_sub(previous)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        write_uleb128(&mut data, delta)?;
+        previous = *value;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_sorted_u32s() {
+        assert_eq!(
+            pack_sorted_u32s(&[0x1000, 0x1004, 0x1004, 0x2000]).unwrap(),
+            [0x04, 0x80, 0x20, 0x04, 0x00, 0xFC, 0x1F]
+        );
+        assert_eq!(pack_sorted_u32s(&[]).unwrap(), [0x00]);
+        let err = pack_sorted_u32s(&[0x1004, 0x1000]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,199 @@
+//! Statistics of compressed ELF32 relocation sections
+//!
+//! [Elf32Relocs::stats](struct.Elf32Relocs.html#method.stats) reports the
+//! sizes of a compressed section and of its groups, e.g. to print them during
+//! post-link processing and track flash savings over time.
+
+use std::fmt;
+
+use crate::Encoding;
+
+/// Statistics of the relocations of one type in a compressed section.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GroupStats {
+    relocation_type: u8,
+    count: u32,
+    max_delta: u32,
+    size: usize,
+}
+
+impl GroupStats {
+    pub(crate) fn new(relocation_type: u8, count: u32, max_delta: u32, size: usize) -> Self {
+        Self {
+            relocation_type,
+            count,
+            max_delta,
+            size,
+        }
+    }
+
+    /// Returns the type of the relocations.
+    pub fn relocation_type(&self) -> u8 {
+        self.relocation_type
+    }
+
+    /// Returns the number of relocations of the type.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the largest distance between the offsets of two consecutive
+    /// relocations of the type, the first one measured from the base address
+    /// of its group.
+    pub fn max_delta(&self) -> u32 {
+        self.max_delta
+    }
+
+    /// Returns the number of compressed bytes encoding the relocations of the
+    /// type: the headers and offsets of their groups, runs or directory
+    /// entries, without padding.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+#[cfg(feature = "serde")]
+relox_core::serde_struct!(GroupStats {
+    relocation_type: u8,
+    count: u32,
+    max_delta: u32,
+    size: usize,
+});
+
+/// Statistics of a compressed ELF32 relocation section.
+///
+/// The `Display` implementation formats them as a table:
+///
+/// ```text
+/// encoding: grouped
+/// input:    64 bytes
+/// output:   15 bytes
+/// saved:    49 bytes (76.5%)
+/// type  count   max delta   bytes
+/// 0x02      2  0x00000008       5
+/// 0x17      3  0x00000004       5
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressionStats {
+    input_size: usize,
+    output_size: usize,
+    encoding: Encoding,
+    groups: Vec<GroupStats>,
+}
+
+impl CompressionStats {
+    pub(crate) fn new(
+        input_size: usize,
+        output_size: usize,
+        encoding: Encoding,
+        groups: Vec<GroupStats>,
+    ) -> Self {
+        Self {
+            input_size,
+            output_size,
+            encoding,
+            groups,
+        }
+    }
+
+    /// Returns the size of the regular relocation section.
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+
+    /// Returns the size of the compressed section, including metadata.
+    pub fn output_size(&self) -> usize {
+        self.output_size
+    }
+
+    /// Returns the number of bytes saved by compression, 0 if the compressed
+    /// section is larger.
+    pub fn saved(&self) -> usize {
+        self.input_size.saturating_sub(self.output_size)
+    }
+
+    /// Returns the encoding used, the selected one if `Encoding::Auto` is
+    /// set.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the statistics of every relocation type, ordered by type.
+    pub fn groups(&self) -> &[GroupStats] {
+        &self.groups
+    }
+}
+
+#[cfg(feature = "serde")]
+relox_core::serde_struct!(CompressionStats {
+    input_size: usize,
+    output_size: usize,
+    encoding: Encoding,
+    groups: Vec<GroupStats>,
+});
+
+impl fmt::Display for CompressionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoding = match self.encoding {
+            Encoding::Auto | Encoding::Grouped => "grouped",
+            Encoding::Interleaved => "interleaved",
+            Encoding::Directory => "directory",
+        };
+        writeln!(f, "encoding: {}", encoding)?;
+        writeln!(f, "input:    {} bytes", self.input_size)?;
+        writeln!(f, "output:   {} bytes", self.output_size)?;
+        // Tenths of a percent, using integers only.
+        let permille = match self.input_size {
+            0 => 0,
+            input => self.saved() * 1000 / input,
+        };
+        writeln!(
+            f,
+            "saved:    {} bytes ({}.{}%)",
+            self.saved(),
+            permille / 10,
+            permille % 10
+        )?;
+        write!(f, "type  count   max delta   bytes")?;
+        for group in self.groups.iter() {
+            write!(
+                f,
+                "\n{:#04x} {:>6}  {:#010x} {:>7}",
+                group.relocation_type, group.count, group.max_delta, group.size
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_stats_display() {
+        let stats = CompressionStats::new(
+            64,
+            15,
+            Encoding::Grouped,
+            vec![
+                GroupStats::new(0x02, 2, 8, 5),
+                GroupStats::new(0x17, 3, 4, 5),
+            ],
+        );
+        assert_eq!(stats.saved(), 49);
+        assert_eq!(
+            stats.to_string(),
+            "encoding: grouped\n\
+             input:    64 bytes\n\
+             output:   15 bytes\n\
+             saved:    49 bytes (76.5%)\n\
+             type  count   max delta   bytes\n\
+             0x02      2  0x00000008       5\n\
+             0x17      3  0x00000004       5"
+        );
+        let empty = CompressionStats::new(0, 5, Encoding::Interleaved, Vec::new());
+        assert_eq!(empty.saved(), 0);
+        assert!(empty.to_string().contains("saved:    0 bytes (0.0%)"));
+    }
+}
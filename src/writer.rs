@@ -0,0 +1,303 @@
+//! Low-level writer of compressed ELF32 relocation sections
+//!
+//! [Elf32CRelWriter](struct.Elf32CRelWriter.html) emits a compressed section
+//! group at a time, for tools which produce relocations from their own data
+//! structures instead of a regular relocation section.
+
+use relox_core::layout::SCALED;
+use relox_core::uleb128;
+
+use crate::compress::{write_header, write_uleb128, OffsetWriter};
+use crate::{Codec, Endianness, Error, ErrorKind};
+
+/// Group being written.
+struct OpenGroup {
+    relocation_type: u8,
+    count: u32,
+    position: u32,
+    offsets: Vec<u8>,
+    encoder: OffsetWriter,
+}
+
+impl OpenGroup {
+    /// Returns the number of bytes the group takes once written.
+    fn size(&self) -> usize {
+        let mut count = [0; 5];
+        let count = uleb128::write_u32(self.count, &mut count).unwrap_or(count.len());
+        1 + count + self.offsets.len() + self.encoder.pending_size()
+    }
+}
+
+/// Writer of compressed ELF32 relocation sections, laid out as `Elf32CRel`.
+///
+/// Call [begin](#method.begin) once, then [group](#method.group) for every
+/// relocation type followed by [offset](#method.offset) for every relocation
+/// of the group, and [finish](#method.finish) to write the section.
+pub struct Elf32CRelWriter {
+    endianness: Endianness,
+    codec: Codec,
+    machine: Option<u16>,
+    base_address: Option<u32>,
+    count: u8,
+    groups: Vec<u8>,
+    group: Option<OpenGroup>,
+}
+
+impl Default for Elf32CRelWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Elf32CRelWriter {
+    /// Creates a new `Elf32CRelWriter` instance.
+    pub fn new() -> Self {
+        Self {
+            endianness: Endianness::Little,
+            codec: Codec::Uleb128,
+            machine: None,
+            base_address: None,
+            count: 0,
+            groups: Vec::new(),
+            group: None,
+        }
+    }
+
+    /// Sets the byte order of the base address, little-endian by default.
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+
+    /// Sets the codec of the offsets, ULEB128 by default.
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+
+    /// Records the target machine (`e_machine`) in the header.
+    pub fn set_machine(&mut self, machine: Option<u16>) {
+        self.machine = machine;
+    }
+
+    /// Starts a section whose relocations are relative to `base_address`,
+    /// discarding anything written before.
+    pub fn begin(&mut self, base_address: u32) {
+        self.base_address = Some(base_address);
+        self.count = 0;
+        self.groups.clear();
+        self.group = None;
+    }
+
+    /// Starts a group of relocations of the given type, closing the previous
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// `InvalidData` if [begin](#method.begin) was not called, or
+    /// `TooManyGroups` if the section would hold too many groups.
+    pub fn group(&mut self, relocation_type: u8) -> Result<(), Error> {
+        if self.base_address.is_none() {
+            return Err(Error::new(ErrorKind::InvalidData));
+        }
+        if self.count >= SCALED - 1 {
+            return Err(Error::new(ErrorKind::TooManyGroups));
+        }
+        self.close()?;
+        self.count += 1;
+        self.group = Some(OpenGroup {
+            relocation_type,
+            count: 0,
+            position: 0,
+            offsets: Vec::new(),
+            encoder: OffsetWriter::new(self.codec, 0),
+        });
+        Ok(())
+    }
+
+    /// Adds a relocation to the current group, `delta` bytes after the
+    /// previous one, or after the base address for the first one.
+    ///
+    /// # Errors
+    ///
+    /// `InvalidData` if no group was started or if the relocation is beyond
+    /// the address space.
+    pub fn offset(&mut self, delta: u32) -> Result<(), Error> {
+        let base_address = self.base_address.unwrap_or(0);
+        let group = self
+            .group
+            .as_mut()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        let position = group
+            .position
+            .checked_add(delta)
+            .filter(|position| base_address.checked_add(*position).is_some())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        group.encoder.write(&mut group.offsets, position)?;
+        group.position = position;
+        group.count += 1;
+        Ok(())
+    }
+
+    /// Returns the size of the section if it was finished now.
+    pub fn size(&self) -> usize {
+        let prefixes = match self.machine {
+            Some(_) => 3,
+            None => 0,
+        } + match self.codec {
+            Codec::Uleb128 => 0,
+            Codec::GroupVarint | Codec::Bitmap => 1,
+        };
+        5 + prefixes + self.groups.len() + self.group.as_ref().map_or(0, OpenGroup::size)
+    }
+
+    /// Finishes the section and writes it to `output`.
+    ///
+    /// Returns the number of bytes written, [size](#method.size).
+    ///
+    /// # Errors
+    ///
+    /// `InvalidData` if [begin](#method.begin) was not called, or
+    /// `BufferSmall` if `output` is smaller than the section.
+    pub fn finish(&mut self, output: &mut [u8]) -> Result<usize, Error> {
+        let base_address = self
+            .base_address
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData))?;
+        self.close()?;
+        if output.len() < self.size() {
+            return Err(Error::new(ErrorKind::BufferSmall));
+        }
+        let mut header = Vec::with_capacity(9);
+        write_header(
+            &mut header,
+            self.endianness,
+            base_address,
+            self.machine,
+            self.codec,
+            false,
+            self.count,
+        )?;
+        let size = header.len() + self.groups.len();
+        output[..header.len()].copy_from_slice(&header);
+        output[header.len()..size].copy_from_slice(&self.groups);
+        Ok(size)
+    }
+
+    /// Writes the current group, if any.
+    fn close(&mut self) -> Result<(), Error> {
+        if let Some(group) = self.group.take() {
+            self.groups.push(group.relocation_type);
+            write_uleb128(&mut self.groups, group.count)?;
+            self.groups.extend_from_slice(&group.offsets);
+            group.encoder.finish(&mut self.groups)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREL_TWO_GROUPS: [u8; 15] = [
+        0x00, 0x10, 0x00, 0x00, // base_address
+        0x02, // count
+        0x02, // group[0].relocation_type
+        0x02, // group[0].count
+        0x04, 0x80, 0x01, // group[0].offsets
+        0x16, // group[1].relocation_type
+        0x03, // group[1].count
+        0x00, 0x04, 0x04, // group[1].offsets
+    ];
+
+    #[test]
+    fn test_writer() {
+        let mut writer = Elf32CRelWriter::new();
+        writer.begin(0x1000);
+        writer.group(0x02).unwrap();
+        writer.offset(0x04).unwrap();
+        writer.offset(0x80).unwrap();
+        writer.group(0x16).unwrap();
+        for delta in [0x00, 0x04, 0x04] {
+            writer.offset(delta).unwrap();
+        }
+        assert_eq!(writer.size(), CREL_TWO_GROUPS.len());
+        let mut output = [0; 32];
+        let written = writer.finish(&mut output).unwrap();
+        assert_eq!(output[..written], CREL_TWO_GROUPS);
+    }
+
+    #[test]
+    fn test_writer_group_varint() {
+        let mut writer = Elf32CRelWriter::new();
+        writer.set_endianness(Endianness::Big);
+        writer.set_codec(Codec::GroupVarint);
+        writer.set_machine(Some(40));
+        writer.begin(0x1000);
+        writer.group(0x02).unwrap();
+        for delta in [0x04, 0x80, 0x10000, 0x04, 0x04] {
+            writer.offset(delta).unwrap();
+        }
+        assert_eq!(writer.size(), 20);
+        let mut output = [0; 20];
+        let written = writer.finish(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x00, 0x10, 0x00, // base_address
+                0xFC, 0x00, 0x28, // machine
+                0xFB, // group-varint codec
+                0x01, // count
+                0x02, // group[0].relocation_type
+                0x05, // group[0].count
+                0x20, 0x04, 0x80, 0x00, 0x00, 0x01, 0x04, // group[0].offsets[0..4]
+                0x00, 0x04, // group[0].offsets[4]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_writer_bitmap() {
+        let mut writer = Elf32CRelWriter::new();
+        writer.set_codec(Codec::Bitmap);
+        writer.begin(0x1000);
+        writer.group(0x17).unwrap();
+        for delta in [0x04, 0x04, 0x04] {
+            writer.offset(delta).unwrap();
+        }
+        assert_eq!(writer.size(), 10);
+        let mut output = [0; 10];
+        let written = writer.finish(&mut output).unwrap();
+        assert_eq!(
+            output[..written],
+            [
+                0x00, 0x10, 0x00, 0x00, // base_address
+                0xF9, // bitmap codec
+                0x01, // count
+                0x17, 0x03, // group[0].relocation_type, count
+                0x04, 0x03, // group[0].offsets, offset and bitmap
+            ]
+        );
+    }
+
+    #[test]
+    fn test_writer_errors() {
+        let mut writer = Elf32CRelWriter::default();
+        let err = writer.group(0x02).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = writer.finish(&mut [0; 8]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        writer.begin(0xFFFF_FFF0);
+        let err = writer.offset(0x04).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        writer.group(0x02).unwrap();
+        writer.offset(0x0C).unwrap();
+        let err = writer.offset(0x04).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = writer.finish(&mut [0; 7]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BufferSmall);
+        for _ in 1..244 {
+            writer.group(0x02).unwrap();
+        }
+        let err = writer.group(0x02).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooManyGroups);
+    }
+}
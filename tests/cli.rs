@@ -0,0 +1,943 @@
+#[cfg(feature = "cli")]
+mod common;
+
+#[cfg(feature = "cli")]
+fn relox(args: &[&str]) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_relox"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[cfg(feature = "cli")]
+fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("relox-{}-{}", std::process::id(), name));
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_no_command() {
+    let output = relox(&[]);
+    assert!(!output.status.success());
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_bench() {
+    const REL1: [u8; 24] = [
+        0x00, 0x08, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x02, 0x00, 0x00,
+        0x00, 0x18, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00,
+    ];
+
+    let input = temp_file("bench.rel", &REL1);
+    let output = relox(&["bench", input.to_str().unwrap(), "--iterations", "2"]);
+    std::fs::remove_file(&input).unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("3 relocations, 24 bytes, 2 iterations"));
+    assert!(stdout.contains("uleb128"));
+    assert!(stdout.contains("group-varint"));
+    assert!(stdout.contains("bitmap"));
+    assert!(stdout.contains("repeat"));
+    assert!(stdout.contains("scaled"));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_roundtrip() {
+    const REL1: [u8; 24] = [
+        0x00, 0x08, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x02, 0x00, 0x00,
+        0x00, 0x18, 0x08, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00,
+    ];
+
+    let input = temp_file("convert.rel", &REL1);
+    let compressed = input.with_extension("relox");
+    let decompressed = input.with_extension("out.rel");
+    let output = relox(&[
+        "convert",
+        input.to_str().unwrap(),
+        "-o",
+        compressed.to_str().unwrap(),
+        "--from",
+        "rel",
+        "--to",
+        "relox",
+    ]);
+    assert!(output.status.success());
+    let output = relox(&[
+        "convert",
+        compressed.to_str().unwrap(),
+        "-o",
+        decompressed.to_str().unwrap(),
+        "--from",
+        "relox",
+        "--to",
+        "rel",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&compressed).unwrap().len(), 5 + 2 + 3);
+    assert_eq!(std::fs::read(&decompressed).unwrap(), &REL1[..]);
+    for path in &[input, compressed, decompressed] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_symbols() {
+    // An R_ARM_ABS32 against symbol 5 and an R_ARM_RELATIVE.
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x02, 0x05, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let input = temp_file("convert_symbols.rel", &REL1);
+    for format in &["relox", "crel", "aps2"] {
+        let converted = input.with_extension(format);
+        let rel = input.with_extension("out.rel");
+        let output = relox(&[
+            "convert",
+            input.to_str().unwrap(),
+            "-o",
+            converted.to_str().unwrap(),
+            "--from",
+            "rel",
+            "--to",
+            format,
+        ]);
+        assert!(output.status.success());
+        let output = relox(&[
+            "convert",
+            converted.to_str().unwrap(),
+            "-o",
+            rel.to_str().unwrap(),
+            "--from",
+            format,
+            "--to",
+            "rel",
+        ]);
+        assert!(output.status.success());
+        assert_eq!(std::fs::read(&rel).unwrap(), &REL1[..], "{}", format);
+        for path in &[converted, rel] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_relr() {
+    const REL1: [u8; 24] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x04, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00,
+    ];
+
+    let input = temp_file("convert_relr.rel", &REL1);
+    let relr = input.with_extension("relr");
+    let rel = input.with_extension("out.rel");
+    let output = relox(&[
+        "convert",
+        input.to_str().unwrap(),
+        "-o",
+        relr.to_str().unwrap(),
+        "--from",
+        "rel",
+        "--to",
+        "relr",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read(&relr).unwrap(),
+        [0x00, 0x08, 0x00, 0x40, 0x13, 0x00, 0x00, 0x00]
+    );
+    let args = [
+        "convert",
+        relr.to_str().unwrap(),
+        "-o",
+        rel.to_str().unwrap(),
+        "--from",
+        "relr",
+        "--to",
+        "rel",
+    ];
+    let output = relox(&args);
+    assert!(!output.status.success());
+    let output = relox(&[&args[..], &["--machine", "40"]].concat());
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&rel).unwrap(), &REL1[..]);
+
+    // RELR sections only hold relative relocations.
+    let output = relox(&[
+        "convert",
+        rel.to_str().unwrap(),
+        "-o",
+        relr.to_str().unwrap(),
+        "--from",
+        "rel",
+        "--to",
+        "relr",
+        "--machine",
+        "3",
+    ]);
+    assert!(!output.status.success());
+    for path in &[input, relr, rel] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_recompress() {
+    const REL1: [u8; 24] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x04, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00,
+    ];
+
+    let input = temp_file("convert_recompress.rel", &REL1);
+    let compressed = input.with_extension("relox");
+    let recompressed = input.with_extension("out.relox");
+    let rel = input.with_extension("out.rel");
+    let output = relox(&[
+        "convert",
+        input.to_str().unwrap(),
+        "-o",
+        compressed.to_str().unwrap(),
+        "--from",
+        "rel",
+        "--to",
+        "relox",
+    ]);
+    assert!(output.status.success());
+    let output = relox(&[
+        "convert",
+        compressed.to_str().unwrap(),
+        "-o",
+        recompressed.to_str().unwrap(),
+        "--from",
+        "relox",
+        "--to",
+        "relox",
+        "--encoding",
+        "grouped",
+        "--codec",
+        "bitmap",
+        "--no-repeat-offsets",
+    ]);
+    assert!(output.status.success());
+    // The bitmap codec is recorded in the header.
+    assert_eq!(std::fs::read(&recompressed).unwrap()[4], 0xF9);
+    let output = relox(&[
+        "convert",
+        recompressed.to_str().unwrap(),
+        "-o",
+        rel.to_str().unwrap(),
+        "--from",
+        "relox",
+        "--to",
+        "rel",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&rel).unwrap(), &REL1[..]);
+
+    let output = relox(&[
+        "convert",
+        compressed.to_str().unwrap(),
+        "-o",
+        rel.to_str().unwrap(),
+        "--from",
+        "relox",
+        "--to",
+        "rel",
+        "--scaled-offsets",
+    ]);
+    assert!(!output.status.success());
+    for path in &[input, compressed, recompressed, rel] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_crel() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let input = temp_file("convert_crel.rel", &REL1);
+    let crel = input.with_extension("crel");
+    let rel = input.with_extension("out.rel");
+    let output = relox(&[
+        "convert",
+        input.to_str().unwrap(),
+        "-o",
+        crel.to_str().unwrap(),
+        "--from",
+        "rel",
+        "--to",
+        "crel",
+    ]);
+    assert!(output.status.success());
+    let output = relox(&[
+        "convert",
+        crel.to_str().unwrap(),
+        "-o",
+        rel.to_str().unwrap(),
+        "--from",
+        "crel",
+        "--to",
+        "rel",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&crel).unwrap().len(), 8);
+    assert_eq!(std::fs::read(&rel).unwrap(), &REL1[..]);
+    for path in &[input, crel, rel] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_aps2() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let input = temp_file("convert_aps2.rel", &REL1);
+    let aps2 = input.with_extension("aps2");
+    let rel = input.with_extension("out.rel");
+    let output = relox(&[
+        "convert",
+        input.to_str().unwrap(),
+        "-o",
+        aps2.to_str().unwrap(),
+        "--from",
+        "rel",
+        "--to",
+        "aps2",
+    ]);
+    assert!(output.status.success());
+    let output = relox(&[
+        "convert",
+        aps2.to_str().unwrap(),
+        "-o",
+        rel.to_str().unwrap(),
+        "--from",
+        "aps2",
+        "--to",
+        "rel",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(&std::fs::read(&aps2).unwrap()[..4], b"APS2");
+    assert_eq!(std::fs::read(&rel).unwrap(), &REL1[..]);
+    for path in &[input, aps2, rel] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_unknown_format() {
+    let output = relox(&[
+        "convert", "in", "-o", "out", "--from", "rel", "--to", "aps2",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_pipe() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_relox"))
+        .args(["convert", "-", "-o", "-", "--from", "rel", "--to", "relox"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&REL1).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        [0x00, 0x08, 0x00, 0x40, 0x01, 0x17, 0x02, 0x00, 0x10]
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "2 relocations, 16 bytes -> 9 bytes\n");
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_convert_batch() {
+    const REL1: [u8; 8] = [0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00];
+
+    let directory = std::env::temp_dir().join(format!("relox-{}-batch", std::process::id()));
+    let out_dir = directory.join("out");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    for name in &["a.rel", "b.rel", "c.txt"] {
+        std::fs::write(directory.join(name), REL1).unwrap();
+    }
+    let pattern = directory.join("*.rel");
+    let output = relox(&[
+        "convert",
+        pattern.to_str().unwrap(),
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+        "--from",
+        "rel",
+        "--to",
+        "relox",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 2);
+    let mut outputs: Vec<_> = std::fs::read_dir(&out_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    outputs.sort();
+    assert_eq!(outputs, ["a.relox", "b.relox"]);
+
+    let output = relox(&[
+        "convert",
+        pattern.to_str().unwrap(),
+        "-o",
+        "out",
+        "--from",
+        "rel",
+        "--to",
+        "relox",
+    ]);
+    assert!(!output.status.success());
+    let missing = directory.join("*.crel");
+    let output = relox(&["explain", missing.to_str().unwrap()]);
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_explain() {
+    const CREL: [u8; 9] = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04];
+
+    let input = temp_file("explain.relox", &CREL);
+    let output = relox(&["explain", input.to_str().unwrap(), "--machine", "40"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("00000000  00 10 00 00"));
+    assert!(lines[2].ends_with("group[0].relocation_type = 0x17 (R_ARM_RELATIVE)"));
+    assert!(lines[5].ends_with("group[0].offsets[1] = +0x4 -> 0x00001008"));
+
+    std::fs::write(&input, &CREL[..8]).unwrap();
+    let output = relox(&["explain", input.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.ends_with("malformed section: not enough data\n"));
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_explain_several() {
+    const CREL: [u8; 9] = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04];
+
+    let first = temp_file("explain_first.relox", &CREL);
+    let second = temp_file("explain_second.relox", &CREL);
+    let output = relox(&["explain", first.to_str().unwrap(), second.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let headers: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("==> "))
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            format!("==> {} <==", first.display()),
+            format!("==> {} <==", second.display())
+        ]
+    );
+    assert_eq!(stdout.lines().count(), 14);
+    for path in &[first, second] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_explain_json() {
+    const CREL: [u8; 9] = [0x00, 0x10, 0x00, 0x00, 0x01, 0x17, 0x02, 0x04, 0x04];
+
+    let input = temp_file("explain_json.relox", &CREL);
+    let output = relox(&["explain", input.to_str().unwrap(), "--json"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\"size\": 9, \"valid\": true, \"error\": null, "));
+    assert!(stdout.contains(
+        "\"relocations\": [{\"type\": 23, \"offset\": 4100}, {\"type\": 23, \"offset\": 4104}]"
+    ));
+    assert!(stdout
+        .contains("\"groups\": [{\"type\": 23, \"count\": 2, \"first\": 4100, \"last\": 4104}]"));
+
+    std::fs::write(&input, &CREL[..8]).unwrap();
+    let output = relox(&["explain", input.to_str().unwrap(), "--json"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"valid\": false, \"error\": \"decompression failed: NotEnoughData\""));
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_elf_input() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let elf = common::build_elf32(&[(".rel.dyn", 9, 0, &REL1), (".rel.plt", 9, 0, &REL1[..8])]);
+    let input = temp_file("input.elf", &elf);
+    let output = relox(&["bench", input.to_str().unwrap(), "--iterations", "1"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("2 relocations, 16 bytes"));
+    let output = relox(&[
+        "bench",
+        input.to_str().unwrap(),
+        "--section",
+        ".rel.plt",
+        "--iterations",
+        "1",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1 relocations, 8 bytes"));
+    let output = relox(&["bench", input.to_str().unwrap(), "--section", ".rel.foo"]);
+    assert!(!output.status.success());
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_elf_input_unsupported() {
+    let mut elf = common::build_elf32(&[]);
+    elf[4] = 2;
+    let input = temp_file("input64.elf", &elf);
+    let output = relox(&["bench", input.to_str().unwrap()]);
+    std::fs::remove_file(&input).unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unsupported ELF file (Elf64, Little endian, machine 40)"));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_strip() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let elf = common::build_elf32(&[
+        (".text", 1, 0x1000, &[0; 16]),
+        (".rel.dyn", 9, 0, &REL1),
+        (".rel.plt", 9, 0, &REL1[..8]),
+    ]);
+    let input = temp_file("strip.elf", &elf);
+    let stripped = input.with_extension("stripped.elf");
+    let output = relox(&[
+        "strip",
+        input.to_str().unwrap(),
+        "-o",
+        stripped.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("removed `.rel.dyn` (16 bytes)"));
+    assert!(stdout.contains("removed `.rel.plt` (8 bytes)"));
+    assert!(stdout.contains(&format!("{} bytes -> {} bytes", elf.len(), elf.len() - 104)));
+    let output = relox(&["bench", stripped.to_str().unwrap()]);
+    assert!(!output.status.success());
+
+    let output = relox(&[
+        "strip",
+        input.to_str().unwrap(),
+        "-o",
+        stripped.to_str().unwrap(),
+        "--section",
+        ".rel.plt",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("`.rel.dyn`"));
+    let output = relox(&["bench", stripped.to_str().unwrap(), "--iterations", "1"]);
+    assert!(output.status.success());
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&stripped).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_size() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let elf = common::build_elf32(&[(".rel.dyn", 9, 0, &REL1), (".rel.plt", 9, 0, &REL1[..8])]);
+    let input = temp_file("size.elf", &elf);
+    let output = relox(&["size", input.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(lines[0], ["section", "rel", "relox", "crel", "aps2"]);
+    assert_eq!(lines[1], [".rel.dyn", "16", "9", "8", "15"]);
+    assert_eq!(lines[2], [".rel.plt", "8", "8", "7", "14"]);
+    assert_eq!(lines[3], ["total", "24", "17", "15", "29"]);
+    assert_eq!(lines[4], ["merged", "24", "10", "13", "16"]);
+
+    let output = relox(&["size", input.to_str().unwrap(), "--json"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.trim_end(),
+        "{\"sections\": [\
+         {\"name\": \".rel.dyn\", \"sizes\": {\"rel\": 16, \"relox\": 9, \"crel\": 8, \"aps2\": 15}}, \
+         {\"name\": \".rel.plt\", \"sizes\": {\"rel\": 8, \"relox\": 8, \"crel\": 7, \"aps2\": 14}}], \
+         \"total\": {\"rel\": 24, \"relox\": 17, \"crel\": 15, \"aps2\": 29}, \
+         \"merged\": {\"rel\": 24, \"relox\": 10, \"crel\": 13, \"aps2\": 16}}"
+    );
+
+    let elf = common::build_elf32(&[]);
+    std::fs::write(&input, &elf).unwrap();
+    let output = relox(&["size", input.to_str().unwrap()]);
+    assert!(!output.status.success());
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_object() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let input = temp_file("object.rel", &REL1);
+    let object = input.with_extension("o");
+    let output = relox(&[
+        "object",
+        input.to_str().unwrap(),
+        "-o",
+        object.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("missing --machine"));
+    let output = relox(&[
+        "object",
+        input.to_str().unwrap(),
+        "-o",
+        object.to_str().unwrap(),
+        "--machine",
+        "40",
+        "--symbols",
+        "crel_begin",
+        "crel_finish",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("16 bytes -> 9 bytes"));
+    let data = std::fs::read(&object).unwrap();
+    assert_eq!(&data[0..4], b"\x7FELF");
+    assert_eq!(
+        &data[52..61],
+        &[0x00, 0x08, 0x00, 0x40, 0x01, 0x17, 0x02, 0x00, 0x10]
+    );
+    assert!(data
+        .windows(b"crel_finish".len())
+        .any(|name| name == b"crel_finish"));
+
+    let elf = common::build_elf32(&[(".rel.dyn", 9, 0, &REL1)]);
+    std::fs::write(&input, &elf).unwrap();
+    let output = relox(&[
+        "object",
+        input.to_str().unwrap(),
+        "-o",
+        object.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let data = std::fs::read(&object).unwrap();
+    assert_eq!(&data[18..20], &[40, 0]);
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&object).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_elf_input_dynamic() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let elf = common::build_elf32_dynamic(&REL1, &REL1[..8]);
+    let input = temp_file("dynamic.elf", &elf);
+    let output = relox(&["bench", input.to_str().unwrap(), "--iterations", "1"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("2 relocations, 16 bytes"));
+    let output = relox(&["bench", input.to_str().unwrap(), "--section", ".rel.dyn"]);
+    assert!(!output.status.success());
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_object_all() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let elf = common::build_elf32(&[
+        (".rel.text", 9, 0, &REL1[8..]),
+        (".rel.data", 9, 0, &REL1[..8]),
+    ]);
+    let input = temp_file("all.elf", &elf);
+    let object = input.with_extension("o");
+    let output = relox(&[
+        "object",
+        input.to_str().unwrap(),
+        "--all",
+        "-o",
+        object.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("16 bytes -> 9 bytes"));
+    let data = std::fs::read(&object).unwrap();
+    assert_eq!(
+        &data[52..61],
+        &[0x00, 0x08, 0x00, 0x40, 0x01, 0x17, 0x02, 0x00, 0x10]
+    );
+    let output = relox(&[
+        "object",
+        input.to_str().unwrap(),
+        "--all",
+        "--section",
+        ".rel.text",
+        "-o",
+        object.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&object).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_append() {
+    const CREL: [u8; 9] = [0x00, 0x08, 0x00, 0x40, 0x01, 0x17, 0x02, 0x00, 0x10];
+    const REL: [u8; 16] = [
+        0x20, 0x08, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x18, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let compressed = temp_file("append.relox", &CREL);
+    let input = temp_file("append.rel", &REL);
+    let appended = compressed.with_extension("out.relox");
+    let output = relox(&[
+        "append",
+        compressed.to_str().unwrap(),
+        input.to_str().unwrap(),
+        "-o",
+        appended.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("2 relocations appended, 9 bytes -> 15 bytes"));
+    assert_eq!(
+        std::fs::read(&appended).unwrap(),
+        [
+            0x00, 0x08, 0x00, 0x40, 0x03, 0x17, 0x02, 0x00, 0x10, 0x02, 0x01, 0x20, 0x17, 0x01,
+            0x18
+        ]
+    );
+    for path in &[compressed, input, appended] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_mcuboot() {
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let elf = common::build_elf32(&[
+        (".text", 1, 0x1000, &[0; 16]),
+        (".rel.dyn", 9, 0x1010, &REL1),
+    ]);
+    let mut image = vec![0; 32];
+    image[0..4].copy_from_slice(&0x96F3_B83Du32.to_le_bytes());
+    image[8..10].copy_from_slice(&32u16.to_le_bytes());
+    image[12..16].copy_from_slice(&32u32.to_le_bytes());
+    image.extend_from_slice(&[0; 16]);
+    image.extend_from_slice(&REL1);
+    image.extend_from_slice(&[0x07, 0x69, 40, 0x00, 0x10, 0x00, 32, 0x00]);
+    image.extend_from_slice(&[0; 32]);
+    let elf_input = temp_file("mcuboot.elf", &elf);
+    let input = temp_file("mcuboot.bin", &image);
+    let processed = input.with_extension("processed.bin");
+    let args = [
+        "mcuboot",
+        input.to_str().unwrap(),
+        elf_input.to_str().unwrap(),
+        "-o",
+        processed.to_str().unwrap(),
+    ];
+    let output = relox(&args);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--load-address"));
+
+    let output = relox(&[&args[..], &["--load-address", "0x1000"]].concat());
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("payload 32 bytes -> 25 bytes"));
+    let data = std::fs::read(&processed).unwrap();
+    assert_eq!(data.len(), 32 + 25 + 40);
+    assert_eq!(data[12..16], 25u32.to_le_bytes());
+    std::fs::remove_file(&elf_input).unwrap();
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&processed).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_compress_inspect_verify() {
+    const REL1: [u8; 24] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00, 0x08, 0x08, 0x00, 0x40, 0x02, 0x01, 0x00, 0x00,
+    ];
+
+    let elf = common::build_elf32(&[(".text", 1, 0x1000, &[0; 16]), (".rel.dyn", 9, 0, &REL1)]);
+    let input = temp_file("compress.elf", &elf);
+    let compressed = input.with_extension("compressed.elf");
+    let output = relox(&[
+        "compress",
+        input.to_str().unwrap(),
+        "-o",
+        compressed.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("`.rel.dyn` (24 bytes) -> `.crel.dyn` ("));
+
+    let output = relox(&[
+        "inspect",
+        compressed.to_str().unwrap(),
+        "--section",
+        ".crel.dyn",
+        "--machine",
+        "40",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("base address: 0x40000800"));
+    assert!(stdout.contains("relocations:  3"));
+    assert!(stdout.contains("type  23 R_ARM_RELATIVE: 2 relocations, 0x40000800..=0x40000810"));
+
+    let output = relox(&[
+        "verify",
+        compressed.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("3 relocations match, 24 bytes -> "));
+
+    let mut other = REL1;
+    other[16] = 0x0C;
+    let other = temp_file("verify.rel", &other);
+    let output = relox(&[
+        "verify",
+        compressed.to_str().unwrap(),
+        other.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("expected type 2 at 0x4000080c (symbol 1), decoded type 2 at 0x40000808")
+    );
+
+    let output = relox(&[
+        "compress",
+        compressed.to_str().unwrap(),
+        "-o",
+        compressed.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    for path in &[input, compressed, other] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_compress_big_endian() {
+    const REL1: [u8; 16] = [
+        0x40, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x17, 0x40, 0x00, 0x08, 0x10, 0x00, 0x00, 0x00,
+        0x17,
+    ];
+
+    let elf = common::build_elf32_endian(
+        &[(".text", 1, 0x1000, &[0; 16]), (".rel.dyn", 9, 0, &REL1)],
+        true,
+    );
+    let input = temp_file("compress-be.elf", &elf);
+    let compressed = input.with_extension("compressed.elf");
+    let output = relox(&[
+        "compress",
+        input.to_str().unwrap(),
+        "-o",
+        compressed.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let data = std::fs::read(&compressed).unwrap();
+    let file = relox::elf::Elf32File::parse(&data).unwrap();
+    let section = file.section_by_name(".crel.dyn").unwrap();
+    assert_eq!(
+        file.section_data(section).unwrap(),
+        &[0x40, 0x00, 0x08, 0x00, 0x01, 0x17, 0x02, 0x00, 0x10][..]
+    );
+
+    let output = relox(&["size", input.to_str().unwrap()]);
+    assert!(output.status.success());
+    let output = relox(&["bench", input.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("big-endian ELF files are not supported by this command"));
+    for path in &[input, compressed] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
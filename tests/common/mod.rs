@@ -0,0 +1,129 @@
+//! Helpers shared by integration tests.
+
+#![allow(dead_code)]
+
+/// Builds a little-endian ELF32 file for ARM with the given sections, each
+/// described by its name, type, address and contents.
+pub fn build_elf32(sections: &[(&str, u32, u32, &[u8])]) -> Vec<u8> {
+    build_elf32_endian(sections, false)
+}
+
+/// Builds an ELF32 file for ARM like [build_elf32](fn.build_elf32.html),
+/// which is big-endian if `big` is set.
+pub fn build_elf32_endian(sections: &[(&str, u32, u32, &[u8])], big: bool) -> Vec<u8> {
+    let word = |value: u32| match big {
+        false => value.to_le_bytes(),
+        true => value.to_be_bytes(),
+    };
+    let half = |value: u16| match big {
+        false => value.to_le_bytes(),
+        true => value.to_be_bytes(),
+    };
+    let mut names = vec![0];
+    let mut name_offsets = Vec::new();
+    for (name, _, _, _) in sections.iter() {
+        name_offsets.push(names.len() as u32);
+        names.extend_from_slice(name.as_bytes());
+        names.push(0);
+    }
+    let shstrtab_name = names.len() as u32;
+    names.extend_from_slice(b".shstrtab\0");
+
+    let mut data = vec![0; 52];
+    let mut offsets = Vec::new();
+    for (_, _, _, contents) in sections.iter() {
+        offsets.push(data.len() as u32);
+        data.extend_from_slice(contents);
+    }
+    let names_offset = data.len() as u32;
+    data.extend_from_slice(&names);
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+    let shoff = data.len() as u32;
+    let mut header = |name: u32, section_type: u32, address: u32, offset: u32, size: u32| {
+        for field in &[name, section_type, 0, address, offset, size, 0, 0, 4, 0] {
+            data.extend_from_slice(&word(*field));
+        }
+    };
+    header(0, 0, 0, 0, 0);
+    for (index, (_, section_type, address, contents)) in sections.iter().enumerate() {
+        header(
+            name_offsets[index],
+            *section_type,
+            *address,
+            offsets[index],
+            contents.len() as u32,
+        );
+    }
+    header(shstrtab_name, 3, 0, names_offset, names.len() as u32);
+
+    let shnum = sections.len() as u16 + 2;
+    data[0..4].copy_from_slice(&[0x7F, b'E', b'L', b'F']);
+    data[4] = 1;
+    data[5] = if big { 2 } else { 1 };
+    data[6] = 1;
+    data[16..18].copy_from_slice(&half(2));
+    data[18..20].copy_from_slice(&half(40));
+    data[20..24].copy_from_slice(&word(1));
+    data[32..36].copy_from_slice(&word(shoff));
+    data[40..42].copy_from_slice(&half(52));
+    data[46..48].copy_from_slice(&half(40));
+    data[48..50].copy_from_slice(&half(shnum));
+    data[50..52].copy_from_slice(&half(shnum - 1));
+    data
+}
+
+/// Builds a little-endian ELF32 file for ARM without section headers,
+/// whose dynamic segment references the given `DT_REL` and `DT_JMPREL`
+/// tables.
+pub fn build_elf32_dynamic(rel: &[u8], jmprel: &[u8]) -> Vec<u8> {
+    let base_address = 0x8000;
+    let mut data = vec![0; 52 + 2 * 32];
+    let rel_address = base_address + data.len() as u32;
+    data.extend_from_slice(rel);
+    let jmprel_address = base_address + data.len() as u32;
+    data.extend_from_slice(jmprel);
+    let mut entries = vec![(17, rel_address), (18, rel.len() as u32), (19, 8)];
+    if !jmprel.is_empty() {
+        entries.extend_from_slice(&[(23, jmprel_address), (2, jmprel.len() as u32), (20, 17)]);
+    }
+    entries.push((0, 0));
+    let dynamic_offset = data.len() as u32;
+    for (tag, value) in entries.iter() {
+        data.extend_from_slice(&(*tag as u32).to_le_bytes());
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    let size = data.len() as u32;
+    let dynamic_size = size - dynamic_offset;
+    let mut segments = Vec::new();
+    for field in &[1, 0, base_address, base_address, size, size, 6, 4] {
+        segments.extend_from_slice(&field.to_le_bytes());
+    }
+    let dynamic_address = base_address + dynamic_offset;
+    for field in &[
+        2,
+        dynamic_offset,
+        dynamic_address,
+        dynamic_address,
+        dynamic_size,
+        dynamic_size,
+        6,
+        4,
+    ] {
+        segments.extend_from_slice(&field.to_le_bytes());
+    }
+    data[52..116].copy_from_slice(&segments);
+    data[0..4].copy_from_slice(&[0x7F, b'E', b'L', b'F']);
+    data[4] = 1;
+    data[5] = 1;
+    data[6] = 1;
+    data[16..18].copy_from_slice(&3u16.to_le_bytes());
+    data[18..20].copy_from_slice(&40u16.to_le_bytes());
+    data[20..24].copy_from_slice(&1u32.to_le_bytes());
+    data[28..32].copy_from_slice(&52u32.to_le_bytes());
+    data[40..42].copy_from_slice(&52u16.to_le_bytes());
+    data[42..44].copy_from_slice(&32u16.to_le_bytes());
+    data[44..46].copy_from_slice(&2u16.to_le_bytes());
+    data
+}
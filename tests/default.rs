@@ -1,3 +1,6 @@
+#[cfg(feature = "object")]
+mod common;
+
 #[cfg(all(feature = "compress", feature = "decompress"))]
 #[test]
 fn test_compress_decompress() {
@@ -26,10 +29,10 @@ fn test_compress_decompress() {
         0x400008f8, 0x40000908, 0x40000918,
     ];
 
-    let mut compressed: [u8; 32] = [0; 32];
+    let mut compressed: [u8; 64] = [0; 64];
     let mut el32relocs = Elf32Relocs::new(&REL1);
     let written = el32relocs.compress(&mut compressed).unwrap();
-    assert_eq!(written, 5 + (1 + 1 + 24));
+    assert_eq!(written, 7 + (1 + 1 + 25 + 16));
 
     let mut index = 0;
     elf32_relocate(&compressed, &mut |relocation_type, address| {
@@ -40,3 +43,469 @@ fn test_compress_decompress() {
     })
     .unwrap();
 }
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_group_varint() {
+    use relox::{elf32_relocate, Codec, Elf32Relocs, Encoding};
+
+    let mut expected = Vec::new();
+    let mut memory = Vec::new();
+    let mut offset = 0x1000u32;
+    for (index, delta) in [0, 4, 0x100, 0x1_0000, 0x100_0000, 8, 4, 0x7F, 0x80, 12]
+        .iter()
+        .enumerate()
+    {
+        offset += delta;
+        let relocation_type = if index % 3 == 0 { 0x17 } else { 0x02 };
+        expected.push((relocation_type, offset));
+        memory.extend_from_slice(&offset.to_le_bytes());
+        memory.extend_from_slice(&u32::from(relocation_type).to_le_bytes());
+    }
+    expected.sort_unstable();
+
+    for encoding in [
+        Encoding::Grouped,
+        Encoding::Interleaved,
+        Encoding::Directory,
+    ]
+    .iter()
+    {
+        let mut compressed = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(*encoding);
+        relocs.set_codec(Codec::GroupVarint);
+        let written = relocs.compress(&mut compressed).unwrap();
+        let mut decompressed = Vec::new();
+        let read = elf32_relocate(&compressed[..written], &mut |relocation_type, address| {
+            decompressed.push((relocation_type, address));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read, written);
+        decompressed.sort_unstable();
+        assert_eq!(decompressed, expected);
+    }
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_bitmap() {
+    use relox::{elf32_relocate, Codec, Elf32CRel, Elf32Relocs, Encoding};
+    use std::convert::TryFrom;
+
+    // Dense runs of relative relocations with gaps, unaligned and repeated
+    // offsets.
+    let mut expected = Vec::new();
+    let mut memory = Vec::new();
+    let mut offset = 0x1000u32;
+    for index in 0..1000u32 {
+        offset += match index % 97 {
+            0 => 0x100,
+            50 => 2,
+            60 => 0,
+            _ => 4,
+        };
+        let relocation_type = if index % 211 == 0 { 0x02 } else { 0x17 };
+        expected.push((relocation_type, offset));
+        memory.extend_from_slice(&offset.to_le_bytes());
+        memory.extend_from_slice(&u32::from(relocation_type).to_le_bytes());
+    }
+    expected.sort_unstable();
+
+    let mut uleb128 = [0; 2048];
+    let mut relocs = Elf32Relocs::new(&memory);
+    relocs.set_repeat_offsets(false);
+    let uleb128 = relocs.compress(&mut uleb128).unwrap();
+    for encoding in [
+        Encoding::Grouped,
+        Encoding::Interleaved,
+        Encoding::Directory,
+    ]
+    .iter()
+    {
+        for memory_limit in [None, Some(0)].iter() {
+            let mut compressed = [0; 2048];
+            let mut relocs = Elf32Relocs::new(&memory);
+            relocs.set_encoding(*encoding);
+            relocs.set_codec(Codec::Bitmap);
+            if let Some(limit) = memory_limit {
+                relocs.set_memory_limit(*limit);
+            }
+            let written = relocs.compress(&mut compressed).unwrap();
+            assert!(written < uleb128 / 3);
+            let mut decompressed = Vec::new();
+            let read = elf32_relocate(&compressed[..written], &mut |relocation_type, address| {
+                decompressed.push((relocation_type, address));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(read, written);
+            let mut iterated: Vec<_> = Elf32CRel::try_from(&compressed[..written])
+                .unwrap()
+                .into_iter()
+                .map(|relocation| {
+                    let relocation = relocation.unwrap();
+                    (relocation.relocation_type(), relocation.offset())
+                })
+                .collect();
+            decompressed.sort_unstable();
+            iterated.sort_unstable();
+            assert_eq!(decompressed, expected);
+            assert_eq!(iterated, expected);
+        }
+    }
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_signed() {
+    use relox::{elf32_relocate, Codec, Elf32CRel, Elf32Relocs, Encoding};
+    use std::convert::TryFrom;
+
+    // Relocations in no particular order, some below the first one.
+    let mut expected = Vec::new();
+    let mut memory = Vec::new();
+    let mut state = 0x2545_f491u32;
+    for index in 0..200u32 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let offset = 0x4000_0000 + (state % 0x1_0000) * 4;
+        let relocation_type = if index % 3 == 0 { 0x02 } else { 0x17 };
+        expected.push((relocation_type, offset));
+        memory.extend_from_slice(&offset.to_le_bytes());
+        memory.extend_from_slice(&u32::from(relocation_type).to_le_bytes());
+    }
+    expected.sort_unstable();
+
+    for codec in [Codec::Uleb128, Codec::GroupVarint, Codec::Bitmap].iter() {
+        for memory_limit in [None, Some(0)].iter() {
+            let mut compressed = [0; 2048];
+            let mut relocs = Elf32Relocs::new(&memory);
+            relocs.set_encoding(Encoding::Grouped);
+            relocs.set_codec(*codec);
+            if let Some(limit) = memory_limit {
+                relocs.set_memory_limit(*limit);
+            }
+            let written = relocs.compress(&mut compressed).unwrap();
+            let mut decompressed = Vec::new();
+            let read = elf32_relocate(&compressed[..written], &mut |relocation_type, address| {
+                decompressed.push((relocation_type, address));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(read, written);
+            let mut iterated: Vec<_> = Elf32CRel::try_from(&compressed[..written])
+                .unwrap()
+                .into_iter()
+                .map(|relocation| {
+                    let relocation = relocation.unwrap();
+                    (relocation.relocation_type(), relocation.offset())
+                })
+                .collect();
+            decompressed.sort_unstable();
+            iterated.sort_unstable();
+            assert_eq!(decompressed, expected);
+            assert_eq!(iterated, expected);
+        }
+    }
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_aligned() {
+    use relox::{elf32_relocate, Codec, Elf32CRel, Elf32Relocs, Encoding};
+    use std::convert::TryFrom;
+
+    let mut expected = Vec::new();
+    let mut memory = Vec::new();
+    for index in 0..100u32 {
+        let offset = 0x4000_0000 + index * 12;
+        let relocation_type = [0x02, 0x17, 0x17, 0x15][index as usize % 4];
+        expected.push((relocation_type, offset));
+        memory.extend_from_slice(&offset.to_le_bytes());
+        memory.extend_from_slice(&u32::from(relocation_type).to_le_bytes());
+    }
+    expected.sort_unstable();
+
+    for alignment in [2, 4, 8].iter() {
+        for encoding in [Encoding::Grouped, Encoding::Interleaved].iter() {
+            for codec in [Codec::Uleb128, Codec::Bitmap].iter() {
+                for based in [false, true].iter() {
+                    if *based && *encoding == Encoding::Interleaved {
+                        continue;
+                    }
+                    let mut compressed = [0; 2048];
+                    let mut relocs = Elf32Relocs::new(&memory);
+                    relocs.set_encoding(*encoding);
+                    relocs.set_codec(*codec);
+                    relocs.set_group_alignment(*alignment).unwrap();
+                    if *based {
+                        relocs.set_type_base_address(0x15, 0x4000_0024).unwrap();
+                    }
+                    let written = relocs.compress(&mut compressed).unwrap();
+                    let mut decompressed = Vec::new();
+                    let read =
+                        elf32_relocate(&compressed[..written], &mut |relocation_type, address| {
+                            decompressed.push((relocation_type, address));
+                            Ok(())
+                        })
+                        .unwrap();
+                    assert_eq!(read, written);
+                    let mut iterated: Vec<_> = Elf32CRel::try_from(&compressed[..written])
+                        .unwrap()
+                        .into_iter()
+                        .map(|relocation| {
+                            let relocation = relocation.unwrap();
+                            (relocation.relocation_type(), relocation.offset())
+                        })
+                        .collect();
+                    decompressed.sort_unstable();
+                    iterated.sort_unstable();
+                    assert_eq!(decompressed, expected);
+                    assert_eq!(iterated, expected);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_strict() {
+    use relox::{elf32_relocate, elf32_relocate_strict, Codec, Elf32Relocs, Encoding};
+
+    let mut memory = Vec::new();
+    let mut offset = 0x4000_0000u32;
+    for index in 0..100 {
+        offset += [4, 12, 260][index % 3];
+        let relocation_type = [0x17u8, 0x02, 0x17, 0x15][index % 4];
+        memory.extend_from_slice(&offset.to_le_bytes());
+        memory.extend_from_slice(&u32::from(relocation_type).to_le_bytes());
+    }
+
+    for encoding in [
+        Encoding::Grouped,
+        Encoding::Interleaved,
+        Encoding::Directory,
+    ]
+    .iter()
+    {
+        for codec in [Codec::Uleb128, Codec::GroupVarint, Codec::Bitmap].iter() {
+            let mut compressed = [0; 2048];
+            let mut relocs = Elf32Relocs::new(&memory);
+            relocs.set_encoding(*encoding);
+            relocs.set_codec(*codec);
+            let written = relocs.compress(&mut compressed).unwrap();
+            let mut expected = Vec::new();
+            elf32_relocate(&compressed[..written], &mut |relocation_type, address| {
+                expected.push((relocation_type, address));
+                Ok(())
+            })
+            .unwrap();
+            let mut decompressed = Vec::new();
+            let read =
+                elf32_relocate_strict(&compressed[..written], &mut |relocation_type, address| {
+                    decompressed.push((relocation_type, address));
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(read, written);
+            assert_eq!(decompressed, expected);
+        }
+    }
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_symbols() {
+    use relox::{elf32_relocate_symbols, Elf32Rel, Elf32Relocs, Encoding};
+
+    let mut expected = Vec::new();
+    let mut memory = Vec::new();
+    for (index, symbol) in [0x65, 0x29, 0, 0x65, 0x30, 0x12_3456, 0x65, 0]
+        .iter()
+        .enumerate()
+    {
+        let offset = 0x4000_0800 + 8 * index as u32;
+        let relocation_type = if index % 4 == 3 { 0x17 } else { 0x02 };
+        expected.push((relocation_type, offset, *symbol));
+        memory
+            .extend_from_slice(&Elf32Rel::with_symbol(offset, relocation_type, *symbol).to_bytes());
+    }
+    // Relocations against symbol 0 only do not carry symbol indices.
+    memory.extend_from_slice(&Elf32Rel::new(0x4000_0900, 0x16).to_bytes());
+    expected.push((0x16, 0x4000_0900, 0));
+    expected.sort_unstable();
+
+    for (encoding, based) in [
+        (Encoding::Grouped, false),
+        (Encoding::Grouped, true),
+        (Encoding::Interleaved, false),
+    ]
+    .iter()
+    {
+        let mut compressed = [0; 128];
+        let mut relocs = Elf32Relocs::new(&memory);
+        relocs.set_encoding(*encoding);
+        if *based {
+            relocs.set_type_base_address(0x17, 0x4000_0818).unwrap();
+        }
+        let written = relocs.compress(&mut compressed).unwrap();
+        let mut decompressed = Vec::new();
+        let read = elf32_relocate_symbols(
+            &compressed[..written],
+            &mut |relocation_type, address, symbol| {
+                decompressed.push((relocation_type, address, symbol));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(read, written);
+        decompressed.sort_unstable();
+        assert_eq!(decompressed, expected);
+    }
+
+    let mut relocs = Elf32Relocs::new(&memory);
+    relocs.set_encoding(Encoding::Directory);
+    let err = relocs.compress(&mut [0; 128]).unwrap_err();
+    assert_eq!(err.kind(), relox::ErrorKind::Unsupported);
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_metadata() {
+    use relox::{elf32_relocate, read_metadata, Elf32Relocs, Metadata, TOOL_VERSION};
+
+    const REL1: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let mut metadata = Metadata::new();
+    metadata.set_build_id(&[0x12, 0x34, 0x56, 0x78]);
+    metadata.set_target("thumbv7em-none-eabihf");
+    metadata.set_tool_version(TOOL_VERSION);
+    metadata.insert("commit", "339fe94");
+
+    let mut compressed = [0; 128];
+    let mut relocs = Elf32Relocs::new(&REL1);
+    let plain = relocs.compress(&mut compressed).unwrap();
+    relocs.set_metadata(&metadata).unwrap();
+    let written = relocs.compress(&mut compressed).unwrap();
+    assert_eq!(written, plain + metadata.to_bytes().unwrap().len());
+
+    let mut addresses = Vec::new();
+    let read = elf32_relocate(&compressed[..written], &mut |_, address| {
+        addresses.push(address);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(read, plain);
+    assert_eq!(addresses, [0x4000_0800, 0x4000_0810]);
+    assert_eq!(
+        read_metadata(&compressed[..written]).unwrap(),
+        Some(metadata)
+    );
+    assert_eq!(read_metadata(&compressed[..plain]).unwrap(), None);
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_pointer_table() {
+    use relox::{compress_pointer_table, decompress_pointer_table, pointer_table_len, Endianness};
+
+    let pointers: Vec<u32> = (0..64)
+        .map(|index| 0x0800_0000 + index * 0x34 % 0x400)
+        .collect();
+    let mut table = Vec::new();
+    for pointer in pointers.iter() {
+        table.extend_from_slice(&pointer.to_ne_bytes());
+    }
+    let compressed = compress_pointer_table(&table, Endianness::native()).unwrap();
+    assert!(compressed.len() < table.len() / 2);
+    assert_eq!(pointer_table_len(&compressed).unwrap(), pointers.len());
+
+    let mut rebuilt = vec![0; table.len()];
+    let read = decompress_pointer_table(&compressed, 0x1000_0000, &mut rebuilt).unwrap();
+    assert_eq!(read, compressed.len());
+    for (word, pointer) in rebuilt.chunks(4).zip(pointers.iter()) {
+        assert_eq!(word, (pointer + 0x1000_0000).to_ne_bytes());
+    }
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_pack_unpack_sorted_u32s() {
+    use relox::{pack_sorted_u32s, unpack_sorted_u32s};
+
+    let values: Vec<u32> = (0..100).map(|index| 0x2000_0000 + index * index).collect();
+    let packed = pack_sorted_u32s(&values).unwrap();
+    let mut unpacked = Vec::new();
+    let read = unpack_sorted_u32s(&packed, &mut |value| {
+        unpacked.push(value);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(read, packed.len());
+    assert_eq!(unpacked, values);
+}
+
+#[cfg(all(feature = "compress", feature = "decompress"))]
+#[test]
+fn test_compress_decompress_elf64() {
+    use relox::{elf64_relocate, Elf64Rel, Elf64Relocs};
+
+    // R_AARCH64_RELATIVE and R_AARCH64_GLOB_DAT
+    let entries = [
+        Elf64Rel::new(0xFFFF_0000_0001_0000, 0x403),
+        Elf64Rel::new(0xFFFF_0000_0001_0010, 0x401),
+        Elf64Rel::new(0xFFFF_0000_0001_0008, 0x403),
+        Elf64Rel::new(0xFFFF_0000_0002_0000, 0x403),
+    ];
+    let mut rel = Vec::new();
+    for entry in entries.iter() {
+        rel.extend_from_slice(&entry.to_bytes());
+    }
+    let mut compressed = [0; 64];
+    let size = Elf64Relocs::new(&rel).compress(&mut compressed).unwrap();
+    assert!(size < rel.len() / 2);
+
+    let mut decompressed = Vec::new();
+    let end = elf64_relocate(&compressed[..size], &mut |relocation_type, address| {
+        decompressed.push(Elf64Rel::new(address, relocation_type));
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(end, size);
+    decompressed.sort_unstable();
+    let mut expected = entries.to_vec();
+    expected.sort_unstable();
+    assert_eq!(decompressed, expected);
+}
+
+#[cfg(feature = "object")]
+#[test]
+fn test_compress_object_section() {
+    use relox::{Elf32Relocs, ErrorKind};
+
+    const REL: [u8; 16] = [
+        0x00, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00, 0x00, 0x10, 0x08, 0x00, 0x40, 0x17, 0x00, 0x00,
+        0x00,
+    ];
+
+    let elf = common::build_elf32(&[(".text", 1, 0x1000, &[0; 16]), (".rel.dyn", 9, 0, &REL)]);
+    let file = object::File::parse(&elf[..]).unwrap();
+    let mut relocs = Elf32Relocs::from_object_section(&file, ".rel.dyn").unwrap();
+    let mut expected = [0; 16];
+    let mut compressed = [0; 16];
+    let size = Elf32Relocs::new(&REL).compress(&mut expected).unwrap();
+    assert_eq!(relocs.compress(&mut compressed).unwrap(), size);
+    assert_eq!(compressed[..size], expected[..size]);
+
+    for name in [".text", ".rel.plt"].iter() {
+        let err = Elf32Relocs::from_object_section(&file, name).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::SectionNotFound);
+    }
+}